@@ -0,0 +1,15 @@
+declare namespace jsonata {
+  interface Expression {
+    /** Binds a variable for every subsequent evaluation. */
+    assign(name: string, value: any): void;
+    /** Registers a function for every subsequent evaluation. */
+    registerFunction(name: string, implementation: (...args: any[]) => any): void;
+    /** Evaluates the expression, returning `undefined` if the result is undefined. */
+    evaluate(input?: any, bindings?: Record<string, any>): any;
+  }
+}
+
+/** Parses an expression, throwing if it isn't valid. */
+declare function jsonata(expression: string): jsonata.Expression;
+
+export = jsonata;
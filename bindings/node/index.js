@@ -0,0 +1,4 @@
+const { jsonata, Expression } = require("./jsonata-rs.node");
+
+module.exports = jsonata;
+module.exports.Expression = Expression;
@@ -0,0 +1,203 @@
+//! Node.js bindings, exposing the same API as the [jsonata-js] library:
+//!
+//! ```js
+//! const jsonata = require("@stedi/jsonata-rs");
+//!
+//! const expression = jsonata("$sum(items.price) * $rate");
+//! expression.assign("rate", 1.2);
+//! expression.registerFunction("double", (n) => n * 2);
+//! const result = expression.evaluate(input);
+//! ```
+//!
+//! Evaluation is synchronous, and inputs, bindings and results cross the boundary as JSON values,
+//! so functions and other non-JSON values can't be passed in or returned.
+//!
+//! [jsonata-js]: https://github.com/jsonata-js/jsonata
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use jsonata_rs::compat;
+use jsonata_rs::{FunctionContext, Value};
+use napi::bindgen_prelude::*;
+use napi::{Env, JsFunction, JsUnknown, Ref, ValueType};
+use napi_derive::napi;
+
+/// Parses an expression, like `jsonata(expr)` in JavaScript.
+#[napi]
+pub fn jsonata(expression: String) -> Result<Expression> {
+    Ok(Expression {
+        inner: compat::jsonata(&expression).map_err(to_napi_error)?,
+        functions: HashMap::new(),
+    })
+}
+
+#[napi(custom_finalize)]
+pub struct Expression {
+    inner: compat::Expression,
+    // References to the registered JavaScript functions, released when the expression is
+    // garbage collected
+    functions: HashMap<String, Ref<()>>,
+}
+
+#[napi]
+impl Expression {
+    /// Binds a variable for every subsequent evaluation.
+    #[napi]
+    pub fn assign(&mut self, name: String, value: serde_json::Value) {
+        self.inner.assign(&name, value);
+    }
+
+    /// Registers a JavaScript function for every subsequent evaluation. It's called with its
+    /// arguments converted to JSON values, and its result is converted back.
+    #[napi]
+    pub fn register_function(
+        &mut self,
+        env: Env,
+        name: String,
+        implementation: JsFunction,
+    ) -> Result<()> {
+        let implementation = implementation.coerce_to_object()?;
+        let arity = implementation
+            .get_named_property::<napi::JsNumber>("length")?
+            .get_uint32()? as usize;
+
+        if let Some(mut previous) = self
+            .functions
+            .insert(name.clone(), env.create_reference(implementation)?)
+        {
+            previous.unref(env)?;
+        }
+        self.inner.register_function(&name, arity, call_js_function);
+
+        Ok(())
+    }
+
+    /// Evaluates the expression, returning `undefined` if the result is undefined. An input of
+    /// `undefined`, or none, stays undefined rather than becoming `null`. Errors are thrown,
+    /// including any thrown by registered functions.
+    #[napi]
+    pub fn evaluate(
+        &self,
+        env: Env,
+        input: JsUnknown,
+        bindings: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<JsUnknown> {
+        let input: Option<serde_json::Value> = match input.get_type()? {
+            ValueType::Undefined => None,
+            _ => Some(env.from_js_value(input)?),
+        };
+
+        let previous = SCOPE.with(|scope| {
+            scope.replace(Some(Scope {
+                env: env.raw(),
+                functions: &self.functions,
+                error: None,
+            }))
+        });
+
+        let result = self.inner.evaluate(input.as_ref(), bindings.as_ref());
+
+        // Restore the enclosing scope, in case this evaluation was started by a registered
+        // function of another expression
+        let scope = SCOPE.with(|scope| scope.replace(previous));
+        if let Some(error) = scope.and_then(|scope| scope.error) {
+            return Err(error);
+        }
+
+        match result.map_err(to_napi_error)? {
+            Some(result) => env.to_js_value(&result),
+            None => env
+                .get_undefined()
+                .map(|undefined| undefined.into_unknown()),
+        }
+    }
+}
+
+impl ObjectFinalize for Expression {
+    fn finalize(mut self, env: Env) -> Result<()> {
+        for (_, mut function) in self.functions.drain() {
+            function.unref(env)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a registered function needs from the evaluation which is calling it.
+struct Scope {
+    env: napi::sys::napi_env,
+    functions: *const HashMap<String, Ref<()>>,
+    // The error thrown by a registered function, which is rethrown as is rather than as a
+    // JSONata error
+    error: Option<Error>,
+}
+
+thread_local! {
+    static SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+}
+
+// Every registered function is bound to this, which finds the JavaScript function by the name it
+// was called with.
+fn call_js_function<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> jsonata_rs::Result<&'a Value<'a>> {
+    let (env, functions) = SCOPE.with(|scope| {
+        let scope = scope.borrow();
+        let scope = scope
+            .as_ref()
+            .expect("Registered function called outside of an evaluation");
+        (scope.env, scope.functions)
+    });
+
+    // SAFETY: the scope only exists for the duration of `Expression::evaluate`, which holds the
+    // environment and borrows the functions. The scope isn't borrowed while calling the function,
+    // as it may evaluate another expression.
+    let env = unsafe { Env::from_raw(env) };
+    let functions = unsafe { &*functions };
+
+    let result = functions
+        .get(context.name)
+        .ok_or_else(|| Error::from_reason(format!("Unknown function `{}`", context.name)))
+        .and_then(|function| call(env, function, args));
+
+    match result {
+        Ok(Some(value)) => Ok(Value::from_json(context.arena, &value) as &Value),
+        Ok(None) => Ok(Value::undefined()),
+        Err(error) => {
+            let message = error.reason.clone();
+            SCOPE.with(|scope| {
+                if let Some(scope) = scope.borrow_mut().as_mut() {
+                    scope.error.get_or_insert(error);
+                }
+            });
+            Err(jsonata_rs::Error::D3137Error(message))
+        }
+    }
+}
+
+fn call<'a>(
+    env: Env,
+    function: &Ref<()>,
+    args: &'a Value<'a>,
+) -> Result<Option<serde_json::Value>> {
+    let function: JsFunction = env.get_reference_value(function)?;
+    let args = args
+        .members()
+        .map(|arg| {
+            let arg: serde_json::Value = arg.deserialize().map_err(to_napi_error)?;
+            env.to_js_value(&arg)
+        })
+        .collect::<Result<Vec<JsUnknown>>>()?;
+
+    let result = function.call(None, &args)?;
+    if result.get_type()? == ValueType::Undefined {
+        Ok(None)
+    } else {
+        env.from_js_value(result).map(Some)
+    }
+}
+
+fn to_napi_error(error: jsonata_rs::Error) -> Error {
+    Error::new(Status::GenericFailure, error.to_string())
+}
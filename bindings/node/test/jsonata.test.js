@@ -0,0 +1,50 @@
+const assert = require("node:assert");
+const test = require("node:test");
+
+const jsonata = require("..");
+
+test("evaluates with assigned variables and bindings", () => {
+  const expression = jsonata("$sum(items.price) * $rate + $bonus");
+  expression.assign("rate", 2);
+  expression.assign("bonus", 100);
+
+  const input = { items: [{ price: 1 }, { price: 2.5 }] };
+  assert.strictEqual(expression.evaluate(input, { bonus: 1 }), 8);
+  assert.strictEqual(expression.evaluate(input), 107);
+});
+
+test("undefined result", () => {
+  assert.strictEqual(jsonata("missing").evaluate({}), undefined);
+});
+
+test("no input is undefined", () => {
+  const expression = jsonata("$exists($)");
+  assert.strictEqual(expression.evaluate(), false);
+  assert.strictEqual(expression.evaluate(undefined), false);
+  assert.strictEqual(expression.evaluate(null), true);
+});
+
+test("registered functions", () => {
+  const expression = jsonata("$map(items, $double)");
+  expression.registerFunction("double", (n, i) => n * 2 + i);
+  assert.deepStrictEqual(expression.evaluate({ items: [1, 2, 3] }), [2, 5, 8]);
+});
+
+test("errors thrown by registered functions are rethrown", () => {
+  const expression = jsonata("$fail()");
+  expression.registerFunction("fail", () => {
+    throw new Error("boom");
+  });
+  assert.throws(() => expression.evaluate(null), /boom/);
+});
+
+test("nested evaluation from a registered function", () => {
+  const inner = jsonata("$ * 10");
+  const outer = jsonata("$inner(n) + 1");
+  outer.registerFunction("inner", (n) => inner.evaluate(n));
+  assert.strictEqual(outer.evaluate({ n: 4 }), 41);
+});
+
+test("syntax errors", () => {
+  assert.throws(() => jsonata("("), /S0211/);
+});
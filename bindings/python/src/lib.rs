@@ -0,0 +1,258 @@
+//! Python bindings:
+//!
+//! ```python
+//! import jsonata_rs
+//!
+//! expression = jsonata_rs.compile("$sum(items.price) * $rate")
+//! expression.assign("rate", 1.2)
+//! expression.register_function("double", lambda n: n * 2)
+//! result = expression.evaluate({"items": [{"price": 5}]})
+//! ```
+//!
+//! Inputs, bindings and results are converted to and from JSON-like Python values (`dict`,
+//! `list`, `str`, `int`, `float`, `bool` and `None`), and an undefined result is `None`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use jsonata::compat;
+use jsonata::{FunctionContext, Value};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+create_exception!(
+    jsonata_rs,
+    JsonataError,
+    PyException,
+    "Raised when an expression can't be compiled or evaluated."
+);
+
+/// Compiles an expression, raising `JsonataError` if it isn't valid.
+#[pyfunction]
+fn compile(expression: &str) -> PyResult<Expression> {
+    Ok(Expression {
+        inner: compat::jsonata(expression).map_err(to_py_err)?,
+        functions: HashMap::new(),
+    })
+}
+
+/// A compiled expression along with the variables and functions assigned to it.
+#[pyclass(module = "jsonata_rs")]
+struct Expression {
+    inner: compat::Expression,
+    functions: HashMap<String, Py<PyAny>>,
+}
+
+#[pymethods]
+impl Expression {
+    /// Binds a variable for every subsequent evaluation.
+    fn assign(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.assign(name, py_to_json(value)?);
+        Ok(())
+    }
+
+    /// Registers a Python callable for every subsequent evaluation. It's called with its
+    /// arguments converted to Python values, and its result is converted back.
+    fn register_function(&mut self, name: &str, implementation: Bound<'_, PyAny>) -> PyResult<()> {
+        if !implementation.is_callable() {
+            return Err(PyTypeError::new_err("implementation must be callable"));
+        }
+
+        // Higher-order functions like `$map` pass as many arguments as the function takes
+        let arity = implementation
+            .py()
+            .import("inspect")?
+            .call_method1("signature", (&implementation,))
+            .and_then(|signature| signature.getattr("parameters")?.len())
+            .unwrap_or(1);
+
+        self.functions
+            .insert(name.to_string(), implementation.unbind());
+        self.inner
+            .register_function(name, arity, call_python_function);
+
+        Ok(())
+    }
+
+    /// Evaluates the expression, returning `None` if the result is undefined. Without an input,
+    /// or with `None`, the input is undefined, as when jsonata-js is given no input. The bindings
+    /// are only used for this evaluation. The GIL is released while evaluating, except while calling
+    /// registered functions.
+    #[pyo3(signature = (input=None, bindings=None))]
+    fn evaluate(
+        &self,
+        py: Python<'_>,
+        input: Option<&Bound<'_, PyAny>>,
+        bindings: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        let input = input.map(py_to_json).transpose()?;
+        let bindings = match bindings {
+            Some(bindings) => match py_to_json(bindings.as_any())? {
+                serde_json::Value::Object(bindings) => Some(bindings),
+                _ => unreachable!("A dict converts to an object"),
+            },
+            None => None,
+        };
+
+        let previous = SCOPE.with(|scope| {
+            scope.replace(Some(Scope {
+                functions: &self.functions,
+                error: None,
+            }))
+        });
+
+        let inner = &self.inner;
+        let result = py.allow_threads(|| inner.evaluate(input.as_ref(), bindings.as_ref()));
+
+        // Restore the enclosing scope, in case this evaluation was started by a registered
+        // function of another expression
+        let scope = SCOPE.with(|scope| scope.replace(previous));
+        if let Some(error) = scope.and_then(|scope| scope.error) {
+            return Err(error);
+        }
+
+        match result.map_err(to_py_err)? {
+            Some(result) => json_to_py(py, &result),
+            None => Ok(py.None()),
+        }
+    }
+}
+
+/// What a registered function needs from the evaluation which is calling it.
+struct Scope {
+    functions: *const HashMap<String, Py<PyAny>>,
+    // The exception raised by a registered function, which is reraised as is rather than as a
+    // `JsonataError`
+    error: Option<PyErr>,
+}
+
+thread_local! {
+    static SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+}
+
+// Every registered function is bound to this, which finds the Python callable by the name it was
+// called with.
+fn call_python_function<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> jsonata::Result<&'a Value<'a>> {
+    let functions = SCOPE.with(|scope| {
+        scope
+            .borrow()
+            .as_ref()
+            .expect("Registered function called outside of an evaluation")
+            .functions
+    });
+
+    // SAFETY: the scope only exists for the duration of `Expression::evaluate`, which borrows the
+    // functions. The scope isn't borrowed while calling the function, as it may evaluate another
+    // expression.
+    let functions = unsafe { &*functions };
+
+    let result = Python::with_gil(|py| {
+        let function = functions
+            .get(context.name)
+            .ok_or_else(|| JsonataError::new_err(format!("Unknown function `{}`", context.name)))?;
+
+        let args = args
+            .members()
+            .map(|arg| {
+                let arg: serde_json::Value = arg.deserialize().map_err(to_py_err)?;
+                json_to_py(py, &arg)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let result = function.call1(py, PyTuple::new(py, args)?)?;
+        py_to_json(result.bind(py))
+    });
+
+    match result {
+        Ok(value) => Ok(Value::from_json(context.arena, &value)),
+        Err(error) => {
+            let message = error.to_string();
+            SCOPE.with(|scope| {
+                if let Some(scope) = scope.borrow_mut().as_mut() {
+                    scope.error.get_or_insert(error);
+                }
+            });
+            Err(jsonata::Error::D3137Error(message))
+        }
+    }
+}
+
+fn py_to_json(value: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    if value.is_none() {
+        Ok(serde_json::Value::Null)
+    } else if let Ok(b) = value.downcast::<PyBool>() {
+        Ok(serde_json::Value::Bool(b.is_true()))
+    } else if let Ok(n) = value.downcast::<PyInt>() {
+        match n.extract::<i64>() {
+            Ok(n) => Ok(n.into()),
+            Err(_) => Ok(n.extract::<f64>()?.into()),
+        }
+    } else if let Ok(n) = value.downcast::<PyFloat>() {
+        serde_json::Number::from_f64(n.value())
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| PyValueError::new_err(format!("{} can't be converted to JSON", n)))
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        Ok(serde_json::Value::String(s.to_cow()?.into_owned()))
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut object = serde_json::Map::with_capacity(dict.len());
+        for (key, value) in dict.iter() {
+            let key = key
+                .downcast::<PyString>()
+                .map_err(|_| PyTypeError::new_err("dict keys must be strings"))?;
+            object.insert(key.to_cow()?.into_owned(), py_to_json(&value)?);
+        }
+        Ok(serde_json::Value::Object(object))
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        list.iter().map(|item| py_to_json(&item)).collect()
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        tuple.iter().map(|item| py_to_json(&item)).collect()
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "{} can't be converted to JSON",
+            value.get_type().name()?
+        )))
+    }
+}
+
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+    Ok(match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any().unbind(),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(n) => n.into_pyobject(py)?.into_any().unbind(),
+            None => n.as_f64().into_pyobject(py)?.into_any().unbind(),
+        },
+        serde_json::Value::String(s) => PyString::new(py, s).into_any().unbind(),
+        serde_json::Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any().unbind()
+        }
+        serde_json::Value::Object(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(key, json_to_py(py, value)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+fn to_py_err(error: jsonata::Error) -> PyErr {
+    JsonataError::new_err(error.to_string())
+}
+
+#[pymodule]
+fn jsonata_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compile, m)?)?;
+    m.add_class::<Expression>()?;
+    m.add("JsonataError", m.py().get_type::<JsonataError>())?;
+    Ok(())
+}
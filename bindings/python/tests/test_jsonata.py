@@ -0,0 +1,78 @@
+import threading
+import unittest
+
+import jsonata_rs
+
+
+class ExpressionTest(unittest.TestCase):
+    def test_evaluate_with_assigned_variables_and_bindings(self):
+        expression = jsonata_rs.compile("$sum(items.price) * $rate + $bonus")
+        expression.assign("rate", 2)
+        expression.assign("bonus", 100)
+
+        data = {"items": [{"price": 1}, {"price": 2.5}]}
+        self.assertEqual(expression.evaluate(data, {"bonus": 1}), 8)
+        self.assertEqual(expression.evaluate(data), 107)
+
+    def test_results_are_python_values(self):
+        expression = jsonata_rs.compile('{"a": [1, 2.5, "s", true, null], "b": {"c": $}}')
+        self.assertEqual(
+            expression.evaluate("x"),
+            {"a": [1, 2.5, "s", True, None], "b": {"c": "x"}},
+        )
+
+    def test_undefined_result(self):
+        self.assertIsNone(jsonata_rs.compile("missing").evaluate({}))
+
+    def test_no_input_is_undefined(self):
+        expression = jsonata_rs.compile("$exists($)")
+        self.assertIs(expression.evaluate(), False)
+        self.assertIs(expression.evaluate(None), False)
+        self.assertIs(expression.evaluate({}), True)
+
+    def test_registered_functions(self):
+        expression = jsonata_rs.compile("$map(items, $double)")
+        expression.register_function("double", lambda n, i: n * 2 + i)
+        self.assertEqual(expression.evaluate({"items": [1, 2, 3]}), [2, 5, 8])
+
+    def test_exceptions_from_registered_functions_are_reraised(self):
+        def fail():
+            raise KeyError("boom")
+
+        expression = jsonata_rs.compile("$fail()")
+        expression.register_function("fail", fail)
+        with self.assertRaises(KeyError):
+            expression.evaluate()
+
+    def test_nested_evaluation_from_a_registered_function(self):
+        inner = jsonata_rs.compile("$ * 10")
+        outer = jsonata_rs.compile("$inner(n) + 1")
+        outer.register_function("inner", inner.evaluate)
+        self.assertEqual(outer.evaluate({"n": 4}), 41)
+
+    def test_errors(self):
+        with self.assertRaisesRegex(jsonata_rs.JsonataError, "S0211"):
+            jsonata_rs.compile("(")
+        with self.assertRaisesRegex(jsonata_rs.JsonataError, "T2001"):
+            jsonata_rs.compile('"a" - 1').evaluate()
+        with self.assertRaises(TypeError):
+            jsonata_rs.compile("$").evaluate(object())
+
+    def test_concurrent_evaluation(self):
+        expression = jsonata_rs.compile("$sum($) * 2")
+        results = [None] * 8
+
+        def run(n):
+            results[n] = expression.evaluate(list(range(n)))
+
+        threads = [threading.Thread(target=run, args=(n,)) for n in range(8)]
+        for thread in threads:
+            thread.start()
+        for thread in threads:
+            thread.join()
+
+        self.assertEqual(results, [sum(range(n)) * 2 for n in range(8)])
+
+
+if __name__ == "__main__":
+    unittest.main()
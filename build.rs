@@ -41,6 +41,19 @@ fn main() {
             .unwrap();
         }
     }
+
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+#[cfg(feature = "capi")]
+fn generate_header() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("Failed to read cbindgen.toml");
+    cbindgen::generate_with_config(&crate_dir, config)
+        .expect("Failed to generate the C header")
+        .write_to_file(Path::new(&crate_dir).join("include/jsonata.h"));
 }
 
 fn get_test_resources(pattern: &str) -> Vec<String> {
@@ -0,0 +1,60 @@
+#ifndef JSONATA_H
+#define JSONATA_H
+
+/* Generated by the jsonata-rs build with the `capi` feature, don't edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A compiled expression, created by `jsonata_compile` and released by `jsonata_free`.
+typedef struct JsonataExpression JsonataExpression;
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Compiles an expression.
+//
+// Returns NULL if the expression isn't valid, in which case `error`, if it isn't NULL, is set to
+// a description of the problem.
+//
+// # Safety
+//
+// `expr` must be a valid, NUL-terminated string, and `error` must be NULL or valid for writes.
+JsonataExpression *jsonata_compile(const char *expr, char **error);
+
+// Evaluates an expression against `input`, which is JSON text or NULL for no input, and
+// returns the result as JSON text.
+//
+// Returns NULL if the evaluation fails, in which case `error`, if it isn't NULL, is set to a
+// description of the problem. An undefined result is also NULL, but leaves `error` as it was.
+//
+// # Safety
+//
+// `expression` must have been returned by `jsonata_compile` and not yet freed, `input` must be
+// NULL or a valid, NUL-terminated string, and `error` must be NULL or valid for writes.
+char *jsonata_evaluate(const JsonataExpression *expression, const char *input, char **error);
+
+// Releases an expression returned by `jsonata_compile`. Does nothing if it's NULL.
+//
+// # Safety
+//
+// `expression` must be NULL, or have been returned by `jsonata_compile` and not yet freed.
+void jsonata_free(JsonataExpression *expression);
+
+// Releases a result or error returned by this library. Does nothing if it's NULL.
+//
+// # Safety
+//
+// `string` must be NULL, or have been returned by this library and not yet freed.
+void jsonata_free_string(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* JSONATA_H */
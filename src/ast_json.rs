@@ -0,0 +1,340 @@
+use serde_json::{json, Map, Value as Json};
+
+use crate::evaluator::integer::Integer;
+use crate::parser::ast::{Ast, AstKind, BinaryOp, Object, UnaryOp};
+use crate::{CompiledExpression, JsonAta};
+
+impl CompiledExpression {
+    /// The parsed expression as JSON, in the shape of the AST jsonata.js produces, for tools
+    /// which are written in other languages or already work with that AST:
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// # use serde_json::json;
+    /// let expression = CompiledExpression::new("$x + 1")?;
+    /// assert_eq!(
+    ///     expression.ast_json(),
+    ///     json!({
+    ///         "type": "binary",
+    ///         "value": "+",
+    ///         "lhs": {"type": "variable", "value": "x", "position": 0},
+    ///         "rhs": {"type": "number", "value": 1, "position": 5},
+    ///         "position": 3
+    ///     })
+    /// );
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Positions are the index of the char each node starts at, rather than the one after its
+    /// token as in jsonata.js. Like the types of the `unstable-ast` feature, the shape isn't part of the
+    /// stable API.
+    pub fn ast_json(&self) -> Json {
+        node(&self.ast)
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::ast_json`].
+    pub fn ast_json(&self) -> Json {
+        node(&self.ast)
+    }
+}
+
+fn node(ast: &Ast) -> Json {
+    let mut fields = Map::new();
+    let mut set = |key: &str, value: Json| {
+        fields.insert(key.to_string(), value);
+    };
+
+    match ast.kind {
+        AstKind::Empty => set("type", json!("empty")),
+        AstKind::Null => {
+            set("type", json!("value"));
+            set("value", Json::Null);
+        }
+        AstKind::Bool(b) => {
+            set("type", json!("value"));
+            set("value", json!(b));
+        }
+        AstKind::String(ref s) => {
+            set("type", json!("string"));
+            set("value", json!(s));
+        }
+        AstKind::Number(n) => {
+            set("type", json!("number"));
+            set("value", json!(n));
+        }
+        AstKind::Integer(n) => {
+            set("type", json!("number"));
+            set(
+                "value",
+                match n {
+                    Integer::Signed(n) => json!(n),
+                    Integer::Unsigned(n) => json!(n),
+                },
+            );
+        }
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(n, _) => {
+            set("type", json!("number"));
+            set("value", json!(n));
+        }
+        AstKind::Name(ref name) => {
+            set("type", json!("name"));
+            set("value", json!(name));
+        }
+        AstKind::Var(ref name) => {
+            set("type", json!("variable"));
+            set("value", json!(name));
+        }
+        AstKind::Unary(UnaryOp::Minus(ref expression)) => {
+            set("type", json!("unary"));
+            set("value", json!("-"));
+            set("expression", node(expression));
+        }
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref expressions)) => {
+            set("type", json!("unary"));
+            set("value", json!("["));
+            set("expressions", nodes(expressions));
+        }
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref object)) => {
+            set("type", json!("unary"));
+            set("value", json!("{"));
+            set("lhs", pairs(object));
+        }
+        AstKind::Binary(ref op, ref lhs, ref rhs) => {
+            let kind = match op {
+                BinaryOp::Bind => "bind",
+                BinaryOp::Apply => "apply",
+                _ => "binary",
+            };
+            set("type", json!(kind));
+            set("value", json!(op.to_string()));
+            set("lhs", node(lhs));
+            set("rhs", node(rhs));
+        }
+        AstKind::GroupBy(ref expression, ref object) => {
+            set("type", json!("group"));
+            set("expression", node(expression));
+            set("lhs", pairs(object));
+        }
+        AstKind::OrderBy(ref expression, ref terms) => {
+            set("type", json!("sort"));
+            set("expression", node(expression));
+            set("terms", sort_terms(terms));
+        }
+        AstKind::Block(ref expressions) => {
+            set("type", json!("block"));
+            set("expressions", nodes(expressions));
+        }
+        AstKind::Wildcard => {
+            set("type", json!("wildcard"));
+            set("value", json!("*"));
+        }
+        AstKind::Descendent => {
+            set("type", json!("descendant"));
+            set("value", json!("**"));
+        }
+        AstKind::Parent => set("type", json!("parent")),
+        AstKind::Function {
+            ref proc,
+            ref args,
+            is_partial,
+            ..
+        } => {
+            set(
+                "type",
+                json!(if is_partial { "partial" } else { "function" }),
+            );
+            set("value", json!("("));
+            set("procedure", node(proc));
+            set("arguments", nodes(args));
+        }
+        AstKind::PartialArg => {
+            set("type", json!("operator"));
+            set("value", json!("?"));
+        }
+        AstKind::Lambda {
+            ref args,
+            ref body,
+            thunk,
+            ref signature,
+            ..
+        } => {
+            set("type", json!("lambda"));
+            set("arguments", nodes(args));
+            if let Some(signature) = signature {
+                set("signature", json!(signature.definition()));
+            }
+            set("body", node(body));
+            if thunk {
+                set("thunk", json!(true));
+            }
+        }
+        AstKind::Ternary {
+            ref cond,
+            ref truthy,
+            ref falsy,
+        } => {
+            set("type", json!("condition"));
+            set("condition", node(cond));
+            set("then", node(truthy));
+            if let Some(falsy) = falsy {
+                set("else", node(falsy));
+            }
+        }
+        AstKind::Transform {
+            ref pattern,
+            ref update,
+            ref delete,
+        } => {
+            set("type", json!("transform"));
+            set("pattern", node(pattern));
+            set("update", node(update));
+            if let Some(delete) = delete {
+                set("delete", node(delete));
+            }
+        }
+        AstKind::Path(ref steps) => {
+            set("type", json!("path"));
+            set("steps", nodes(steps));
+        }
+        AstKind::Filter(ref expr) => {
+            set("type", json!("filter"));
+            set("expr", node(expr));
+        }
+        AstKind::Sort(ref terms) => {
+            set("type", json!("sort"));
+            set("terms", sort_terms(terms));
+        }
+        AstKind::Index(ref name) => {
+            set("type", json!("index"));
+            set("value", json!(name));
+        }
+    }
+
+    set("position", json!(ast.char_index));
+    if ast.keep_array {
+        set("keepArray", json!(true));
+    }
+    if ast.cons_array {
+        set("consarray", json!(true));
+    }
+    if ast.keep_singleton_array {
+        set("keepSingletonArray", json!(true));
+    }
+    if let Some((position, ref object)) = ast.group_by {
+        set("group", json!({"lhs": pairs(object), "position": position}));
+    }
+    if let Some(ref predicates) = ast.predicates {
+        set("predicate", nodes(predicates));
+    }
+    if let Some(ref stages) = ast.stages {
+        set("stages", nodes(stages));
+    }
+    if ast.tuple {
+        set("tuple", json!(true));
+    }
+    if let Some(ref index) = ast.index {
+        set("index", json!(index));
+    }
+    if let Some(ref focus) = ast.focus {
+        set("focus", json!(focus));
+    }
+
+    Json::Object(fields)
+}
+
+fn nodes(asts: &[Ast]) -> Json {
+    Json::Array(asts.iter().map(node).collect())
+}
+
+fn pairs(object: &Object) -> Json {
+    Json::Array(
+        object
+            .iter()
+            .map(|(key, value)| json!([node(key), node(value)]))
+            .collect(),
+    )
+}
+
+fn sort_terms(terms: &[(Ast, bool)]) -> Json {
+    Json::Array(
+        terms
+            .iter()
+            .map(|(expression, descending)| {
+                json!({"descending": descending, "expression": node(expression)})
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("'a'", json!({"type": "string", "value": "a", "position": 0}))]
+    #[test_case("null", json!({"type": "value", "value": null, "position": 0}))]
+    #[test_case("-x", json!({
+        "type": "unary",
+        "value": "-",
+        "expression": {
+            "type": "path",
+            "steps": [{"type": "name", "value": "x", "position": 1}],
+            "position": 1
+        },
+        "position": 0
+    }))]
+    #[test_case("$f(1, ?)", json!({
+        "type": "partial",
+        "value": "(",
+        "procedure": {"type": "variable", "value": "f", "position": 0},
+        "arguments": [
+            {"type": "number", "value": 1, "position": 3},
+            {"type": "operator", "value": "?", "position": 6}
+        ],
+        "position": 2
+    }))]
+    #[test_case("$x := [1]", json!({
+        "type": "bind",
+        "value": ":=",
+        "lhs": {"type": "variable", "value": "x", "position": 0},
+        "rhs": {
+            "type": "unary",
+            "value": "[",
+            "expressions": [{"type": "number", "value": 1, "position": 7}],
+            "position": 6
+        },
+        "position": 3
+    }))]
+    #[test_case("function($a)<n:n>{ $a ? 1 }", json!({
+        "type": "lambda",
+        "arguments": [{"type": "variable", "value": "a", "position": 9}],
+        "signature": "<n:n>",
+        "body": {
+            "type": "condition",
+            "condition": {"type": "variable", "value": "a", "position": 19},
+            "then": {"type": "number", "value": 1, "position": 24},
+            "position": 22
+        },
+        "position": 8
+    }))]
+    fn shapes(expr: &str, expected: Json) {
+        let expression = CompiledExpression::new(expr).unwrap();
+        assert_eq!(expression.ast_json(), expected);
+    }
+
+    #[test]
+    fn steps() {
+        let expression = CompiledExpression::new("orders[total > 1]^(>total)").unwrap();
+        let ast = expression.ast_json();
+        let steps = ast["steps"].as_array().unwrap();
+        assert_eq!(ast["type"], "path");
+        assert_eq!(steps[0]["value"], "orders");
+        assert_eq!(steps[0]["stages"][0]["type"], "filter");
+        assert_eq!(steps[1]["type"], "sort");
+        assert_eq!(steps[1]["terms"][0]["descending"], true);
+    }
+}
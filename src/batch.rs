@@ -0,0 +1,177 @@
+use bumpalo::Bump;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{CompiledExpression, OwnedValue, Result};
+
+/// The results of evaluating an expression against each of a sequence of inputs, see
+/// [`CompiledExpression::evaluate_batch`].
+pub struct Batch<'e, I> {
+    expression: &'e CompiledExpression,
+    inputs: I,
+    arena: Bump,
+}
+
+impl CompiledExpression {
+    /// Evaluates the expression against each of the JSON `inputs` as they're iterated over,
+    /// yielding a result for each input in turn, such as for each document of an ETL job:
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("$sum(items.price)")?;
+    /// let inputs = [r#"{"items": [{"price": 1}]}"#, "{", r#"{"items": [{"price": 2}, {"price": 3}]}"#];
+    ///
+    /// let totals: Vec<_> = expression.evaluate_batch(inputs).map(|total| total.map(|total| total.as_f64())).collect();
+    /// assert_eq!(totals[0], Ok(Some(1.0)));
+    /// assert_eq!(totals[1].as_ref().unwrap_err().code(), "S0211");
+    /// assert_eq!(totals[2], Ok(Some(5.0)));
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// The expression is only compiled once, and each input is evaluated in the same arena, which
+    /// is reset between them, so its memory is reused rather than allocated again. The results
+    /// are [`OwnedValue`]s, as they outlive the evaluation. An input which fails doesn't stop the
+    /// rest.
+    pub fn evaluate_batch<I>(&self, inputs: I) -> Batch<'_, I::IntoIter>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        Batch {
+            expression: self,
+            inputs: inputs.into_iter(),
+            arena: Bump::new(),
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl CompiledExpression {
+    /// Evaluates the expression against each of the JSON `inputs` like
+    /// [`CompiledExpression::evaluate_batch`], but on the threads of rayon's pool, returning the
+    /// results in the order of the inputs:
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("id & ': ' & $sum(items.price)")?;
+    /// let inputs: Vec<String> = (0..1000)
+    ///     .map(|id| format!(r#"{{"id": "{}", "items": [{{"price": 2}}, {{"price": 3}}]}}"#, id))
+    ///     .collect();
+    ///
+    /// let results = expression.evaluate_batch_parallel(&inputs);
+    /// assert_eq!(results[999].as_ref().unwrap().as_str(), Some("999: 5"));
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// The pool is rayon's global pool, unless it's called within another with
+    /// `ThreadPool::install`. Each thread evaluates in an arena of its own which it reuses for
+    /// the inputs it's given. The inputs and the results are all in memory at once, so a job over
+    /// more records than fit can evaluate them a chunk at a time.
+    pub fn evaluate_batch_parallel<I>(&self, inputs: I) -> Vec<Result<OwnedValue>>
+    where
+        I: IntoParallelIterator,
+        I::Iter: IndexedParallelIterator,
+        I::Item: AsRef<str>,
+    {
+        inputs
+            .into_par_iter()
+            .map_init(Bump::new, |arena, input| {
+                self.evaluate_in(Some(input.as_ref()), arena, |result| {
+                    OwnedValue::from(result)
+                })
+            })
+            .collect()
+    }
+}
+
+impl<I> Iterator for Batch<'_, I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = Result<OwnedValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.inputs.next()?;
+        Some(
+            self.expression
+                .evaluate_in(Some(input.as_ref()), &mut self.arena, |result| {
+                    OwnedValue::from(result)
+                }),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Integer;
+
+    #[test]
+    fn results_in_order() {
+        let expression = CompiledExpression::new("{'id': id, 'n': $count(items)}").unwrap();
+        let inputs = (0..100).map(|id| format!(r#"{{"id": {}, "items": [{}]}}"#, id, id));
+        let results: Vec<String> = expression
+            .evaluate_batch(inputs)
+            .map(|result| result.unwrap().serialize(false))
+            .collect();
+        assert_eq!(results.len(), 100);
+        assert_eq!(results[0], r#"{"id":0,"n":1}"#);
+        assert_eq!(results[99], r#"{"id":99,"n":1}"#);
+    }
+
+    #[test]
+    fn reuses_the_arena() {
+        let expression = CompiledExpression::new("[1..1000].($ * 2)").unwrap();
+        let arena = Bump::new();
+        expression.evaluate(Some("{}"), &arena).unwrap();
+        let once = arena.allocated_bytes();
+
+        let mut batch = expression.evaluate_batch(["{}"; 20]);
+        for result in batch.by_ref() {
+            result.unwrap();
+        }
+        assert!(batch.arena.allocated_bytes() <= 2 * once);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_results_in_order() {
+        let expression = CompiledExpression::new("$number(n) * 2").unwrap();
+        let inputs: Vec<String> = (0..10_000)
+            .map(|n| match n {
+                5000 => r#"{"n": "x"}"#.to_string(),
+                n => format!(r#"{{"n": "{}"}}"#, n),
+            })
+            .collect();
+
+        let sequential: Vec<_> = expression.evaluate_batch(&inputs).collect();
+        let parallel = expression.evaluate_batch_parallel(&inputs);
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel[5000].as_ref().unwrap_err().code(), "D3030");
+        assert_eq!(parallel[9999].as_ref().unwrap().as_f64(), Some(19998.0));
+    }
+
+    #[test]
+    fn errors_are_per_input() {
+        let expression = CompiledExpression::new("$number(n)").unwrap();
+        let codes: Vec<_> = expression
+            .evaluate_batch(vec![
+                r#"{"n": "1"}"#.to_string(),
+                r#"{"n": "x"}"#.to_string(),
+            ])
+            .map(|result| result.map_err(|e| e.code().to_string()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                Ok(OwnedValue::Integer(Integer::Signed(1))),
+                Err("D3030".to_string())
+            ]
+        );
+    }
+}
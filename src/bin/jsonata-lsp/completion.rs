@@ -0,0 +1,98 @@
+/// The variables bound at `char_index` by the text before it: those bound with `:=` in the
+/// enclosing blocks, and the parameters of the enclosing lambdas. This works on the text rather
+/// than the AST, as the expression usually doesn't parse while it's being typed.
+pub fn variables_in_scope(text: &str, char_index: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().take(char_index).collect();
+    // The variables bound inside each open bracket, with the outermost first
+    let mut scopes: Vec<Vec<String>> = vec![vec![]];
+    // The parameters of a lambda, which are bound once its body opens
+    let mut params: Option<Vec<String>> = None;
+    let mut position = 0;
+
+    while position < chars.len() {
+        match chars[position] {
+            quote @ ('"' | '\'' | '`') => {
+                position += 1;
+                while position < chars.len() && chars[position] != quote {
+                    if chars[position] == '\\' {
+                        position += 1;
+                    }
+                    position += 1;
+                }
+            }
+            '/' if chars.get(position + 1) == Some(&'*') => {
+                position += 2;
+                while position < chars.len()
+                    && !(chars[position] == '*' && chars.get(position + 1) == Some(&'/'))
+                {
+                    position += 1;
+                }
+                position += 1;
+            }
+            '(' | '[' => scopes.push(vec![]),
+            '{' => scopes.push(params.take().unwrap_or_default()),
+            ')' | ']' | '}' if scopes.len() > 1 => {
+                scopes.pop();
+            }
+            '$' => {
+                let start = position + 1;
+                let end = identifier_end(&chars, start);
+                let name: String = chars[start..end].iter().collect();
+                position = end;
+
+                let rest = chars[end..].iter().skip_while(|c| c.is_whitespace());
+                if rest.take(2).eq([':', '='].iter()) && !name.is_empty() {
+                    scopes.last_mut().unwrap().push(name);
+                } else if let Some(params) = params.as_mut() {
+                    params.push(name);
+                }
+                continue;
+            }
+            c if c.is_alphabetic() || c == 'λ' => {
+                let end = identifier_end(&chars, position);
+                let word: String = chars[position..end].iter().collect();
+                if word == "function" || word == "λ" {
+                    params = Some(vec![]);
+                }
+                position = end;
+                continue;
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+
+    let mut variables: Vec<String> = vec![];
+    for name in scopes.into_iter().rev().flatten() {
+        if !variables.contains(&name) {
+            variables.push(name);
+        }
+    }
+    variables
+}
+
+fn identifier_end(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|&c| !(c.is_alphanumeric() || c == '_' || c == 'λ'))
+        .map_or(chars.len(), |length| start + length)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("($a := 1; $b := 2; ", &["a", "b"])]
+    #[test_case("($a := 1); ", &[])]
+    #[test_case("($a := 1; ($b := 2); ", &["a"])]
+    #[test_case("$f := function($x, $y) { ", &["x", "y", "f"])]
+    #[test_case("$f := function($x) { $x }; ", &["f"])]
+    #[test_case("$f := λ($x) { ", &["x", "f"])]
+    #[test_case("'$a := 1'; /* $b := 2 */ ", &[])]
+    #[test_case("($a := 1; $a := 2; ", &["a"])]
+    fn variables(text: &str, expected: &[&str]) {
+        assert_eq!(variables_in_scope(text, text.chars().count()), expected);
+    }
+}
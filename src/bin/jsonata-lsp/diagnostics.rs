@@ -0,0 +1,111 @@
+use jsonata_rs::{CompiledExpression, PrecedenceWarnings};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::text::position;
+
+/// The syntax errors if the expression doesn't parse, otherwise the issues the linter and the
+/// precedence warnings find in it.
+pub fn diagnostics(text: &str) -> Vec<Diagnostic> {
+    let (compiled, errors) = CompiledExpression::new_recovering(text);
+    if !errors.is_empty() {
+        return errors
+            .into_iter()
+            .map(|error| Diagnostic {
+                // Errors without a position are reported at the start
+                range: range(text, error.char_index().unwrap_or(0)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(error.code().to_string())),
+                message: error.message(),
+                ..diagnostic()
+            })
+            .collect();
+    }
+
+    let lints = compiled.lint().into_iter().map(|lint| Diagnostic {
+        range: range(text, lint.char_index),
+        code: Some(NumberOrString::String(format!("{:?}", lint.kind))),
+        message: lint.message,
+        ..diagnostic()
+    });
+    let warnings = compiled
+        .precedence_warnings(&PrecedenceWarnings::default())
+        .into_iter()
+        .map(|warning| Diagnostic {
+            range: range(text, warning.char_index),
+            code: Some(NumberOrString::String(format!("{:?}", warning.kind))),
+            message: warning.message,
+            ..diagnostic()
+        });
+    lints.chain(warnings).collect()
+}
+
+fn diagnostic() -> Diagnostic {
+    Diagnostic {
+        severity: Some(DiagnosticSeverity::WARNING),
+        source: Some("jsonata".to_string()),
+        ..Default::default()
+    }
+}
+
+/// The range of the one character at `char_index`.
+fn range(text: &str, char_index: usize) -> Range {
+    Range::new(position(text, char_index), position(text, char_index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::Position;
+
+    use super::*;
+
+    #[test]
+    fn syntax_error() {
+        let diagnostics = diagnostics("a +\n  /* b");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            diagnostics[0].code,
+            Some(NumberOrString::String("S0106".to_string()))
+        );
+        assert_eq!(diagnostics[0].message, "Comment has no closing tag");
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 2));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let diagnostics = diagnostics("{'a': (1 + ), 'b': [2, 3}");
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code.clone().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                NumberOrString::String("S0211".to_string()),
+                NumberOrString::String("S0202".to_string())
+            ]
+        );
+        assert_eq!(diagnostics[1].range.start, Position::new(0, 24));
+    }
+
+    #[test]
+    fn lints_and_warnings() {
+        let diagnostics = diagnostics("items[true] and a or b");
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code.clone().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                NumberOrString::String("ConstantPredicate".to_string()),
+                NumberOrString::String("MixedAndOr".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(diagnostics("$sum(items.price)"), vec![]);
+    }
+}
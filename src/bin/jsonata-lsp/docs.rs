@@ -0,0 +1,142 @@
+use jsonata_rs::conformance::{self, Support};
+
+/// The signature and a summary of each built-in function, shown when hovering over a call.
+const FUNCTIONS: &[(&str, &str, &str)] = &[
+    // String functions
+    ("$string", "$string(arg, prettify)", "Casts `arg` to a string, serializing objects and arrays as JSON."),
+    ("$length", "$length(str)", "The number of characters in `str`."),
+    ("$substring", "$substring(str, start, length)", "The part of `str` starting at `start`, with at most `length` characters."),
+    ("$substringBefore", "$substringBefore(str, chars)", "The part of `str` before the first occurrence of `chars`."),
+    ("$substringAfter", "$substringAfter(str, chars)", "The part of `str` after the first occurrence of `chars`."),
+    ("$uppercase", "$uppercase(str)", "`str` with every character in upper case."),
+    ("$lowercase", "$lowercase(str)", "`str` with every character in lower case."),
+    ("$trim", "$trim(str)", "`str` without leading and trailing whitespace, and with runs of whitespace replaced by one space."),
+    ("$pad", "$pad(str, width, char)", "`str` padded to `width` characters with `char`, on the left if `width` is negative."),
+    ("$contains", "$contains(str, pattern)", "Whether `str` contains `pattern`."),
+    ("$split", "$split(str, separator, limit)", "The parts of `str` between each `separator`, at most `limit` of them."),
+    ("$join", "$join(array, separator)", "The strings in `array` joined with `separator`."),
+    ("$match", "$match(str, pattern, limit)", "The matches of the regular expression `pattern` in `str`."),
+    ("$replace", "$replace(str, pattern, replacement, limit)", "`str` with at most `limit` occurrences of `pattern` replaced by `replacement`."),
+    ("$eval", "$eval(expr, context)", "Evaluates the JSONata expression `expr` against `context`."),
+    ("$base64encode", "$base64encode(str)", "`str` encoded as base 64."),
+    ("$base64decode", "$base64decode(str)", "Decodes the base 64 string `str`."),
+    ("$encodeUrlComponent", "$encodeUrlComponent(str)", "`str` encoded as a URL component."),
+    ("$encodeUrl", "$encodeUrl(str)", "`str` encoded as a URL."),
+    ("$decodeUrlComponent", "$decodeUrlComponent(str)", "Decodes a URL component."),
+    ("$decodeUrl", "$decodeUrl(str)", "Decodes a URL."),
+    // Numeric functions
+    ("$formatNumber", "$formatNumber(number, picture, options)", "`number` formatted with the XPath picture string `picture`."),
+    ("$formatBase", "$formatBase(number, radix)", "`number` formatted as an integer in base `radix`."),
+    ("$formatInteger", "$formatInteger(number, picture)", "`number` formatted as an integer with the XPath picture string `picture`."),
+    ("$parseInteger", "$parseInteger(string, picture)", "Parses `string` as an integer formatted with the picture string `picture`."),
+    ("$number", "$number(arg)", "Casts `arg` to a number."),
+    ("$abs", "$abs(number)", "The absolute value of `number`."),
+    ("$floor", "$floor(number)", "`number` rounded down."),
+    ("$ceil", "$ceil(number)", "`number` rounded up."),
+    ("$round", "$round(number, precision)", "`number` rounded half to even, to `precision` decimal places."),
+    ("$power", "$power(base, exponent)", "`base` raised to the power of `exponent`."),
+    ("$sqrt", "$sqrt(number)", "The square root of `number`."),
+    ("$random", "$random()", "A random number between 0 (inclusive) and 1 (exclusive)."),
+    // Aggregation functions
+    ("$sum", "$sum(array)", "The sum of the numbers in `array`."),
+    ("$max", "$max(array)", "The largest number in `array`."),
+    ("$min", "$min(array)", "The smallest number in `array`."),
+    ("$average", "$average(array)", "The mean of the numbers in `array`."),
+    // Boolean functions
+    ("$boolean", "$boolean(arg)", "Casts `arg` to a boolean."),
+    ("$not", "$not(arg)", "The negation of `arg` cast to a boolean."),
+    ("$exists", "$exists(arg)", "Whether `arg` evaluates to a value."),
+    // Array functions
+    ("$count", "$count(array)", "The number of items in `array`."),
+    ("$append", "$append(array1, array2)", "The items of `array1` followed by the items of `array2`."),
+    ("$sort", "$sort(array, function)", "`array` sorted, by `function($l, $r)` returning whether `$l` goes after `$r` if it's given."),
+    ("$reverse", "$reverse(array)", "`array` in reverse order."),
+    ("$shuffle", "$shuffle(array)", "`array` in a random order."),
+    ("$distinct", "$distinct(array)", "`array` without duplicate values."),
+    ("$zip", "$zip(array1, ...)", "An array of arrays, each with the items at the same index in the arguments."),
+    // Object functions
+    ("$keys", "$keys(object)", "The keys of `object`, or of each object in an array."),
+    ("$lookup", "$lookup(object, key)", "The value of `key` in `object`, or in each object in an array."),
+    ("$spread", "$spread(object)", "An array of objects, each with one of the key/value pairs of `object`."),
+    ("$merge", "$merge(array)", "The objects in `array` merged into one."),
+    ("$sift", "$sift(object, function)", "The key/value pairs of `object` for which `function($v, $k)` is truthy."),
+    ("$each", "$each(object, function)", "The results of calling `function($v, $k)` for each key/value pair of `object`."),
+    ("$error", "$error(message)", "Raises an error with `message`."),
+    ("$assert", "$assert(condition, message)", "Raises an error with `message` if `condition` is false."),
+    ("$type", "$type(value)", "The type of `value`, such as `\"string\"` or `\"object\"`."),
+    // Date/time functions
+    ("$now", "$now(picture, timezone)", "The timestamp when evaluation started, as an ISO 8601 string."),
+    ("$millis", "$millis()", "The milliseconds since the Unix epoch when evaluation started."),
+    ("$fromMillis", "$fromMillis(number, picture, timezone)", "Formats the milliseconds since the Unix epoch as a timestamp."),
+    ("$toMillis", "$toMillis(timestamp, picture)", "Parses a timestamp as the milliseconds since the Unix epoch."),
+    // Higher-order functions
+    ("$map", "$map(array, function)", "The results of calling `function($v, $i, $a)` for each item of `array`."),
+    ("$filter", "$filter(array, function)", "The items of `array` for which `function($v, $i, $a)` is truthy."),
+    ("$single", "$single(array, function)", "The one item of `array` for which `function($v, $i, $a)` is truthy, or an error."),
+    ("$reduce", "$reduce(array, function, init)", "Folds `array` with `function($acc, $v)`, starting from `init` if it's given."),
+    // Extensions
+    ("$index", "$index(array, key, function)", "An object of the items of `array` keyed by `key`, or by `function($v)`."),
+    ("$joinOn", "$joinOn(left, right, function, kind)", "The pairs of items from `left` and `right` for which `function($l, $r)` is truthy."),
+    ("$memoize", "$memoize(function)", "`function`, remembering its result for each set of arguments."),
+    ("$mergePatch", "$mergePatch(original, updated)", "The JSON Merge Patch which turns `original` into `updated`."),
+    ("$pointer", "$pointer(value, pointer)", "The member of `value` at the JSON Pointer `pointer`, such as `\"/a.b/0\"`."),
+    ("$sha256", "$sha256(str)", "The SHA-256 digest of `str`, in hex."),
+    ("$hmacSha256", "$hmacSha256(str, key)", "The HMAC-SHA256 of `str` with `key`, in hex, as webhooks are usually signed."),
+    ("$md5", "$md5(str)", "The MD5 digest of `str`, in hex."),
+    ("$crc32", "$crc32(str)", "The CRC-32 checksum of `str`, as eight hex digits."),
+    ("$fetch", "$fetch(url, options)", "The JSON the host at `url` responds with, if the host allows the URL to be requested."),
+    ("$uuid", "$uuid(version)", "A random UUID, of version 4, or of version 7 if `version` is 7."),
+    ("$validate", "$validate(value, schema)", "The ways `value` doesn't conform to the JSON Schema `schema`, an empty array if it does."),
+];
+
+pub struct FunctionDoc {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub summary: &'static str,
+    pub support: Support,
+}
+
+/// The documentation for every built-in function the language has, with whether this build of
+/// the crate supports it.
+pub fn functions() -> Vec<FunctionDoc> {
+    let report = conformance::report();
+    FUNCTIONS
+        .iter()
+        .map(|&(name, signature, summary)| FunctionDoc {
+            name,
+            signature,
+            summary,
+            support: report.support(name).unwrap_or(Support::Missing),
+        })
+        .collect()
+}
+
+impl FunctionDoc {
+    /// The documentation as Markdown, for hovers and completions.
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!("```jsonata\n{}\n```\n\n{}", self.signature, self.summary);
+        if self.support == Support::Missing {
+            markdown.push_str("\n\n*Not supported by this build.*");
+        }
+        markdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_functions_are_documented() {
+        let documented = functions();
+        for feature in conformance::report().features() {
+            if feature.name.starts_with('$') && feature.support != Support::Missing {
+                assert!(
+                    documented.iter().any(|doc| doc.name == feature.name),
+                    "{} isn't documented",
+                    feature.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! A language server for JSONata expressions, offering diagnostics, hover documentation for the
+//! built-in functions and completion of functions and variables. It speaks LSP over stdin and
+//! stdout, so an editor can start it for files such as `*.jsonata`.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use jsonata_rs::conformance::Support;
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{Completion, HoverRequest, Request as _};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Documentation, Hover, HoverContents, HoverParams, HoverProviderCapability, MarkupContent,
+    MarkupKind, PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+
+mod completion;
+mod diagnostics;
+mod docs;
+mod text;
+
+use docs::FunctionDoc;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+fn main() -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        // The documents are short, so they're sent in full rather than as edits
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["$".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    // The server owns the connection, so that it's closed before waiting for the IO threads
+    Server {
+        connection,
+        documents: HashMap::new(),
+        functions: docs::functions(),
+    }
+    .run()?;
+
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server {
+    connection: Connection,
+    /// The text of each open document
+    documents: HashMap<Uri, String>,
+    functions: Vec<FunctionDoc>,
+}
+
+impl Server {
+    fn run(mut self) -> Result<(), BoxError> {
+        let receiver = self.connection.receiver.clone();
+        for message in &receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.request(request)?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(..) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, request: Request) -> Result<(), BoxError> {
+        let request = match request.extract::<HoverParams>(HoverRequest::METHOD) {
+            Ok((id, params)) => return self.respond(id, self.hover(params)),
+            Err(ExtractError::MethodMismatch(request)) => request,
+            Err(error) => return Err(error.into()),
+        };
+        let request = match request.extract::<CompletionParams>(Completion::METHOD) {
+            Ok((id, params)) => return self.respond(id, self.complete(params)),
+            Err(ExtractError::MethodMismatch(request)) => request,
+            Err(error) => return Err(error.into()),
+        };
+
+        let response = Response::new_err(
+            request.id,
+            lsp_server::ErrorCode::MethodNotFound as i32,
+            format!("Unsupported request: {}", request.method),
+        );
+        Ok(self.connection.sender.send(Message::Response(response))?)
+    }
+
+    fn respond(&self, id: RequestId, result: impl serde::Serialize) -> Result<(), BoxError> {
+        let response = Response::new_ok(id, result);
+        Ok(self.connection.sender.send(Message::Response(response))?)
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<(), BoxError> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.update(params.text_document.uri, params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // With full sync, the last change is the whole document
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(params.text_document.uri, change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Uri, text: String) -> Result<(), BoxError> {
+        let diagnostics = diagnostics::diagnostics(&text);
+        self.documents.insert(uri.clone(), text);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Uri, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<(), BoxError> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        Ok(self
+            .connection
+            .sender
+            .send(Message::Notification(notification))?)
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params;
+        let text = self.documents.get(&position.text_document.uri)?;
+        let (start, word) = text::word_at(text, text::char_index(text, position.position))?;
+        let function = self.functions.iter().find(|doc| doc.name == word)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(markdown(function.markdown())),
+            range: Some(Range::new(
+                text::position(text, start),
+                text::position(text, start + word.chars().count()),
+            )),
+        })
+    }
+
+    fn complete(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position;
+        let text = self.documents.get(&position.text_document.uri)?;
+        let char_index = text::char_index(text, position.position);
+
+        let variables = completion::variables_in_scope(text, char_index)
+            .into_iter()
+            .map(|name| CompletionItem {
+                label: format!("${}", name),
+                kind: Some(CompletionItemKind::VARIABLE),
+                ..Default::default()
+            });
+        let functions = self
+            .functions
+            .iter()
+            .filter(|doc| doc.support != Support::Missing)
+            .map(|doc| CompletionItem {
+                label: doc.name.to_string(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: Some(doc.signature.to_string()),
+                documentation: Some(Documentation::MarkupContent(markdown(doc.markdown()))),
+                ..Default::default()
+            });
+
+        Some(CompletionResponse::Array(
+            variables.chain(functions).collect(),
+        ))
+    }
+}
+
+fn markdown(value: String) -> MarkupContent {
+    MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    }
+}
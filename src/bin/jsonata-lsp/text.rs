@@ -0,0 +1,82 @@
+use lsp_types::Position;
+
+/// Converts an index in the chars of `text`, as used by the crate, to a position, whose character
+/// counts UTF-16 code units as LSP expects.
+pub fn position(text: &str, char_index: usize) -> Position {
+    let mut line = 0;
+    let mut character = 0;
+    for c in text.chars().take(char_index) {
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else {
+            character += c.len_utf16() as u32;
+        }
+    }
+    Position { line, character }
+}
+
+/// Converts a position to an index in the chars of `text`, clamped to the end of its line.
+pub fn char_index(text: &str, position: Position) -> usize {
+    let mut line = 0;
+    let mut character = 0;
+    for (index, c) in text.chars().enumerate() {
+        if line == position.line && (character >= position.character || c == '\n') {
+            return index;
+        }
+        if c == '\n' {
+            line += 1;
+            character = 0;
+        } else if line == position.line {
+            character += c.len_utf16() as u32;
+        }
+    }
+    text.chars().count()
+}
+
+/// The identifier around `char_index`, such as `$sum` or `price`, with the index it starts at.
+pub fn word_at(text: &str, char_index: usize) -> Option<(usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let start = (0..char_index.min(chars.len()))
+        .rev()
+        .take_while(|&i| is_word(chars[i]))
+        .last()
+        .unwrap_or(char_index);
+    let end = (char_index..chars.len())
+        .find(|&i| !is_word(chars[i]))
+        .unwrap_or(chars.len());
+    (start < end).then(|| (start, chars[start..end].iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("a.b", 2, 0, 2)]
+    #[test_case("a\n  b", 4, 1, 2)]
+    #[test_case("'😀' & x", 5, 0, 6)]
+    fn round_trip(text: &str, index: usize, line: u32, character: u32) {
+        let expected = Position { line, character };
+        assert_eq!(position(text, index), expected);
+        assert_eq!(char_index(text, expected), index);
+    }
+
+    #[test]
+    fn past_the_end_of_a_line() {
+        assert_eq!(char_index("ab\ncd", Position::new(0, 10)), 2);
+        assert_eq!(char_index("ab\ncd", Position::new(5, 0)), 5);
+    }
+
+    #[test_case("$sum(a)", 2, Some((0, "$sum")))]
+    #[test_case("$sum(a)", 4, Some((0, "$sum")))]
+    #[test_case("a + b", 2, None)]
+    fn words(text: &str, index: usize, expected: Option<(usize, &str)>) {
+        assert_eq!(
+            word_at(text, index),
+            expected.map(|(start, word)| (start, word.to_string()))
+        );
+    }
+}
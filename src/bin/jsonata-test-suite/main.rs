@@ -0,0 +1,71 @@
+//! Runs the test suite of jsonata-js against the engine, printing how many cases of each group
+//! passed. See `jsonata_rs::test_suite`.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use jsonata_rs::test_suite;
+
+/// Runs the jsonata-js test suite and reports the cases that pass in each group
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Opt {
+    /// The test suite directory, `test/test-suite` in a checkout of jsonata-js
+    suite: PathBuf,
+
+    /// Only run these groups
+    #[arg(short, long = "group", value_name = "GROUP")]
+    groups: Vec<String>,
+
+    /// Print each case that failed, with its expression and what went wrong
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    let report = if opt.groups.is_empty() {
+        test_suite::run(&opt.suite)
+    } else {
+        opt.groups
+            .iter()
+            .map(|group| {
+                test_suite::run_group(
+                    opt.suite.join("groups").join(group),
+                    opt.suite.join("datasets"),
+                )
+            })
+            .collect::<Result<_, _>>()
+            .map(|groups| test_suite::Report { groups })
+    };
+    let report = match report {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("Could not read the test suite: {}", error);
+            std::process::exit(2);
+        }
+    };
+
+    print!("{}", report);
+    if opt.verbose {
+        for group in report.groups.iter() {
+            for failure in group.failures.iter() {
+                println!();
+                println!("{}/{}: {}", group.name, failure.case, failure.expr);
+                println!("  {}", failure.reason);
+            }
+        }
+    }
+
+    let passing = report.groups.iter().filter(|group| group.is_passing());
+    println!();
+    println!(
+        "{} of {} cases passed, {} of {} groups entirely",
+        report.passed(),
+        report.cases(),
+        passing.count(),
+        report.groups.len()
+    );
+}
@@ -0,0 +1,178 @@
+//! `jsonata bench`, which evaluates an expression many times and reports how long compiling it,
+//! parsing the input and evaluating it took, and how much of the arena it used.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use bumpalo::Bump;
+use jsonata_rs::{Documents, JsonAta, Result, Value};
+
+/// The percentiles the times are reported at, and their headings.
+const PERCENTILES: [(&str, f64); 4] = [("p50", 50.0), ("p90", 90.0), ("p99", 99.0), ("max", 100.0)];
+
+pub struct Report {
+    iterations: u64,
+    compile: Timings,
+    parse: Option<Timings>,
+    evaluate: Timings,
+    peak_arena: usize,
+}
+
+/// How long each iteration of a phase took, fastest first.
+struct Timings(Vec<Duration>);
+
+impl Timings {
+    fn new(mut durations: Vec<Duration>) -> Self {
+        durations.sort();
+        Self(durations)
+    }
+
+    /// The time `percentile` percent of the iterations took at most, by the nearest rank.
+    fn percentile(&self, percentile: f64) -> Duration {
+        let rank = (percentile / 100.0 * self.0.len() as f64).ceil() as usize;
+        self.0[rank.clamp(1, self.0.len()) - 1]
+    }
+}
+
+/// Compiles `expr`, parses `input` and evaluates the expression against it `iterations` times,
+/// each in an arena which is reset after it.
+pub fn run(expr: &str, input: Option<&str>, iterations: u64) -> Result<Report> {
+    let mut compile = vec![];
+    let mut parse = vec![];
+    let mut evaluate = vec![];
+    let mut peak_arena = 0;
+
+    let mut arena = Bump::new();
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let jsonata = JsonAta::new(expr, &arena)?;
+        compile.push(start.elapsed());
+
+        let value = match input {
+            Some(input) => {
+                let start = Instant::now();
+                let mut documents = Documents::new(&arena);
+                documents.add("input", input)?;
+                parse.push(start.elapsed());
+                documents.get("input").unwrap_or_else(Value::undefined)
+            }
+            None => Value::undefined(),
+        };
+
+        let start = Instant::now();
+        jsonata.evaluate_value(value)?;
+        evaluate.push(start.elapsed());
+
+        drop(jsonata);
+        peak_arena = peak_arena.max(arena.allocated_bytes());
+        arena.reset();
+    }
+
+    Ok(Report {
+        iterations,
+        compile: Timings::new(compile),
+        parse: input.map(|_| Timings::new(parse)),
+        evaluate: Timings::new(evaluate),
+        peak_arena,
+    })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} iterations", self.iterations)?;
+        write!(f, "{:<12}", "")?;
+        for (heading, _) in PERCENTILES {
+            write!(f, "{:>12}", heading)?;
+        }
+        writeln!(f)?;
+
+        let phases = [
+            ("compile", Some(&self.compile)),
+            ("parse input", self.parse.as_ref()),
+            ("evaluate", Some(&self.evaluate)),
+        ];
+        for (phase, timings) in phases {
+            let Some(timings) = timings else {
+                continue;
+            };
+            write!(f, "{:<12}", phase)?;
+            for (_, percentile) in PERCENTILES {
+                write!(
+                    f,
+                    "{:>12}",
+                    format!("{:.1?}", timings.percentile(percentile))
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        write!(f, "{:<12}{:>12}", "peak arena", bytes(self.peak_arena))
+    }
+}
+
+fn bytes(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(50.0, 5)]
+    #[test_case(90.0, 9)]
+    #[test_case(99.0, 10)]
+    #[test_case(100.0, 10)]
+    #[test_case(0.0, 1)]
+    fn percentiles(percentile: f64, millis: u64) {
+        let timings = Timings::new((1..=10).rev().map(Duration::from_millis).collect());
+        assert_eq!(
+            timings.percentile(percentile),
+            Duration::from_millis(millis)
+        );
+    }
+
+    #[test_case(512, "512 B")]
+    #[test_case(1536, "1.5 KiB")]
+    #[test_case(3 * 1024 * 1024, "3.0 MiB")]
+    fn sizes(size: usize, expected: &str) {
+        assert_eq!(bytes(size), expected);
+    }
+
+    #[test]
+    fn report() {
+        let report = run(
+            "$sum(items.price)",
+            Some(r#"{"items": [{"price": 2}]}"#),
+            20,
+        )
+        .unwrap();
+        let report = report.to_string();
+        let lines: Vec<_> = report
+            .lines()
+            .map(|line| line.split_whitespace().next())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                Some("20"),
+                Some("p50"),
+                Some("compile"),
+                Some("parse"),
+                Some("evaluate"),
+                Some("peak")
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(run("$sum(", None, 1).err().unwrap().code(), "S0211");
+        assert_eq!(run("$error('x')", None, 1).err().unwrap().code(), "D3137");
+    }
+}
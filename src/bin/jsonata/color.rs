@@ -0,0 +1,91 @@
+const KEY: &str = "\x1b[34;1m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const BOOLEAN: &str = "\x1b[33m";
+const NULL: &str = "\x1b[1;30m";
+const RESET: &str = "\x1b[0m";
+
+/// Adds terminal colors to serialized JSON, so that keys, strings, numbers, booleans and null can
+/// be told apart. It works on the serialized text, so numbers keep whatever form they were
+/// written in.
+pub fn colorize(json: &str) -> String {
+    let mut output = String::with_capacity(json.len() * 2);
+    let mut rest = json;
+
+    while let Some(c) = rest.chars().next() {
+        let (token, color) = match c {
+            '"' => {
+                let token = &rest[..string_len(rest)];
+                let is_key = rest[token.len()..].trim_start().starts_with(':');
+                (token, if is_key { KEY } else { STRING })
+            }
+            '-' | '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len());
+                (&rest[..len], NUMBER)
+            }
+            't' | 'f' | 'n' => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(rest.len());
+                let token = &rest[..len];
+                (token, if token == "null" { NULL } else { BOOLEAN })
+            }
+            _ => {
+                output.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+        };
+
+        output.push_str(color);
+        output.push_str(token);
+        output.push_str(RESET);
+        rest = &rest[token.len()..];
+    }
+
+    output
+}
+
+// The length of the string at the start of `json`, including its quotes
+fn string_len(json: &str) -> usize {
+    let mut escaped = false;
+    for (index, c) in json.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return index + 1,
+            _ => {}
+        }
+    }
+    json.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(
+            colorize(r#"{"a": [1.5, "x\"y", true, null]}"#),
+            format!(
+                "{{{KEY}\"a\"{RESET}: [{NUMBER}1.5{RESET}, {STRING}\"x\\\"y\"{RESET}, \
+                 {BOOLEAN}true{RESET}, {NULL}null{RESET}]}}"
+            )
+        );
+    }
+
+    #[test]
+    fn keys_and_values_alike() {
+        let colored = colorize("{\n  \"null\": \"null\",\n  \"n\": -1e-7\n}");
+        assert_eq!(
+            colored,
+            format!(
+                "{{\n  {KEY}\"null\"{RESET}: {STRING}\"null\"{RESET},\n  {KEY}\"n\"{RESET}: \
+                 {NUMBER}-1e-7{RESET}\n}}"
+            )
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use bumpalo::Bump;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+
+use jsonata_rs::{JsonAta, TraceStep, Value};
+
+const HELP: &str = "\
+Evaluation pauses after each step, showing where it is in the expression, the value it was
+evaluated against and the value it evaluated to. Positions are the index of a char in the
+expression, as shown in error messages.
+
+step, s        Carry on to the next step (or just press Enter)
+continue, c    Carry on to the next breakpoint, or to the end
+break, b [N]   Set a breakpoint at position N, or at this step's position
+delete, d [N]  Remove the breakpoint at position N, or every breakpoint
+breakpoints    List the breakpoints
+input, i       Print the value this step was evaluated against
+output, o      Print the value this step evaluated to
+vars, v        List the variables in scope
+where, w       Show this step again
+help, h        Show this message
+quit, q        Stop evaluating and exit";
+
+#[derive(Debug, PartialEq)]
+enum DebugCommand {
+    Step,
+    Continue,
+    Break(Option<usize>),
+    Delete(Option<usize>),
+    Breakpoints,
+    Input,
+    Output,
+    Variables,
+    Where,
+    Help,
+    Quit,
+}
+
+/// Evaluates `expr` against the contents of `input_file`, pausing to take commands after each step
+/// or, if there are `breakpoints`, after each step at one of their positions.
+pub fn run(
+    expr: &str,
+    input_file: Option<PathBuf>,
+    breakpoints: Vec<usize>,
+) -> rustyline::Result<()> {
+    let input = input_file.map(|input_file| {
+        std::fs::read_to_string(input_file).expect("Could not read the JSON input file")
+    });
+
+    let mut debugger = Debugger {
+        expr,
+        editor: Editor::new()?,
+        stepping: breakpoints.is_empty(),
+        breakpoints: breakpoints.into_iter().collect(),
+        steps: 0,
+        error: None,
+    };
+
+    let arena = Bump::new();
+    let mut jsonata = match JsonAta::new(expr, &arena) {
+        Ok(jsonata) => jsonata,
+        Err(error) => {
+            println!("{}", error);
+            return Ok(());
+        }
+    };
+
+    println!("Type help for help");
+    jsonata.set_trace(|step| debugger.pause(step));
+    let result = jsonata.evaluate(input.as_deref(), None);
+    drop(jsonata);
+
+    match result {
+        Ok(result) if result.is_undefined() => println!("undefined"),
+        Ok(result) => println!("{}", result.serialize(true)),
+        Err(error) => println!("{}", error),
+    }
+    println!("Evaluated {} steps", debugger.steps);
+
+    match debugger.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+struct Debugger<'e> {
+    expr: &'e str,
+    editor: Editor<(), DefaultHistory>,
+    breakpoints: BTreeSet<usize>,
+
+    /// Whether to pause after every step, rather than only at breakpoints
+    stepping: bool,
+
+    steps: u64,
+
+    /// An error reading a command, after which evaluation carries on to the end without pausing
+    error: Option<ReadlineError>,
+}
+
+impl Debugger<'_> {
+    fn pause(&mut self, step: &TraceStep) {
+        self.steps += 1;
+        if self.error.is_some() || !(self.stepping || self.breakpoints.contains(&step.char_index)) {
+            return;
+        }
+
+        self.show(step);
+        loop {
+            let line = match self.editor.readline("(debug) ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => std::process::exit(0),
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            };
+            if !line.trim().is_empty() {
+                let _ = self.editor.add_history_entry(line.trim());
+            }
+
+            let command = match parse_command(&line) {
+                Ok(command) => command,
+                Err(message) => {
+                    println!("{}", message);
+                    continue;
+                }
+            };
+            match command {
+                DebugCommand::Step => {
+                    self.stepping = true;
+                    return;
+                }
+                DebugCommand::Continue => {
+                    self.stepping = false;
+                    return;
+                }
+                DebugCommand::Break(position) => {
+                    let position = position.unwrap_or(step.char_index);
+                    self.breakpoints.insert(position);
+                    println!("Breakpoint set at {}", position);
+                }
+                DebugCommand::Delete(Some(position)) => {
+                    if !self.breakpoints.remove(&position) {
+                        println!("There's no breakpoint at {}", position);
+                    }
+                }
+                DebugCommand::Delete(None) => self.breakpoints.clear(),
+                DebugCommand::Breakpoints => {
+                    if self.breakpoints.is_empty() {
+                        println!("No breakpoints");
+                    }
+                    for &position in self.breakpoints.iter() {
+                        let (line, column) = locate(self.expr, position);
+                        println!("{} (line {}, column {})", position, line + 1, column + 1);
+                    }
+                }
+                DebugCommand::Input => print_value(step.input),
+                DebugCommand::Output => print_value(step.output),
+                DebugCommand::Variables => {
+                    for (name, value) in step.variables() {
+                        let value = if value.is_function() {
+                            "function".to_string()
+                        } else if value.is_undefined() {
+                            "undefined".to_string()
+                        } else {
+                            value.serialize(false)
+                        };
+                        println!("${} = {}", name, value);
+                    }
+                }
+                DebugCommand::Where => self.show(step),
+                DebugCommand::Help => println!("{}", HELP),
+                DebugCommand::Quit => std::process::exit(0),
+            }
+        }
+    }
+
+    /// Prints the step, pointing at its position in the expression.
+    fn show(&self, step: &TraceStep) {
+        let (line, column) = locate(self.expr, step.char_index);
+        let source = self.expr.lines().nth(line).unwrap_or_default();
+        println!(
+            "#{} {} at {}, depth {}",
+            self.steps, step.kind, step.char_index, step.depth
+        );
+        println!("  {}", source);
+        println!("  {}^", " ".repeat(column));
+        println!("  input:  {}", step.input_summary());
+        println!("  output: {}", step.output_summary());
+    }
+}
+
+fn print_value<'a>(value: &'a Value<'a>) {
+    if value.is_undefined() {
+        println!("undefined");
+    } else {
+        println!("{}", value.serialize(true));
+    }
+}
+
+fn parse_command(line: &str) -> Result<DebugCommand, String> {
+    let line = line.trim();
+    let (command, arg) = line
+        .split_once(char::is_whitespace)
+        .map(|(command, arg)| (command, arg.trim()))
+        .unwrap_or((line, ""));
+    let position = || match arg {
+        "" => Ok(None),
+        arg => arg
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("`{}` isn't a position in the expression", arg)),
+    };
+
+    Ok(match command {
+        "" | "s" | "step" => DebugCommand::Step,
+        "c" | "continue" => DebugCommand::Continue,
+        "b" | "break" => DebugCommand::Break(position()?),
+        "d" | "delete" => DebugCommand::Delete(position()?),
+        "breakpoints" => DebugCommand::Breakpoints,
+        "i" | "input" => DebugCommand::Input,
+        "o" | "output" => DebugCommand::Output,
+        "v" | "vars" => DebugCommand::Variables,
+        "w" | "where" => DebugCommand::Where,
+        "h" | "help" => DebugCommand::Help,
+        "q" | "quit" => DebugCommand::Quit,
+        _ => return Err(format!("Unknown command `{}`, type help for help", command)),
+    })
+}
+
+/// The line and column, counting from 0, of the char at `char_index` in `expr`.
+fn locate(expr: &str, char_index: usize) -> (usize, usize) {
+    let mut line = 0;
+    let mut column = 0;
+    for c in expr.chars().take(char_index) {
+        if c == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("", Ok(DebugCommand::Step))]
+    #[test_case("  c ", Ok(DebugCommand::Continue))]
+    #[test_case("break 12", Ok(DebugCommand::Break(Some(12))))]
+    #[test_case("b", Ok(DebugCommand::Break(None)))]
+    #[test_case("d  3", Ok(DebugCommand::Delete(Some(3))))]
+    #[test_case("vars", Ok(DebugCommand::Variables))]
+    #[test_case("b x", Err("`x` isn't a position in the expression".to_string()))]
+    #[test_case("jump", Err("Unknown command `jump`, type help for help".to_string()))]
+    fn commands(line: &str, expected: Result<DebugCommand, String>) {
+        assert_eq!(parse_command(line), expected);
+    }
+
+    #[test_case("a.b + 1", 4, (0, 4))]
+    #[test_case("(\n  $x := 1;\n  $x\n)", 15, (2, 2))]
+    #[test_case("'é' & x", 6, (0, 6))]
+    fn locations(expr: &str, char_index: usize, expected: (usize, usize)) {
+        assert_eq!(locate(expr, char_index), expected);
+    }
+}
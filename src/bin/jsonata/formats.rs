@@ -0,0 +1,396 @@
+use bumpalo::Bump;
+use clap::ValueEnum;
+use jsonata_rs::{JsonAta, Value};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// The format of the inputs. Inputs in other formats than JSON are converted to JSON, or decoded
+/// straight into values, as they're read, and then evaluated like any other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum InputFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+    Msgpack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+/// The format results are printed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Yaml,
+    Csv,
+    Msgpack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl OutputFormat {
+    /// Whether results are written in a binary format, one after the other with nothing between
+    /// them, rather than as text.
+    pub fn is_binary(self) -> bool {
+        match self {
+            OutputFormat::Json | OutputFormat::Yaml | OutputFormat::Csv => false,
+            OutputFormat::Msgpack => true,
+            #[cfg(feature = "cbor")]
+            OutputFormat::Cbor => true,
+        }
+    }
+}
+
+/// A document of an input, ready to be evaluated.
+pub enum Document<'a> {
+    /// JSON text, which the engine parses like any other input
+    Json(String),
+
+    /// A value decoded straight into the arena, for the formats the crate can decode
+    Value(&'a Value<'a>),
+}
+
+impl Document<'_> {
+    /// The document as JSON text.
+    pub fn into_json(self) -> String {
+        match self {
+            Document::Json(json) => json,
+            Document::Value(value) => value.serialize(false),
+        }
+    }
+}
+
+impl InputFormat {
+    /// Reads an input, with a document for each document in it. JSON is passed through as it is,
+    /// so that it's parsed by the engine like any other input, and MessagePack, CBOR and XML are
+    /// decoded into `arena`, rather than being converted into JSON only to be parsed again.
+    pub fn read(self, input: Vec<u8>, arena: &Bump) -> Result<Vec<Document<'_>>, String> {
+        let text = |input| String::from_utf8(input).map_err(|e| e.to_string());
+        let json = |json| vec![Document::Json(json)];
+        let value = |value| vec![Document::Value(value)];
+        match self {
+            InputFormat::Json => Ok(json(text(input)?)),
+            InputFormat::Yaml => Ok(yaml_to_json(&text(input)?)?
+                .into_iter()
+                .map(Document::Json)
+                .collect()),
+            InputFormat::Toml => toml_to_json(&text(input)?).map(json),
+            InputFormat::Csv => csv_to_json(&text(input)?).map(json),
+            InputFormat::Msgpack => jsonata_rs::msgpack::from_slice(&input, arena)
+                .map(value)
+                .map_err(|e| e.to_string()),
+            // Byte strings are tagged, so that they're written as byte strings again with
+            // `--output-format cbor`
+            #[cfg(feature = "cbor")]
+            InputFormat::Cbor => {
+                jsonata_rs::cbor::from_slice(&input, arena, jsonata_rs::cbor::ByteStrings::Tagged)
+                    .map(value)
+                    .map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "xml")]
+            InputFormat::Xml => jsonata_rs::xml::parse(&text(input)?, arena)
+                .map(value)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Converts each document of a YAML stream, such as a set of Kubernetes manifests separated by
+/// `---`, into JSON. Mappings keep the order of their keys.
+fn yaml_to_json(input: &str) -> Result<Vec<String>, String> {
+    let mut documents = vec![];
+    for document in serde_yaml::Deserializer::from_str(input) {
+        let mut value = serde_yaml::Value::deserialize(document).map_err(|e| e.to_string())?;
+        // Anchors are resolved by the deserializer, but merge keys (`<<: *base`) have to be
+        // applied
+        value.apply_merge().map_err(|e| e.to_string())?;
+        documents.push(serde_json::to_string(&value).map_err(|e| e.to_string())?);
+    }
+    Ok(documents)
+}
+
+/// Converts a TOML document, such as a `Cargo.toml`, into JSON. Tables keep the order of their
+/// keys, and dates and times become strings in the format they're written in, which for an offset
+/// date-time is one `$toMillis` understands.
+fn toml_to_json(input: &str) -> Result<String, String> {
+    let table: toml::Table = input.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    serde_json::to_string(&Toml(&toml::Value::Table(table))).map_err(|e| e.to_string())
+}
+
+/// Serializes a TOML value as JSON, which would otherwise turn dates and times into objects.
+struct Toml<'a>(&'a toml::Value);
+
+impl Serialize for Toml<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            toml::Value::Datetime(datetime) => serializer.collect_str(datetime),
+            toml::Value::Array(array) => serializer.collect_seq(array.iter().map(Toml)),
+            toml::Value::Table(table) => {
+                serializer.collect_map(table.iter().map(|(key, value)| (key, Toml(value))))
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+/// Converts CSV with a header row into a JSON array with an object for each row, keyed by the
+/// header. Fields are strings, as CSV doesn't say which are numbers, so `$number()` converts them.
+fn csv_to_json(input: &str) -> Result<String, String> {
+    let mut reader = csv::Reader::from_reader(input.as_bytes());
+    let header = reader.headers().map_err(|e| e.to_string())?.clone();
+    let rows = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let rows: Vec<_> = rows
+        .iter()
+        .map(|row| CsvRow {
+            header: &header,
+            row,
+        })
+        .collect();
+    serde_json::to_string(&rows).map_err(|e| e.to_string())
+}
+
+/// Serializes a record as a map from the header's names to its fields, in the order of the columns.
+struct CsvRow<'a> {
+    header: &'a csv::StringRecord,
+    row: &'a csv::StringRecord,
+}
+
+impl Serialize for CsvRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.header.iter().zip(self.row.iter()))
+    }
+}
+
+/// Converts a result into CSV. An array of objects, or an object, becomes a header row of every
+/// key in the order they're first seen, followed by a row for each object. Members which are
+/// arrays are rows of their own, and any other value is a row with one field. Fields which are
+/// arrays or objects are written as JSON, and null or missing fields are empty.
+pub fn to_csv<'a>(jsonata: &JsonAta<'a>, result: &'a Value<'a>) -> String {
+    let rows: Vec<&Value> = if result.is_array() {
+        result.members().collect()
+    } else if result.is_undefined() {
+        vec![]
+    } else {
+        vec![result]
+    };
+
+    let mut header: Vec<&str> = vec![];
+    for row in rows.iter().filter(|row| row.is_object()) {
+        for (key, _) in row.entries() {
+            if !header.contains(&key.as_str()) {
+                header.push(key);
+            }
+        }
+    }
+
+    let field = |value: &'a Value<'a>| {
+        if value.is_string() {
+            value.as_str().into_owned()
+        } else if value.is_null() || value.is_undefined() || value.is_function() {
+            String::new()
+        } else {
+            jsonata.serialize(value, false)
+        }
+    };
+
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(vec![]);
+    let mut write = |record: Vec<String>| {
+        writer
+            .write_record(record)
+            .expect("Writing to a Vec doesn't fail")
+    };
+    if !header.is_empty() {
+        write(header.iter().map(|key| key.to_string()).collect());
+    }
+    for row in rows {
+        if row.is_object() {
+            write(
+                header
+                    .iter()
+                    .map(|&key| field(row.get_entry(key)))
+                    .collect(),
+            );
+        } else if row.is_array() {
+            write(row.members().map(field).collect());
+        } else {
+            write(vec![field(row)]);
+        }
+    }
+
+    let csv = writer.into_inner().expect("Writing to a Vec doesn't fail");
+    String::from_utf8(csv).expect("The fields are strings")
+}
+
+/// Converts a result, serialized as JSON, into a YAML document.
+pub fn json_to_yaml(json: &str) -> String {
+    // Deserializing into a YAML value rather than a JSON value keeps the order of the keys
+    let value: serde_yaml::Value = serde_json::from_str(json).expect("Results are valid JSON");
+    serde_yaml::to_string(&value).expect("JSON values can be YAML")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(format: InputFormat, input: impl Into<Vec<u8>>) -> Result<Vec<String>, String> {
+        let arena = Bump::new();
+        let documents = format.read(input.into(), &arena)?;
+        Ok(documents.into_iter().map(Document::into_json).collect())
+    }
+
+    #[test]
+    fn yaml_documents() {
+        let input = "\
+kind: Deployment
+metadata:
+  name: web
+  labels: {app: web}
+spec:
+  replicas: 3
+  ports: [80, 443]
+---
+kind: Service
+enabled: true
+ratio: 0.5
+empty: ~
+";
+        assert_eq!(
+            read(InputFormat::Yaml, input).unwrap(),
+            [
+                r#"{"kind":"Deployment","metadata":{"name":"web","labels":{"app":"web"}},"spec":{"replicas":3,"ports":[80,443]}}"#,
+                r#"{"kind":"Service","enabled":true,"ratio":0.5,"empty":null}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn yaml_anchors_and_merge_keys() {
+        let input = "\
+base: &base {image: nginx, port: 80}
+web:
+  <<: *base
+  port: 8080
+";
+        assert_eq!(
+            read(InputFormat::Yaml, input).unwrap(),
+            [r#"{"base":{"image":"nginx","port":80},"web":{"port":8080,"image":"nginx"}}"#]
+        );
+    }
+
+    #[test]
+    fn invalid_yaml() {
+        assert!(read(InputFormat::Yaml, "a: [1, 2").is_err());
+    }
+
+    #[test]
+    fn toml_document() {
+        let input = r#"
+title = "Example"
+released = 1979-05-27T07:32:00-08:00
+birthday = 1979-05-27
+
+[package]
+name = "jsonata-rs"
+version = "0.1.3"
+keywords = ["jsonata", "json"]
+
+[[bin]]
+name = "jsonata"
+ratio = 0.5
+test = false
+"#;
+        assert_eq!(
+            read(InputFormat::Toml, input).unwrap(),
+            [concat!(
+                r#"{"title":"Example","released":"1979-05-27T07:32:00-08:00","birthday":"1979-05-27","#,
+                r#""package":{"name":"jsonata-rs","version":"0.1.3","keywords":["jsonata","json"]},"#,
+                r#""bin":[{"name":"jsonata","ratio":0.5,"test":false}]}"#
+            )]
+        );
+    }
+
+    #[test]
+    fn invalid_toml() {
+        assert!(read(InputFormat::Toml, "a = ").is_err());
+    }
+
+    #[test]
+    fn csv_rows() {
+        let input = "name,price,notes\nWidget,1.50,\"small, blue\"\nGadget,12,\n";
+        assert_eq!(
+            read(InputFormat::Csv, input).unwrap(),
+            [concat!(
+                r#"[{"name":"Widget","price":"1.50","notes":"small, blue"},"#,
+                r#"{"name":"Gadget","price":"12","notes":""}]"#
+            )]
+        );
+    }
+
+    #[test]
+    fn csv_header_only() {
+        assert_eq!(read(InputFormat::Csv, "a,b\n").unwrap(), ["[]"]);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_document() {
+        assert_eq!(
+            read(InputFormat::Xml, r#"<a id="1"><b>x</b><b>y</b></a>"#).unwrap(),
+            [r#"{"a":{"@id":"1","b":["x","y"]}}"#]
+        );
+    }
+
+    #[test]
+    fn msgpack_value() {
+        assert_eq!(
+            read(InputFormat::Msgpack, b"\x82\xa1a\x01\xa1b\x91\xc3").unwrap(),
+            [r#"{"a":1,"b":[true]}"#]
+        );
+        assert!(read(InputFormat::Msgpack, b"\x82").is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_value() {
+        assert_eq!(
+            read(InputFormat::Cbor, b"\xa2\x61a\x01\x61b\x42\x01\x02").unwrap(),
+            [r#"{"a":1,"b":{"$bytes":"AQI="}}"#]
+        );
+    }
+
+    #[test]
+    fn csv_output() {
+        let arena = bumpalo::Bump::new();
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+        let csv = |json: &str| {
+            let result = jsonata.evaluate(Some(json), None).unwrap();
+            to_csv(&jsonata, result)
+        };
+
+        assert_eq!(
+            csv(
+                r#"[{"name": "Widget", "price": 1.5}, {"name": "a, \"b\"","tags": ["x"], "price": null}]"#
+            ),
+            "name,price,tags\nWidget,1.5,\n\"a, \"\"b\"\"\",,\"[\"\"x\"\"]\"\n"
+        );
+        assert_eq!(
+            csv(r#"{"a": true, "b": {"c": 1}}"#),
+            "a,b\ntrue,\"{\"\"c\"\":1}\"\n"
+        );
+        assert_eq!(csv("[[1, 2], [3], 4]"), "1,2\n3\n4\n");
+    }
+
+    #[test]
+    fn yaml_output() {
+        assert_eq!(
+            json_to_yaml(r#"{"z":1,"a":[true,null,"x"],"n":{"b":1.5}}"#),
+            "z: 1\na:\n- true\n- null\n- x\nn:\n  b: 1.5\n"
+        );
+    }
+}
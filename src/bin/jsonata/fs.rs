@@ -0,0 +1,91 @@
+//! `$readFile(path)` and `$readJson(path)`, which `--allow-fs` binds so that expressions can read
+//! lookup tables and the like from disk. Paths are relative to the working directory.
+
+use jsonata_rs::{Documents, Error, FunctionContext, JsonAta, Result, Value};
+
+/// Binds the functions for reading files.
+pub fn allow(jsonata: &JsonAta) {
+    jsonata.register_function("readFile", 1, read_file);
+    jsonata.register_function("readJson", 1, read_json);
+}
+
+/// The contents of a UTF-8 text file.
+fn read_file<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    match read(&context, args)? {
+        Some(text) => Ok(Value::string(context.arena, text)),
+        None => Ok(Value::undefined()),
+    }
+}
+
+/// The JSON in a file, parsed as `--document` parses it.
+fn read_json<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    let Some(text) = read(&context, args)? else {
+        return Ok(Value::undefined());
+    };
+    let mut documents = Documents::new(context.arena);
+    documents.add("file", &text)?;
+    Ok(documents.get("file").unwrap_or_else(Value::undefined))
+}
+
+fn read<'a>(context: &FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<Option<String>> {
+    let path = &args[0];
+    if path.is_undefined() {
+        return Ok(None);
+    }
+    if !path.is_string() {
+        return Err(Error::T0410ArgumentNotValid(
+            context.char_index,
+            1,
+            context.name.to_string(),
+        ));
+    }
+    let path = path.as_str();
+    std::fs::read_to_string(path.as_ref())
+        .map(Some)
+        .map_err(|error| Error::H0101InputRead(format!("{}: {}", path, error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    /// A directory with the files the tests read, written once for all of them.
+    fn dir() -> &'static PathBuf {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("jsonata-fs-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("rates.json"), r#"{"GBP": 1.17, "USD": 0.92}"#).unwrap();
+            std::fs::write(dir.join("greeting.txt"), "Hello, world\n").unwrap();
+            dir
+        })
+    }
+
+    fn evaluate(expr: &str) -> std::result::Result<String, String> {
+        let dir = dir();
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        allow(&jsonata);
+        jsonata.assign_json("dir", &dir.to_string_lossy().into());
+        jsonata
+            .evaluate(None, None)
+            .map(|result| result.serialize(false))
+            .map_err(|error| error.code().to_string())
+    }
+
+    #[test_case("$readJson($dir & '/rates.json').GBP", Ok("1.17"))]
+    #[test_case("$readFile($dir & '/greeting.txt')", Ok(r#""Hello, world\n""#))]
+    #[test_case("$readFile($dir & '/missing.txt')", Err("H0101"))]
+    #[test_case("$readJson($dir & '/greeting.txt')", Err("S0201"))]
+    #[test_case("$readFile(1)", Err("T0410"))]
+    fn read(expr: &str, expected: std::result::Result<&str, &str>) {
+        let expected = expected.map(String::from).map_err(String::from);
+        assert_eq!(evaluate(expr), expected);
+    }
+}
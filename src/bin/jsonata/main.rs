@@ -0,0 +1,872 @@
+use bumpalo::Bump;
+use clap::{ColorChoice, Parser, Subcommand, ValueEnum};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use jsonata_rs::{CompiledExpression, Documents, JsonAta, NdjsonCursor, SerializeOptions, Value};
+
+mod bench;
+mod color;
+#[cfg(not(target_family = "wasm"))]
+mod debug;
+mod formats;
+mod fs;
+mod output;
+mod pipeline;
+#[cfg(not(target_family = "wasm"))]
+mod repl;
+#[cfg(not(target_family = "wasm"))]
+mod watch;
+
+use formats::{Document, InputFormat, OutputFormat};
+use output::Output;
+
+/// A command line JSON processor using JSONata
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Opt {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Parse the given expression, print the AST and exit
+    #[arg(short, long)]
+    ast: bool,
+
+    /// How to print the AST: as JSON in the shape jsonata.js uses, or as Rust's debug output with
+    /// the `unstable-ast` feature
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = AstFormat::Json, requires = "ast")]
+    ast_format: AstFormat,
+
+    /// Parse the given expression, print how it's evaluated and exit
+    #[arg(long, conflicts_with = "ast")]
+    explain: bool,
+
+    /// File containing the JSONata expression to evaluate (overrides expr on command line)
+    #[arg(short, long)]
+    expr_file: Option<PathBuf>,
+
+    /// Input JSON file (if not specified, STDIN), which can be given more than once to evaluate
+    /// the expression against each file in turn
+    #[arg(short, long)]
+    input_file: Vec<PathBuf>,
+
+    /// Write the output to FILE, which is only replaced once evaluation has succeeded, so it can
+    /// also be the input file
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Bind $file to the name of the input file the expression is being evaluated against
+    #[arg(long)]
+    bind_file: bool,
+
+    /// Treat the input as JSON Lines, evaluating the expression against each line and printing
+    /// each result on its own line
+    #[arg(long)]
+    jsonl: bool,
+
+    /// Read every input, or every line of the inputs with --jsonl, into one array and evaluate the
+    /// expression against it once
+    #[arg(short, long, conflicts_with = "bind_file")]
+    slurp: bool,
+
+    /// If the result is a string, print it without quotes or escaping
+    #[arg(short, long)]
+    raw_output: bool,
+
+    /// Print the result on a single line rather than pretty-printing it
+    #[arg(short, long)]
+    compact: bool,
+
+    /// Indent each level of nesting by N spaces when pretty-printing [default: 2]
+    #[arg(long, value_name = "N", conflicts_with_all = ["compact", "tab"])]
+    indent: Option<usize>,
+
+    /// Indent each level of nesting with a tab when pretty-printing
+    #[arg(long, conflicts_with = "compact")]
+    tab: bool,
+
+    /// Print the members of objects in the order of their keys
+    #[arg(short = 'S', long)]
+    sort_keys: bool,
+
+    /// Escape characters outside of ASCII in strings, as \uXXXX
+    #[arg(long)]
+    ascii_output: bool,
+
+    /// Don't print a newline after each result
+    #[arg(long)]
+    no_newline: bool,
+
+    /// The format of the input, which is converted to JSON before it's evaluated
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = InputFormat::Json)]
+    input_format: InputFormat,
+
+    /// The format to print results in
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Json)]
+    output_format: OutputFormat,
+
+    /// Set the exit status from the last result: 0 if it's truthy, 1 if it's false, null,
+    /// undefined or otherwise falsy, and 5 if anything failed to evaluate
+    #[arg(long)]
+    exit_status: bool,
+
+    /// When to color the output: auto colors it when writing to a terminal, unless NO_COLOR is set
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Print numbers from the input that are passed through unchanged exactly as they were
+    /// written, such as 1.10 or 1e2
+    #[arg(long)]
+    preserve_numbers: bool,
+
+    /// Bind $NAME to the string VALUE
+    #[arg(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+    arg: Vec<String>,
+
+    /// Bind $NAME to the JSON value VALUE
+    #[arg(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+    argjson: Vec<String>,
+
+    /// Parse the JSON in FILE once and bind it to $documents.NAME
+    #[arg(long, num_args = 2, value_names = ["NAME", "FILE"])]
+    document: Vec<String>,
+
+    /// Let the expression read files, relative to the working directory, with $readFile(PATH) for
+    /// text and $readJson(PATH) for JSON
+    #[arg(long)]
+    allow_fs: bool,
+
+    /// Evaluate again whenever the expression file, an input file or a document changes, clearing
+    /// the screen first
+    #[arg(long)]
+    watch: bool,
+
+    /// JSONata expression to evaluate
+    expr: Option<String>,
+
+    /// JSON input, or any number of input files to evaluate the expression against in turn
+    inputs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum AstFormat {
+    Json,
+    #[cfg(feature = "unstable-ast")]
+    Debug,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Start an interactive prompt for evaluating expressions against an input
+    Repl {
+        /// Input JSON file
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+    },
+
+    /// Evaluate an expression step by step, pausing to inspect the values and variables at each
+    /// step or at breakpoints
+    Debug {
+        /// JSONata expression to evaluate
+        expr: String,
+
+        /// Input JSON file
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Pause only at steps at this position in the expression, the index of a char as shown
+        /// in error messages. Can be given more than once
+        #[arg(short, long = "break", value_name = "POSITION")]
+        breakpoints: Vec<usize>,
+    },
+
+    /// Run the stages of a pipeline one after the other, each against the result of the one
+    /// before
+    Pipeline {
+        /// The pipeline's manifest, or a directory containing a pipeline.json manifest
+        manifest: PathBuf,
+
+        /// JSON input, or an input file (if not specified, STDIN)
+        input: Option<String>,
+
+        /// Print the result on a single line rather than pretty-printing it
+        #[arg(short, long)]
+        compact: bool,
+    },
+
+    /// Evaluate an expression many times, reporting how long compiling it, parsing the input and
+    /// evaluating it took, and the most arena memory an evaluation used
+    Bench {
+        /// JSONata expression to evaluate
+        expr: String,
+
+        /// Input JSON file
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// How many times to evaluate the expression
+        #[arg(short = 'n', long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+        iterations: u64,
+    },
+}
+
+fn main() {
+    let opt = Opt::parse();
+
+    match opt.command {
+        #[cfg(not(target_family = "wasm"))]
+        Some(Command::Repl { input }) => {
+            if let Err(error) = repl::run(input) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(target_family = "wasm")]
+        Some(Command::Repl { .. }) => {
+            eprintln!("The REPL isn't available on this platform");
+            std::process::exit(1);
+        }
+        #[cfg(not(target_family = "wasm"))]
+        Some(Command::Debug {
+            expr,
+            input,
+            breakpoints,
+        }) => {
+            if let Err(error) = debug::run(&expr, input, breakpoints) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(target_family = "wasm")]
+        Some(Command::Debug { .. }) => {
+            eprintln!("The debugger isn't available on this platform");
+            std::process::exit(1);
+        }
+        Some(Command::Pipeline {
+            manifest,
+            input,
+            compact,
+        }) => {
+            let result = pipeline::Pipeline::load(&manifest).and_then(|pipeline| {
+                let input = match input {
+                    Some(input) if Path::new(&input).is_file() => {
+                        std::fs::read_to_string(input).expect("Could not read the JSON input file")
+                    }
+                    Some(input) => input,
+                    None => io::read_to_string(io::stdin()).expect("Could not read stdin"),
+                };
+                pipeline.run(&input, !compact, &mut io::stdout().lock())
+            });
+            if let Err(error) = result {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Bench {
+            expr,
+            input,
+            iterations,
+        }) => {
+            let input = input.map(|input| {
+                std::fs::read_to_string(input).expect("Could not read the JSON input file")
+            });
+            match bench::run(&expr, input.as_deref(), iterations) {
+                Ok(report) => println!("{}", report),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if opt.watch {
+        watch(&opt);
+    }
+
+    // Each line of JSON Lines is a JSON document, and so is each result unless they're slurped
+    if opt.jsonl
+        && (opt.input_format != InputFormat::Json
+            || (opt.output_format != OutputFormat::Json && !opt.slurp))
+    {
+        eprintln!("--jsonl reads and writes JSON, so it can't be used with another format");
+        std::process::exit(2);
+    }
+
+    let expr = match opt.expr_file {
+        Some(expr_file) => {
+            let expr = std::fs::read(expr_file).expect("Could not read expression input file");
+            String::from_utf8_lossy(&expr).to_string()
+        }
+        None => opt.expr.expect("No JSONata expression provided"),
+    };
+
+    let bindings = bindings(&opt.arg, &opt.argjson);
+    // The documents are parsed once, into the arena the expression is evaluated in unless each
+    // line of JSON Lines gets its own
+    let arena = Bump::new();
+    let setup = Setup {
+        preserve_numbers: opt.preserve_numbers,
+        allow_fs: opt.allow_fs,
+        documents: read_documents(&arena, &opt.document),
+    };
+    let (input, input_files) = inputs(opt.input_file, opt.inputs);
+    // Slurped inputs have already been converted into a JSON array
+    let (input, input_files, input_format) = if opt.slurp {
+        (
+            Some(slurp(input, &input_files, opt.jsonl, opt.input_format)),
+            vec![],
+            InputFormat::Json,
+        )
+    } else {
+        (input, input_files, opt.input_format)
+    };
+
+    // The bindings for each file, including `$file` if it's wanted
+    let file_bindings = |file: &Path| {
+        let mut bindings = bindings.clone();
+        if opt.bind_file {
+            let name = file.to_string_lossy().into_owned();
+            bindings.push(("file".to_string(), serde_json::Value::String(name)));
+        }
+        bindings
+    };
+
+    let mut out = Output::new(opt.output).expect("Could not create the output file");
+    let mut status = Status::default();
+    let format = Format {
+        output: opt.output_format,
+        raw_output: opt.raw_output,
+        json: SerializeOptions {
+            indent: match (opt.compact, opt.tab, opt.indent) {
+                (true, ..) => None,
+                (_, true, _) => Some("\t".to_string()),
+                (_, _, indent) => Some(" ".repeat(indent.unwrap_or(2))),
+            },
+            sort_keys: opt.sort_keys,
+            ascii: opt.ascii_output,
+            trailing_newline: false,
+        },
+        newline: !opt.no_newline,
+        color: match opt.color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                opt.output_format == OutputFormat::Json
+                    && out.is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none()
+            }
+        },
+    };
+
+    if opt.jsonl && !opt.slurp && !opt.ast && !opt.explain {
+        match CompiledExpression::new(&expr) {
+            Ok(compiled) => {
+                if input_files.is_empty() {
+                    let reader: Box<dyn BufRead> = match input {
+                        Some(input) => Box::new(Cursor::new(input)),
+                        None => Box::new(io::stdin().lock()),
+                    };
+                    status.merge(evaluate_jsonl(
+                        &compiled, &bindings, reader, &format, &setup, &arena, &mut out,
+                    ));
+                }
+
+                for input_file in input_files.iter() {
+                    let reader = BufReader::new(
+                        File::open(input_file).expect("Could not read the JSON input file"),
+                    );
+                    status.merge(evaluate_jsonl(
+                        &compiled,
+                        &file_bindings(input_file),
+                        reader,
+                        &format,
+                        &setup,
+                        &arena,
+                        &mut out,
+                    ));
+                }
+            }
+            Err(error) => {
+                status.failed = true;
+                report(&mut out, &error);
+            }
+        }
+    } else {
+        match JsonAta::new(&expr, &arena) {
+            Ok(jsonata) if opt.ast => {
+                let ast = match opt.ast_format {
+                    AstFormat::Json if opt.compact => jsonata.ast_json().to_string(),
+                    AstFormat::Json => serde_json::to_string_pretty(&jsonata.ast_json())
+                        .expect("Could not serialize the AST"),
+                    #[cfg(feature = "unstable-ast")]
+                    AstFormat::Debug => format!("{:#?}", jsonata.ast()),
+                };
+                writeln!(out, "{}", ast).expect("Could not write the output")
+            }
+            Ok(jsonata) if opt.explain => {
+                write!(out, "{}", jsonata.explain()).expect("Could not write the output")
+            }
+            Ok(mut jsonata) => {
+                setup.apply(&mut jsonata);
+                if let Some(ref documents) = setup.documents {
+                    jsonata.use_documents(documents);
+                }
+                let evaluate = |document, bindings: &[(String, serde_json::Value)]| {
+                    for (name, value) in bindings.iter() {
+                        jsonata.assign_json(name, value);
+                    }
+                    let result = match document {
+                        // Blank input, such as an empty stdin, means there's no input
+                        Document::Json(ref input) if input.trim().is_empty() => {
+                            jsonata.evaluate(None, None)
+                        }
+                        Document::Json(ref input) => jsonata.evaluate(Some(input), None),
+                        Document::Value(input) => jsonata.evaluate_value(input),
+                    };
+                    result.map(|result| {
+                        let formatted = format_result(&jsonata, result, &format);
+                        (formatted, result.is_truthy())
+                    })
+                };
+
+                if input_files.is_empty() {
+                    let input = match input {
+                        Some(input) => input.into_bytes(),
+                        None => {
+                            let mut input = vec![];
+                            io::stdin()
+                                .read_to_end(&mut input)
+                                .expect("Could not read stdin");
+                            input
+                        }
+                    };
+                    match input_format.read(input, &arena) {
+                        Ok(documents) => {
+                            for document in documents {
+                                match evaluate(document, &bindings) {
+                                    Ok((result, truthy)) => write_result(
+                                        &mut out,
+                                        &mut status,
+                                        &result,
+                                        truthy,
+                                        &format,
+                                    ),
+                                    Err(error) => {
+                                        status.failed = true;
+                                        report(&mut out, &error);
+                                    }
+                                }
+                            }
+                        }
+                        Err(error) => {
+                            status.failed = true;
+                            report(&mut out, &error);
+                        }
+                    }
+                }
+
+                for input_file in input_files.iter() {
+                    let input =
+                        std::fs::read(input_file).expect("Could not read the JSON input file");
+                    let documents = match input_format.read(input, &arena) {
+                        Ok(documents) => documents,
+                        Err(error) => {
+                            status.failed = true;
+                            eprintln!("{}: {}", input_file.display(), error);
+                            continue;
+                        }
+                    };
+                    for document in documents {
+                        match evaluate(document, &file_bindings(input_file)) {
+                            Ok((result, truthy)) => {
+                                write_result(&mut out, &mut status, &result, truthy, &format)
+                            }
+                            Err(error) => {
+                                status.failed = true;
+                                eprintln!("{}: {}", input_file.display(), error);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                status.failed = true;
+                report(&mut out, &error);
+            }
+        };
+    }
+
+    // A file that's being written to is left as it was if anything failed
+    if status.failed && out.is_file() {
+        drop(out);
+        std::process::exit(if opt.exit_status { status.code() } else { 1 });
+    }
+    out.commit().expect("Could not write the output");
+
+    if opt.exit_status {
+        std::process::exit(status.code());
+    }
+}
+
+/// What the results so far mean for the exit status.
+#[derive(Debug, Default)]
+struct Status {
+    failed: bool,
+
+    /// Whether the last result was truthy, if there's been one
+    last_truthy: Option<bool>,
+}
+
+impl Status {
+    /// Takes in the status of a later batch of results.
+    fn merge(&mut self, later: Status) {
+        self.failed |= later.failed;
+        self.last_truthy = later.last_truthy.or(self.last_truthy);
+    }
+
+    /// The exit status for `--exit-status`.
+    fn code(&self) -> i32 {
+        match (self.failed, self.last_truthy) {
+            (true, _) => 5,
+            (false, Some(true)) => 0,
+            (false, _) => 1,
+        }
+    }
+}
+
+/// Runs the command again whenever one of the files it reads changes, until it's interrupted.
+#[cfg(not(target_family = "wasm"))]
+fn watch(opt: &Opt) -> ! {
+    let paths = opt
+        .expr_file
+        .iter()
+        .chain(opt.input_file.iter())
+        .cloned()
+        .chain(
+            opt.inputs
+                .iter()
+                .map(PathBuf::from)
+                .filter(|path| path.is_file()),
+        )
+        .chain(opt.document.iter().skip(1).step_by(2).map(PathBuf::from))
+        .collect();
+
+    let error = watch::run(paths).expect_err("Only returns when it fails");
+    eprintln!("{}", error);
+    std::process::exit(2);
+}
+
+#[cfg(target_family = "wasm")]
+fn watch(_: &Opt) -> ! {
+    eprintln!("--watch isn't available on this platform");
+    std::process::exit(2);
+}
+
+/// Writes a result, after a `---` if it's a YAML document that follows another. Results in binary
+/// formats follow each other without anything in between.
+fn write_result(
+    out: &mut Output,
+    status: &mut Status,
+    result: &[u8],
+    truthy: bool,
+    format: &Format,
+) {
+    if format.output == OutputFormat::Yaml && status.last_truthy.is_some() {
+        writeln!(out, "---").expect("Could not write the output");
+    }
+    status.last_truthy = Some(truthy);
+    out.write_all(result).expect("Could not write the output");
+    if !format.output.is_binary() && format.newline {
+        writeln!(out).expect("Could not write the output");
+    }
+}
+
+/// Reports an error with the expression or the input. Errors go to stderr when writing to a file,
+/// so that they don't end up in it.
+fn report(out: &mut Output, error: &dyn std::fmt::Display) {
+    if out.is_file() {
+        eprintln!("{}", error);
+    } else {
+        writeln!(out, "{}", error).expect("Could not write the output");
+    }
+}
+
+/// Splits the inputs into JSON given on the command line and input files. A single positional
+/// input is JSON, unless it's the name of a file.
+fn inputs(input_files: Vec<PathBuf>, inputs: Vec<String>) -> (Option<String>, Vec<PathBuf>) {
+    match <[String; 1]>::try_from(inputs) {
+        Ok([input]) if !Path::new(&input).is_file() => (Some(input), input_files),
+        Ok([input]) => (None, [input_files, vec![input.into()]].concat()),
+        Err(inputs) => (
+            None,
+            input_files
+                .into_iter()
+                .chain(inputs.into_iter().map(PathBuf::from))
+                .collect(),
+        ),
+    }
+}
+
+/// Reads the inputs into a single JSON array: the input given on the command line, the input files
+/// or stdin, with a member for each of them, or for each of their lines with `jsonl` or their
+/// documents in other formats. Exits if an input can't be read or isn't valid.
+fn slurp(
+    input: Option<String>,
+    input_files: &[PathBuf],
+    jsonl: bool,
+    input_format: InputFormat,
+) -> String {
+    let fail = |source: &dyn std::fmt::Display, error: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}: {}", source, error);
+        std::process::exit(2);
+    };
+
+    let sources: Vec<(String, Vec<u8>)> = match input {
+        Some(input) => vec![("input".to_string(), input.into_bytes())],
+        None if input_files.is_empty() => {
+            let mut input = vec![];
+            io::stdin()
+                .read_to_end(&mut input)
+                .unwrap_or_else(|e| fail(&"stdin", &e));
+            vec![("stdin".to_string(), input)]
+        }
+        None => input_files
+            .iter()
+            .map(|file| {
+                let input = std::fs::read(file).unwrap_or_else(|e| fail(&file.display(), &e));
+                (file.display().to_string(), input)
+            })
+            .collect(),
+    };
+
+    // The inputs are joined as JSON text, so documents which are decoded into values are
+    // serialized again
+    let arena = Bump::new();
+    let mut members = vec![];
+    for (source, input) in sources.into_iter() {
+        let documents: Vec<String> = if jsonl {
+            let input = String::from_utf8(input).unwrap_or_else(|e| fail(&source, &e));
+            input.lines().map(str::to_string).collect()
+        } else {
+            let documents = input_format
+                .read(input, &arena)
+                .unwrap_or_else(|e| fail(&source, &e));
+            documents.into_iter().map(Document::into_json).collect()
+        };
+
+        for document in documents.into_iter().filter(|d| !d.trim().is_empty()) {
+            // Checked separately, so that one input can't run into the next
+            if let Err(error) = serde_json::from_str::<serde::de::IgnoredAny>(&document) {
+                fail(&source, &error);
+            }
+            members.push(document);
+        }
+    }
+
+    format!("[{}]", members.join(","))
+}
+
+/// Evaluates the expression against each line of the input, writing each result on its own line.
+/// Records that fail to evaluate are reported on stderr and skipped, and undefined results write
+/// nothing. Returns whether every record was evaluated, and whether the last result was truthy.
+fn evaluate_jsonl<'d>(
+    compiled: &CompiledExpression,
+    bindings: &[(String, serde_json::Value)],
+    reader: impl BufRead,
+    format: &Format,
+    setup: &Setup<'d>,
+    arena: &'d Bump,
+    out: &mut impl Write,
+) -> Status {
+    let mut cursor = NdjsonCursor::new(reader);
+    let mut status = Status::default();
+
+    match setup.documents {
+        // The documents are only parsed once, so every line is evaluated in their arena, which
+        // then grows with the length of the input
+        Some(ref documents) => {
+            let jsonata = setup.bind(compiled, bindings, arena);
+            jsonata.use_documents(documents);
+            while evaluate_record(&mut cursor, &jsonata, format, out, &mut status) {}
+        }
+        // Otherwise each line gets a fresh arena, so memory use doesn't grow with the length of
+        // the input
+        None => loop {
+            let arena = Bump::new();
+            let jsonata = setup.bind(compiled, bindings, &arena);
+            if !evaluate_record(&mut cursor, &jsonata, format, out, &mut status) {
+                break;
+            }
+        },
+    }
+
+    status
+}
+
+/// Evaluates the next record of JSON Lines and writes its result, returning false once there are
+/// no more records.
+fn evaluate_record<'a>(
+    cursor: &mut NdjsonCursor<impl BufRead>,
+    jsonata: &JsonAta<'a>,
+    format: &Format,
+    out: &mut impl Write,
+    status: &mut Status,
+) -> bool {
+    match cursor.evaluate_next(jsonata) {
+        Some(Ok(result)) => {
+            status.last_truthy = Some(result.is_truthy());
+            if !result.is_undefined() {
+                let format = Format {
+                    json: SerializeOptions {
+                        indent: None,
+                        ..format.json.clone()
+                    },
+                    ..format.clone()
+                };
+                out.write_all(&format_result(jsonata, result, &format))
+                    .and_then(|_| writeln!(out))
+                    .expect("Could not write the output");
+            }
+        }
+        Some(Err(error)) => {
+            status.failed = true;
+            // The checkpoint is before the record which failed
+            eprintln!("record {}: {}", cursor.checkpoint().records + 1, error);
+        }
+        None => return false,
+    }
+    true
+}
+
+/// What's set up for each evaluation, besides the bindings.
+struct Setup<'d> {
+    preserve_numbers: bool,
+
+    /// Bind the functions for reading files
+    allow_fs: bool,
+
+    /// The documents given with `--document`, if there are any
+    documents: Option<Documents<'d>>,
+}
+
+impl Setup<'_> {
+    /// Sets up an evaluation in any arena, apart from binding the documents, which can only be
+    /// used in their own.
+    fn apply(&self, jsonata: &mut JsonAta) {
+        jsonata.set_preserve_numbers(self.preserve_numbers);
+        if self.allow_fs {
+            fs::allow(jsonata);
+        }
+    }
+
+    /// Creates a [`JsonAta`] for evaluating the expression in `arena`, with the bindings.
+    fn bind<'a>(
+        &self,
+        compiled: &CompiledExpression,
+        bindings: &[(String, serde_json::Value)],
+        arena: &'a Bump,
+    ) -> JsonAta<'a> {
+        let mut jsonata = compiled.bind(arena);
+        self.apply(&mut jsonata);
+        for (name, value) in bindings.iter() {
+            jsonata.assign_json(name, value);
+        }
+        jsonata
+    }
+}
+
+/// How results are printed.
+#[derive(Debug, Clone)]
+struct Format {
+    output: OutputFormat,
+
+    /// Print strings as they are, rather than as JSON
+    raw_output: bool,
+    json: SerializeOptions,
+    color: bool,
+
+    /// Print a newline after each result in a text format
+    newline: bool,
+}
+
+/// Serializes a result for printing, unless `raw_output` is set and it's a string, in which case
+/// it's printed as is. Strings in binary formats are never printed as they are.
+fn format_result<'a>(jsonata: &JsonAta<'a>, result: &'a Value<'a>, format: &Format) -> Vec<u8> {
+    match format.output {
+        OutputFormat::Msgpack => return jsonata_rs::msgpack::to_vec(result),
+        #[cfg(feature = "cbor")]
+        OutputFormat::Cbor => {
+            return jsonata_rs::cbor::to_vec(result, jsonata_rs::cbor::ByteStrings::Tagged)
+        }
+        _ => {}
+    }
+
+    let formatted = if format.raw_output && result.is_string() {
+        result.as_str().into_owned()
+    } else if format.output == OutputFormat::Csv {
+        formats::to_csv(jsonata, result).trim_end().to_string()
+    } else if format.output == OutputFormat::Yaml {
+        if result.is_undefined() {
+            String::new()
+        } else {
+            let yaml = formats::json_to_yaml(&jsonata.serialize(result, false));
+            yaml.trim_end().to_string()
+        }
+    } else if format.color {
+        color::colorize(&jsonata.serialize_with(result, &format.json))
+    } else {
+        jsonata.serialize_with(result, &format.json)
+    };
+    formatted.into_bytes()
+}
+
+/// Collects the variables bound with `--arg` and `--argjson`, each of which is a flattened list of
+/// name and value pairs. Exits if an `--argjson` value isn't valid JSON.
+fn bindings(args: &[String], json_args: &[String]) -> Vec<(String, serde_json::Value)> {
+    let mut bindings = Vec::with_capacity((args.len() + json_args.len()) / 2);
+
+    for pair in args.chunks_exact(2) {
+        bindings.push((pair[0].clone(), serde_json::Value::String(pair[1].clone())));
+    }
+
+    for pair in json_args.chunks_exact(2) {
+        match serde_json::from_str(&pair[1]) {
+            Ok(value) => bindings.push((pair[0].clone(), value)),
+            Err(error) => {
+                eprintln!("Invalid JSON for --argjson {}: {}", pair[0], error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    bindings
+}
+
+/// Reads and parses the documents given with `--document`, a flattened list of name and file
+/// pairs, into `arena`. Exits if a file can't be read or isn't valid JSON, before anything has
+/// been written.
+fn read_documents<'a>(arena: &'a Bump, args: &[String]) -> Option<Documents<'a>> {
+    if args.is_empty() {
+        return None;
+    }
+
+    let mut documents = Documents::new(arena);
+    for pair in args.chunks_exact(2) {
+        let document = std::fs::read_to_string(&pair[1]).unwrap_or_else(|error| {
+            eprintln!("Could not read --document {}: {}", pair[0], error);
+            std::process::exit(2);
+        });
+        if let Err(error) = documents.add(&pair[0], &document) {
+            eprintln!("Invalid JSON for --document {}: {}", pair[0], error);
+            std::process::exit(2);
+        }
+    }
+    Some(documents)
+}
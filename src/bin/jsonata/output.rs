@@ -0,0 +1,153 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Stdout, Write};
+use std::path::PathBuf;
+
+/// Where results are written: stdout, or a file which is only replaced once everything has been
+/// written to a temporary file next to it, so that it's left as it was if anything fails.
+pub enum Output {
+    Stdout(Stdout),
+    File {
+        // Only taken when committing
+        writer: Option<BufWriter<File>>,
+        temp: PathBuf,
+        path: PathBuf,
+    },
+}
+
+impl Output {
+    pub fn new(path: Option<PathBuf>) -> io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Output::Stdout(io::stdout()));
+        };
+
+        // The temporary file has to be on the same filesystem for the rename to be atomic
+        let name = path.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "The output must be a file")
+        })?;
+        let temp = path.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+
+        let file = File::create(&temp)?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+
+        Ok(Output::File {
+            writer: Some(BufWriter::new(file)),
+            temp,
+            path,
+        })
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self, Output::File { .. })
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Output::Stdout(stdout) => stdout.is_terminal(),
+            Output::File { .. } => false,
+        }
+    }
+
+    /// Finishes writing, moving the temporary file into place.
+    pub fn commit(mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(ref mut stdout) => stdout.flush(),
+            Output::File {
+                ref mut writer,
+                ref temp,
+                ref path,
+            } => {
+                if let Some(writer) = writer.take() {
+                    // The file is closed before it's renamed, which some platforms require
+                    let file = writer.into_inner().map_err(|e| e.into_error())?;
+                    file.sync_all()?;
+                }
+                fs::rename(temp, path)
+            }
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(stdout) => stdout.write(buf),
+            Output::File { writer, .. } => writer
+                .as_mut()
+                .expect("Output already committed")
+                .write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(stdout) => stdout.flush(),
+            Output::File { writer, .. } => writer.as_mut().map_or(Ok(()), |writer| writer.flush()),
+        }
+    }
+}
+
+impl Drop for Output {
+    // An output that wasn't committed leaves the file it would have replaced alone
+    fn drop(&mut self) {
+        if let Output::File { ref temp, .. } = self {
+            let _ = fs::remove_file(temp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("jsonata-output-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn replaces_on_commit() {
+        let dir = temp_dir("commit");
+        let path = dir.join("data.json");
+        fs::write(&path, "old").unwrap();
+
+        let mut output = Output::new(Some(path.clone())).unwrap();
+        write!(output, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+
+        output.commit().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(entries(&dir), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn left_alone_without_commit() {
+        let dir = temp_dir("drop");
+        let path = dir.join("data.json");
+        fs::write(&path, "old").unwrap();
+
+        let mut output = Output::new(Some(path.clone())).unwrap();
+        write!(output, "partial").unwrap();
+        drop(output);
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(entries(&dir), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
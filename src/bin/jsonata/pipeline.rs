@@ -0,0 +1,292 @@
+//! Pipelines run several expressions one after the other, each against the result of the one
+//! before, in the same process and arena. They're described by a manifest, `pipeline.json`:
+//!
+//! ```json
+//! {
+//!   "name": "orders",
+//!   "stages": [
+//!     { "name": "clean", "expr-file": "clean.jsonata", "input": "jsonl" },
+//!     { "name": "total", "expr": "$sum(amount)" }
+//!   ]
+//! }
+//! ```
+//!
+//! Each stage has an expression, either inline or in a file relative to the manifest, and format
+//! adapters for either side of it:
+//!
+//! - `input`: with `"json"`, the default, the stage is evaluated once against the whole input.
+//!   With `"jsonl"`, it's evaluated against each record, and the results are collected into an
+//!   array. For the first stage, records are the lines of the input; after that they're the members
+//!   of the previous stage's result.
+//! - `output`: how the last stage's result is printed. With `"json"`, the default, it's printed as
+//!   a single document. With `"jsonl"`, each member is printed on its own line.
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use serde_json::Value as Json;
+
+use jsonata_rs::{ArrayFlags, JsonAta, Value};
+
+/// The name of the manifest when a directory is given.
+const MANIFEST: &str = "pipeline.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Jsonl,
+}
+
+#[derive(Debug)]
+struct Stage {
+    name: String,
+    expr: String,
+    input: Format,
+    output: Format,
+}
+
+#[derive(Debug)]
+pub struct Pipeline {
+    name: String,
+    stages: Vec<Stage>,
+}
+
+#[derive(Debug)]
+pub struct PipelineError(String);
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn error(message: impl Into<String>) -> PipelineError {
+    PipelineError(message.into())
+}
+
+impl Pipeline {
+    /// Loads a pipeline from a manifest, or a directory containing a `pipeline.json` manifest.
+    pub fn load(path: &Path) -> Result<Self, PipelineError> {
+        let path = if path.is_dir() {
+            path.join(MANIFEST)
+        } else {
+            path.to_path_buf()
+        };
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let manifest = std::fs::read_to_string(&path)
+            .map_err(|e| error(format!("{}: {}", path.display(), e)))?;
+        let manifest: Json = serde_json::from_str(&manifest)
+            .map_err(|e| error(format!("{}: {}", path.display(), e)))?;
+
+        Self::from_manifest(&manifest, &dir)
+            .map_err(|e| error(format!("{}: {}", path.display(), e)))
+    }
+
+    fn from_manifest(manifest: &Json, dir: &Path) -> Result<Self, PipelineError> {
+        let name = manifest["name"].as_str().unwrap_or("pipeline").to_string();
+        let stages = manifest["stages"]
+            .as_array()
+            .filter(|stages| !stages.is_empty())
+            .ok_or_else(|| error("`stages` must be a non-empty array"))?;
+
+        let stages = stages
+            .iter()
+            .enumerate()
+            .map(|(index, stage)| Stage::from_manifest(index, stage, dir))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(stage) = stages[..stages.len() - 1]
+            .iter()
+            .find(|stage| stage.output != Format::Json)
+        {
+            return Err(error(format!(
+                "stage `{}`: `output` only applies to the last stage",
+                stage.name
+            )));
+        }
+
+        Ok(Self { name, stages })
+    }
+
+    /// Runs the stages against `input`, writing the last stage's result to `out`.
+    pub fn run(
+        &self,
+        input: &str,
+        pretty: bool,
+        out: &mut impl Write,
+    ) -> Result<(), PipelineError> {
+        let arena = Bump::new();
+        let mut value: Option<&Value> = None;
+
+        for stage in self.stages.iter() {
+            let fail = |e: &dyn fmt::Display| error(format!("{}/{}: {}", self.name, stage.name, e));
+            let jsonata = JsonAta::new(&stage.expr, &arena).map_err(|e| fail(&e))?;
+
+            let result = match (value, stage.input) {
+                (None, Format::Json) => jsonata.evaluate(Some(input), None),
+                (Some(value), Format::Json) => jsonata.evaluate_value(value),
+                (None, Format::Jsonl) => each(
+                    input.lines().filter(|line| !line.trim().is_empty()),
+                    &arena,
+                    |line| jsonata.evaluate(Some(line), None),
+                ),
+                (Some(value), Format::Jsonl) => {
+                    let records: Vec<_> = if value.is_array() {
+                        value.members().collect()
+                    } else if value.is_undefined() {
+                        vec![]
+                    } else {
+                        vec![value]
+                    };
+                    each(records.into_iter(), &arena, |record| {
+                        jsonata.evaluate_value(record)
+                    })
+                }
+            };
+            value = Some(result.map_err(|e| fail(&e))?);
+        }
+
+        let result = value.expect("There's always a stage");
+        let last = self.stages.last().expect("There's always a stage");
+        match last.output {
+            _ if result.is_undefined() => Ok(()),
+            Format::Json => write(out, result, pretty),
+            Format::Jsonl if result.is_array() => result
+                .members()
+                .filter(|member| !member.is_undefined())
+                .try_for_each(|member| write(out, member, false)),
+            Format::Jsonl => write(out, result, false),
+        }
+    }
+}
+
+fn write<'a>(
+    out: &mut impl Write,
+    value: &'a Value<'a>,
+    pretty: bool,
+) -> Result<(), PipelineError> {
+    writeln!(out, "{}", value.serialize(pretty))
+        .map_err(|e| error(format!("Could not write the output: {}", e)))
+}
+
+/// Evaluates a stage against each record, collecting the defined results into an array.
+fn each<'a, T>(
+    records: impl Iterator<Item = T>,
+    arena: &'a Bump,
+    mut evaluate: impl FnMut(T) -> jsonata_rs::Result<&'a Value<'a>>,
+) -> jsonata_rs::Result<&'a Value<'a>> {
+    let results = Value::array(arena, ArrayFlags::empty());
+    for record in records {
+        let result = evaluate(record)?;
+        if !result.is_undefined() {
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+impl Stage {
+    fn from_manifest(index: usize, stage: &Json, dir: &Path) -> Result<Self, PipelineError> {
+        let name = stage["name"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| (index + 1).to_string());
+        let fail = |message: &str| error(format!("stage `{}`: {}", name, message));
+
+        let expr = match (stage["expr"].as_str(), stage["expr-file"].as_str()) {
+            (Some(expr), None) => expr.to_string(),
+            (None, Some(file)) => {
+                let file: PathBuf = dir.join(file);
+                std::fs::read_to_string(&file)
+                    .map_err(|e| fail(&format!("{}: {}", file.display(), e)))?
+            }
+            _ => return Err(fail("needs either `expr` or `expr-file`")),
+        };
+
+        let format = |key: &str| match stage[key].as_str() {
+            None | Some("json") => Ok(Format::Json),
+            Some("jsonl") => Ok(Format::Jsonl),
+            Some(other) => Err(fail(&format!(
+                "unknown `{}` format `{}`, expected `json` or `jsonl`",
+                key, other
+            ))),
+        };
+
+        Ok(Self {
+            input: format("input")?,
+            output: format("output")?,
+            name,
+            expr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn run(manifest: Json, input: &str) -> Result<String, String> {
+        let pipeline =
+            Pipeline::from_manifest(&manifest, Path::new(".")).map_err(|e| e.to_string())?;
+        let mut out = vec![];
+        pipeline
+            .run(input, false, &mut out)
+            .map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn stages() {
+        let manifest = json!({
+            "name": "orders",
+            "stages": [
+                { "name": "paid", "expr": "orders[paid]" },
+                { "name": "total", "expr": "$sum(amount)" }
+            ]
+        });
+        let input = r#"{"orders": [{"amount": 1, "paid": true}, {"amount": 2, "paid": false}, {"amount": 4, "paid": true}]}"#;
+        assert_eq!(run(manifest, input).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn format_adapters() {
+        let manifest = json!({
+            "stages": [
+                { "expr": "amount * 2", "input": "jsonl" },
+                { "expr": "$ > 2 ? { 'big': $ }", "input": "jsonl", "output": "jsonl" }
+            ]
+        });
+        let input = "{\"amount\": 1}\n\n{\"amount\": 2}\n{\"amount\": 3}\n";
+        assert_eq!(run(manifest, input).unwrap(), "{\"big\":4}\n{\"big\":6}\n");
+    }
+
+    #[test]
+    fn errors_name_the_stage() {
+        let manifest = json!({
+            "name": "p",
+            "stages": [{ "name": "one", "expr": "$error('oops')" }]
+        });
+        assert_eq!(run(manifest, "{}").unwrap_err(), "p/one: D3137 @ oops");
+
+        let manifest = json!({ "stages": [{ "name": "one" }] });
+        assert_eq!(
+            run(manifest, "{}").unwrap_err(),
+            "stage `one`: needs either `expr` or `expr-file`"
+        );
+
+        let manifest = json!({
+            "stages": [{ "expr": "$", "output": "jsonl" }, { "expr": "$" }]
+        });
+        assert_eq!(
+            run(manifest, "{}").unwrap_err(),
+            "stage `1`: `output` only applies to the last stage"
+        );
+
+        assert!(run(json!({ "stages": [] }), "{}").is_err());
+    }
+}
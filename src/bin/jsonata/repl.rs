@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Editor, Helper};
+
+use jsonata_rs::{JsonAta, Value};
+
+const HELP: &str = "\
+Enter an expression to evaluate it against the input. Expressions with unclosed brackets,
+strings or comments continue onto the next line, and `$name := ...` keeps the variable for
+later expressions.
+
+:ast [expr]   Print the AST of an expression, or of the last one evaluated
+:bindings     List the variables bound so far
+:help         Show this message
+:quit         Exit (or Ctrl-D)";
+
+/// Runs an interactive prompt which evaluates expressions against the contents of `input_file`.
+pub fn run(input_file: Option<PathBuf>) -> rustyline::Result<()> {
+    let input = input_file.map(|input_file| {
+        std::fs::read_to_string(input_file).expect("Could not read the JSON input file")
+    });
+
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper));
+
+    let history = std::env::var_os("HOME").map(|home| Path::new(&home).join(".jsonata_history"));
+    if let Some(ref history) = history {
+        // There won't be a history file the first time around
+        let _ = editor.load_history(history);
+    }
+
+    // Everything lives as long as the session, so that bound values (including functions, which
+    // capture the frame they were defined in) stay valid for later expressions
+    let arena = Bump::new();
+    let mut bindings: BTreeMap<String, &Value> = BTreeMap::new();
+    let mut last_expr: Option<String> = None;
+
+    println!("Type :help for help");
+
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error),
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        if let Some(command) = line.strip_prefix(':') {
+            let (command, arg) = command
+                .split_once(char::is_whitespace)
+                .unwrap_or((command, ""));
+            match command {
+                "ast" => {
+                    let expr = if arg.trim().is_empty() {
+                        last_expr.as_deref()
+                    } else {
+                        Some(arg)
+                    };
+                    match expr.map(|expr| JsonAta::new(expr, &arena)) {
+                        #[cfg(feature = "unstable-ast")]
+                        Some(Ok(jsonata)) => println!("{:#?}", jsonata.ast()),
+                        #[cfg(not(feature = "unstable-ast"))]
+                        Some(Ok(jsonata)) => println!("{:#}", jsonata.ast_json()),
+                        Some(Err(error)) => println!("{}", error),
+                        None => println!("No expression has been evaluated yet"),
+                    }
+                }
+                "bindings" => {
+                    for (name, value) in bindings.iter() {
+                        println!("${} = {}", name, value.serialize(false));
+                    }
+                }
+                "help" => println!("{}", HELP),
+                "quit" | "q" => break,
+                _ => println!("Unknown command `:{}`, type :help for help", command),
+            }
+            continue;
+        }
+
+        let jsonata = match JsonAta::new(line, &arena) {
+            Ok(jsonata) => jsonata,
+            Err(error) => {
+                println!("{}", error);
+                continue;
+            }
+        };
+        last_expr = Some(line.to_string());
+
+        for (name, value) in bindings.iter() {
+            jsonata.assign_var(name, value);
+        }
+
+        match jsonata.evaluate(input.as_deref(), None) {
+            Ok(result) => {
+                if result.is_undefined() {
+                    println!("undefined");
+                } else {
+                    println!("{}", result.serialize(true));
+                }
+                if let Some(name) = bound_variable(line) {
+                    bindings.insert(name.to_string(), result);
+                }
+            }
+            Err(error) => println!("{}", error),
+        }
+    }
+
+    if let Some(ref history) = history {
+        editor.save_history(history)?;
+    }
+
+    Ok(())
+}
+
+/// The name of the variable bound by an expression of the form `$name := ...`.
+fn bound_variable(expr: &str) -> Option<&str> {
+    let rest = expr.strip_prefix('$')?;
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(end);
+    if !name.is_empty() && rest.trim_start().starts_with(":=") {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Whether an expression has unclosed brackets, strings or comments, and so should carry on onto
+/// the next line.
+fn is_incomplete(expr: &str) -> bool {
+    let mut depth = 0isize;
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '"' | '\'' | '`' => loop {
+                match chars.next() {
+                    Some('\\') if c != '`' => {
+                        chars.next();
+                    }
+                    Some(end) if end == c => break,
+                    Some(_) => {}
+                    None => return true,
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('*') if chars.peek() == Some(&'/') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(_) => {}
+                        None => return true,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+struct ReplHelper;
+
+impl Helper for ReplHelper {}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        if !input.starts_with(':') && is_incomplete(input) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("$x := 1", Some("x"))]
+    #[test_case("$total_2:=$sum(a)", Some("total_2"))]
+    #[test_case("$x = 1", None)]
+    #[test_case("$ := 1", None)]
+    #[test_case("($x := 1)", None)]
+    fn bound_variables(expr: &str, name: Option<&str>) {
+        assert_eq!(bound_variable(expr), name);
+    }
+
+    #[test_case("a.b", false)]
+    #[test_case("$f := function($x) {", true)]
+    #[test_case("a[0", true)]
+    #[test_case("\"(\"", false)]
+    #[test_case("\"unterminated \\\"", true)]
+    #[test_case("a /* comment", true)]
+    #[test_case("a /* ( */ + 1", false)]
+    #[test_case("a)", false)]
+    fn incomplete(expr: &str, incomplete: bool) {
+        assert_eq!(is_incomplete(expr), incomplete);
+    }
+}
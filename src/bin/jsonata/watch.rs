@@ -0,0 +1,56 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs the command again, without `--watch`, every time one of `paths` changes, clearing the
+/// screen before each run. Changes are found by polling modification times, which works the same
+/// on every platform and filesystem.
+pub fn run(paths: Vec<PathBuf>) -> io::Result<()> {
+    if paths.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--watch needs an expression file or an input file to watch",
+        ));
+    }
+
+    let exe = std::env::current_exe()?;
+    let args: Vec<OsString> = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--watch")
+        .collect();
+
+    loop {
+        // Taken before running, so that a change made during the run causes another one
+        let before = modified(&paths);
+
+        print!("\x1b[2J\x1b[H");
+        io::stdout().flush()?;
+        Command::new(&exe).args(&args).status()?;
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let now = modified(&paths);
+            // A file that's missing, for example while an editor is replacing it, is waited for
+            if now != before && now.iter().all(Option::is_some) {
+                break;
+            }
+        }
+    }
+}
+
+fn modified(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
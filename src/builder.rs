@@ -0,0 +1,328 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use bumpalo::Bump;
+
+use crate::{
+    Backend, FunctionContext, FunctionPolicy, JsonAta, Operators, Progress, RandomSource, Result,
+    StringUnits, TraceStep, Value, WatchdogAction,
+};
+
+type NativeFn<'a> = fn(FunctionContext<'a, '_>, &'a Value<'a>) -> Result<&'a Value<'a>>;
+type WatchdogFn<'a> = Box<dyn FnMut(&Progress) -> WatchdogAction + 'a>;
+type TraceFn<'a> = Box<dyn FnMut(&TraceStep<'a>) + 'a>;
+
+/// Configures a [`JsonAta`] before it's created, with the same options as its setters, see
+/// [`JsonAta::builder`].
+pub struct JsonAtaBuilder<'a> {
+    operators: Option<Operators>,
+    backend: Backend,
+    max_depth: Option<usize>,
+    timeout: Option<Duration>,
+    gas_limit: Option<u64>,
+    random: Option<Rc<RefCell<dyn RandomSource + 'a>>>,
+    random_seed: Option<u64>,
+    string_units: StringUnits,
+    policy: FunctionPolicy,
+    incremental: bool,
+    watchdog: Option<(u64, WatchdogFn<'a>)>,
+    trace: Option<TraceFn<'a>>,
+    variables: Vec<(String, &'a Value<'a>)>,
+    json_variables: Vec<(String, serde_json::Value)>,
+    functions: Vec<(String, usize, NativeFn<'a>)>,
+    #[cfg(feature = "icu")]
+    locale: Option<String>,
+    #[cfg(feature = "decimal")]
+    decimal: bool,
+    #[cfg(feature = "fetch")]
+    fetch: Option<Vec<String>>,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: Option<usize>,
+}
+
+impl<'a> JsonAta<'a> {
+    /// Starts configuring a [`JsonAta`], for hosts which set several options, rather than
+    /// creating one and calling its setters:
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::{FunctionPolicy, JsonAta};
+    /// let arena = Bump::new();
+    /// let jsonata = JsonAta::builder()
+    ///     .max_depth(100)
+    ///     .timeout(Duration::from_secs(1))
+    ///     .function_policy(FunctionPolicy::deny(["shuffle"]))
+    ///     .json_variable("rate", serde_json::json!(2))
+    ///     .build("price * $rate", &arena)?;
+    /// assert_eq!(jsonata.evaluate(Some(r#"{"price": 3}"#), None)?.as_f64(), 6.0);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn builder() -> JsonAtaBuilder<'a> {
+        JsonAtaBuilder {
+            operators: None,
+            backend: Backend::default(),
+            max_depth: None,
+            timeout: None,
+            gas_limit: None,
+            random: None,
+            random_seed: None,
+            string_units: StringUnits::default(),
+            policy: FunctionPolicy::AllowAll,
+            incremental: false,
+            watchdog: None,
+            trace: None,
+            variables: vec![],
+            json_variables: vec![],
+            functions: vec![],
+            #[cfg(feature = "icu")]
+            locale: None,
+            #[cfg(feature = "decimal")]
+            decimal: false,
+            #[cfg(feature = "fetch")]
+            fetch: None,
+            #[cfg(feature = "parallel")]
+            parallel_threshold: None,
+        }
+    }
+}
+
+impl<'a> JsonAtaBuilder<'a> {
+    /// Parses with `operators` as well as the built-in ones, see [`JsonAta::with_operators`].
+    pub fn operators(mut self, operators: Operators) -> Self {
+        self.operators = Some(operators);
+        self
+    }
+
+    /// See [`JsonAta::set_backend`].
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// See [`JsonAta::set_max_depth`].
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// See [`JsonAta::set_timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`JsonAta::set_gas_limit`].
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// See [`JsonAta::set_random_source`].
+    pub fn random_source(mut self, random: impl RandomSource + 'a) -> Self {
+        self.random = Some(Rc::new(RefCell::new(random)));
+        self.random_seed = None;
+        self
+    }
+
+    /// See [`JsonAta::set_random_seed`].
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random = None;
+        self.random_seed = Some(seed);
+        self
+    }
+
+    /// See [`JsonAta::set_string_units`].
+    pub fn string_units(mut self, string_units: StringUnits) -> Self {
+        self.string_units = string_units;
+        self
+    }
+
+    /// See [`JsonAta::set_function_policy`].
+    pub fn function_policy(mut self, policy: FunctionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// See [`JsonAta::set_incremental`].
+    pub fn incremental(mut self, incremental: bool) -> Self {
+        self.incremental = incremental;
+        self
+    }
+
+    /// See [`JsonAta::set_watchdog`].
+    pub fn watchdog(
+        mut self,
+        interval: u64,
+        callback: impl FnMut(&Progress) -> WatchdogAction + 'a,
+    ) -> Self {
+        self.watchdog = Some((interval, Box::new(callback)));
+        self
+    }
+
+    /// See [`JsonAta::set_trace`].
+    pub fn trace(mut self, callback: impl FnMut(&TraceStep<'a>) + 'a) -> Self {
+        self.trace = Some(Box::new(callback));
+        self
+    }
+
+    /// Binds a variable, see [`JsonAta::assign_var`].
+    pub fn variable(mut self, name: &str, value: &'a Value<'a>) -> Self {
+        self.variables.push((name.to_string(), value));
+        self
+    }
+
+    /// Binds a variable to a JSON value, see [`JsonAta::assign_json`].
+    pub fn json_variable(mut self, name: &str, value: serde_json::Value) -> Self {
+        self.json_variables.push((name.to_string(), value));
+        self
+    }
+
+    /// See [`JsonAta::register_function`].
+    pub fn function(mut self, name: &str, arity: usize, implementation: NativeFn<'a>) -> Self {
+        self.functions
+            .push((name.to_string(), arity, implementation));
+        self
+    }
+
+    /// See [`JsonAta::set_locale`]. An unknown locale fails when the expression is built.
+    #[cfg(feature = "icu")]
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_string());
+        self
+    }
+
+    /// See [`JsonAta::set_decimal`].
+    #[cfg(feature = "decimal")]
+    pub fn decimal(mut self, decimal: bool) -> Self {
+        self.decimal = decimal;
+        self
+    }
+
+    /// See [`JsonAta::allow_fetch`].
+    #[cfg(feature = "fetch")]
+    pub fn allow_fetch<S: Into<String>>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+        self.fetch = Some(prefixes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// See [`JsonAta::set_parallel_threshold`].
+    #[cfg(feature = "parallel")]
+    pub fn parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = Some(threshold);
+        self
+    }
+
+    /// Parses `expr` and creates a [`JsonAta`] for evaluating it in `arena` with the options.
+    pub fn build(self, expr: &str, arena: &'a Bump) -> Result<JsonAta<'a>> {
+        let mut jsonata = match self.operators {
+            Some(ref operators) => JsonAta::with_operators(expr, arena, operators)?,
+            None => JsonAta::new(expr, arena)?,
+        };
+
+        jsonata.set_backend(self.backend);
+        jsonata.set_max_depth(self.max_depth);
+        jsonata.set_timeout(self.timeout);
+        jsonata.set_gas_limit(self.gas_limit);
+        jsonata.random = self.random;
+        jsonata.random_seed = self.random_seed;
+        jsonata.set_string_units(self.string_units);
+        jsonata.set_function_policy(self.policy);
+        if let Some((interval, callback)) = self.watchdog {
+            jsonata.set_watchdog(interval, callback);
+        }
+        if let Some(callback) = self.trace {
+            jsonata.set_trace(callback);
+        }
+        for (name, value) in self.variables {
+            jsonata.assign_var(&name, value);
+        }
+        for (name, value) in self.json_variables {
+            jsonata.assign_json(&name, &value);
+        }
+        for (name, arity, implementation) in self.functions {
+            jsonata.register_function(&name, arity, implementation);
+        }
+        #[cfg(feature = "icu")]
+        jsonata.set_locale(self.locale.as_deref())?;
+        #[cfg(feature = "decimal")]
+        jsonata.set_decimal(self.decimal);
+        #[cfg(feature = "fetch")]
+        if let Some(prefixes) = self.fetch {
+            jsonata.allow_fetch(prefixes);
+        }
+        #[cfg(feature = "parallel")]
+        jsonata.set_parallel_threshold(self.parallel_threshold);
+        // Last, as the remembered results depend on the number mode
+        jsonata.set_incremental(self.incremental);
+
+        Ok(jsonata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::builder()
+            .random_seed(7)
+            .variable("n", Value::number(&arena, 3))
+            .function("double", 1, |context, args| {
+                Ok(Value::number(context.arena, args[0].as_f64() * 2.0))
+            })
+            .build("[$double($n), $random()]", &arena)
+            .unwrap();
+        let first = jsonata.evaluate(None, None).unwrap().serialize(false);
+        assert!(first.starts_with("[6,"));
+        assert_eq!(
+            jsonata.evaluate(None, None).unwrap().serialize(false),
+            first
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::builder()
+            .max_depth(10)
+            .build(
+                "($f := function($n) { $n = 0 ? 0 : 1 + $f($n - 1) }; $f(20))",
+                &arena,
+            )
+            .unwrap();
+        assert_eq!(jsonata.evaluate(None, None).unwrap_err().code(), "U1001");
+        // A limit given to the evaluation replaces it
+        assert!(jsonata.evaluate_timeboxed(None, Some(1000), None).is_ok());
+
+        let jsonata = JsonAta::builder()
+            .timeout(Duration::ZERO)
+            .build("[1..1000].($ * 2)", &arena)
+            .unwrap();
+        assert_eq!(jsonata.evaluate(None, None).unwrap_err().code(), "U1001");
+
+        let jsonata = JsonAta::builder()
+            .gas_limit(10)
+            .build("[1..1000].($ * 2)", &arena)
+            .unwrap();
+        assert_eq!(jsonata.evaluate(None, None).unwrap_err().code(), "H0302");
+    }
+
+    #[test]
+    fn policy_and_syntax_errors() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::builder()
+            .function_policy(FunctionPolicy::deny(["sum"]))
+            .build("$sum([1])", &arena)
+            .unwrap();
+        assert_eq!(jsonata.evaluate(None, None).unwrap_err().code(), "H0401");
+
+        assert_eq!(
+            JsonAta::builder().build("(", &arena).err().unwrap().code(),
+            "S0211"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::{CompiledExpression, Result};
+
+/// A thread-safe, least-recently-used cache of compiled expressions keyed by their source text.
+///
+/// Expressions are compiled on a miss, and hits hand out a cheap clone of the shared compiled
+/// expression. Expressions which fail to compile are not cached.
+pub struct ExpressionCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    // Source text of each entry keyed by when it was last used, oldest first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+struct Entry {
+    compiled: CompiledExpression,
+    last_used: u64,
+}
+
+impl Inner {
+    fn touch(&mut self, expr: &str) -> Option<CompiledExpression> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(expr)?;
+        let key = self.recency.remove(&entry.last_used)?;
+        entry.last_used = self.tick;
+        self.recency.insert(self.tick, key);
+        Some(entry.compiled.clone())
+    }
+}
+
+impl ExpressionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.recency.clear();
+    }
+
+    /// Returns the compiled form of `expr`, compiling it and evicting the least recently used
+    /// expression if it isn't already cached.
+    pub fn get_or_compile(&self, expr: &str) -> Result<CompiledExpression> {
+        if let Some(compiled) = self.inner.lock().unwrap().touch(expr) {
+            return Ok(compiled);
+        }
+
+        // Compile without holding the lock, so that a slow parse doesn't hold up other threads
+        let compiled = CompiledExpression::new(expr)?;
+
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(existing) = inner.touch(expr) {
+            // Another thread got there first
+            return Ok(existing);
+        }
+
+        if inner.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = inner.recency.pop_first() {
+                inner.entries.remove(&oldest);
+            }
+        }
+
+        let last_used = inner.tick;
+        inner.recency.insert(last_used, expr.to_string());
+        inner.entries.insert(
+            expr.to_string(),
+            Entry {
+                compiled: compiled.clone(),
+                last_used,
+            },
+        );
+
+        Ok(compiled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bumpalo::Bump;
+
+    use super::*;
+
+    #[test]
+    fn hit_returns_shared_expression() {
+        let cache = ExpressionCache::new(10);
+        let a = cache.get_or_compile("1 + 1").unwrap();
+        let b = cache.get_or_compile("1 + 1").unwrap();
+
+        assert!(Arc::ptr_eq(&a.ast, &b.ast));
+        assert_eq!(cache.len(), 1);
+
+        let arena = Bump::new();
+        assert_eq!(b.evaluate(None, &arena).unwrap().as_f64(), 2.0);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ExpressionCache::new(2);
+        let a = cache.get_or_compile("a").unwrap();
+        cache.get_or_compile("b").unwrap();
+
+        // Using `a` again makes `b` the least recently used
+        cache.get_or_compile("a").unwrap();
+        cache.get_or_compile("c").unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&a.ast, &cache.get_or_compile("a").unwrap().ast));
+        let inner = cache.inner.lock().unwrap();
+        assert!(inner.entries.contains_key("c"));
+        assert!(!inner.entries.contains_key("b"));
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = ExpressionCache::new(2);
+        assert!(cache.get_or_compile("(").is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let cache = ExpressionCache::new(4);
+
+        std::thread::scope(|scope| {
+            for n in 0..8 {
+                let cache = &cache;
+                scope.spawn(move || {
+                    let arena = Bump::new();
+                    let compiled = cache.get_or_compile("$ * 2").unwrap();
+                    let result = compiled.evaluate(Some(&n.to_string()), &arena).unwrap();
+                    assert_eq!(result.as_f64(), (n * 2) as f64);
+                });
+            }
+        });
+
+        assert_eq!(cache.len(), 1);
+    }
+}
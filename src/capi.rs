@@ -0,0 +1,250 @@
+//! A C interface, for embedding the engine in other languages:
+//!
+//! ```c
+//! #include "jsonata.h"
+//!
+//! char *error = NULL;
+//! JsonataExpression *expression = jsonata_compile("$sum(items.price)", &error);
+//! char *result = jsonata_evaluate(expression, "{\"items\": [{\"price\": 5}]}", &error);
+//! jsonata_free_string(result);
+//! jsonata_free(expression);
+//! ```
+//!
+//! The header, `include/jsonata.h`, is generated by the build when the `capi` feature is enabled.
+//! Inputs and results are JSON text, and every string returned to the caller is owned by it and
+//! must be released with `jsonata_free_string`.
+//!
+//! A compiled expression can be evaluated from any number of threads at once.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use bumpalo::Bump;
+
+use crate::CompiledExpression;
+
+/// A compiled expression, created by `jsonata_compile` and released by `jsonata_free`.
+pub struct JsonataExpression(CompiledExpression);
+
+/// Compiles an expression.
+///
+/// Returns NULL if the expression isn't valid, in which case `error`, if it isn't NULL, is set to
+/// a description of the problem.
+///
+/// # Safety
+///
+/// `expr` must be a valid, NUL-terminated string, and `error` must be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonata_compile(
+    expr: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut JsonataExpression {
+    let result = guard(|| {
+        let expr = to_str(expr, "expression")?;
+        CompiledExpression::new(expr).map_err(|e| e.to_string())
+    });
+
+    match result {
+        Ok(compiled) => Box::into_raw(Box::new(JsonataExpression(compiled))),
+        Err(message) => {
+            set_error(error, message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Evaluates an expression against `input`, which is JSON text or NULL for no input, and
+/// returns the result as JSON text.
+///
+/// Returns NULL if the evaluation fails, in which case `error`, if it isn't NULL, is set to a
+/// description of the problem. An undefined result is also NULL, but leaves `error` as it was.
+///
+/// # Safety
+///
+/// `expression` must have been returned by `jsonata_compile` and not yet freed, `input` must be
+/// NULL or a valid, NUL-terminated string, and `error` must be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn jsonata_evaluate(
+    expression: *const JsonataExpression,
+    input: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let result = guard(|| {
+        let expression = expression.as_ref().ok_or("The expression is NULL")?;
+        let input = if input.is_null() {
+            None
+        } else {
+            Some(to_str(input, "input")?)
+        };
+
+        let arena = Bump::new();
+        let result = expression
+            .0
+            .evaluate(input, &arena)
+            .map_err(|e| e.to_string())?;
+        Ok((!result.is_undefined()).then(|| result.serialize(false)))
+    });
+
+    match result {
+        Ok(Some(json)) => to_c_string(json),
+        Ok(None) => ptr::null_mut(),
+        Err(message) => {
+            set_error(error, message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases an expression returned by `jsonata_compile`. Does nothing if it's NULL.
+///
+/// # Safety
+///
+/// `expression` must be NULL, or have been returned by `jsonata_compile` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn jsonata_free(expression: *mut JsonataExpression) {
+    if !expression.is_null() {
+        drop(Box::from_raw(expression));
+    }
+}
+
+/// Releases a result or error returned by this library. Does nothing if it's NULL.
+///
+/// # Safety
+///
+/// `string` must be NULL, or have been returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn jsonata_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+// Panics mustn't unwind into the caller, so they're reported like any other error
+fn guard<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Unknown error".to_string());
+        Err(format!("Internal error: {}", message))
+    })
+}
+
+unsafe fn to_str<'a>(string: *const c_char, what: &str) -> Result<&'a str, String> {
+    if string.is_null() {
+        return Err(format!("The {} is NULL", what));
+    }
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| format!("The {} isn't valid UTF-8", what))
+}
+
+fn to_c_string(string: String) -> *mut c_char {
+    // JSON escapes NUL, but an error message might quote one from the input
+    let string = CString::new(string).unwrap_or_else(|e| {
+        CString::new(
+            e.into_vec()
+                .into_iter()
+                .filter(|&b| b != 0)
+                .collect::<Vec<_>>(),
+        )
+        .expect("NULs were removed")
+    });
+    string.into_raw()
+}
+
+unsafe fn set_error(error: *mut *mut c_char, message: String) {
+    if !error.is_null() {
+        *error = to_c_string(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(string: *mut c_char) -> Option<String> {
+        if string.is_null() {
+            return None;
+        }
+        let owned = unsafe { CStr::from_ptr(string) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { jsonata_free_string(string) };
+        Some(owned)
+    }
+
+    fn compile(expr: &str) -> *mut JsonataExpression {
+        let expr = CString::new(expr).unwrap();
+        let mut error = ptr::null_mut();
+        let expression = unsafe { jsonata_compile(expr.as_ptr(), &mut error) };
+        assert_eq!(take(error), None);
+        expression
+    }
+
+    fn evaluate(
+        expression: *const JsonataExpression,
+        input: Option<&str>,
+    ) -> (Option<String>, Option<String>) {
+        let input = input.map(|input| CString::new(input).unwrap());
+        let mut error = ptr::null_mut();
+        let result = unsafe {
+            jsonata_evaluate(
+                expression,
+                input.as_ref().map_or(ptr::null(), |input| input.as_ptr()),
+                &mut error,
+            )
+        };
+        (take(result), take(error))
+    }
+
+    #[test]
+    fn evaluates() {
+        let expression = compile("$sum(items.price)");
+        assert_eq!(
+            evaluate(
+                expression,
+                Some(r#"{"items": [{"price": 5}, {"price": 2}]}"#)
+            ),
+            (Some("7".to_string()), None)
+        );
+        assert_eq!(evaluate(expression, Some("{}")), (None, None));
+        unsafe { jsonata_free(expression) };
+    }
+
+    #[test]
+    fn without_input() {
+        let expression = compile("{ 'a': [1, 'two'] }");
+        assert_eq!(
+            evaluate(expression, None),
+            (Some(r#"{"a":[1,"two"]}"#.to_string()), None)
+        );
+        unsafe { jsonata_free(expression) };
+    }
+
+    #[test]
+    fn errors() {
+        let expr = CString::new("1 +").unwrap();
+        let mut error = ptr::null_mut();
+        let expression = unsafe { jsonata_compile(expr.as_ptr(), &mut error) };
+        assert!(expression.is_null());
+        assert!(take(error).is_some());
+
+        let expression = compile("$error('oops')");
+        assert_eq!(
+            evaluate(expression, None),
+            (None, Some("D3137 @ oops".to_string()))
+        );
+        assert!(evaluate(expression, Some("{")).1.is_some());
+        unsafe { jsonata_free(expression) };
+
+        assert_eq!(
+            evaluate(ptr::null(), None),
+            (None, Some("The expression is NULL".to_string()))
+        );
+        unsafe { jsonata_free(ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,293 @@
+//! Reads and writes [CBOR](https://cbor.io), converting it to and from values in the arena rather
+//! than going through JSON text:
+//!
+//! ```
+//! # use bumpalo::Bump;
+//! # use jsonata_rs::JsonAta;
+//! use jsonata_rs::cbor::{self, ByteStrings};
+//!
+//! let arena = Bump::new();
+//! // {"id": 7, "payload": h'010203'}
+//! let input = b"\xa2\x62id\x07\x67payload\x43\x01\x02\x03";
+//! let input = cbor::from_slice(input, &arena, ByteStrings::Tagged)?;
+//! assert_eq!(input.serialize(false), r#"{"id":7,"payload":{"$bytes":"AQID"}}"#);
+//!
+//! // The payload is written back as a byte string
+//! let jsonata = JsonAta::new("payload", &arena)?;
+//! let result = jsonata.evaluate_value(input)?;
+//! assert_eq!(cbor::to_vec(result, ByteStrings::Tagged), b"\x43\x01\x02\x03");
+//! # Ok::<(), jsonata_rs::Error>(())
+//! ```
+//!
+//! Tags are dropped, leaving the value they tag, except that bignums become numbers. Keys of maps
+//! which aren't strings become strings.
+
+use base64::Engine;
+use bumpalo::Bump;
+use ciborium::Value as Cbor;
+
+use crate::evaluator::integer::Integer;
+use crate::{ArrayFlags, Error, Result, Value};
+
+// Integral numbers up to this size are written as integers, which are more compact
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// The tags of bignums, which are byte strings of the big-endian magnitude of the number.
+const POSITIVE_BIGNUM: u64 = 2;
+const NEGATIVE_BIGNUM: u64 = 3;
+
+/// How byte strings, which JSON doesn't have, are represented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteStrings {
+    /// A base64 string, which is written as a text string.
+    #[default]
+    Base64,
+
+    /// An array with a number for each byte, which is written as an array.
+    Array,
+
+    /// An object with a single `$bytes` member which is a base64 string, as in
+    /// `{"$bytes": "AQID"}`, so that expressions can tell them apart from text. It's written as a
+    /// byte string again, so byte strings which are passed through are preserved.
+    Tagged,
+}
+
+/// Reads a CBOR value into `arena`. Fails if there's anything after the value.
+pub fn from_slice<'a>(
+    bytes: &[u8],
+    arena: &'a Bump,
+    byte_strings: ByteStrings,
+) -> Result<&'a Value<'a>> {
+    let mut rest = bytes;
+    let cbor: Cbor =
+        ciborium::from_reader(&mut rest).map_err(|e| Error::H0106MalformedCbor(e.to_string()))?;
+    if !rest.is_empty() {
+        return Err(Error::H0106MalformedCbor(format!(
+            "{} bytes after the value",
+            rest.len()
+        )));
+    }
+    from_cbor(&cbor, arena, byte_strings)
+}
+
+/// Writes a value, such as the result of an expression, as CBOR. Undefined is written as nothing
+/// at all, as it is by [`Value::serialize`].
+pub fn to_vec<'a>(value: &'a Value<'a>, byte_strings: ByteStrings) -> Vec<u8> {
+    if value.is_undefined() {
+        return vec![];
+    }
+    let mut bytes = vec![];
+    ciborium::into_writer(&to_cbor(value, byte_strings), &mut bytes)
+        .expect("Writing to a Vec doesn't fail");
+    bytes
+}
+
+fn from_cbor<'a>(cbor: &Cbor, arena: &'a Bump, byte_strings: ByteStrings) -> Result<&'a Value<'a>> {
+    Ok(match cbor {
+        Cbor::Null => Value::null(arena),
+        Cbor::Bool(b) => Value::bool(arena, *b),
+        Cbor::Integer(n) => integer(arena, i128::from(*n)),
+        Cbor::Float(n) => Value::number(arena, *n),
+        Cbor::Text(s) => Value::string(arena, s.as_str()),
+        Cbor::Bytes(bytes) => match byte_strings {
+            ByteStrings::Base64 => Value::string(arena, base64(bytes)),
+            ByteStrings::Array => {
+                let array = Value::array_with_capacity(arena, bytes.len(), ArrayFlags::empty());
+                for byte in bytes {
+                    array.push(Value::integer(arena, *byte as i64));
+                }
+                array
+            }
+            ByteStrings::Tagged => {
+                let object = Value::object_with_capacity(arena, 1);
+                object.insert("$bytes", Value::string(arena, base64(bytes)));
+                object
+            }
+        },
+        Cbor::Tag(tag @ (POSITIVE_BIGNUM | NEGATIVE_BIGNUM), content) if content.is_bytes() => {
+            let bytes = content.as_bytes().expect("Checked");
+            // Bignums that fit in a `u64` are read as integers, so these are beyond an `Integer`
+            let magnitude = bytes.iter().fold(0.0, |n, byte| n * 256.0 + *byte as f64);
+            if *tag == POSITIVE_BIGNUM {
+                Value::number(arena, magnitude)
+            } else {
+                Value::number(arena, -1.0 - magnitude)
+            }
+        }
+        Cbor::Tag(_, content) => from_cbor(content, arena, byte_strings)?,
+        Cbor::Array(members) => {
+            let array = Value::array_with_capacity(arena, members.len(), ArrayFlags::empty());
+            for member in members {
+                array.push(from_cbor(member, arena, byte_strings)?);
+            }
+            array
+        }
+        Cbor::Map(entries) => {
+            let object = Value::object_with_capacity(arena, entries.len());
+            for (key, value) in entries {
+                object.insert(&key_to_string(key)?, from_cbor(value, arena, byte_strings)?);
+            }
+            object
+        }
+        _ => {
+            return Err(Error::H0106MalformedCbor(format!(
+                "unsupported value {:?}",
+                cbor
+            )))
+        }
+    })
+}
+
+fn integer(arena: &Bump, n: i128) -> &Value<'_> {
+    match Integer::new(n) {
+        Some(n) => Value::integer(arena, n),
+        None => Value::number(arena, n as f64),
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn key_to_string(key: &Cbor) -> Result<String> {
+    match key {
+        Cbor::Text(s) => Ok(s.clone()),
+        Cbor::Integer(n) => Ok(i128::from(*n).to_string()),
+        Cbor::Float(n) => Ok(n.to_string()),
+        Cbor::Bool(b) => Ok(b.to_string()),
+        Cbor::Null => Ok("null".to_string()),
+        Cbor::Bytes(bytes) => Ok(base64(bytes)),
+        Cbor::Tag(_, key) => key_to_string(key),
+        _ => Err(Error::H0106MalformedCbor(format!(
+            "unsupported map key {:?}",
+            key
+        ))),
+    }
+}
+
+fn to_cbor<'a>(value: &'a Value<'a>, byte_strings: ByteStrings) -> Cbor {
+    match value {
+        Value::Undefined | Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(*b),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+            Cbor::Integer((*n as i64).into())
+        }
+        Value::Number(n) => Cbor::Float(*n),
+        Value::Integer(Integer::Signed(n)) => Cbor::Integer((*n).into()),
+        Value::Integer(Integer::Unsigned(n)) => Cbor::Integer((*n).into()),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => match i64::try_from(*d) {
+            Ok(n) if d.fract().is_zero() => Cbor::Integer(n.into()),
+            _ => Cbor::Float(value.as_f64()),
+        },
+        Value::String(s) => Cbor::Text(s.to_string()),
+        Value::Array(..) | Value::Range(..) => Cbor::Array(
+            value
+                .members()
+                .map(|member| to_cbor(member, byte_strings))
+                .collect(),
+        ),
+        Value::Object(..) => match tagged_bytes(value, byte_strings) {
+            Some(bytes) => Cbor::Bytes(bytes),
+            None => Cbor::Map(
+                value
+                    .entries()
+                    .map(|(key, value)| (Cbor::Text(key.clone()), to_cbor(value, byte_strings)))
+                    .collect(),
+            ),
+        },
+        Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+            Cbor::Text(String::new())
+        }
+    }
+}
+
+/// The bytes of an object which is a byte string in the [`ByteStrings::Tagged`] representation.
+fn tagged_bytes<'a>(object: &'a Value<'a>, byte_strings: ByteStrings) -> Option<Vec<u8>> {
+    let bytes = object.get_entry("$bytes");
+    if byte_strings != ByteStrings::Tagged || object.entries().len() != 1 || !bytes.is_string() {
+        return None;
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(&*bytes.as_str())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(r#"{"a":[1,-2,1.5,"x",true,null],"b":{}}"#)]
+    #[test_case("18446744073709551615")]
+    #[test_case("-9223372036854775808")]
+    #[test_case(r#""é""#)]
+    fn round_trip(json: &str) {
+        let arena = Bump::new();
+        let value = crate::parse_value(json, &arena).unwrap();
+        let bytes = to_vec(value, ByteStrings::Base64);
+        assert_eq!(
+            from_slice(&bytes, &arena, ByteStrings::Base64)
+                .unwrap()
+                .serialize(false),
+            json
+        );
+    }
+
+    #[test_case(ByteStrings::Base64, r#""AQID""#, b"\x64AQID")]
+    #[test_case(ByteStrings::Array, "[1,2,3]", b"\x83\x01\x02\x03")]
+    #[test_case(ByteStrings::Tagged, r#"{"$bytes":"AQID"}"#, b"\x43\x01\x02\x03")]
+    fn byte_strings(byte_strings: ByteStrings, expected: &str, written: &[u8]) {
+        let arena = Bump::new();
+        let value = from_slice(b"\x43\x01\x02\x03", &arena, byte_strings).unwrap();
+        assert_eq!(value.serialize(false), expected);
+        assert_eq!(to_vec(value, byte_strings), written);
+    }
+
+    #[test]
+    fn tagged_bytes_only_with_tagged() {
+        let arena = Bump::new();
+        let value = crate::parse_value(r#"{"$bytes": "AQID"}"#, &arena).unwrap();
+        assert_eq!(to_vec(value, ByteStrings::Base64)[0], 0xa1);
+        let value = crate::parse_value(r#"{"$bytes": "not base64!"}"#, &arena).unwrap();
+        assert_eq!(to_vec(value, ByteStrings::Tagged)[0], 0xa1);
+    }
+
+    #[test]
+    fn tags_and_keys() {
+        let arena = Bump::new();
+        // {1: 0("2024-01-01T00:00:00Z"), "f": 0.5 as f16, "big": 2(h'010000000000000000')}
+        let bytes = b"\xa3\x01\xc0\x742024-01-01T00:00:00Z\x61f\xf9\x38\x00\x63big\xc2\x49\x01\x00\x00\x00\x00\x00\x00\x00\x00";
+        let value = from_slice(bytes, &arena, ByteStrings::Base64).unwrap();
+        assert_eq!(value["1"], "2024-01-01T00:00:00Z");
+        assert_eq!(value["f"].as_f64(), 0.5);
+        assert_eq!(value["big"].as_f64(), 2f64.powi(64));
+    }
+
+    #[test]
+    fn compact_numbers() {
+        let arena = Bump::new();
+        assert_eq!(
+            to_vec(Value::number(&arena, 3.0), ByteStrings::Base64),
+            [0x03]
+        );
+        assert_eq!(
+            to_vec(Value::number(&arena, -1.0), ByteStrings::Base64),
+            [0x20]
+        );
+    }
+
+    #[test_case(b"\x82\x01")]
+    #[test_case(b"\x01\x02")]
+    #[test_case(b"")]
+    fn malformed(bytes: &[u8]) {
+        let arena = Bump::new();
+        assert_eq!(
+            from_slice(bytes, &arena, ByteStrings::Base64)
+                .unwrap_err()
+                .code(),
+            "H0106"
+        );
+    }
+}
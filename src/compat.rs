@@ -0,0 +1,181 @@
+//! A thin layer over the crate which mirrors the API of the [jsonata-js] library, to make porting
+//! code from JavaScript more straightforward:
+//!
+//! ```js
+//! const expression = jsonata("$sum(items.price) * $rate");
+//! expression.assign("rate", 1.2);
+//! const result = await expression.evaluate(input);
+//! ```
+//!
+//! becomes
+//!
+//! ```ignore
+//! let mut expression = jsonata("$sum(items.price) * $rate")?;
+//! expression.assign("rate", json!(1.2));
+//! let result = expression.evaluate(Some(&input), None)?;
+//! ```
+//!
+//! Inputs, bindings and results are [`serde_json::Value`]s, and an undefined input or result is
+//! `None`.
+//!
+//! [jsonata-js]: https://github.com/jsonata-js/jsonata
+
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+
+use crate::{CompiledExpression, Error, FunctionContext, Result, Value};
+
+type NativeFn = for<'a, 'e> fn(FunctionContext<'a, 'e>, &'a Value<'a>) -> Result<&'a Value<'a>>;
+
+/// Parses an expression, like `jsonata(expr)` in JavaScript.
+pub fn jsonata(expr: &str) -> Result<Expression> {
+    Ok(Expression {
+        compiled: CompiledExpression::new(expr)?,
+        bindings: HashMap::new(),
+        functions: HashMap::new(),
+    })
+}
+
+/// A parsed expression along with the variables and functions assigned to it.
+pub struct Expression {
+    compiled: CompiledExpression,
+    bindings: HashMap<String, serde_json::Value>,
+    functions: HashMap<String, (usize, NativeFn)>,
+}
+
+impl Expression {
+    /// Binds a variable for every subsequent evaluation, like `expression.assign(name, value)`.
+    pub fn assign(&mut self, name: &str, value: serde_json::Value) {
+        self.bindings.insert(name.to_string(), value);
+    }
+
+    /// Registers a function for every subsequent evaluation, like
+    /// `expression.registerFunction(name, implementation)`. Function signatures aren't supported,
+    /// so the number of arguments is given instead.
+    pub fn register_function(&mut self, name: &str, arity: usize, implementation: NativeFn) {
+        self.functions
+            .insert(name.to_string(), (arity, implementation));
+    }
+
+    /// Evaluates the expression, like `expression.evaluate(input, bindings)`, where an input of
+    /// `None` is `undefined`. The bindings are only used for this evaluation, and take precedence
+    /// over assigned variables.
+    pub fn evaluate(
+        &self,
+        input: Option<&serde_json::Value>,
+        bindings: Option<&serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<Option<serde_json::Value>> {
+        let arena = Bump::new();
+        let jsonata = self.compiled.bind(&arena);
+
+        for (name, value) in self.bindings.iter().chain(bindings.into_iter().flatten()) {
+            jsonata.assign_var(name, Value::from_json(&arena, value));
+        }
+        for (name, (arity, implementation)) in self.functions.iter() {
+            jsonata.register_function(name, *arity, *implementation);
+        }
+
+        let input = match input {
+            Some(input) => Value::from_json(&arena, input),
+            None => Value::undefined(),
+        };
+        let result = jsonata.evaluate_input(input, None, None)?;
+        if result.is_undefined() {
+            Ok(None)
+        } else {
+            result.deserialize().map(Some)
+        }
+    }
+
+    /// Evaluates the expression and passes the outcome to `callback`, like the callback form
+    /// `expression.evaluate(input, bindings, (err, result) => ...)`.
+    pub fn evaluate_with_callback<F>(
+        &self,
+        input: Option<&serde_json::Value>,
+        bindings: Option<&serde_json::Map<String, serde_json::Value>>,
+        callback: F,
+    ) where
+        F: FnOnce(Option<Error>, Option<serde_json::Value>),
+    {
+        match self.evaluate(input, bindings) {
+            Ok(result) => callback(None, result),
+            Err(error) => callback(Some(error), None),
+        }
+    }
+
+    /// The parsed expression, like `expression.ast()`, which isn't part of the stable API, see
+    /// [`crate::ast`].
+    #[cfg(feature = "unstable-ast")]
+    pub fn ast(&self) -> &crate::parser::ast::Ast {
+        self.compiled.ast()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn evaluate_with_assigned_and_call_bindings() {
+        let mut expression = jsonata("$sum(items.price) * $rate + $bonus").unwrap();
+        expression.assign("rate", json!(2));
+        expression.assign("bonus", json!(100));
+
+        let input = json!({"items": [{"price": 1}, {"price": 2.5}]});
+        let bindings = json!({"bonus": 1});
+
+        assert_eq!(
+            expression
+                .evaluate(Some(&input), bindings.as_object())
+                .unwrap(),
+            Some(json!(8))
+        );
+        assert_eq!(
+            expression.evaluate(Some(&input), None).unwrap(),
+            Some(json!(107))
+        );
+    }
+
+    #[test]
+    fn undefined_result_is_none() {
+        let expression = jsonata("missing").unwrap();
+        assert_eq!(expression.evaluate(Some(&json!({})), None).unwrap(), None);
+    }
+
+    #[test]
+    fn no_input_is_undefined() {
+        let expression = jsonata("$exists($)").unwrap();
+        assert_eq!(expression.evaluate(None, None).unwrap(), Some(json!(false)));
+        assert_eq!(
+            expression.evaluate(Some(&json!(null)), None).unwrap(),
+            Some(json!(true))
+        );
+    }
+
+    #[test]
+    fn registered_function() {
+        let mut expression = jsonata("$double(n)").unwrap();
+        expression.register_function("double", 1, |ctx, args| {
+            Ok(Value::number(ctx.arena, args[0].as_f64() * 2.0))
+        });
+
+        assert_eq!(
+            expression.evaluate(Some(&json!({"n": 21})), None).unwrap(),
+            Some(json!(42))
+        );
+    }
+
+    #[test]
+    fn callback_style() {
+        let expression = jsonata("$error(\"boom\")").unwrap();
+        let mut code = None;
+        expression.evaluate_with_callback(None, None, |err, result| {
+            assert!(result.is_none());
+            code = err.map(|e| e.code().to_string());
+        });
+        assert_eq!(code.as_deref(), Some("D3137"));
+    }
+}
@@ -0,0 +1,429 @@
+//! Which parts of the JSONata language this build of the engine supports, so that platforms which
+//! accept expressions from users can check them against what will actually work:
+//!
+//! ```
+//! use jsonata_rs::conformance::{self, Support};
+//!
+//! let report = conformance::report();
+//! assert_eq!(report.support("$sum"), Some(Support::Implemented));
+//! assert_eq!(report.support("parent-operator"), Some(Support::Missing));
+//! ```
+//!
+//! Syntax is named by feature, such as `order-by`, and functions by their name with the `$`, such
+//! as `$sum`. Functions are reported as they're bound in a new expression, so the report reflects
+//! the features the crate was built with.
+
+use std::fmt;
+
+use bumpalo::Bump;
+use serde_json::json;
+
+use crate::JsonAta;
+
+/// How well a feature is supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Support {
+    Implemented,
+    /// Works, but not in every form the reference implementation accepts, see [`Feature::note`].
+    Partial,
+    Missing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Operators and other syntax.
+    Syntax,
+    /// Built-in functions of reference JSONata.
+    Function,
+    /// Built-in functions which aren't part of reference JSONata.
+    Extension,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feature {
+    pub name: &'static str,
+    pub category: Category,
+    pub support: Support,
+    /// What's missing from a partially supported feature, or why a feature is missing.
+    pub note: Option<&'static str>,
+}
+
+/// The support for every feature, see [`report`].
+#[derive(Debug, Clone)]
+pub struct Report {
+    features: Vec<Feature>,
+}
+
+const REGEX: &str = "Regular expressions aren't supported";
+
+// Syntax, in roughly the order of the reference documentation
+const SYNTAX: &[(&str, Support, Option<&str>)] = &[
+    ("path", Support::Implemented, None),
+    ("wildcard", Support::Implemented, None),
+    ("descendants", Support::Implemented, None),
+    ("parent-operator", Support::Missing, None),
+    ("predicate", Support::Implemented, None),
+    ("array-index", Support::Implemented, None),
+    ("keep-array", Support::Implemented, None),
+    ("array-constructor", Support::Implemented, None),
+    ("object-constructor", Support::Implemented, None),
+    ("group-by", Support::Implemented, None),
+    ("order-by", Support::Implemented, None),
+    ("focus-binding", Support::Implemented, None),
+    ("index-binding", Support::Implemented, None),
+    ("context-variable", Support::Implemented, None),
+    ("root-variable", Support::Implemented, None),
+    ("arithmetic", Support::Implemented, None),
+    ("comparison", Support::Implemented, None),
+    ("boolean-operators", Support::Implemented, None),
+    ("string-concatenation", Support::Implemented, None),
+    ("membership", Support::Implemented, None),
+    ("range", Support::Implemented, None),
+    ("conditional", Support::Implemented, None),
+    ("default-operator", Support::Missing, None),
+    ("coalescing-operator", Support::Missing, None),
+    ("block", Support::Implemented, None),
+    ("variable-binding", Support::Implemented, None),
+    ("lambda", Support::Implemented, None),
+    ("function-chaining", Support::Implemented, None),
+    ("partial-application", Support::Implemented, None),
+    ("function-signature", Support::Implemented, None),
+    ("transform", Support::Implemented, None),
+    ("regex", Support::Missing, None),
+    ("comments", Support::Implemented, None),
+    ("backtick-names", Support::Implemented, None),
+];
+
+// The built-in functions of reference JSONata, and any limitations of ours
+const FUNCTIONS: &[(&str, Option<&str>)] = &[
+    // String
+    ("$string", None),
+    ("$length", None),
+    ("$substring", None),
+    ("$substringBefore", None),
+    ("$substringAfter", None),
+    ("$uppercase", None),
+    ("$lowercase", None),
+    ("$trim", None),
+    ("$pad", None),
+    ("$contains", Some(REGEX)),
+    ("$split", Some(REGEX)),
+    ("$join", None),
+    ("$match", Some(REGEX)),
+    ("$replace", Some(REGEX)),
+    ("$eval", None),
+    ("$base64encode", None),
+    ("$base64decode", None),
+    ("$encodeUrlComponent", None),
+    ("$encodeUrl", None),
+    ("$decodeUrlComponent", None),
+    ("$decodeUrl", None),
+    ("$formatNumber", None),
+    ("$formatBase", None),
+    ("$formatInteger", None),
+    ("$parseInteger", None),
+    // Numeric
+    ("$number", None),
+    ("$abs", None),
+    ("$floor", None),
+    ("$ceil", None),
+    ("$round", None),
+    ("$power", None),
+    ("$sqrt", None),
+    ("$random", None),
+    // Aggregation
+    ("$sum", None),
+    ("$max", None),
+    ("$min", None),
+    ("$average", None),
+    // Boolean
+    ("$boolean", None),
+    ("$not", None),
+    ("$exists", None),
+    // Array
+    ("$count", None),
+    ("$append", None),
+    ("$sort", None),
+    ("$reverse", None),
+    ("$shuffle", None),
+    ("$distinct", None),
+    ("$zip", None),
+    // Object
+    ("$keys", None),
+    ("$lookup", None),
+    ("$spread", None),
+    ("$merge", None),
+    ("$sift", None),
+    ("$each", None),
+    ("$error", None),
+    ("$assert", None),
+    ("$type", None),
+    // Date/time
+    ("$now", None),
+    ("$millis", None),
+    ("$fromMillis", None),
+    ("$toMillis", None),
+    // Higher-order
+    ("$map", None),
+    ("$filter", None),
+    ("$single", None),
+    ("$reduce", None),
+];
+
+// Functions of our own, which are reported if they're bound
+const EXTENSIONS: &[&str] = &[
+    "$crc32",
+    "$fetch",
+    "$hmacSha256",
+    "$index",
+    "$joinOn",
+    "$md5",
+    "$memoize",
+    "$mergePatch",
+    "$pointer",
+    "$sha256",
+    "$uuid",
+    "$validate",
+];
+
+/// Reports the support for each feature of the language in this build.
+pub fn report() -> Report {
+    let arena = Bump::new();
+    let jsonata = JsonAta::new("$", &arena).expect("A valid expression");
+    // Binds the built-in functions
+    let _ = jsonata.evaluator(None, None);
+    let is_bound = |name: &str| {
+        jsonata
+            .frame
+            .lookup(&name[1..])
+            .is_some_and(|f| f.is_function())
+    };
+
+    let syntax = SYNTAX.iter().map(|&(name, support, note)| Feature {
+        name,
+        category: Category::Syntax,
+        support,
+        note,
+    });
+
+    let functions = FUNCTIONS.iter().map(|&(name, note)| {
+        let support = match (is_bound(name), note) {
+            (false, _) => Support::Missing,
+            (true, Some(_)) => Support::Partial,
+            (true, None) => Support::Implemented,
+        };
+        Feature {
+            name,
+            category: Category::Function,
+            support,
+            note: note.filter(|_| support == Support::Partial),
+        }
+    });
+
+    let extensions = EXTENSIONS
+        .iter()
+        .filter(|name| is_bound(name))
+        .map(|&name| Feature {
+            name,
+            category: Category::Extension,
+            support: Support::Implemented,
+            note: None,
+        });
+
+    Report {
+        features: syntax.chain(functions).chain(extensions).collect(),
+    }
+}
+
+impl Report {
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+
+    /// The feature named `name`, such as `order-by` or `$sum`.
+    pub fn get(&self, name: &str) -> Option<&Feature> {
+        self.features.iter().find(|feature| feature.name == name)
+    }
+
+    /// The support for the feature named `name`, or `None` if there's no such feature.
+    pub fn support(&self, name: &str) -> Option<Support> {
+        self.get(name).map(|feature| feature.support)
+    }
+
+    /// The report as JSON, for platforms which aren't written in Rust:
+    ///
+    /// ```json
+    /// {
+    ///   "version": "0.1.3",
+    ///   "features": [{ "name": "$match", "category": "function", "support": "partial", "note": "..." }]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        let features = self
+            .features
+            .iter()
+            .map(|feature| {
+                let mut json = json!({
+                    "name": feature.name,
+                    "category": feature.category.to_string(),
+                    "support": feature.support.to_string(),
+                });
+                if let Some(note) = feature.note {
+                    json["note"] = note.into();
+                }
+                json
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "features": features,
+        })
+    }
+}
+
+impl fmt::Display for Support {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Support::Implemented => "implemented",
+            Support::Partial => "partial",
+            Support::Missing => "missing",
+        })
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Syntax => "syntax",
+            Category::Function => "function",
+            Category::Extension => "extension",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use super::*;
+
+    // An example of each syntax feature, and its result when it's supported
+    const EXAMPLES: &[(&str, &str, &str)] = &[
+        ("path", "a.b", "[1,0]"),
+        ("wildcard", "*.b", "[1,0]"),
+        ("descendants", "**.b", "[1,0]"),
+        ("parent-operator", "a.%.c", "[5,5]"),
+        ("predicate", "a[b=1].b", "1"),
+        ("array-index", "a[-1].b", "0"),
+        ("keep-array", "a[b=1][].b", "[1]"),
+        ("array-constructor", "[c, 1]", "[5,1]"),
+        ("object-constructor", "{'x': c}", r#"{"x":5}"#),
+        ("group-by", "a{$string(b): b}", r#"{"1":1,"0":0}"#),
+        ("order-by", "a^(b).b", "[0,1]"),
+        ("focus-binding", "a@$x.$x.b", "[1,0]"),
+        ("index-binding", "a#$i.$i", "[0,1]"),
+        ("context-variable", "c.$", "5"),
+        ("root-variable", "a.$$.c", "[5,5]"),
+        ("arithmetic", "c * 2 - 1 / 2 % 1", "9.5"),
+        (
+            "comparison",
+            "[c > 1, c <= 1, c = 5, c != 5]",
+            "[true,false,true,false]",
+        ),
+        ("boolean-operators", "true and false or true", "true"),
+        ("string-concatenation", "'a' & c", r#""a5""#),
+        ("membership", "5 in [c]", "true"),
+        ("range", "[1..3]", "[1,2,3]"),
+        ("conditional", "c > 1 ? 'y' : 'n'", r#""y""#),
+        ("default-operator", "x ?: 1", "1"),
+        ("coalescing-operator", "x ?? 1", "1"),
+        ("block", "(1; 2)", "2"),
+        ("variable-binding", "($x := 2; $x)", "2"),
+        ("lambda", "($f := function($n) { $n + 1 }; $f(1))", "2"),
+        ("function-chaining", "'a' ~> $uppercase()", r#""A""#),
+        (
+            "partial-application",
+            "($f := $substring(?, 0, 1); $f('ab'))",
+            r#""a""#,
+        ),
+        (
+            "function-signature",
+            "($f := function($n)<n:n> { $n }; $f(1))",
+            "1",
+        ),
+        ("transform", "$ ~> |$|{'c': 1}, ['a']|", r#"{"c":1}"#),
+        ("regex", "$contains('abc', /b/)", "true"),
+        ("comments", "/* c */ c", "5"),
+        ("backtick-names", "`c`", "5"),
+    ];
+
+    const INPUT: &str = r#"{"a": [{"b": 1}, {"b": 0}], "c": 5}"#;
+
+    fn evaluate(expr: &str) -> Option<String> {
+        let result = panic::catch_unwind(|| {
+            let arena = Bump::new();
+            let jsonata = JsonAta::new(expr, &arena).ok()?;
+            let result = jsonata.evaluate(Some(INPUT), None).ok()?;
+            Some(result.serialize(false))
+        });
+        result.ok().flatten()
+    }
+
+    #[test]
+    fn syntax_matches_the_engine() {
+        let report = report();
+        for &(name, expr, expected) in EXAMPLES {
+            let feature = report.get(name).expect(name);
+            let works = evaluate(expr).as_deref() == Some(expected);
+            assert_eq!(
+                works,
+                feature.support != Support::Missing,
+                "`{}` is reported as {}",
+                name,
+                feature.support
+            );
+        }
+        assert_eq!(
+            EXAMPLES.len(),
+            report
+                .features()
+                .iter()
+                .filter(|feature| feature.category == Category::Syntax)
+                .count()
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let report = report();
+        assert_eq!(report.support("$sum"), Some(Support::Implemented));
+        assert_eq!(report.support("$replace"), Some(Support::Partial));
+        assert_eq!(report.get("$replace").unwrap().note, Some(REGEX));
+        assert_eq!(report.support("$sift"), Some(Support::Missing));
+        assert_eq!(report.get("$sift").unwrap().note, None);
+        assert_eq!(report.get("$joinOn").unwrap().category, Category::Extension);
+        assert_eq!(report.get("sum"), None);
+    }
+
+    #[test]
+    fn json() {
+        let json = report().to_json();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        let replace = json["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|feature| feature["name"] == "$replace")
+            .unwrap();
+        assert_eq!(
+            replace,
+            &json!({
+                "name": "$replace",
+                "category": "function",
+                "support": "partial",
+                "note": REGEX,
+            })
+        );
+    }
+}
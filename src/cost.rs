@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::ast::{Ast, AstKind, BinaryOp, UnaryOp};
+use crate::parser::visit::{for_each_operand, for_each_stage, walk_node, Visitor};
+use crate::{CompiledExpression, JsonAta};
+
+/// An estimate of how expensive an expression is to evaluate, made from its AST without
+/// evaluating it, see [`CompiledExpression::cost`]. None of these make an expression expensive
+/// on their own, as that depends on the input, but they're what makes the work grow faster than
+/// the input does, so a host accepting expressions from users can reject the ones over its limits
+/// before evaluating anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cost {
+    /// The deepest nesting of paths, where a path nests inside another if it's evaluated for each
+    /// item of the other, such as in a later step, a predicate or the body of a function. For
+    /// example `orders.items` is 1, `orders[total > 100]` is 2 and `orders.(items.(tags.name))`
+    /// is 3. The number of items visited can grow with the size of the input to this power.
+    pub map_depth: usize,
+
+    /// The number of steps bound with `@` which are followed by another step, such as
+    /// `loans@$l.books`. The following step is evaluated against the same context for each item
+    /// of the bound step, which joins every pair of them.
+    pub cartesian_joins: usize,
+
+    /// The names of functions bound by the expression which call themselves, directly or through
+    /// other functions bound by it, so their depth depends on the input.
+    pub recursive_functions: Vec<String>,
+
+    /// The number of ranges, such as `[1..$count]`, whose bounds aren't both number literals, so
+    /// their size depends on the input.
+    pub unbounded_ranges: usize,
+}
+
+impl CompiledExpression {
+    /// Estimates how expensive the expression is to evaluate, without evaluating it.
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("loans@$l.books@$b[$l.isbn = $b.isbn]")?;
+    /// let cost = expression.cost();
+    /// assert_eq!(cost.map_depth, 2);
+    /// assert_eq!(cost.cartesian_joins, 1);
+    ///
+    /// // Rejected before it's evaluated
+    /// assert!(cost.map_depth > 1 || cost.cartesian_joins > 0);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn cost(&self) -> Cost {
+        cost(&self.ast)
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::cost`].
+    pub fn cost(&self) -> Cost {
+        cost(&self.ast)
+    }
+}
+
+fn cost(ast: &Ast) -> Cost {
+    let mut estimator = Estimator::default();
+    estimator.visit(ast, 0);
+
+    let mut recursive_functions: Vec<String> = estimator
+        .functions
+        .keys()
+        .filter(|name| estimator.is_recursive(name))
+        .cloned()
+        .collect();
+    recursive_functions.sort();
+
+    Cost {
+        map_depth: estimator.map_depth,
+        cartesian_joins: estimator.cartesian_joins,
+        recursive_functions,
+        unbounded_ranges: estimator.unbounded_ranges,
+    }
+}
+
+#[derive(Default)]
+struct Estimator {
+    map_depth: usize,
+    cartesian_joins: usize,
+    unbounded_ranges: usize,
+
+    /// The variables referred to by each function the expression binds. Scopes aren't tracked,
+    /// so functions bound to the same name in different blocks are merged.
+    functions: HashMap<String, HashSet<String>>,
+}
+
+impl Estimator {
+    /// Visits a node evaluated `depth` paths deep, whose predicates and stages are evaluated for
+    /// each item it produces.
+    fn visit(&mut self, ast: &Ast, depth: usize) {
+        self.visit_step(ast, depth, depth + 1);
+    }
+
+    fn visit_step(&mut self, ast: &Ast, depth: usize, stage_depth: usize) {
+        match ast.kind {
+            AstKind::Path(ref steps) => {
+                self.map_depth = self.map_depth.max(depth + 1);
+                // The step after a focus bind is evaluated against the context of the bound step
+                self.cartesian_joins += steps
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .filter(|step| step.focus.is_some())
+                    .count();
+
+                // Later steps are evaluated for each item of the earlier ones, as are the
+                // predicates of every step
+                for (index, step) in steps.iter().enumerate() {
+                    let step_depth = if index == 0 { depth } else { depth + 1 };
+                    self.visit_step(step, step_depth, depth + 1);
+                }
+            }
+            AstKind::Lambda { ref body, .. } => {
+                // Functions are usually called for each item, as by `$map` or `$filter`
+                self.visit(body, depth + 1);
+            }
+            _ => {
+                if let AstKind::Binary(ref op, ref lhs, ref rhs) = ast.kind {
+                    match (op, &lhs.kind, &rhs.kind) {
+                        (BinaryOp::Bind, AstKind::Var(name), AstKind::Lambda { .. }) => {
+                            let mut variables = Variables::default();
+                            variables.visit_node(rhs);
+                            self.functions
+                                .entry(name.clone())
+                                .or_default()
+                                .extend(variables.0);
+                        }
+                        (BinaryOp::Range, _, _) if !is_number(lhs) || !is_number(rhs) => {
+                            self.unbounded_ranges += 1;
+                        }
+                        _ => {}
+                    }
+                }
+                for_each_operand(ast, &mut |child| self.visit(child, depth));
+            }
+        }
+        for_each_stage(ast, &mut |child| self.visit(child, stage_depth));
+    }
+
+    /// Whether the function bound to `name` can call itself through the functions it refers to.
+    fn is_recursive(&self, name: &str) -> bool {
+        let mut seen = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(function) = pending.pop() {
+            for called in self.functions.get(function).into_iter().flatten() {
+                if called == name {
+                    return true;
+                }
+                if seen.insert(called.as_str()) {
+                    pending.push(called);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Collects the names of the variables referred to in an expression.
+#[derive(Default)]
+struct Variables(HashSet<String>);
+
+impl Visitor for Variables {
+    fn visit_node(&mut self, ast: &Ast) {
+        if let AstKind::Var(ref name) = ast.kind {
+            self.0.insert(name.clone());
+        }
+        walk_node(self, ast);
+    }
+}
+
+fn is_number(ast: &Ast) -> bool {
+    match ast.kind {
+        AstKind::Number(..) | AstKind::Integer(..) => true,
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(..) => true,
+        AstKind::Unary(UnaryOp::Minus(ref value)) => is_number(value),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn cost(expr: &str) -> Cost {
+        CompiledExpression::new(expr).unwrap().cost()
+    }
+
+    #[test_case("1 + 2", 0)]
+    #[test_case("orders", 1)]
+    #[test_case("orders.items.price", 1)]
+    #[test_case("$sum(orders.items.price)", 1)]
+    #[test_case("orders[total > 100]", 2)]
+    #[test_case("orders.items[price > 5].name", 2)]
+    #[test_case("orders.(items.(tags.name))", 3)]
+    #[test_case("orders{customer: $sum(total)}", 2)]
+    #[test_case("$map(orders, function($o) { $o.items.price })", 2)]
+    fn map_depth(expr: &str, expected: usize) {
+        assert_eq!(cost(expr).map_depth, expected);
+    }
+
+    #[test_case("loans@$l.books@$b[$l.isbn = $b.isbn]", 1)]
+    #[test_case("a@$x.b@$y.c", 2)]
+    #[test_case("a@$x", 0)]
+    #[test_case("a#$i.b", 0)]
+    fn cartesian_joins(expr: &str, expected: usize) {
+        assert_eq!(cost(expr).cartesian_joins, expected);
+    }
+
+    #[test_case("($f := function($n) { $n > 0 ? $f($n - 1) : 0 }; $f(3))", &["f"])]
+    #[test_case(
+        "($even := function($n) { $n = 0 or $odd($n - 1) }; $odd := function($n) { $n != 0 and $even($n - 1) }; $even(4))",
+        &["even", "odd"]
+    )]
+    #[test_case("($g := function($n) { $n + 1 }; $f := function($n) { $g($n) }; $f(1))", &[])]
+    fn recursive_functions(expr: &str, expected: &[&str]) {
+        assert_eq!(cost(expr).recursive_functions, expected);
+    }
+
+    #[test_case("[1..10]", 0)]
+    #[test_case("[-5..5]", 0)]
+    #[test_case("[1..$count(items)]", 1)]
+    #[test_case("[0..n, 1..m]", 2)]
+    fn unbounded_ranges(expr: &str, expected: usize) {
+        assert_eq!(cost(expr).unbounded_ranges, expected);
+    }
+
+    #[test]
+    fn from_the_engine() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("orders[total > 100]", &arena).unwrap();
+        assert_eq!(
+            jsonata.cost(),
+            Cost {
+                map_depth: 2,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    fmt,
+    io::{BufRead, Seek, SeekFrom},
+    str::FromStr,
+};
+
+use crate::{Error, JsonAta, Result, Value};
+
+/// The position of an [`NdjsonCursor`] within its input.
+///
+/// A checkpoint can be persisted (its `Display` and `FromStr` forms are `records:offset`) and
+/// handed to [`NdjsonCursor::resume`] to carry on where a previous process left off.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The number of records consumed so far.
+    pub records: u64,
+
+    /// The byte offset in the input of the first record that hasn't been consumed yet.
+    pub offset: u64,
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.records, self.offset)
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::H0102InvalidCheckpoint(s.to_string());
+        let (records, offset) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Checkpoint {
+            records: records.parse().map_err(|_| invalid())?,
+            offset: offset.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Evaluates an expression against each record of a newline-delimited JSON input, keeping track
+/// of how far through the input it has got.
+///
+/// The cursor isn't tied to an arena, so a long-running transform can evaluate a batch of
+/// records, drop the arena, take a [`Checkpoint`] and then carry on with a fresh arena.
+pub struct NdjsonCursor<R> {
+    reader: R,
+    checkpoint: Checkpoint,
+    /// How far the reader has got, which is past the checkpoint while the record it last read
+    /// is being handled.
+    position: Checkpoint,
+    line: String,
+}
+
+impl<R: BufRead> NdjsonCursor<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            checkpoint: Checkpoint::default(),
+            position: Checkpoint::default(),
+            line: String::new(),
+        }
+    }
+
+    /// The position after the records which have been handled, which doesn't include the record
+    /// the cursor read last until the next one is read, or until `f` returns `Ok` for it in
+    /// [`NdjsonCursor::evaluate_batch`]. So resuming from it after a failure evaluates the record
+    /// which failed again.
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint
+    }
+
+    /// Reads the next record and evaluates the expression against it, or returns `None` when the
+    /// input is exhausted. Blank lines are skipped. Reading the next record moves past the last
+    /// one even if evaluating it failed, so that a bad record doesn't stop the cursor from making
+    /// progress.
+    pub fn evaluate_next<'a>(&mut self, jsonata: &JsonAta<'a>) -> Option<Result<&'a Value<'a>>> {
+        // Asking for the next record means the last one has been handled
+        self.checkpoint = self.position;
+        loop {
+            self.line.clear();
+            let read = match self.reader.read_line(&mut self.line) {
+                Ok(read) => read,
+                Err(e) => return Some(Err(Error::H0101InputRead(e.to_string()))),
+            };
+            if read == 0 {
+                return None;
+            }
+            self.position.offset += read as u64;
+
+            let record = self.line.trim();
+            if record.is_empty() {
+                continue;
+            }
+            self.position.records += 1;
+
+            return Some(jsonata.evaluate(Some(record), None));
+        }
+    }
+
+    /// Evaluates at most `max_records` records, passing each result to `f`, and returns the
+    /// number of records evaluated. Stops at the first error, whether from the evaluation or from
+    /// `f`, without moving the checkpoint past the record which failed.
+    pub fn evaluate_batch<'a, F>(
+        &mut self,
+        jsonata: &JsonAta<'a>,
+        max_records: usize,
+        mut f: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(&'a Value<'a>) -> Result<()>,
+    {
+        let mut count = 0;
+        while count < max_records {
+            match self.evaluate_next(jsonata) {
+                Some(result) => f(result?)?,
+                None => break,
+            }
+            self.checkpoint = self.position;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<R: BufRead + Seek> NdjsonCursor<R> {
+    /// Creates a cursor which carries on from `checkpoint`, seeking the input past the records
+    /// that have already been consumed.
+    pub fn resume(mut reader: R, checkpoint: Checkpoint) -> Result<Self> {
+        reader
+            .seek(SeekFrom::Start(checkpoint.offset))
+            .map_err(|e| Error::H0101InputRead(e.to_string()))?;
+
+        Ok(Self {
+            reader,
+            checkpoint,
+            position: checkpoint,
+            line: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use bumpalo::Bump;
+
+    use super::*;
+
+    const INPUT: &str = "{\"a\": 1}\n{\"a\": 2}\n\n{\"a\": 3}\n{\"a\": 4}\n";
+
+    #[test]
+    fn evaluate_all() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("a * 10", &arena).unwrap();
+        let mut cursor = NdjsonCursor::new(Cursor::new(INPUT));
+
+        let mut results = vec![];
+        while let Some(result) = cursor.evaluate_next(&jsonata) {
+            results.push(result.unwrap().as_f64());
+        }
+
+        assert_eq!(results, vec![10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(cursor.checkpoint().records, 4);
+        assert_eq!(cursor.checkpoint().offset, INPUT.len() as u64);
+    }
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let checkpoint = {
+            let arena = Bump::new();
+            let jsonata = JsonAta::new("a", &arena).unwrap();
+            let mut cursor = NdjsonCursor::new(Cursor::new(INPUT));
+            let count = cursor.evaluate_batch(&jsonata, 2, |_| Ok(())).unwrap();
+            assert_eq!(count, 2);
+            cursor.checkpoint().to_string()
+        };
+
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("a", &arena).unwrap();
+        let mut cursor =
+            NdjsonCursor::resume(Cursor::new(INPUT), checkpoint.parse().unwrap()).unwrap();
+
+        let mut results = vec![];
+        let count = cursor
+            .evaluate_batch(&jsonata, 10, |v| {
+                results.push(v.as_f64());
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(results, vec![3.0, 4.0]);
+        assert_eq!(cursor.checkpoint().records, 4);
+    }
+
+    #[test]
+    fn bad_record_is_consumed() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("a", &arena).unwrap();
+        let mut cursor = NdjsonCursor::new(Cursor::new("{\"a\": \n{\"a\": 2}\n"));
+
+        assert!(cursor.evaluate_next(&jsonata).unwrap().is_err());
+        assert_eq!(
+            cursor.evaluate_next(&jsonata).unwrap().unwrap().as_f64(),
+            2.0
+        );
+        assert!(cursor.evaluate_next(&jsonata).is_none());
+    }
+
+    #[test]
+    fn resume_after_failure() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("a", &arena).unwrap();
+        let mut cursor = NdjsonCursor::new(Cursor::new(INPUT));
+        let result = cursor.evaluate_batch(&jsonata, 10, |v| {
+            if v.as_f64() == 3.0 {
+                Err(Error::H0101InputRead("sink is full".to_string()))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(cursor.checkpoint().records, 2);
+
+        let mut cursor = NdjsonCursor::resume(Cursor::new(INPUT), cursor.checkpoint()).unwrap();
+        let mut results = vec![];
+        cursor
+            .evaluate_batch(&jsonata, 10, |v| {
+                results.push(v.as_f64());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(results, vec![3.0, 4.0]);
+
+        // An evaluation which fails isn't handled either
+        let mut cursor = NdjsonCursor::new(Cursor::new("{\"a\": 1}\n{\"a\": \n"));
+        assert!(cursor.evaluate_batch(&jsonata, 10, |_| Ok(())).is_err());
+        assert_eq!(cursor.checkpoint().offset, 9);
+    }
+
+    #[test]
+    fn invalid_checkpoint() {
+        assert_eq!(
+            "12".parse::<Checkpoint>(),
+            Err(Error::H0102InvalidCheckpoint("12".to_string()))
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::JsonAta;
+
+impl JsonAta<'_> {
+    /// Evaluates with exact decimal numbers rather than `f64`s, so that `0.1 + 0.2` is `0.3` and
+    /// monetary amounts and large identifiers from the input aren't rounded.
+    ///
+    /// Numbers in the expression and the input are parsed as decimals of up to 28 significant
+    /// digits, and arithmetic, comparisons, `$sum`, `$max`, `$min`, `$abs`, `$floor`, `$ceil`,
+    /// `$round` and `$number` keep them exact. Other functions work with `f64`s as usual, and any
+    /// result a decimal can't hold, such as one of more than 28 significant digits, is an `f64`.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new("$sum(items.price) * 3", &arena)?;
+    /// jsonata.set_decimal(true);
+    ///
+    /// let result = jsonata.evaluate(Some(r#"{"items": [{"price": 0.1}, {"price": 0.2}]}"#), None)?;
+    /// assert_eq!(result.serialize(false), "0.9");
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn set_decimal(&mut self, decimal: bool) {
+        self.decimal = decimal;
+        // Results remembered in the other mode have the wrong kind of numbers
+        if self.step_memo.is_some() {
+            self.set_incremental(true);
+        }
+    }
+
+    /// Whether the expression is evaluated with exact decimal numbers, see
+    /// [`JsonAta::set_decimal`].
+    pub fn is_decimal(&self) -> bool {
+        self.decimal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn evaluate(expr: &str, input: &str, decimal: bool) -> String {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_decimal(decimal);
+        let result = jsonata.evaluate(Some(input), None).unwrap();
+        result.serialize(false)
+    }
+
+    #[test_case("0.1 + 0.2 = 0.3", "true", "false")]
+    #[test_case("1.15 * 3", "3.45", "3.45")]
+    #[test_case("$sum(prices) = 0.6", "true", "false")]
+    #[test_case("id", "12345678901234567890", "12345678901234567890")]
+    #[test_case("id + 0.5", "12345678901234567890.5", "12345678901234600000")]
+    #[test_case("-amount", "-10.01", "-10.01")]
+    #[test_case("amount - 10 = 0.01", "true", "false")]
+    #[test_case("$round(2.675, 2)", "2.68", "2.68")]
+    #[test_case("$number('0.1') + 0.2 = 0.3", "true", "false")]
+    #[test_case("$max(prices)", "0.3", "0.3")]
+    #[test_case(
+        "prices^(>$).$string()",
+        r#"["0.3","0.2","0.1"]"#,
+        r#"["0.3","0.2","0.1"]"#
+    )]
+    #[test_case("$floor(-amount)", "-11", "-11")]
+    #[test_case("1 / 3", "0.3333333333333333333333333333", "0.333333333333333")]
+    #[test_case("1e300 * 10", "1e+301", "1e+301")]
+    fn decimal_and_f64(expr: &str, decimal: &str, f64: &str) {
+        let input = r#"{"prices": [0.1, 0.2, 0.3], "id": 12345678901234567890, "amount": 10.01}"#;
+        assert_eq!(evaluate(expr, input, true), decimal, "decimal");
+        assert_eq!(evaluate(expr, input, false), f64, "f64");
+    }
+
+    #[test]
+    fn mixed_with_f64() {
+        // `$power` only works with `f64`s
+        assert_eq!(evaluate("$power(2, 0.5) > 1.41", "{}", true), "true");
+        assert_eq!(evaluate("$sqrt(0.04) + 0.1", "{}", true), "0.3");
+        assert_eq!(evaluate("[1..3]", "{}", true), "[1,2,3]");
+        assert_eq!(evaluate("$string(1.50)", "{}", true), r#""1.5""#);
+    }
+
+    #[test]
+    fn compiled_expression() {
+        let arena = Bump::new();
+        let compiled = crate::CompiledExpression::new("0.1 + 0.2")
+            .unwrap()
+            .with_backend(crate::Backend::Bytecode);
+        let mut jsonata = compiled.bind(&arena);
+        jsonata.set_decimal(true);
+        assert_eq!(
+            jsonata.evaluate(None, None).unwrap().serialize(false),
+            "0.3"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use bumpalo::Bump;
+
+use crate::{JsonAta, Result, Value};
+
+/// A set of named documents, bound to `$documents` so that an expression can combine several
+/// sources, as in `$documents.orders` and `$documents.customers`, rather than having them merged
+/// into a single input.
+///
+/// Each document is parsed once into the arena, and can be used by any number of expressions in
+/// the same arena without being parsed or copied again.
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{Documents, JsonAta};
+/// let arena = Bump::new();
+/// let mut documents = Documents::new(&arena);
+/// documents.add("orders", r#"[{"customer": 1, "total": 10}, {"customer": 2, "total": 5}]"#)?;
+/// documents.add("customers", r#"[{"id": 1, "name": "Ada"}, {"id": 2, "name": "Alan"}]"#)?;
+///
+/// let expr = JsonAta::new(
+///     "$documents.orders.($order := $; {
+///         'name': $documents.customers[id = $order.customer].name,
+///         'total': total
+///     })",
+///     &arena,
+/// )?;
+/// expr.use_documents(&documents);
+/// let result = expr.evaluate(None, None)?;
+/// assert_eq!(
+///     result.serialize(false),
+///     r#"[{"name":"Ada","total":10},{"name":"Alan","total":5}]"#
+/// );
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Documents<'a> {
+    arena: &'a Bump,
+    documents: &'a Value<'a>,
+}
+
+impl<'a> Documents<'a> {
+    pub fn new(arena: &'a Bump) -> Self {
+        Self {
+            arena,
+            documents: Value::object(arena),
+        }
+    }
+
+    /// Parses `document` and adds it as `name`, replacing any document with the same name.
+    pub fn add(&mut self, name: &str, document: &str) -> Result<()> {
+        let document = crate::parse_value(document, self.arena)?;
+        self.add_value(name, document);
+        Ok(())
+    }
+
+    /// Adds a value which is already in the arena as `name`, replacing any document with the
+    /// same name.
+    pub fn add_value(&mut self, name: &str, document: &'a Value<'a>) {
+        // Expressions which are already using these documents keep seeing the ones they had
+        let documents = Value::object(self.arena);
+        for (key, value) in self.documents.entries() {
+            documents.insert(key, value);
+        }
+        documents.insert(name, document);
+        self.documents = documents;
+    }
+
+    /// The document named `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<&'a Value<'a>> {
+        let document = self.documents.get_entry(name);
+        (!document.is_undefined()).then_some(document)
+    }
+
+    /// All of the documents, as an object keyed by name.
+    pub fn as_value(&self) -> &'a Value<'a> {
+        self.documents
+    }
+}
+
+impl<'a> JsonAta<'a> {
+    /// Binds `$documents` to the documents for every subsequent evaluation. Documents added
+    /// afterwards are only seen once this is called again.
+    pub fn use_documents(&self, documents: &Documents<'a>) {
+        self.assign_var("documents", documents.as_value());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+
+    use super::*;
+
+    #[test]
+    fn multiple_expressions() {
+        let arena = Bump::new();
+        let mut documents = Documents::new(&arena);
+        documents.add("a", r#"{"x": 1}"#).unwrap();
+        documents.add("b", "[1, 2, 3]").unwrap();
+
+        let first = JsonAta::new("$documents.a.x + $sum($documents.b)", &arena).unwrap();
+        let second = JsonAta::new("$keys($documents)", &arena).unwrap();
+        first.use_documents(&documents);
+        second.use_documents(&documents);
+
+        assert_eq!(first.evaluate(None, None).unwrap().as_f64(), 7.0);
+        assert_eq!(
+            second.evaluate(None, None).unwrap().serialize(false),
+            r#"["a","b"]"#
+        );
+    }
+
+    #[test]
+    fn alongside_input() {
+        let arena = Bump::new();
+        let mut documents = Documents::new(&arena);
+        documents.add("rates", r#"{"GBP": 1.25}"#).unwrap();
+
+        let jsonata = JsonAta::new("amount * $lookup($documents.rates, currency)", &arena).unwrap();
+        jsonata.use_documents(&documents);
+        let result = jsonata
+            .evaluate(Some(r#"{"amount": 10, "currency": "GBP"}"#), None)
+            .unwrap();
+        assert_eq!(result.as_f64(), 12.5);
+    }
+
+    #[test]
+    fn replacing() {
+        let arena = Bump::new();
+        let mut documents = Documents::new(&arena);
+        documents.add("a", "1").unwrap();
+
+        let jsonata = JsonAta::new("$documents.a", &arena).unwrap();
+        jsonata.use_documents(&documents);
+
+        documents.add("a", "2").unwrap();
+        assert_eq!(jsonata.evaluate(None, None).unwrap().as_f64(), 1.0);
+
+        jsonata.use_documents(&documents);
+        assert_eq!(jsonata.evaluate(None, None).unwrap().as_f64(), 2.0);
+        assert!(documents.get("b").is_none());
+    }
+
+    #[test]
+    fn invalid_document() {
+        let arena = Bump::new();
+        let mut documents = Documents::new(&arena);
+        assert!(documents.add("a", "{").is_err());
+        assert!(documents.get("a").is_none());
+    }
+}
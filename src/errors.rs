@@ -1,6 +1,6 @@
 use std::{char, error, fmt};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     // Compile time errors
     S0101UnterminatedStringLiteral(usize),
@@ -22,6 +22,8 @@ pub enum Error {
     S0214ExpectedVarRight(usize, String),
     S0215BindingAfterPredicates(usize),
     S0216BindingAfterSort(usize),
+    S0401TypeParametersNotAllowed(usize),
+    S0402ChoiceGroupParameterized(usize),
 
     // Runtime errors
     D1001NumberOfOutRange(f64),
@@ -41,10 +43,13 @@ pub enum Error {
 
     // Type errors
     T0410ArgumentNotValid(usize, usize, String),
+    T0411ContextNotCompatible(usize, usize, String),
     T0412ArgumentMustBeArrayOfType(usize, usize, String, String),
     T1003NonStringKey(usize, String),
     T1005InvokedNonFunctionSuggest(usize, String),
     T1006InvokedNonFunction(usize),
+    T1007PartiallyAppliedNonFunctionSuggest(usize, String),
+    T1008PartiallyAppliedNonFunction(usize),
     T2001LeftSideNotNumber(usize, String),
     T2002RightSideNotNumber(usize, String),
     T2003LeftSideNotInteger(usize),
@@ -61,6 +66,28 @@ pub enum Error {
     // Expression timebox/depth errors
     U1001StackOverflow,
     U1001Timeout,
+
+    // Host errors, which aren't part of reference JSONata, mostly raised by the API surrounding the
+    // evaluator rather than by the expression
+    H0101InputRead(String),
+    H0102InvalidCheckpoint(String),
+    H0103MalformedArray(String),
+    H0104MalformedXml(String),
+    H0105MalformedMsgpack(String),
+    H0106MalformedCbor(String),
+    H0201Deserialize(String),
+    H0301Cancelled(u64),
+    H0302GasExhausted(u64),
+    H0401FunctionDenied(usize, String),
+    H0402FetchNotAllowed(usize, String),
+    H0501InvalidPath(String),
+    H0502InvalidLocale(String),
+    H0503InvalidLibrary(String),
+    H0601UnboundVariable(usize, String),
+    H0602UnknownFunction(usize, String),
+    H0701InvalidSchema(usize, String),
+    H0702FetchFailed(usize, String),
+    H0703InvalidPointer(usize, String),
 }
 
 impl error::Error for Error {}
@@ -79,6 +106,15 @@ impl Error {
      *  10xx    - evaluator
      *  20xx    - operators
      *  3xxx    - functions (blocks of 10 for each function)
+     *
+     * Hxxxx    - Host errors (not part of reference JSONata)
+     *  01xx    - input
+     *  02xx    - deserialization
+     *  03xx    - evaluation control
+     *  04xx    - host policy
+     *  05xx    - invalid arguments to the API
+     *  06xx    - static checks
+     *  07xx    - functions which aren't in reference JSONata
      */
     pub fn code(&self) -> &str {
         match *self {
@@ -102,6 +138,8 @@ impl Error {
             Error::S0214ExpectedVarRight(..) => "S0214",
             Error::S0215BindingAfterPredicates(..) => "S0215",
             Error::S0216BindingAfterSort(..) => "S0216",
+            Error::S0401TypeParametersNotAllowed(..) => "S0401",
+            Error::S0402ChoiceGroupParameterized(..) => "S0402",
 
             // Runtime errors
             Error::D1001NumberOfOutRange(..) => "D1001",
@@ -121,10 +159,13 @@ impl Error {
 
             // Type errors
             Error::T0410ArgumentNotValid(..) => "T0410",
+            Error::T0411ContextNotCompatible(..) => "T0411",
             Error::T0412ArgumentMustBeArrayOfType(..) => "T0412",
             Error::T1003NonStringKey(..) => "T1003",
             Error::T1005InvokedNonFunctionSuggest(..) => "T1005",
             Error::T1006InvokedNonFunction(..) => "T1006",
+            Error::T1007PartiallyAppliedNonFunctionSuggest(..) => "T1007",
+            Error::T1008PartiallyAppliedNonFunction(..) => "T1008",
             Error::T2001LeftSideNotNumber(..) => "T2001",
             Error::T2002RightSideNotNumber(..) => "T2002",
             Error::T2003LeftSideNotInteger(..) => "T2003",
@@ -141,6 +182,75 @@ impl Error {
             // Expression timebox/depth errors
             Error::U1001StackOverflow => "U1001",
             Error::U1001Timeout => "U1001",
+
+            // Host errors
+            Error::H0101InputRead(..) => "H0101",
+            Error::H0102InvalidCheckpoint(..) => "H0102",
+            Error::H0103MalformedArray(..) => "H0103",
+            Error::H0104MalformedXml(..) => "H0104",
+            Error::H0105MalformedMsgpack(..) => "H0105",
+            Error::H0106MalformedCbor(..) => "H0106",
+            Error::H0201Deserialize(..) => "H0201",
+            Error::H0301Cancelled(..) => "H0301",
+            Error::H0302GasExhausted(..) => "H0302",
+            Error::H0401FunctionDenied(..) => "H0401",
+            Error::H0402FetchNotAllowed(..) => "H0402",
+            Error::H0501InvalidPath(..) => "H0501",
+            Error::H0502InvalidLocale(..) => "H0502",
+            Error::H0503InvalidLibrary(..) => "H0503",
+            Error::H0601UnboundVariable(..) => "H0601",
+            Error::H0602UnknownFunction(..) => "H0602",
+            Error::H0701InvalidSchema(..) => "H0701",
+            Error::H0702FetchFailed(..) => "H0702",
+            Error::H0703InvalidPointer(..) => "H0703",
+        }
+    }
+
+    /// The index in the expression where a compile time error was found, so that tools can point
+    /// at it, or `None` for errors raised while evaluating.
+    pub fn char_index(&self) -> Option<usize> {
+        use Error::*;
+
+        match *self {
+            S0101UnterminatedStringLiteral(p)
+            | S0102LexedNumberOutOfRange(p, ..)
+            | S0103UnsupportedEscape(p, ..)
+            | S0104InvalidUnicodeEscape(p)
+            | S0105UnterminatedQuoteProp(p)
+            | S0106UnterminatedComment(p)
+            | S0201SyntaxError(p, ..)
+            | S0202UnexpectedToken(p, ..)
+            | S0203ExpectedTokenBeforeEnd(p, ..)
+            | S0204UnknownOperator(p, ..)
+            | S0208InvalidFunctionParam(p, ..)
+            | S0209InvalidPredicate(p)
+            | S0210MultipleGroupBy(p)
+            | S0211InvalidUnary(p, ..)
+            | S0212ExpectedVarLeft(p)
+            | S0213InvalidStep(p, ..)
+            | S0214ExpectedVarRight(p, ..)
+            | S0215BindingAfterPredicates(p)
+            | S0216BindingAfterSort(p)
+            | S0401TypeParametersNotAllowed(p)
+            | S0402ChoiceGroupParameterized(p)
+            | H0601UnboundVariable(p, ..)
+            | H0602UnknownFunction(p, ..) => Some(p),
+            _ => None,
+        }
+    }
+
+    /// The message without the code and position, such as `Comment has no closing tag`.
+    pub fn message(&self) -> String {
+        let display = self.to_string();
+        let message = display
+            .split_once(" @ ")
+            .map_or(&*display, |(_, rest)| rest);
+        match self.char_index() {
+            Some(p) => message
+                .strip_prefix(&format!("{}: ", p))
+                .unwrap_or(message)
+                .to_string(),
+            None => message.to_string(),
         }
     }
 }
@@ -192,6 +302,10 @@ impl fmt::Display for Error {
                 write!(f, "{}: A context variable binding must precede any predicates on a step", p),
             S0216BindingAfterSort(ref p) =>
                 write!(f, "{}: A context variable binding must precede the 'order-by' clause on a step", p),
+            S0401TypeParametersNotAllowed(ref p) =>
+                write!(f, "{}: Type parameters can only be applied to functions and arrays", p),
+            S0402ChoiceGroupParameterized(ref p) =>
+                write!(f, "{}: Choice groups containing parameterized types are not supported", p),
             // Runtime errors
             D1001NumberOfOutRange(ref n) => write!(f, "Number out of range: {}", n),
             D1002NegatingNonNumeric(ref p, ref v) =>
@@ -223,6 +337,8 @@ impl fmt::Display for Error {
             // Type errors
             T0410ArgumentNotValid(ref p, ref i, ref t) =>
                 write!(f, "{}: Argument {} of function {} does not match function signature", p, i, t),
+            T0411ContextNotCompatible(ref p, ref i, ref t) =>
+                write!(f, "{}: Context value is not a compatible type with argument {} of function {}", p, i, t),
             T0412ArgumentMustBeArrayOfType(ref p, ref i, ref t, ref ty) =>
                 write!(f, "{}: Argument {} of function {} must be an array of {}", p, i, t, ty),
             T1003NonStringKey(ref p, ref v) =>
@@ -231,6 +347,10 @@ impl fmt::Display for Error {
                 write!(f, "{}: Attempted to invoke a non-function. Did you mean ${}?", p, t),
             T1006InvokedNonFunction(ref p) =>
                 write!(f, "{}: Attempted to invoke a non-function", p),
+            T1007PartiallyAppliedNonFunctionSuggest(ref p, ref t) =>
+                write!(f, "{}: Attempted to partially apply a non-function. Did you mean ${}?", p, t),
+            T1008PartiallyAppliedNonFunction(ref p) =>
+                write!(f, "{}: Attempted to partially apply a non-function", p),
             T2001LeftSideNotNumber(ref p, ref o) =>
                 write!( f, "{}: The left side of the `{}` operator must evaluate to a number", p, o),
             T2002RightSideNotNumber(ref p, ref o) =>
@@ -259,7 +379,46 @@ impl fmt::Display for Error {
             U1001StackOverflow =>
                 write!(f, "Stack overflow error: Check for non-terminating recursive function.  Consider rewriting as tail-recursive."),
             U1001Timeout =>
-                write!(f, "Expression evaluation timeout: Check for infinite loop")
+                write!(f, "Expression evaluation timeout: Check for infinite loop"),
+            // Host errors
+            H0101InputRead(ref m) =>
+                write!(f, "Failed to read input: {}", m),
+            H0102InvalidCheckpoint(ref c) =>
+                write!(f, "Invalid checkpoint `{}`, expected `records:offset`", c),
+            H0103MalformedArray(ref m) =>
+                write!(f, "Malformed JSON array in input: {}", m),
+            H0104MalformedXml(ref m) =>
+                write!(f, "Malformed XML in input: {}", m),
+            H0105MalformedMsgpack(ref m) =>
+                write!(f, "Malformed MessagePack in input: {}", m),
+            H0106MalformedCbor(ref m) =>
+                write!(f, "Malformed CBOR in input: {}", m),
+            H0201Deserialize(ref m) =>
+                write!(f, "Failed to deserialize value: {}", m),
+            H0301Cancelled(ref n) =>
+                write!(f, "Evaluation cancelled by watchdog after {} nodes", n),
+            H0302GasExhausted(ref n) =>
+                write!(f, "Evaluation used more than its limit of {} gas", n),
+            H0401FunctionDenied(ref p, ref name) =>
+                write!(f, "{}: The function ${} is not allowed", p, name),
+            H0402FetchNotAllowed(ref p, ref url) =>
+                write!(f, "{}: The fetch function is not allowed to request {}", p, url),
+            H0501InvalidPath(ref p) =>
+                write!(f, "There is no value to replace at {}", p),
+            H0502InvalidLocale(ref l) =>
+                write!(f, "{} is not a locale, or has no data", l),
+            H0503InvalidLibrary(ref n) =>
+                write!(f, "The library imported as {} is not an object of functions", n),
+            H0601UnboundVariable(ref p, ref name) =>
+                write!(f, "{}: The variable ${} is not bound", p, name),
+            H0602UnknownFunction(ref p, ref name) =>
+                write!(f, "{}: The function ${} is not defined", p, name),
+            H0701InvalidSchema(ref p, ref m) =>
+                write!(f, "{}: The schema given to the validate function is not a valid JSON Schema: {}", p, m),
+            H0702FetchFailed(ref p, ref m) =>
+                write!(f, "{}: The fetch function's request failed: {}", p, m),
+            H0703InvalidPointer(ref p, ref pointer) =>
+                write!(f, "{}: {:?} is not a JSON Pointer, which is empty or starts with /", p, pointer),
         }
     }
 }
@@ -271,13 +430,8 @@ impl fmt::Display for Error {
 
 // "S0301": "Empty regular expressions are not allowed",
 // "S0302": "No terminating / in regular expression",
-// "S0402": "Choice groups containing parameterized types are not supported",
-// "S0401": "Type parameters can only be applied to functions and arrays",
 // "S0500": "Attempted to evaluate an expression containing syntax error(s)",
-// "T0411": "Context value is not a compatible type with argument {{index}} of function {{token}}",
 // "D1004": "Regular expression matches zero length string",
-// "T1007": "Attempted to partially apply a non-function. Did you mean ${{{token}}}?",
-// "T1008": "Attempted to partially apply a non-function",
 // // "T1010": "The matcher function argument passed to function {{token}} does not return the correct object structure",
 // "D2005": "The left side of := must be a variable name (start with $)",  // defunct - replaced by S0212 parser error
 // define_error!(
@@ -1,14 +1,38 @@
+pub mod bytecode;
+#[cfg(feature = "decimal")]
+pub mod decimal;
 pub mod frame;
 pub mod functions;
+#[cfg(feature = "hash")]
+pub mod hash;
+pub mod integer;
+pub mod merge_patch;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pointer;
+pub mod random;
+pub mod signature;
+pub mod step_memo;
+pub mod trace;
+pub mod units;
 pub mod value;
+pub mod watchdog;
 
 use frame::Frame;
 use functions::*;
+use random::{DefaultRandom, RandomSource};
+use step_memo::StepMemo;
+use trace::Tracer;
+use units::StringUnits;
 use value::{ArrayFlags, Value};
+use watchdog::{Progress, Watchdog, WatchdogAction};
 
 use bumpalo::Bump;
+use indexmap::{map::Entry, IndexMap};
 use std::cell::RefCell;
-use std::collections::{hash_map, HashMap};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use std::time::Instant;
 
 use super::parser::ast::*;
@@ -16,15 +40,51 @@ use crate::{Error, Result};
 
 struct EvaluatorInternal {
     depth: usize,
+    nodes: u64,
+    gas: u64,
+    gas_limit: Option<u64>,
     started_at: Option<Instant>,
     max_depth: Option<usize>,
     time_limit: Option<usize>,
 }
 
+/// The JSON Patch operations transforms have made, see [`crate::JsonAta::evaluate_patch`].
+#[derive(Default)]
+struct Patch<'a> {
+    operations: Vec<&'a Value<'a>>,
+    /// The addresses of the input and of the results of transforming it, which are the values
+    /// whose changes are recorded, as they're all at the root of the input.
+    roots: HashSet<usize>,
+}
+
+/// The functions returned by `$memoize` and the results they've cached, keyed by the address of
+/// the function.
+#[derive(Default)]
+struct Memo<'a> {
+    functions: HashSet<usize>,
+    results: HashMap<(usize, String), &'a Value<'a>>,
+}
+
 pub struct Evaluator<'a> {
     chain_ast: Option<Ast>,
     arena: &'a Bump,
     internal: RefCell<EvaluatorInternal>,
+    watchdog: Option<Rc<Watchdog<'a>>>,
+    tracer: Option<Rc<Tracer<'a>>>,
+    random: Rc<RefCell<dyn RandomSource + 'a>>,
+    host_random: bool,
+    memo: RefCell<Memo<'a>>,
+    step_memo: Option<Rc<StepMemo<'a>>>,
+    patch: Option<RefCell<Patch<'a>>>,
+    #[cfg(feature = "parallel")]
+    parallel: Option<(Rc<parallel::Parallel>, crate::FunctionPolicy)>,
+    string_units: StringUnits,
+    #[cfg(feature = "icu")]
+    locale: Option<Rc<crate::locale::Locale>>,
+    #[cfg(feature = "fetch")]
+    fetch: Option<Rc<crate::fetch::Fetch>>,
+    #[cfg(feature = "decimal")]
+    decimal: bool,
 }
 
 impl<'a> Evaluator<'a> {
@@ -39,11 +99,195 @@ impl<'a> Evaluator<'a> {
             arena,
             internal: RefCell::new(EvaluatorInternal {
                 depth: 0,
+                nodes: 0,
+                gas: 0,
+                gas_limit: None,
                 started_at: None,
                 max_depth,
                 time_limit,
             }),
+            watchdog: None,
+            tracer: None,
+            random: Rc::new(RefCell::new(DefaultRandom::new())),
+            host_random: false,
+            memo: RefCell::default(),
+            step_memo: None,
+            patch: None,
+            #[cfg(feature = "parallel")]
+            parallel: None,
+            string_units: StringUnits::default(),
+            #[cfg(feature = "icu")]
+            locale: None,
+            #[cfg(feature = "fetch")]
+            fetch: None,
+            #[cfg(feature = "decimal")]
+            decimal: false,
+        }
+    }
+
+    /// Evaluates number literals to exact decimals rather than `f64`s.
+    #[cfg(feature = "decimal")]
+    pub fn with_decimal(mut self, decimal: bool) -> Self {
+        self.decimal = decimal;
+        self
+    }
+
+    /// Whether number literals are evaluated to exact decimals.
+    #[cfg(feature = "decimal")]
+    pub fn is_decimal(&self) -> bool {
+        self.decimal
+    }
+
+    pub fn with_watchdog(mut self, watchdog: Option<Rc<Watchdog<'a>>>) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    pub fn with_tracer(mut self, tracer: Option<Rc<Tracer<'a>>>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    /// Uses `random` for the random numbers functions need, rather than a source of its own.
+    pub fn with_random(mut self, random: Option<Rc<RefCell<dyn RandomSource + 'a>>>) -> Self {
+        if let Some(random) = random {
+            self.random = random;
+            self.host_random = true;
+        }
+        self
+    }
+
+    /// Reuses the results of steps in `step_memo` which were evaluated against the same items by
+    /// an earlier evaluation, and remembers the results of the ones which weren't.
+    pub fn with_step_memo(mut self, step_memo: Option<Rc<StepMemo<'a>>>) -> Self {
+        self.step_memo = step_memo;
+        self
+    }
+
+    /// Records the changes transforms of `input` make as JSON Patch operations, see
+    /// [`crate::JsonAta::evaluate_patch`].
+    pub fn with_patch(mut self, input: &'a Value<'a>) -> Self {
+        let mut patch = Patch::default();
+        patch.roots.insert(input as *const Value as usize);
+        self.patch = Some(RefCell::new(patch));
+        self
+    }
+
+    /// The JSON Patch operations recorded so far, if they're being recorded.
+    pub fn take_patch(&self) -> Option<Vec<&'a Value<'a>>> {
+        self.patch
+            .as_ref()
+            .map(|patch| std::mem::take(&mut patch.borrow_mut().operations))
+    }
+
+    /// Evaluates the steps in `parallel` against large inputs on several threads, binding the
+    /// built-in functions allowed by `policy` on each of them. The steps aren't metered, so this
+    /// shouldn't be combined with any limits.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel(
+        mut self,
+        parallel: Option<(Rc<parallel::Parallel>, crate::FunctionPolicy)>,
+    ) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Counts the characters of strings in `string_units` in the string functions.
+    pub fn with_string_units(mut self, string_units: StringUnits) -> Self {
+        self.string_units = string_units;
+        self
+    }
+
+    /// What the string functions count the characters of strings in.
+    pub fn string_units(&self) -> StringUnits {
+        self.string_units
+    }
+
+    /// Cases and sorts strings by the rules of `locale` rather than independently of any.
+    #[cfg(feature = "icu")]
+    pub fn with_locale(mut self, locale: Option<Rc<crate::locale::Locale>>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// The locale strings are cased and sorted by the rules of, if any.
+    #[cfg(feature = "icu")]
+    pub fn locale(&self) -> Option<&crate::locale::Locale> {
+        self.locale.as_deref()
+    }
+
+    /// Lets `$fetch` request the URLs `fetch` allows, with its client.
+    #[cfg(feature = "fetch")]
+    pub(crate) fn with_fetch(mut self, fetch: Option<Rc<crate::fetch::Fetch>>) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    /// The URLs `$fetch` can request and the client it requests them with, if it can request any.
+    #[cfg(feature = "fetch")]
+    pub(crate) fn fetch(&self) -> Option<&crate::fetch::Fetch> {
+        self.fetch.as_deref()
+    }
+
+    /// Compares strings in the order of the locale's collation, or by their code points without
+    /// one.
+    pub fn compare_strings(&self, a: &str, b: &str) -> Ordering {
+        #[cfg(feature = "icu")]
+        if let Some(ref locale) = self.locale {
+            return locale.compare(a, b);
         }
+        a.cmp(b)
+    }
+
+    /// A random number in the range `[0, 1)`.
+    pub fn random(&self) -> f64 {
+        self.random.borrow_mut().next_f64()
+    }
+
+    /// Whether the host gave the source of random numbers, such as to make them reproducible,
+    /// rather than it being the evaluation's own.
+    pub fn has_host_random(&self) -> bool {
+        self.host_random
+    }
+
+    /// Meters the evaluation, failing with `H0302` once more than `gas_limit` units of gas have
+    /// been used. See [`crate::DeterministicProfile`] for how gas is charged.
+    pub fn with_gas_limit(self, gas_limit: Option<u64>) -> Self {
+        self.internal.borrow_mut().gas_limit = gas_limit;
+        self
+    }
+
+    /// The gas used so far, which is only tracked when there's a gas limit.
+    pub fn gas_used(&self) -> u64 {
+        self.internal.borrow().gas
+    }
+
+    /// Whether the evaluation has any limits, a watchdog or a tracer, which need the tree-walker to
+    /// track its progress, or memoizes steps or evaluates them in parallel, which only the
+    /// tree-walker does.
+    pub fn is_limited(&self) -> bool {
+        let internal = self.internal.borrow();
+        #[cfg(feature = "parallel")]
+        if self.parallel.is_some() {
+            return true;
+        }
+        internal.max_depth.is_some()
+            || internal.time_limit.is_some()
+            || internal.gas_limit.is_some()
+            || self.watchdog.is_some()
+            || self.tracer.is_some()
+            || self.step_memo.is_some()
+    }
+
+    fn charge_gas(&self, gas: u64) -> Result<()> {
+        let mut internal = self.internal.borrow_mut();
+        if let Some(gas_limit) = internal.gas_limit {
+            internal.gas += gas;
+            if internal.gas > gas_limit {
+                return Err(Error::H0302GasExhausted(gas_limit));
+            }
+        }
+        Ok(())
     }
 
     fn fn_context<'e>(
@@ -84,6 +328,31 @@ impl<'a> Evaluator<'a> {
                 return Err(Error::U1001StackOverflow);
             }
         }
+        if inc_or_dec {
+            internal.nodes += 1;
+            if let Some(gas_limit) = internal.gas_limit {
+                internal.gas += 1;
+                if internal.gas > gas_limit {
+                    return Err(Error::H0302GasExhausted(gas_limit));
+                }
+            }
+            if let Some(ref watchdog) = self.watchdog {
+                let progress = Progress {
+                    nodes: internal.nodes,
+                    depth: internal.depth,
+                    elapsed: internal
+                        .started_at
+                        .map(|started_at| started_at.elapsed())
+                        .unwrap_or_default(),
+                };
+                // Release the borrow, as the callback is free to evaluate, though that evaluation
+                // won't call it again
+                drop(internal);
+                if watchdog.tick(&progress) == WatchdogAction::Cancel {
+                    return Err(Error::H0301Cancelled(progress.nodes));
+                }
+            }
+        }
         Ok(())
     }
 
@@ -100,6 +369,13 @@ impl<'a> Evaluator<'a> {
             AstKind::Bool(b) => Value::bool(self.arena, b),
             AstKind::String(ref s) => Value::string(self.arena, String::from(s)),
             AstKind::Number(n) => Value::number(self.arena, n),
+            #[cfg(feature = "decimal")]
+            AstKind::Integer(n) if self.decimal => Value::decimal(self.arena, n.get().into()),
+            AstKind::Integer(n) => Value::integer(self.arena, n),
+            #[cfg(feature = "decimal")]
+            AstKind::Decimal(_, d) if self.decimal => Value::decimal(self.arena, d),
+            #[cfg(feature = "decimal")]
+            AstKind::Decimal(n, _) => Value::number(self.arena, n),
             AstKind::Block(ref exprs) => self.evaluate_block(exprs, input, frame)?,
             AstKind::Unary(ref op) => self.evaluate_unary_op(node, op, input, frame)?,
             AstKind::Binary(ref op, ref lhs, ref rhs) => {
@@ -134,6 +410,43 @@ impl<'a> Evaluator<'a> {
             _ => unimplemented!("TODO: node kind not yet supported: {:#?}", node.kind),
         };
 
+        if node.predicates.is_some() || node.group_by.is_some() {
+            result = self.evaluate_predicates_and_group_by(node, result, frame)?;
+        }
+
+        self.check_limits(false)?;
+
+        let result = self.normalize_sequence(result, node.keep_array);
+        if let Some(ref tracer) = self.tracer {
+            self.trace(tracer, node, input, result, frame);
+        }
+        Ok(result)
+    }
+
+    /// Reports an evaluated node to the tracer. Kept out of `evaluate` so that tracing doesn't add
+    /// to the size of its frames.
+    #[inline(never)]
+    fn trace(
+        &self,
+        tracer: &Tracer<'a>,
+        node: &Ast,
+        input: &'a Value<'a>,
+        output: &'a Value<'a>,
+        frame: &Frame<'a>,
+    ) {
+        // The depth has already been decremented for this node
+        let depth = self.internal.borrow().depth + 1;
+        tracer.step(node, depth, input, output, frame);
+    }
+
+    /// Applies the filters and group-by that follow a node to its result. Their locals would take
+    /// up space in every frame of `evaluate` if this was inlined there.
+    fn evaluate_predicates_and_group_by(
+        &self,
+        node: &Ast,
+        mut result: &'a Value<'a>,
+        frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
         if let Some(filters) = &node.predicates {
             for filter in filters {
                 if let AstKind::Filter(ref expr) = filter.kind {
@@ -142,32 +455,41 @@ impl<'a> Evaluator<'a> {
             }
         }
 
-        self.check_limits(false)?;
+        // Paths group their tuple streams themselves, see `evaluate_path`
+        if let Some((char_index, ref object)) = node.group_by {
+            if !matches!(node.kind, AstKind::Path(..)) {
+                result = self.evaluate_group_expression(char_index, object, result, frame)?;
+            }
+        }
 
-        Ok(
-            if result.has_flags(ArrayFlags::SEQUENCE) && !result.has_flags(ArrayFlags::TUPLE_STREAM)
-            {
-                if node.keep_array {
-                    result = result.clone_array_with_flags(
-                        self.arena,
-                        result.get_flags() | ArrayFlags::SINGLETON,
-                    )
-                }
-                if result.is_empty() {
-                    Value::undefined()
-                } else if result.len() == 1 {
-                    if result.has_flags(ArrayFlags::SINGLETON) {
-                        result
-                    } else {
-                        result.get_member(0)
-                    }
-                } else {
+        Ok(result)
+    }
+
+    /// Collapses a result sequence, an empty sequence becomes undefined and a sequence of one
+    /// becomes its only member unless it's marked as a singleton (or `keep_array` asks for it to
+    /// be kept as one).
+    fn normalize_sequence(&self, result: &'a Value<'a>, keep_array: bool) -> &'a Value<'a> {
+        if result.has_flags(ArrayFlags::SEQUENCE) && !result.has_flags(ArrayFlags::TUPLE_STREAM) {
+            let result = if keep_array {
+                result
+                    .clone_array_with_flags(self.arena, result.get_flags() | ArrayFlags::SINGLETON)
+            } else {
+                result
+            };
+            if result.is_empty() {
+                Value::undefined()
+            } else if result.len() == 1 {
+                if result.has_flags(ArrayFlags::SINGLETON) {
                     result
+                } else {
+                    result.get_member(0)
                 }
             } else {
                 result
-            },
-        )
+            }
+        } else {
+            result
+        }
     }
 
     fn evaluate_block(
@@ -218,16 +540,7 @@ impl<'a> Evaluator<'a> {
         match *op {
             UnaryOp::Minus(ref value) => {
                 let result = self.evaluate(value, input, frame)?;
-                match result {
-                    Value::Undefined => Ok(Value::undefined()),
-                    Value::Number(n) if result.is_valid_number()? => {
-                        Ok(Value::number(self.arena, -n))
-                    }
-                    _ => Err(Error::D1002NegatingNonNumeric(
-                        node.char_index,
-                        result.to_string(),
-                    )),
-                }
+                self.negate(node.char_index, result)
             }
             UnaryOp::ArrayConstructor(ref array) => {
                 let mut result = Value::array(
@@ -270,7 +583,7 @@ impl<'a> Evaluator<'a> {
             pub index: usize,
         }
 
-        let mut groups: HashMap<String, Group> = HashMap::new();
+        let mut groups: IndexMap<String, Group> = IndexMap::new();
         let reduce = input.has_flags(ArrayFlags::TUPLE_STREAM);
 
         let input = if input.is_array() && input.is_empty() {
@@ -305,7 +618,7 @@ impl<'a> Evaluator<'a> {
                 let key = key.as_str();
 
                 match groups.entry(key.to_string()) {
-                    hash_map::Entry::Occupied(mut entry) => {
+                    Entry::Occupied(mut entry) => {
                         let group = entry.get_mut();
                         if group.index != index {
                             return Err(Error::D1009MultipleKeys(char_index, key.to_string()));
@@ -316,7 +629,7 @@ impl<'a> Evaluator<'a> {
                         group.data =
                             fn_append(self.fn_context("append", char_index, input, frame), args)?;
                     }
-                    hash_map::Entry::Vacant(entry) => {
+                    Entry::Vacant(entry) => {
                         entry.insert(Group { data: item, index });
                     }
                 };
@@ -397,35 +710,131 @@ impl<'a> Evaluator<'a> {
         // of boolean expressions.
         let lhs = self.evaluate(lhs_ast, input, frame)?;
 
+        match op {
+            BinaryOp::And => Ok(Value::bool(
+                self.arena,
+                lhs.is_truthy() && self.evaluate(rhs_ast, input, frame)?.is_truthy(),
+            )),
+
+            BinaryOp::Or => Ok(Value::bool(
+                self.arena,
+                lhs.is_truthy() || self.evaluate(rhs_ast, input, frame)?.is_truthy(),
+            )),
+
+            BinaryOp::Apply => {
+                // A partial application is evaluated to a function, which is then applied to lhs
+                if let AstKind::Function {
+                    ref proc,
+                    ref args,
+                    is_partial: false,
+                    ..
+                } = rhs_ast.kind
+                {
+                    // Function invocation with lhs as the first argument
+                    Ok(self.evaluate_function(input, proc, args, false, frame, Some(lhs))?)
+                } else {
+                    let rhs = self.evaluate(rhs_ast, input, frame)?;
+
+                    if !rhs.is_function() {
+                        return Err(Error::T2006RightSideNotFunction(rhs_ast.char_index));
+                    }
+
+                    if lhs.is_function() {
+                        // Apply function chaining
+                        let chain = self.evaluate(
+                            self.chain_ast.as_ref().unwrap(),
+                            Value::undefined(),
+                            frame,
+                        )?;
+
+                        let args = Value::array_with_capacity(self.arena, 2, ArrayFlags::empty());
+                        args.push(lhs);
+                        args.push(rhs);
+
+                        Ok(self.apply_function(
+                            lhs_ast.char_index,
+                            Value::undefined(),
+                            chain,
+                            args,
+                            frame,
+                        )?)
+                    } else {
+                        let args = Value::array_with_capacity(self.arena, 1, ArrayFlags::empty());
+                        args.push(lhs);
+                        Ok(self.apply_function(
+                            rhs_ast.char_index,
+                            Value::undefined(),
+                            rhs,
+                            args,
+                            frame,
+                        )?)
+                    }
+                }
+            }
+
+            _ => {
+                let rhs = self.evaluate(rhs_ast, input, frame)?;
+                self.apply_binary_op(node.char_index, op, lhs, rhs, input, frame)
+            }
+        }
+    }
+
+    /// Negates a number, which is shared with the bytecode backend.
+    fn negate(&self, char_index: usize, value: &'a Value<'a>) -> Result<&'a Value<'a>> {
+        match value {
+            Value::Undefined => Ok(Value::undefined()),
+            Value::Number(n) if value.is_valid_number()? => Ok(Value::number(self.arena, -n)),
+            Value::Integer(n) => Ok(match integer::negate(*n) {
+                Some(n) => Value::integer(self.arena, n),
+                None => Value::number(self.arena, -n.to_f64()),
+            }),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => Ok(Value::decimal(self.arena, -d)),
+            _ => Err(Error::D1002NegatingNonNumeric(
+                char_index,
+                value.to_string(),
+            )),
+        }
+    }
+
+    /// Applies a binary operator which needs both of its operands to be evaluated up front.
+    fn apply_binary_op(
+        &self,
+        char_index: usize,
+        op: &BinaryOp,
+        lhs: &'a Value<'a>,
+        rhs: &'a Value<'a>,
+        input: &'a Value<'a>,
+        frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
         match op {
             BinaryOp::Add
             | BinaryOp::Subtract
             | BinaryOp::Multiply
             | BinaryOp::Divide
             | BinaryOp::Modulus => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
-
-                let lhs = if lhs.is_undefined() {
+                if lhs.is_undefined() {
                     return Ok(Value::undefined());
-                } else if lhs.is_valid_number()? {
-                    lhs.as_f64()
-                } else {
-                    return Err(Error::T2001LeftSideNotNumber(
-                        node.char_index,
-                        op.to_string(),
-                    ));
-                };
+                } else if !lhs.is_valid_number()? {
+                    return Err(Error::T2001LeftSideNotNumber(char_index, op.to_string()));
+                }
 
-                let rhs = if rhs.is_undefined() {
+                if rhs.is_undefined() {
                     return Ok(Value::undefined());
-                } else if rhs.is_valid_number()? {
-                    rhs.as_f64()
-                } else {
-                    return Err(Error::T2002RightSideNotNumber(
-                        node.char_index,
-                        op.to_string(),
-                    ));
-                };
+                } else if !rhs.is_valid_number()? {
+                    return Err(Error::T2002RightSideNotNumber(char_index, op.to_string()));
+                }
+
+                #[cfg(feature = "decimal")]
+                if let Some(result) = decimal::arithmetic(op, lhs, rhs) {
+                    return Ok(Value::decimal(self.arena, result));
+                }
+
+                if let Some(result) = integer::arithmetic(op, lhs, rhs) {
+                    return Ok(Value::integer(self.arena, result));
+                }
+
+                let (lhs, rhs) = (lhs.as_f64(), rhs.as_f64());
 
                 let result = match op {
                     BinaryOp::Add => lhs + rhs,
@@ -443,26 +852,27 @@ impl<'a> Evaluator<'a> {
             | BinaryOp::LessThanEqual
             | BinaryOp::GreaterThan
             | BinaryOp::GreaterThanEqual => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
-
                 if lhs.is_undefined() || rhs.is_undefined() {
                     return Ok(Value::undefined());
                 }
 
                 if !((lhs.is_number() || lhs.is_string()) && (rhs.is_number() || rhs.is_string())) {
-                    return Err(Error::T2010BinaryOpTypes(node.char_index, op.to_string()));
+                    return Err(Error::T2010BinaryOpTypes(char_index, op.to_string()));
                 }
 
                 if lhs.is_number() && rhs.is_number() {
-                    let lhs = lhs.as_f64();
-                    let rhs = rhs.as_f64();
+                    let ordering = lhs.compare_numbers(rhs);
                     return Ok(Value::bool(
                         self.arena,
                         match op {
-                            BinaryOp::LessThan => lhs < rhs,
-                            BinaryOp::LessThanEqual => lhs <= rhs,
-                            BinaryOp::GreaterThan => lhs > rhs,
-                            BinaryOp::GreaterThanEqual => lhs >= rhs,
+                            BinaryOp::LessThan => ordering == Some(Ordering::Less),
+                            BinaryOp::LessThanEqual => {
+                                matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                            }
+                            BinaryOp::GreaterThan => ordering == Some(Ordering::Greater),
+                            BinaryOp::GreaterThanEqual => {
+                                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                            }
                             _ => unreachable!(),
                         },
                     ));
@@ -482,7 +892,7 @@ impl<'a> Evaluator<'a> {
                 }
 
                 Err(Error::T2009BinaryOpMismatch(
-                    node.char_index,
+                    char_index,
                     lhs.to_string(),
                     rhs.to_string(),
                     op.to_string(),
@@ -490,8 +900,6 @@ impl<'a> Evaluator<'a> {
             }
 
             BinaryOp::Equal | BinaryOp::NotEqual => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
-
                 if lhs.is_undefined() || rhs.is_undefined() {
                     return Ok(Value::bool(self.arena, false));
                 }
@@ -507,14 +915,12 @@ impl<'a> Evaluator<'a> {
             }
 
             BinaryOp::Range => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
-
                 if !lhs.is_undefined() && !lhs.is_integer() {
-                    return Err(Error::T2003LeftSideNotInteger(node.char_index));
+                    return Err(Error::T2003LeftSideNotInteger(char_index));
                 };
 
                 if !rhs.is_undefined() && !rhs.is_integer() {
-                    return Err(Error::T2004RightSideNotInteger(node.char_index));
+                    return Err(Error::T2004RightSideNotInteger(char_index));
                 }
 
                 if lhs.is_undefined() || rhs.is_undefined() {
@@ -530,19 +936,18 @@ impl<'a> Evaluator<'a> {
 
                 let size = rhs - lhs + 1;
                 if size > 10_000_000 {
-                    return Err(Error::D2014RangeOutOfBounds(node.char_index, size));
+                    return Err(Error::D2014RangeOutOfBounds(char_index, size));
                 }
 
                 Ok(Value::range(self.arena, lhs, rhs))
             }
 
             BinaryOp::Concat => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
                 let mut result = String::new();
                 if !lhs.is_undefined() {
                     result.push_str(
                         &fn_string(
-                            self.fn_context("string", node.char_index, input, frame),
+                            self.fn_context("string", char_index, input, frame),
                             Value::wrap_in_array(self.arena, lhs, ArrayFlags::empty()),
                         )?
                         .as_str(),
@@ -551,7 +956,7 @@ impl<'a> Evaluator<'a> {
                 if !rhs.is_undefined() {
                     result.push_str(
                         &fn_string(
-                            self.fn_context("string", node.char_index, input, frame),
+                            self.fn_context("string", char_index, input, frame),
                             Value::wrap_in_array(self.arena, rhs, ArrayFlags::empty()),
                         )?
                         .as_str(),
@@ -560,69 +965,7 @@ impl<'a> Evaluator<'a> {
                 Ok(Value::string(self.arena, result))
             }
 
-            BinaryOp::And => Ok(Value::bool(
-                self.arena,
-                lhs.is_truthy() && self.evaluate(rhs_ast, input, frame)?.is_truthy(),
-            )),
-
-            BinaryOp::Or => Ok(Value::bool(
-                self.arena,
-                lhs.is_truthy() || self.evaluate(rhs_ast, input, frame)?.is_truthy(),
-            )),
-
-            BinaryOp::Apply => {
-                if let AstKind::Function {
-                    ref proc,
-                    ref args,
-                    is_partial,
-                    ..
-                } = rhs_ast.kind
-                {
-                    // Function invocation with lhs as the first argument
-                    Ok(self.evaluate_function(input, proc, args, is_partial, frame, Some(lhs))?)
-                } else {
-                    let rhs = self.evaluate(rhs_ast, input, frame)?;
-
-                    if !rhs.is_function() {
-                        return Err(Error::T2006RightSideNotFunction(rhs_ast.char_index));
-                    }
-
-                    if lhs.is_function() {
-                        // Apply function chaining
-                        let chain = self.evaluate(
-                            self.chain_ast.as_ref().unwrap(),
-                            Value::undefined(),
-                            frame,
-                        )?;
-
-                        let args = Value::array_with_capacity(self.arena, 2, ArrayFlags::empty());
-                        args.push(lhs);
-                        args.push(rhs);
-
-                        Ok(self.apply_function(
-                            lhs_ast.char_index,
-                            Value::undefined(),
-                            chain,
-                            args,
-                            frame,
-                        )?)
-                    } else {
-                        let args = Value::array_with_capacity(self.arena, 1, ArrayFlags::empty());
-                        args.push(lhs);
-                        Ok(self.apply_function(
-                            rhs_ast.char_index,
-                            Value::undefined(),
-                            rhs,
-                            args,
-                            frame,
-                        )?)
-                    }
-                }
-            }
-
             BinaryOp::In => {
-                let rhs = self.evaluate(rhs_ast, input, frame)?;
-
                 if lhs.is_undefined() || rhs.is_undefined() {
                     return Ok(Value::bool(self.arena, false));
                 }
@@ -725,15 +1068,7 @@ impl<'a> Evaluator<'a> {
         }
 
         if node.keep_singleton_array {
-            let flags = result.get_flags();
-            if flags.contains(ArrayFlags::CONS) && !flags.contains(ArrayFlags::SEQUENCE) {
-                result = Value::wrap_in_array(
-                    self.arena,
-                    result,
-                    flags.clone() | ArrayFlags::SEQUENCE | ArrayFlags::SINGLETON,
-                );
-            }
-            result = result.clone_array_with_flags(self.arena, flags | ArrayFlags::SINGLETON);
+            result = self.keep_singleton_array(result);
         }
 
         if let Some((char_index, ref object)) = node.group_by {
@@ -752,6 +1087,20 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    fn keep_singleton_array(&self, result: &'a Value<'a>) -> &'a Value<'a> {
+        let flags = result.get_flags();
+        let result = if flags.contains(ArrayFlags::CONS) && !flags.contains(ArrayFlags::SEQUENCE) {
+            Value::wrap_in_array(
+                self.arena,
+                result,
+                flags.clone() | ArrayFlags::SEQUENCE | ArrayFlags::SINGLETON,
+            )
+        } else {
+            result
+        };
+        result.clone_array_with_flags(self.arena, flags | ArrayFlags::SINGLETON)
+    }
+
     fn evaluate_step(
         &self,
         step: &Ast,
@@ -769,50 +1118,84 @@ impl<'a> Evaluator<'a> {
 
         let result = Value::array(self.arena, ArrayFlags::SEQUENCE);
 
+        #[cfg(feature = "parallel")]
+        if let Some(item_results) = self.evaluate_step_in_parallel(step, input)? {
+            for item_result in item_results {
+                if !item_result.is_undefined() {
+                    result.push(item_result);
+                }
+            }
+            return Ok(self.flatten_step_result(result, last_step));
+        }
+
+        let step_memo = self
+            .step_memo
+            .as_deref()
+            .filter(|step_memo| step_memo.is_pure(step));
+
         // Evaluate the step on each member of the input
         for (item_index, item) in input.members().enumerate() {
             if let Some(ref index_var) = step.index {
                 frame.bind(index_var, Value::number(self.arena, item_index as f64));
             }
 
-            let mut item_result = self.evaluate(step, item, frame)?;
-
-            if let Some(ref stages) = step.stages {
-                for stage in stages {
-                    if let AstKind::Filter(ref expr) = stage.kind {
-                        item_result = self.evaluate_filter(expr, item_result, frame)?
-                    }
-                }
-            }
+            let item_result = match step_memo {
+                Some(step_memo) => step_memo
+                    .get_or_evaluate(step, item, || self.evaluate_step_item(step, item, frame))?,
+                None => self.evaluate_step_item(step, item, frame)?,
+            };
 
             if !item_result.is_undefined() {
                 result.push(item_result);
             }
         }
 
-        Ok(
-            if last_step
-                && result.len() == 1
-                && result.get_member(0).is_array()
-                && !result.get_member(0).has_flags(ArrayFlags::SEQUENCE)
-            {
-                result.get_member(0)
-            } else {
-                // Flatten the result sequence
-                let result_sequence = Value::array(self.arena, ArrayFlags::SEQUENCE);
+        Ok(self.flatten_step_result(result, last_step))
+    }
 
-                for result_item in result.members() {
-                    if !result_item.is_array() || result_item.has_flags(ArrayFlags::CONS) {
-                        result_sequence.push(result_item);
-                    } else {
-                        for item in result_item.members() {
-                            result_sequence.push(item);
-                        }
+    fn evaluate_step_item(
+        &self,
+        step: &Ast,
+        item: &'a Value<'a>,
+        frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
+        let mut item_result = self.evaluate(step, item, frame)?;
+
+        if let Some(ref stages) = step.stages {
+            for stage in stages {
+                if let AstKind::Filter(ref expr) = stage.kind {
+                    item_result = self.evaluate_filter(expr, item_result, frame)?
+                }
+            }
+        }
+
+        Ok(item_result)
+    }
+
+    /// Flattens the results of evaluating a step against each member of its input into a single
+    /// sequence, except for a single array produced by the last step which is left as it is.
+    fn flatten_step_result(&self, result: &'a Value<'a>, last_step: bool) -> &'a Value<'a> {
+        if last_step
+            && result.len() == 1
+            && result.get_member(0).is_array()
+            && !result.get_member(0).has_flags(ArrayFlags::SEQUENCE)
+        {
+            result.get_member(0)
+        } else {
+            // Flatten the result sequence
+            let result_sequence = Value::array(self.arena, ArrayFlags::SEQUENCE);
+
+            for result_item in result.members() {
+                if !result_item.is_array() || result_item.has_flags(ArrayFlags::CONS) {
+                    result_sequence.push(result_item);
+                } else {
+                    for item in result_item.members() {
+                        result_sequence.push(item);
                     }
                 }
-                result_sequence
-            },
-        )
+            }
+            result_sequence
+        }
     }
 
     fn evaluate_tuple_step(
@@ -928,8 +1311,6 @@ impl<'a> Evaluator<'a> {
         let is_tuple_sort = input.has_flags(ArrayFlags::TUPLE_STREAM);
 
         let comp = |a: &'a Value<'a>, b: &'a Value<'a>| {
-            let mut result = 0;
-
             for (sort_term, descending) in sort_terms {
                 let aa = if is_tuple_sort {
                     let tuple_frame = Frame::from_tuple(frame, a);
@@ -945,54 +1326,43 @@ impl<'a> Evaluator<'a> {
                     self.evaluate(sort_term, b, frame)?
                 };
 
-                if aa.is_undefined() {
-                    result = if bb.is_undefined() { 0 } else { 1 };
-                    continue;
-                }
-
-                if bb.is_undefined() {
-                    result = -1;
-                    continue;
-                }
-
-                if !(aa.is_string() || aa.is_number()) || !(bb.is_string() || bb.is_number()) {
-                    return Err(Error::T2008InvalidOrderBy(char_index));
-                }
+                let ordering = match (aa.is_undefined(), bb.is_undefined()) {
+                    // Undefined goes last whichever the direction, as in jsonata.js
+                    (true, true) => continue,
+                    (true, false) => return Ok(true),
+                    (false, true) => return Ok(false),
+                    (false, false) => {
+                        if !(aa.is_string() || aa.is_number())
+                            || !(bb.is_string() || bb.is_number())
+                        {
+                            return Err(Error::T2008InvalidOrderBy(char_index));
+                        }
 
-                match (aa, bb) {
-                    (Value::String(a), Value::String(b)) if *a == *b => {
-                        continue;
-                    }
-                    (Value::String(a), Value::String(b)) if *a < *b => {
-                        result = -1;
-                    }
-                    (Value::String(..), Value::String(..)) => {
-                        result = 1;
-                    }
-                    (Value::Number(a), Value::Number(b)) if *a == *b => {
-                        continue;
-                    }
-                    (Value::Number(a), Value::Number(b)) if *a < *b => {
-                        result = -1;
-                    }
-                    (Value::Number(..), Value::Number(..)) => {
-                        result = 1;
-                    }
-                    _ => {
-                        return Err(Error::T2007CompareTypeMismatch(
-                            char_index,
-                            a.to_string(),
-                            b.to_string(),
-                        ));
+                        match (aa, bb) {
+                            (Value::String(a), Value::String(b)) => self.compare_strings(a, b),
+                            (a, b) if a.is_number() && b.is_number() => {
+                                a.compare_numbers(b).unwrap_or(Ordering::Greater)
+                            }
+                            _ => {
+                                return Err(Error::T2007CompareTypeMismatch(
+                                    char_index,
+                                    a.to_string(),
+                                    b.to_string(),
+                                ));
+                            }
+                        }
                     }
                 };
 
-                if *descending {
-                    result = -result;
+                // Later terms only break ties
+                match ordering {
+                    Ordering::Equal => continue,
+                    ordering if *descending => return Ok(ordering == Ordering::Less),
+                    ordering => return Ok(ordering == Ordering::Greater),
                 }
             }
 
-            Ok(result == 1)
+            Ok(false)
         };
 
         let sorted = merge_sort(unsorted, &comp)?;
@@ -1068,8 +1438,16 @@ impl<'a> Evaluator<'a> {
             index as usize
         };
 
-        match predicate.kind {
-            AstKind::Number(n) => {
+        let literal_index = match predicate.kind {
+            AstKind::Number(n) => Some(n),
+            AstKind::Integer(n) => Some(n.to_f64()),
+            #[cfg(feature = "decimal")]
+            AstKind::Decimal(n, _) => Some(n),
+            _ => None,
+        };
+
+        match literal_index {
+            Some(n) => {
                 let index = get_index(n);
                 let item = input.get_member(index);
                 if !item.is_undefined() {
@@ -1080,7 +1458,7 @@ impl<'a> Evaluator<'a> {
                     }
                 }
             }
-            _ => {
+            None => {
                 for (item_index, item) in input.members().enumerate() {
                     let mut index = if input.has_flags(ArrayFlags::TUPLE_STREAM) {
                         let tuple_frame = Frame::from_tuple(frame, item);
@@ -1188,12 +1566,16 @@ impl<'a> Evaluator<'a> {
         input: &'a Value<'a>,
         proc: &Ast,
         args: &[Ast],
-        _is_partial: bool,
+        is_partial: bool,
         frame: &Frame<'a>,
         context: Option<&'a Value<'a>>,
     ) -> Result<&'a Value<'a>> {
         let evaluated_proc = self.evaluate(proc, input, frame)?;
 
+        if is_partial {
+            return self.partially_apply(input, proc, evaluated_proc, args, frame);
+        }
+
         // Help the user out if they forgot a '$'
         if evaluated_proc.is_undefined() {
             if let AstKind::Path(ref steps) = proc.kind {
@@ -1269,6 +1651,99 @@ impl<'a> Evaluator<'a> {
         Ok(result)
     }
 
+    /// Partially applies a function, returning a lambda which takes the arguments given as `?`
+    /// placeholders and calls the function with them and the other arguments, which are evaluated
+    /// now. The function and the fixed arguments are bound in the lambda's frame under names which
+    /// can't be written in an expression, so they can't clash with the caller's variables.
+    fn partially_apply(
+        &self,
+        input: &'a Value<'a>,
+        proc: &Ast,
+        evaluated_proc: &'a Value<'a>,
+        args: &[Ast],
+        frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
+        if !evaluated_proc.is_function() {
+            if let AstKind::Path(ref steps) = proc.kind {
+                if let AstKind::Name(ref name) = steps[0].kind {
+                    if frame.lookup(name).is_some() {
+                        return Err(Error::T1007PartiallyAppliedNonFunctionSuggest(
+                            proc.char_index,
+                            name.clone(),
+                        ));
+                    }
+                }
+            }
+            return Err(Error::T1008PartiallyAppliedNonFunction(proc.char_index));
+        }
+
+        let partial_frame = Frame::new_with_parent(frame);
+        partial_frame.bind("partial proc", evaluated_proc);
+
+        let mut params = Vec::new();
+        let mut call_args = Vec::with_capacity(args.len());
+        for (index, arg) in args.iter().enumerate() {
+            let name = format!("partial arg {}", index);
+            if let AstKind::PartialArg = arg.kind {
+                params.push(Ast::new(AstKind::Var(name.clone()), arg.char_index));
+            } else {
+                partial_frame.bind(&name, self.evaluate(arg, input, frame)?);
+            }
+            call_args.push(Ast::new(AstKind::Var(name), arg.char_index));
+        }
+
+        let name = match evaluated_proc {
+            Value::NativeFn { ref name, .. } => name.clone(),
+            _ => String::from("partial"),
+        };
+        let body = Ast::new(
+            AstKind::Function {
+                name: name.clone(),
+                proc: Box::new(Ast::new(
+                    AstKind::Var(String::from("partial proc")),
+                    proc.char_index,
+                )),
+                args: call_args,
+                is_partial: false,
+            },
+            proc.char_index,
+        );
+        let lambda = Ast::new(
+            AstKind::Lambda {
+                name,
+                args: params,
+                body: Box::new(body),
+                thunk: false,
+                signature: None,
+            },
+            proc.char_index,
+        );
+
+        Ok(Value::lambda(self.arena, &lambda, input, partial_frame))
+    }
+
+    /// Returns a copy of a function which caches its results for the rest of the evaluation,
+    /// keyed by its arguments, or `None` if it's not a lambda or a native function.
+    pub fn memoize(&self, proc: &'a Value<'a>) -> Option<&'a Value<'a>> {
+        let memoized = match proc {
+            Value::Lambda {
+                ref ast,
+                input,
+                ref frame,
+            } => Value::lambda(self.arena, ast, input, frame.clone()),
+            Value::NativeFn {
+                ref name,
+                arity,
+                func,
+            } => Value::nativefn(self.arena, name, *arity, *func),
+            _ => return None,
+        };
+
+        let address = memoized as *const Value as usize;
+        self.memo.borrow_mut().functions.insert(address);
+        Some(memoized)
+    }
+
     pub fn apply_function(
         &self,
         char_index: usize,
@@ -1276,32 +1751,93 @@ impl<'a> Evaluator<'a> {
         evaluated_proc: &'a Value<'a>,
         evaluated_args: &'a Value<'a>,
         frame: &Frame<'a>,
+    ) -> Result<&'a Value<'a>> {
+        let address = evaluated_proc as *const Value as usize;
+        let memoized = self.memo.borrow().functions.contains(&address)
+            && evaluated_args.is_array()
+            && !evaluated_args.members().any(|arg| arg.is_function());
+        if !memoized {
+            return self.call_function(char_index, input, evaluated_proc, evaluated_args, frame);
+        }
+
+        // Arguments are compared by their JSON, as functions which take other functions aren't
+        // memoized
+        let key = (address, evaluated_args.serialize(false));
+        let cached = self.memo.borrow().results.get(&key).copied();
+        if let Some(result) = cached {
+            return Ok(result);
+        }
+
+        let result =
+            self.call_function(char_index, input, evaluated_proc, evaluated_args, frame)?;
+        self.memo.borrow_mut().results.insert(key, result);
+        Ok(result)
+    }
+
+    /// Creates the frame for a call to a lambda, with its arguments bound to the names of its
+    /// parameters. This is kept out of `call_function` so that it doesn't add to the stack used by
+    /// each level of recursion.
+    fn bind_lambda_args(
+        &self,
+        char_index: usize,
+        input: &'a Value<'a>,
+        ast: &Ast,
+        evaluated_args: &'a Value<'a>,
+        parent: &Frame<'a>,
+    ) -> Result<Frame<'a>> {
+        let AstKind::Lambda {
+            ref name,
+            ref args,
+            ref signature,
+            ..
+        } = ast.kind
+        else {
+            unreachable!()
+        };
+
+        // The context is used for missing arguments which the signature allows it for
+        let evaluated_args = if let Some(signature) = signature {
+            signature.validate(self.arena, evaluated_args, input, char_index, name)?
+        } else {
+            evaluated_args
+        };
+
+        // Create a new frame for use in the lambda, so it can have locals
+        let frame = Frame::new_with_parent(parent);
+
+        // Bind the arguments to their respective names
+        for (index, arg) in args.iter().enumerate() {
+            if let AstKind::Var(ref name) = arg.kind {
+                frame.bind(name, evaluated_args.get_member(index));
+            } else {
+                unreachable!()
+            }
+        }
+
+        Ok(frame)
+    }
+
+    fn call_function(
+        &self,
+        char_index: usize,
+        input: &'a Value<'a>,
+        evaluated_proc: &'a Value<'a>,
+        evaluated_args: &'a Value<'a>,
+        frame: &Frame<'a>,
     ) -> Result<&'a Value<'a>> {
         match evaluated_proc {
             Value::Lambda {
                 ref ast,
                 ref frame,
-                input,
+                input: lambda_input,
                 ..
             } => {
-                if let AstKind::Lambda {
-                    ref body, ref args, ..
-                } = ast.kind
-                {
-                    // Create a new frame for use in the lambda, so it can have locals
-                    let frame = Frame::new_with_parent(frame);
-
-                    // Bind the arguments to their respective names
-                    for (index, arg) in args.iter().enumerate() {
-                        if let AstKind::Var(ref name) = arg.kind {
-                            frame.bind(name, evaluated_args.get_member(index));
-                        } else {
-                            unreachable!()
-                        }
-                    }
+                if let AstKind::Lambda { ref body, .. } = ast.kind {
+                    let frame =
+                        self.bind_lambda_args(char_index, input, ast, evaluated_args, frame)?;
 
                     // Evaluate the lambda!
-                    self.evaluate(body, input, &frame)
+                    self.evaluate(body, lambda_input, &frame)
                 } else {
                     unreachable!()
                 }
@@ -1309,6 +1845,16 @@ impl<'a> Evaluator<'a> {
             Value::NativeFn {
                 ref name, ref func, ..
             } => {
+                // Built-in and registered functions do work proportional to the size of their
+                // array arguments, which evaluating the argument nodes doesn't account for
+                if self.internal.borrow().gas_limit.is_some() {
+                    let members = evaluated_args
+                        .members()
+                        .filter(|arg| arg.is_array())
+                        .map(|arg| arg.len() as u64)
+                        .sum();
+                    self.charge_gas(members)?;
+                }
                 let context = self.fn_context(name, char_index, input, frame);
                 func(context, evaluated_args)
             }
@@ -1344,7 +1890,19 @@ impl<'a> Evaluator<'a> {
             ));
         }
 
-        let result = input.clone(self.arena);
+        // The updates change the objects the pattern matches, which mustn't change the input
+        let result = input.clone_deep(self.arena);
+
+        // Only changes to the input are recorded, as a pointer into a value within it, or into one
+        // which was created, wouldn't be a path from the root
+        let recorded = self.patch.as_ref().is_some_and(|patch| {
+            let mut patch = patch.borrow_mut();
+            let recorded = patch.roots.contains(&(input as *const Value as usize));
+            if recorded {
+                patch.roots.insert(result as *const Value as usize);
+            }
+            recorded
+        });
 
         let matches = self.evaluate(
             pattern_ast,
@@ -1354,7 +1912,14 @@ impl<'a> Evaluator<'a> {
 
         if !matches.is_undefined() {
             let matches = Value::wrap_in_array_if_needed(self.arena, matches, ArrayFlags::empty());
+            let pointers = recorded.then(|| pointer::index(result));
             for m in matches.members() {
+                // Where the match is in the value being transformed, if changes to it are recorded
+                let at = pointers
+                    .as_ref()
+                    .and_then(|pointers| pointers.get(&(m as *const Value as usize)))
+                    .filter(|_| m.is_object());
+
                 let update = self.evaluate(update_ast, m, frame)?;
                 if !update.is_undefined() {
                     if !update.is_object() {
@@ -1364,6 +1929,14 @@ impl<'a> Evaluator<'a> {
                         ));
                     } else {
                         for (key, value) in update.entries() {
+                            if let Some(at) = at {
+                                let op = if m.get_entry(key).is_undefined() {
+                                    "add"
+                                } else {
+                                    "replace"
+                                };
+                                self.record_patch(op, at, key, Some(value));
+                            }
                             m.__very_unsafe_make_mut().insert(key, value);
                         }
                     }
@@ -1385,7 +1958,11 @@ impl<'a> Evaluator<'a> {
                                 ));
                             }
                             if m.is_object() {
-                                m.__very_unsafe_make_mut().remove(&deletion.as_str());
+                                let key = deletion.as_str();
+                                if let Some(at) = at.filter(|_| !m.get_entry(&key).is_undefined()) {
+                                    self.record_patch("remove", at, &key, None);
+                                }
+                                m.__very_unsafe_make_mut().remove(&key);
                             }
                         }
                     }
@@ -1395,4 +1972,19 @@ impl<'a> Evaluator<'a> {
 
         Ok(result)
     }
+
+    /// Records a JSON Patch operation on the member `key` of the object at the pointer `at`.
+    fn record_patch(&self, op: &str, at: &str, key: &str, value: Option<&'a Value<'a>>) {
+        let Some(ref patch) = self.patch else {
+            return;
+        };
+        let operation = Value::object(self.arena);
+        operation.insert("op", Value::string(self.arena, op));
+        let path = format!("{}/{}", at, pointer::escape(key));
+        operation.insert("path", Value::string(self.arena, &path));
+        if let Some(value) = value {
+            operation.insert("value", value);
+        }
+        patch.borrow_mut().operations.push(operation);
+    }
 }
@@ -0,0 +1,313 @@
+//! An alternative evaluation backend which compiles the processed AST into a flat sequence of
+//! instructions for a small stack machine, avoiding the per-node overhead of the tree-walking
+//! evaluator.
+//!
+//! Only a subset of the language is compiled: literals, variables, simple name paths, and the
+//! arithmetic, comparison, boolean and conditional operators. Anything else makes [`compile`]
+//! return `None` and evaluation falls back to the tree-walking evaluator, so selecting the bytecode
+//! backend never changes the result of an expression.
+
+mod vm;
+
+use super::integer::Integer;
+use crate::parser::ast::{Ast, AstKind, BinaryOp, UnaryOp};
+
+pub use vm::run;
+
+/// Selects how expressions are evaluated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Evaluate by walking the AST.
+    #[default]
+    TreeWalker,
+
+    /// Evaluate compiled bytecode, falling back to walking the AST for expressions which can't be
+    /// compiled.
+    Bytecode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    Integer(Integer),
+    /// Pushes a string from the program's string table.
+    String(usize),
+    /// Pushes the value of a variable, named in the string table.
+    Var(usize),
+    /// Pushes the input as a sequence, ready for the first step of a path.
+    Context,
+    /// Replaces the sequence on top of the stack with the result of looking up a name, from the
+    /// string table, in each of its members.
+    Step {
+        name: usize,
+        keep_array: bool,
+        last: bool,
+    },
+    /// Jumps if the value on top of the stack is undefined or an empty array, leaving it in place.
+    JumpIfEmpty(usize),
+    Normalize {
+        keep_array: bool,
+    },
+    /// Marks the array on top of the stack as a singleton, for paths ending in `[]`.
+    KeepSingleton,
+    Negate(usize),
+    /// Pops the right and left hand sides and pushes the result of applying the operator.
+    Binary(BinaryOp, usize),
+    /// Pops the left hand side of `and`, pushing `false` and jumping if it isn't truthy.
+    And(usize),
+    /// Pops the left hand side of `or`, pushing `true` and jumping if it's truthy.
+    Or(usize),
+    /// Replaces the value on top of the stack with its truthiness.
+    ToBool,
+    /// Pops a condition and jumps if it isn't truthy.
+    JumpIfFalsy(usize),
+    Jump(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct Program {
+    code: Vec<Instr>,
+    strings: Vec<String>,
+}
+
+impl Program {
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.code.push(instr);
+        self.code.len() - 1
+    }
+
+    fn string(&mut self, s: &str) -> usize {
+        match self.strings.iter().position(|existing| existing == s) {
+            Some(index) => index,
+            None => {
+                self.strings.push(s.to_string());
+                self.strings.len() - 1
+            }
+        }
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+        match self.code[at] {
+            Instr::JumpIfEmpty(ref mut t)
+            | Instr::And(ref mut t)
+            | Instr::Or(ref mut t)
+            | Instr::JumpIfFalsy(ref mut t)
+            | Instr::Jump(ref mut t) => *t = target,
+            _ => unreachable!("Patching an instruction that isn't a jump"),
+        }
+    }
+}
+
+/// Compiles an expression, or returns `None` if it uses anything that isn't supported by the
+/// bytecode backend.
+pub fn compile(ast: &Ast) -> Option<Program> {
+    let mut program = Program::default();
+    compile_node(&mut program, ast)?;
+    Some(program)
+}
+
+// Nodes with anything attached which affects how they're evaluated (predicates, grouping,
+// variable binding and so on) are left to the tree-walker.
+fn is_plain(node: &Ast) -> bool {
+    node.predicates.is_none()
+        && node.stages.is_none()
+        && node.group_by.is_none()
+        && !node.tuple
+        && node.index.is_none()
+        && node.focus.is_none()
+        && !node.cons_array
+}
+
+fn compile_node(program: &mut Program, node: &Ast) -> Option<()> {
+    if !is_plain(node) || (node.keep_singleton_array && !matches!(node.kind, AstKind::Path(..))) {
+        return None;
+    }
+
+    match node.kind {
+        AstKind::Null => {
+            program.emit(Instr::Null);
+        }
+        AstKind::Bool(b) => {
+            program.emit(Instr::Bool(b));
+        }
+        AstKind::Number(n) => {
+            program.emit(Instr::Number(n));
+        }
+        AstKind::Integer(n) => {
+            program.emit(Instr::Integer(n));
+        }
+        // Decimal mode always uses the tree-walker, so these are only evaluated as `f64`s
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(n, _) => {
+            program.emit(Instr::Number(n));
+        }
+        AstKind::String(ref s) => {
+            let index = program.string(s);
+            program.emit(Instr::String(index));
+        }
+        AstKind::Var(ref name) => {
+            let index = program.string(name);
+            program.emit(Instr::Var(index));
+            program.emit(Instr::Normalize {
+                keep_array: node.keep_array,
+            });
+        }
+        AstKind::Path(ref steps) => compile_path(program, node, steps)?,
+        AstKind::Unary(UnaryOp::Minus(ref value)) => {
+            compile_node(program, value)?;
+            program.emit(Instr::Negate(node.char_index));
+        }
+        AstKind::Binary(ref op, ref lhs, ref rhs) => match op {
+            BinaryOp::And | BinaryOp::Or => {
+                compile_node(program, lhs)?;
+                let jump = program.emit(if *op == BinaryOp::And {
+                    Instr::And(0)
+                } else {
+                    Instr::Or(0)
+                });
+                compile_node(program, rhs)?;
+                program.emit(Instr::ToBool);
+                program.patch(jump);
+            }
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulus
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessThanEqual
+            | BinaryOp::GreaterThanEqual
+            | BinaryOp::Concat
+            | BinaryOp::In
+            | BinaryOp::Range => {
+                compile_node(program, lhs)?;
+                compile_node(program, rhs)?;
+                program.emit(Instr::Binary(op.clone(), node.char_index));
+            }
+            _ => return None,
+        },
+        AstKind::Ternary {
+            ref cond,
+            ref truthy,
+            ref falsy,
+        } => {
+            compile_node(program, cond)?;
+            let jump_to_falsy = program.emit(Instr::JumpIfFalsy(0));
+            compile_node(program, truthy)?;
+            let jump_to_end = program.emit(Instr::Jump(0));
+            program.patch(jump_to_falsy);
+            match falsy {
+                Some(falsy) => compile_node(program, falsy)?,
+                None => {
+                    program.emit(Instr::Undefined);
+                }
+            }
+            program.patch(jump_to_end);
+            program.emit(Instr::Normalize {
+                keep_array: node.keep_array,
+            });
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+fn compile_path(program: &mut Program, node: &Ast, steps: &[Ast]) -> Option<()> {
+    if steps.is_empty() {
+        return None;
+    }
+
+    program.emit(Instr::Context);
+
+    let mut jumps = Vec::with_capacity(steps.len());
+    for (step_index, step) in steps.iter().enumerate() {
+        let AstKind::Name(ref name) = step.kind else {
+            return None;
+        };
+        if !is_plain(step) {
+            return None;
+        }
+
+        let name = program.string(name);
+        program.emit(Instr::Step {
+            name,
+            keep_array: step.keep_array,
+            last: step_index == steps.len() - 1,
+        });
+        if step_index < steps.len() - 1 {
+            jumps.push(program.emit(Instr::JumpIfEmpty(0)));
+        }
+    }
+
+    for jump in jumps {
+        program.patch(jump);
+    }
+
+    if node.keep_singleton_array {
+        program.emit(Instr::KeepSingleton);
+    }
+
+    program.emit(Instr::Normalize {
+        keep_array: node.keep_array,
+    });
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::parser::parse;
+
+    #[test_case("1 + 2 * 3")]
+    #[test_case("a.b.c")]
+    #[test_case("a.b > 5 and c = \"x\"")]
+    #[test_case("$x ? a : -b")]
+    #[test_case("a.b[]")]
+    fn compiles(expr: &str) {
+        assert!(compile(&parse(expr).unwrap()).is_some());
+    }
+
+    #[test_case("$sum(a)")]
+    #[test_case("a[0]")]
+    #[test_case("a.{\"b\": c}")]
+    #[test_case("$x := 1")]
+    #[test_case("a^(b)")]
+    #[test_case("a#$i.b")]
+    fn falls_back(expr: &str) {
+        assert!(compile(&parse(expr).unwrap()).is_none());
+    }
+
+    #[test]
+    fn path_instructions() {
+        let program = compile(&parse("a.b").unwrap()).unwrap();
+        assert_eq!(
+            program.code,
+            vec![
+                Instr::Context,
+                Instr::Step {
+                    name: 0,
+                    keep_array: false,
+                    last: false
+                },
+                Instr::JumpIfEmpty(4),
+                Instr::Step {
+                    name: 1,
+                    keep_array: false,
+                    last: true
+                },
+                Instr::Normalize { keep_array: false },
+            ]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use super::{Instr, Program};
+use crate::evaluator::frame::Frame;
+use crate::evaluator::functions::fn_lookup_internal;
+use crate::evaluator::value::{ArrayFlags, Value};
+use crate::evaluator::Evaluator;
+use crate::Result;
+
+/// Runs a compiled program against `input`. The evaluator provides the arena and the operator
+/// implementations, which are shared with the tree-walker so that both backends agree.
+pub fn run<'a>(
+    evaluator: &Evaluator<'a>,
+    program: &Program,
+    input: &'a Value<'a>,
+    frame: &Frame<'a>,
+) -> Result<&'a Value<'a>> {
+    let arena = evaluator.arena;
+    let mut stack: Vec<&'a Value<'a>> = Vec::with_capacity(16);
+    let mut pc = 0;
+
+    macro_rules! pop {
+        () => {
+            stack.pop().expect("Bytecode stack underflow")
+        };
+    }
+
+    while let Some(instr) = program.code.get(pc) {
+        pc += 1;
+        match *instr {
+            Instr::Undefined => stack.push(Value::undefined()),
+            Instr::Null => stack.push(Value::null(arena)),
+            Instr::Bool(b) => stack.push(Value::bool(arena, b)),
+            Instr::Number(n) => stack.push(Value::number(arena, n)),
+            Instr::Integer(n) => stack.push(Value::integer(arena, n)),
+            Instr::String(index) => stack.push(Value::string(arena, &program.strings[index])),
+            Instr::Var(index) => {
+                stack.push(evaluator.evaluate_var(&program.strings[index], input, frame)?)
+            }
+            Instr::Context => stack.push(if input.is_array() {
+                input
+            } else {
+                Value::wrap_in_array(arena, input, ArrayFlags::SEQUENCE)
+            }),
+            Instr::Step {
+                name,
+                keep_array,
+                last,
+            } => {
+                let step_input = pop!();
+                let name = &program.strings[name];
+                let context = evaluator.fn_context("lookup", 0, step_input, frame);
+                let result = Value::array(arena, ArrayFlags::SEQUENCE);
+                for item in step_input.members() {
+                    let item_result = evaluator.normalize_sequence(
+                        fn_lookup_internal(context.clone(), item, name),
+                        keep_array,
+                    );
+                    if !item_result.is_undefined() {
+                        result.push(item_result);
+                    }
+                }
+                stack.push(evaluator.flatten_step_result(result, last));
+            }
+            Instr::JumpIfEmpty(target) => {
+                let top = stack.last().expect("Bytecode stack underflow");
+                if top.is_undefined() || (top.is_array() && top.is_empty()) {
+                    pc = target;
+                }
+            }
+            Instr::Normalize { keep_array } => {
+                let value = pop!();
+                stack.push(evaluator.normalize_sequence(value, keep_array));
+            }
+            Instr::KeepSingleton => {
+                let value = pop!();
+                stack.push(evaluator.keep_singleton_array(value));
+            }
+            Instr::Negate(char_index) => {
+                let value = pop!();
+                stack.push(evaluator.negate(char_index, value)?);
+            }
+            Instr::Binary(ref op, char_index) => {
+                let rhs = pop!();
+                let lhs = pop!();
+                stack.push(evaluator.apply_binary_op(char_index, op, lhs, rhs, input, frame)?);
+            }
+            Instr::And(target) => {
+                if !pop!().is_truthy() {
+                    stack.push(Value::bool(arena, false));
+                    pc = target;
+                }
+            }
+            Instr::Or(target) => {
+                if pop!().is_truthy() {
+                    stack.push(Value::bool(arena, true));
+                    pc = target;
+                }
+            }
+            Instr::ToBool => {
+                let value = pop!();
+                stack.push(Value::bool(arena, value.is_truthy()));
+            }
+            Instr::JumpIfFalsy(target) => {
+                if !pop!().is_truthy() {
+                    pc = target;
+                }
+            }
+            Instr::Jump(target) => pc = target,
+        }
+    }
+
+    Ok(pop!())
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::super::compile;
+    use crate::parser::parse;
+    use crate::Error;
+
+    use super::*;
+
+    const INPUT: &str = r#"{
+        "a": {"b": [{"c": 1}, {"c": [2, 3]}, {"d": 4}], "n": 5},
+        "s": "str",
+        "t": true,
+        "e": []
+    }"#;
+
+    fn evaluate_both(expr: &str) -> (Result<String>, Result<String>) {
+        let ast = parse(expr).unwrap();
+        let program = compile(&ast).expect("Expression should compile");
+
+        let arena = Bump::new();
+        let input_ast = parse(INPUT).unwrap();
+        let evaluator = Evaluator::new(None, &arena, None, None);
+        let input = evaluator
+            .evaluate(&input_ast, Value::undefined(), &Frame::new())
+            .unwrap();
+        let frame = Frame::new();
+        frame.bind("x", Value::number(&arena, 10));
+
+        let tree = evaluator
+            .evaluate(&ast, input, &frame)
+            .map(|v| v.serialize(false));
+        let bytecode = run(&evaluator, &program, input, &frame).map(|v| v.serialize(false));
+        (tree, bytecode)
+    }
+
+    #[test_case("1 + 2 * 3")]
+    #[test_case("a.n % 3 - -2")]
+    #[test_case("a.b.c")]
+    #[test_case("a.b.c[]")]
+    #[test_case("a.b.d")]
+    #[test_case("a.missing.c")]
+    #[test_case("e.f")]
+    #[test_case("a.n > 4 and s = \"str\"")]
+    #[test_case("a.missing or t")]
+    #[test_case("t ? a.n : s")]
+    #[test_case("a.missing ? 1")]
+    #[test_case("s & a.n & a.missing")]
+    #[test_case("3 in a.b.c")]
+    #[test_case("$x * a.n")]
+    #[test_case("$")]
+    #[test_case("a.missing[]")]
+    #[test_case("a.n[]")]
+    #[test_case("$y")]
+    fn matches_tree_walker(expr: &str) {
+        let (tree, bytecode) = evaluate_both(expr);
+        assert_eq!(tree.unwrap(), bytecode.unwrap());
+    }
+
+    #[test_case("s + 1")]
+    #[test_case("-s")]
+    #[test_case("s < 1")]
+    fn errors_match_tree_walker(expr: &str) {
+        let (tree, bytecode) = evaluate_both(expr);
+        let (tree, bytecode): (Error, Error) = (tree.unwrap_err(), bytecode.unwrap_err());
+        assert_eq!(tree, bytecode);
+    }
+}
@@ -0,0 +1,90 @@
+//! Exact decimal numbers, which are used instead of `f64`s for the numbers in expressions and
+//! inputs when decimal mode is enabled with [`crate::JsonAta::set_decimal`].
+//!
+//! Decimals and `f64`s can meet, for example when a function which only works with `f64`s returns
+//! one. Arithmetic and comparisons with a decimal operand are done with decimals, converting the
+//! other operand to the shortest decimal that's converted back to the same `f64`. Anything a
+//! decimal can't represent, such as a result of more than 28 significant digits, falls back to
+//! `f64`s.
+
+use std::cmp::Ordering;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use super::value::Value;
+use crate::parser::ast::BinaryOp;
+
+/// Parses the text of a number, if it can be represented exactly.
+pub fn parse(text: &str) -> Option<Decimal> {
+    if !text.contains(['e', 'E']) {
+        return Decimal::from_str_exact(text).ok();
+    }
+    // Scientific notation is rounded to fit rather than failing, so it's only used if it's at
+    // least as precise as an `f64`
+    let decimal = Decimal::from_scientific(text).ok()?;
+    (to_f64(&decimal) == text.parse::<f64>().ok()?).then_some(decimal)
+}
+
+/// The value of a number as a decimal. An `f64` is converted to the shortest decimal which is
+/// converted back to the same `f64`, so `0.1` is `0.1` rather than `0.1000000000000000055511...`.
+pub fn to_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Decimal(decimal) => Some(*decimal),
+        // `Display` gives the shortest representation, without an exponent
+        Value::Number(n) if n.is_finite() => Decimal::from_str_exact(&n.to_string()).ok(),
+        _ => None,
+    }
+}
+
+/// Converts a decimal to the nearest `f64`.
+pub fn to_f64(decimal: &Decimal) -> f64 {
+    decimal
+        .to_string()
+        .parse()
+        .expect("Decimals are valid numbers")
+}
+
+fn is_decimal(value: &Value) -> bool {
+    matches!(value, Value::Decimal(..))
+}
+
+/// Applies an arithmetic operator if either operand is a decimal. Returns `None` if neither is,
+/// or the result can't be represented, in which case the operator should fall back to `f64`s.
+pub fn arithmetic(op: &BinaryOp, lhs: &Value, rhs: &Value) -> Option<Decimal> {
+    if !is_decimal(lhs) && !is_decimal(rhs) {
+        return None;
+    }
+    let (lhs, rhs) = (to_decimal(lhs)?, to_decimal(rhs)?);
+    match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Subtract => lhs.checked_sub(rhs),
+        BinaryOp::Multiply => lhs.checked_mul(rhs),
+        BinaryOp::Divide => lhs.checked_div(rhs),
+        BinaryOp::Modulus => lhs.checked_rem(rhs),
+        _ => None,
+    }
+}
+
+/// Compares two numbers if either is a decimal.
+pub fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    if !is_decimal(lhs) && !is_decimal(rhs) {
+        return None;
+    }
+    Some(to_decimal(lhs)?.cmp(&to_decimal(rhs)?))
+}
+
+/// Sums an array of numbers if any of them is a decimal.
+pub fn sum<'a>(numbers: &'a Value<'a>) -> Option<Decimal> {
+    if !numbers.members().any(is_decimal) {
+        return None;
+    }
+    numbers
+        .members()
+        .try_fold(Decimal::ZERO, |sum, n| sum.checked_add(to_decimal(n)?))
+}
+
+/// Rounds to `precision` decimal places, rounding halves to even like `$round`.
+pub fn round(decimal: &Decimal, precision: isize) -> Option<Decimal> {
+    let precision = u32::try_from(precision).ok()?;
+    Some(decimal.round_dp_with_strategy(precision, RoundingStrategy::MidpointNearestEven))
+}
@@ -1,4 +1,8 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
 use super::value::Value;
 
@@ -32,6 +36,12 @@ impl<'a> Frame<'a> {
         })))
     }
 
+    /// Makes lookups that aren't bound in this frame fall back to `parent`, replacing its
+    /// current parent.
+    pub fn set_parent(&self, parent: &Frame<'a>) {
+        self.0.borrow_mut().parent = Some(parent.clone());
+    }
+
     pub fn bind(&self, name: &str, value: &'a Value<'a>) {
         self.0.borrow_mut().bindings.insert(name.to_string(), value);
     }
@@ -45,6 +55,22 @@ impl<'a> Frame<'a> {
             },
         }
     }
+
+    /// Every binding that can be looked up from this frame, where a binding hides those of the
+    /// same name in the frames it falls back to.
+    pub fn visible_bindings(&self) -> BTreeMap<String, &'a Value<'a>> {
+        let data = self.0.borrow();
+        let mut bindings = match data.parent {
+            Some(ref parent) => parent.visible_bindings(),
+            None => BTreeMap::new(),
+        };
+        bindings.extend(
+            data.bindings
+                .iter()
+                .map(|(name, value)| (name.clone(), *value)),
+        );
+        bindings
+    }
 }
 
 impl Default for Frame<'_> {
@@ -1,9 +1,12 @@
 use base64::Engine;
 use std::borrow::Borrow;
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
 
 use bumpalo::Bump;
 
-use crate::{Error, Result};
+use crate::{Error, FunctionPolicy, Result};
 
 use super::frame::Frame;
 use super::value::serialize::{DumpFormatter, PrettyFormatter, Serializer};
@@ -65,6 +68,95 @@ macro_rules! assert_array_of_type {
     };
 }
 
+/// A native function, which can be bound in any arena.
+pub(crate) type NativeFunction =
+    for<'a, 'e> fn(FunctionContext<'a, 'e>, &'a Value<'a>) -> Result<&'a Value<'a>>;
+
+/// The built-in functions, by name without their `$`, with their arity.
+pub(crate) const BUILTINS: &[(&str, usize, NativeFunction)] = &[
+    ("abs", 1, fn_abs),
+    ("append", 2, fn_append),
+    ("assert", 2, fn_assert),
+    ("base64decode", 1, fn_base64_decode),
+    ("base64encode", 1, fn_base64_encode),
+    ("boolean", 1, fn_boolean),
+    ("ceil", 1, fn_ceil),
+    ("contains", 2, fn_contains),
+    ("count", 1, fn_count),
+    #[cfg(feature = "hash")]
+    ("crc32", 1, super::hash::fn_crc32),
+    ("distinct", 1, fn_distinct),
+    #[cfg(feature = "higher-order")]
+    ("each", 2, fn_each),
+    ("error", 1, fn_error),
+    ("exists", 1, fn_exists),
+    #[cfg(feature = "fetch")]
+    ("fetch", 2, crate::fetch::fn_fetch),
+    #[cfg(feature = "higher-order")]
+    ("filter", 2, fn_filter),
+    ("floor", 1, fn_floor),
+    #[cfg(feature = "hash")]
+    ("hmacSha256", 2, super::hash::fn_hmac_sha256),
+    ("join", 2, fn_join),
+    ("index", 3, fn_index),
+    ("joinOn", 4, fn_join_on),
+    ("keys", 1, fn_keys),
+    ("length", 1, fn_length),
+    ("lookup", 2, fn_lookup),
+    ("lowercase", 1, fn_lowercase),
+    #[cfg(feature = "higher-order")]
+    ("map", 2, fn_map),
+    ("max", 1, fn_max),
+    #[cfg(feature = "hash")]
+    ("md5", 1, super::hash::fn_md5),
+    #[cfg(feature = "higher-order")]
+    ("memoize", 1, fn_memoize),
+    ("merge", 1, fn_merge),
+    ("mergePatch", 2, super::merge_patch::fn_merge_patch),
+    ("min", 1, fn_min),
+    ("not", 1, fn_not),
+    ("number", 1, fn_number),
+    ("pad", 3, fn_pad),
+    ("pointer", 2, super::pointer::fn_pointer),
+    ("power", 2, fn_power),
+    ("random", 0, fn_random),
+    ("replace", 4, fn_replace),
+    ("reverse", 1, fn_reverse),
+    ("round", 2, fn_round),
+    #[cfg(feature = "hash")]
+    ("sha256", 1, super::hash::fn_sha256),
+    ("shuffle", 1, fn_shuffle),
+    ("sort", 2, fn_sort),
+    ("split", 3, fn_split),
+    ("spread", 1, fn_spread),
+    ("sqrt", 1, fn_sqrt),
+    ("string", 1, fn_string),
+    ("substring", 3, fn_substring),
+    ("substringAfter", 2, fn_substring_after),
+    ("substringBefore", 2, fn_substring_before),
+    ("sum", 1, fn_sum),
+    ("trim", 1, fn_trim),
+    ("uppercase", 1, fn_uppercase),
+    #[cfg(feature = "uuid")]
+    ("uuid", 1, fn_uuid),
+    #[cfg(feature = "json-schema")]
+    ("validate", 2, fn_validate),
+    ("zip", 1, fn_zip),
+];
+
+/// Binds the built-in functions in `frame`. Functions `policy` doesn't allow are bound too, so
+/// that calling one says so rather than that it doesn't exist.
+pub(crate) fn bind_builtins<'a>(frame: &Frame<'a>, arena: &'a Bump, policy: &FunctionPolicy) {
+    for &(name, arity, function) in BUILTINS {
+        let function = if policy.allows(name) {
+            Value::nativefn(arena, name, arity, function)
+        } else {
+            Value::nativefn(arena, name, arity, crate::policy::fn_denied)
+        };
+        frame.bind(name, function);
+    }
+}
+
 #[derive(Clone)]
 pub struct FunctionContext<'a, 'e> {
     pub name: &'a str,
@@ -182,6 +274,9 @@ pub fn fn_boolean<'a>(
             arg.is_valid_number()?;
             Value::bool(context.arena, *n != 0.0)
         }
+        Value::Integer(n) => Value::bool(context.arena, n.get() != 0),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => Value::bool(context.arena, !d.is_zero()),
         Value::String(ref str) => Value::bool(context.arena, !str.is_empty()),
         Value::Object(ref obj) => Value::bool(context.arena, !obj.is_empty()),
         Value::Array { .. } => match arg.len() {
@@ -211,6 +306,7 @@ pub fn fn_boolean<'a>(
     })
 }
 
+#[cfg(feature = "higher-order")]
 pub fn fn_map<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
     let arr = &args[0];
     let func = &args[1];
@@ -246,6 +342,7 @@ pub fn fn_map<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Resu
     Ok(result)
 }
 
+#[cfg(feature = "higher-order")]
 pub fn fn_filter<'a>(
     context: FunctionContext<'a, '_>,
     args: &'a Value<'a>,
@@ -285,6 +382,7 @@ pub fn fn_filter<'a>(
     Ok(result)
 }
 
+#[cfg(feature = "higher-order")]
 pub fn fn_each<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
     let (obj, func) = if args.len() == 1 {
         let obj_arg = if context.input.is_array() && context.input.has_flags(ArrayFlags::WRAPPED) {
@@ -403,6 +501,49 @@ pub fn fn_merge<'a>(
     Ok(result)
 }
 
+pub fn fn_spread<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arg = if args.is_empty() {
+        if context.input.is_array() && context.input.has_flags(ArrayFlags::WRAPPED) {
+            &context.input[0]
+        } else {
+            context.input
+        }
+    } else {
+        &args[0]
+    };
+
+    if !arg.is_array() && !arg.is_object() {
+        return Ok(arg);
+    }
+
+    let result = Value::array(context.arena, ArrayFlags::SEQUENCE);
+    spread_into(&context, result, arg);
+    Ok(result)
+}
+
+/// Pushes an object with a single key for each key of `arg`, or of each object in it if it's an
+/// array. Anything else is pushed as it is.
+fn spread_into<'a>(context: &FunctionContext<'a, '_>, result: &mut Value<'a>, arg: &'a Value<'a>) {
+    if arg.is_array() {
+        for member in arg.members() {
+            spread_into(context, result, member);
+        }
+    } else if arg.is_object() {
+        for (key, value) in arg.entries() {
+            let object = Value::object_with_capacity(context.arena, 1);
+            object.insert(key, value);
+            result.push(object);
+        }
+    } else if !arg.is_undefined() {
+        result.push(arg);
+    }
+}
+
 pub fn fn_string<'a>(
     context: FunctionContext<'a, '_>,
     args: &'a Value<'a>,
@@ -459,11 +600,18 @@ pub fn fn_lowercase<'a>(
 ) -> Result<&'a Value<'a>> {
     let arg = &args[0];
 
-    Ok(if !arg.is_string() {
-        Value::undefined()
-    } else {
-        Value::string(context.arena, arg.as_str().to_lowercase())
-    })
+    if !arg.is_string() {
+        return Ok(Value::undefined());
+    }
+
+    #[cfg(feature = "icu")]
+    if let Some(locale) = context.evaluator.locale() {
+        return Ok(Value::string(
+            context.arena,
+            locale.lowercase(&arg.as_str()),
+        ));
+    }
+    Ok(Value::string(context.arena, arg.as_str().to_lowercase()))
 }
 
 pub fn fn_uppercase<'a>(
@@ -473,10 +621,17 @@ pub fn fn_uppercase<'a>(
     let arg = &args[0];
 
     if !arg.is_string() {
-        Ok(Value::undefined())
-    } else {
-        Ok(Value::string(context.arena, arg.as_str().to_uppercase()))
+        return Ok(Value::undefined());
+    }
+
+    #[cfg(feature = "icu")]
+    if let Some(locale) = context.evaluator.locale() {
+        return Ok(Value::string(
+            context.arena,
+            locale.uppercase(&arg.as_str()),
+        ));
     }
+    Ok(Value::string(context.arena, arg.as_str().to_uppercase()))
 }
 
 pub fn fn_trim<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
@@ -505,6 +660,46 @@ pub fn fn_trim<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Res
     }
 }
 
+pub fn fn_pad<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 3);
+
+    let string = &args[0];
+    let width = &args[1];
+    let padding = &args[2];
+
+    if string.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    assert_arg!(string.is_string(), context, 1);
+    assert_arg!(width.is_number(), context, 2);
+    assert_arg!(padding.is_undefined() || padding.is_string(), context, 3);
+
+    let string = string.as_str();
+    let padding = match padding {
+        Value::String(ref padding) if !padding.is_empty() => padding.as_ref(),
+        _ => " ",
+    };
+
+    // Widths are in characters, with the padding repeated and cut off to fit
+    let units = context.evaluator.string_units();
+    let width = width.as_isize();
+    let pad_length = width.unsigned_abs().saturating_sub(units.count(&string));
+    let padding: String = units
+        .split(padding)
+        .into_iter()
+        .cycle()
+        .take(pad_length)
+        .collect();
+
+    let result = if width < 0 {
+        padding + &string
+    } else {
+        string.into_owned() + &padding
+    };
+    Ok(Value::string(context.arena, result))
+}
+
 pub fn fn_substring<'a>(
     context: FunctionContext<'a, '_>,
     args: &'a Value<'a>,
@@ -521,12 +716,8 @@ pub fn fn_substring<'a>(
     assert_arg!(start.is_number(), context, 2);
 
     let string = string.as_str();
-
-    // Scan the string chars for the actual number of characters.
-    // NOTE: Chars are not grapheme clusters, so for some inputs like "नमस्ते" we will get 6
-    //       as it will include the diacritics.
-    //       See: https://doc.rust-lang.org/nightly/book/ch08-02-strings.html
-    let len = string.chars().count() as isize;
+    let units = context.evaluator.string_units().split(&string);
+    let len = units.len() as isize;
     let mut start = start.as_isize();
 
     // If start is negative and runs off the front of the string
@@ -535,35 +726,74 @@ pub fn fn_substring<'a>(
     }
 
     // If start is negative, count from the end of the string
-    let start = if start < 0 { len + start } else { start };
+    let start = if start < 0 { len + start } else { start }.min(len);
 
-    if length.is_undefined() {
-        Ok(Value::string(
-            context.arena,
-            string[start as usize..].to_string(),
-        ))
+    let end = if length.is_undefined() {
+        len
     } else {
         assert_arg!(length.is_number(), context, 3);
 
         let length = length.as_isize();
-        if length < 0 {
-            Ok(Value::string(context.arena, String::from("")))
-        } else {
-            let end = if start >= 0 {
-                (start + length) as usize
-            } else {
-                (len + start + length) as usize
-            };
+        if length <= 0 {
+            return Ok(Value::string(context.arena, String::from("")));
+        }
+        (start + length).min(len)
+    };
 
-            let substring = string
-                .chars()
-                .skip(start as usize)
-                .take(end - start as usize)
-                .collect::<String>();
+    Ok(Value::string(
+        context.arena,
+        units[start as usize..end as usize].concat(),
+    ))
+}
 
-            Ok(Value::string(context.arena, substring))
-        }
+/// The part of a string before the first occurrence of a substring, or all of it if there's none.
+pub fn fn_substring_before<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    substring_around(context, args, true)
+}
+
+/// The part of a string after the first occurrence of a substring, or all of it if there's none.
+pub fn fn_substring_after<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    substring_around(context, args, false)
+}
+
+fn substring_around<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+    before: bool,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 2);
+
+    // Called with only the substring, the string is the context
+    let (string, chars) = if args.len() < 2 && context.input.is_string() {
+        (context.input, &args[0])
+    } else {
+        (&args[0], &args[1])
+    };
+
+    if string.is_undefined() {
+        return Ok(Value::undefined());
     }
+
+    assert_arg!(string.is_string(), context, 1);
+    assert_arg!(chars.is_string(), context, 2);
+
+    let string = string.as_str();
+    let chars = chars.as_str();
+    let Some(index) = context.evaluator.string_units().find(&string, &chars) else {
+        return Ok(Value::string(context.arena, string.into_owned()));
+    };
+    let result = if before {
+        &string[..index]
+    } else {
+        &string[index + chars.len()..]
+    };
+    Ok(Value::string(context.arena, result))
 }
 
 pub fn fn_contains<'a>(
@@ -693,6 +923,17 @@ pub fn fn_abs<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Resu
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Integer(n) = arg {
+        if let Some(abs) = super::integer::Integer::new(n.get().abs()) {
+            return Ok(Value::integer(context.arena, abs));
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.abs()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().abs()))
 }
 
@@ -708,6 +949,15 @@ pub fn fn_floor<'a>(
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Integer(..) = arg {
+        return Ok(arg);
+    }
+
+    #[cfg(feature = "decimal")]
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.floor()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().floor()))
 }
 
@@ -720,6 +970,15 @@ pub fn fn_ceil<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Res
 
     assert_arg!(arg.is_number(), context, 1);
 
+    if let Value::Integer(..) = arg {
+        return Ok(arg);
+    }
+
+    #[cfg(feature = "decimal")]
+    if let Value::Decimal(d) = arg {
+        return Ok(Value::decimal(context.arena, d.ceil()));
+    }
+
     Ok(Value::number(context.arena, arg.as_f64().ceil()))
 }
 
@@ -780,6 +1039,17 @@ pub fn fn_count<'a>(
     ))
 }
 
+// The largest or smallest of some numbers is returned as it is if any of them is exact, so that
+// it's compared exactly and doesn't lose precision
+fn is_exact(value: &Value) -> bool {
+    match value {
+        Value::Integer(..) => true,
+        #[cfg(feature = "decimal")]
+        Value::Decimal(..) => true,
+        _ => false,
+    }
+}
+
 pub fn fn_max<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
     max_args!(context, args, 1);
 
@@ -798,6 +1068,17 @@ pub fn fn_max<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Resu
         assert_array_of_type!(member.is_number(), context, 1, "number");
         max = f64::max(max, member.as_f64());
     }
+
+    if arr.members().any(is_exact) {
+        return Ok(arr
+            .members()
+            .reduce(|max, member| match member.compare_numbers(max) {
+                Some(std::cmp::Ordering::Greater) => member,
+                _ => max,
+            })
+            .expect("The array isn't empty"));
+    }
+
     Ok(Value::number(context.arena, max))
 }
 
@@ -819,6 +1100,17 @@ pub fn fn_min<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Resu
         assert_array_of_type!(member.is_number(), context, 1, "number");
         min = f64::min(min, member.as_f64());
     }
+
+    if arr.members().any(is_exact) {
+        return Ok(arr
+            .members()
+            .reduce(|min, member| match member.compare_numbers(min) {
+                Some(std::cmp::Ordering::Less) => member,
+                _ => min,
+            })
+            .expect("The array isn't empty"));
+    }
+
     Ok(Value::number(context.arena, min))
 }
 
@@ -840,6 +1132,16 @@ pub fn fn_sum<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Resu
         assert_array_of_type!(member.is_number(), context, 1, "number");
         sum += member.as_f64();
     }
+
+    #[cfg(feature = "decimal")]
+    if let Some(sum) = super::decimal::sum(arr) {
+        return Ok(Value::decimal(context.arena, sum));
+    }
+
+    if let Some(sum) = super::integer::sum(arr) {
+        return Ok(Value::integer(context.arena, sum));
+    }
+
     Ok(Value::number(context.arena, sum))
 }
 
@@ -853,24 +1155,41 @@ pub fn fn_number<'a>(
 
     match arg {
         Value::Undefined => Ok(Value::undefined()),
-        Value::Number(..) => Ok(arg),
+        Value::Number(..) | Value::Integer(..) => Ok(arg),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(..) => Ok(arg),
+        #[cfg(feature = "decimal")]
+        Value::String(s) if context.evaluator.is_decimal() => match super::decimal::parse(s) {
+            Some(decimal) => Ok(Value::decimal(context.arena, decimal)),
+            None => fn_number_from_str(context, arg, s),
+        },
         Value::Bool(true) => Ok(Value::number(context.arena, 1)),
         Value::Bool(false) => Ok(Value::number(context.arena, 0)),
-        Value::String(s) => {
-            let result: f64 = s
-                .parse()
-                .map_err(|_e| Error::D3030NonNumericCast(context.char_index, arg.to_string()))?;
-
-            if !result.is_nan() && !result.is_infinite() {
-                Ok(Value::number(context.arena, result))
-            } else {
-                Ok(Value::undefined())
-            }
-        }
+        Value::String(s) => fn_number_from_str(context, arg, s),
         _ => bad_arg!(context, 1),
     }
 }
 
+fn fn_number_from_str<'a>(
+    context: FunctionContext<'a, '_>,
+    arg: &'a Value<'a>,
+    s: &str,
+) -> Result<&'a Value<'a>> {
+    if let Some(n) = super::integer::parse(s) {
+        return Ok(Value::integer(context.arena, n));
+    }
+
+    let result: f64 = s
+        .parse()
+        .map_err(|_e| Error::D3030NonNumericCast(context.char_index, arg.to_string()))?;
+
+    if !result.is_nan() && !result.is_infinite() {
+        Ok(Value::number(context.arena, result))
+    } else {
+        Ok(Value::undefined())
+    }
+}
+
 pub fn fn_exists<'a>(
     context: FunctionContext<'a, '_>,
     args: &'a Value<'a>,
@@ -935,10 +1254,8 @@ pub fn fn_length<'a>(
 
     assert_arg!(arg1.is_string(), context, 1);
 
-    Ok(Value::number(
-        context.arena,
-        arg1.as_str().chars().count() as f64,
-    ))
+    let length = context.evaluator.string_units().count(&arg1.as_str());
+    Ok(Value::number(context.arena, length as f64))
 }
 
 pub fn fn_sqrt<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
@@ -1008,6 +1325,107 @@ pub fn fn_reverse<'a>(
     Ok(result)
 }
 
+pub fn fn_zip<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    min_args!(context, args, 1);
+
+    // Arguments which aren't arrays are treated as arrays of one, and undefined as empty
+    let length = args
+        .members()
+        .map(|arg| match arg {
+            Value::Undefined => 0,
+            _ if arg.is_array() => arg.len(),
+            _ => 1,
+        })
+        .min()
+        .unwrap_or(0);
+
+    let result = Value::array_with_capacity(context.arena, length, ArrayFlags::empty());
+    for index in 0..length {
+        let tuple = Value::array_with_capacity(context.arena, args.len(), ArrayFlags::empty());
+        for arg in args.members() {
+            tuple.push(if arg.is_array() {
+                arg.get_member(index)
+            } else {
+                arg
+            });
+        }
+        result.push(tuple);
+    }
+    Ok(result)
+}
+
+pub fn fn_distinct<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arr = &args[0];
+
+    if !arr.is_array() || arr.len() <= 1 {
+        return Ok(arr);
+    }
+
+    // Members are compared deeply, so arrays and objects with the same contents are duplicates
+    let mut distinct: Vec<&'a Value<'a>> = Vec::new();
+    for member in arr.members() {
+        if !distinct.contains(&member) {
+            distinct.push(member);
+        }
+    }
+
+    let result = Value::array_with_capacity(
+        context.arena,
+        distinct.len(),
+        arr.get_flags() & ArrayFlags::SEQUENCE,
+    );
+    distinct.into_iter().for_each(|member| result.push(member));
+    Ok(result)
+}
+
+pub fn fn_random<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 0);
+
+    Ok(Value::number(context.arena, context.evaluator.random()))
+}
+
+pub fn fn_shuffle<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let arr = &args[0];
+
+    if arr.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let arr = Value::wrap_in_array_if_needed(context.arena, arr, ArrayFlags::empty());
+    if arr.len() <= 1 {
+        return Ok(arr);
+    }
+
+    // The "inside-out" Fisher-Yates shuffle, like reference JSONata
+    let mut shuffled: Vec<&'a Value<'a>> = Vec::with_capacity(arr.len());
+    for (index, member) in arr.members().enumerate() {
+        let other = (context.evaluator.random() * (index + 1) as f64) as usize;
+        if other == index {
+            shuffled.push(member);
+        } else {
+            shuffled.push(shuffled[other]);
+            shuffled[other] = member;
+        }
+    }
+
+    let result = Value::array_with_capacity(context.arena, shuffled.len(), ArrayFlags::empty());
+    shuffled.into_iter().for_each(|member| result.push(member));
+    Ok(result)
+}
+
 pub fn fn_join<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
     max_args!(context, args, 2);
 
@@ -1076,8 +1494,12 @@ pub fn fn_sort<'a, 'e>(
         merge_sort(
             unsorted,
             &|a: &'a Value<'a>, b: &'a Value<'a>| match (a, b) {
-                (Value::Number(a), Value::Number(b)) => Ok(a > b),
-                (Value::String(a), Value::String(b)) => Ok(a > b),
+                (a, b) if a.is_number() && b.is_number() => {
+                    Ok(a.compare_numbers(b) == Some(std::cmp::Ordering::Greater))
+                }
+                (Value::String(a), Value::String(b)) => {
+                    Ok(context.evaluator.compare_strings(a, b) == std::cmp::Ordering::Greater)
+                }
                 _ => Err(Error::D3070InvalidDefaultSort(context.char_index)),
             },
         )?
@@ -1199,12 +1621,30 @@ pub fn fn_round<'a>(
     }
     assert_arg!(number.is_number(), context, 1);
     let precision = &args[1];
+    assert_arg!(
+        precision.is_undefined() || precision.is_number(),
+        context,
+        2
+    );
     let precision = if precision.is_undefined() {
         0
     } else {
         precision.as_isize()
     };
 
+    if let Value::Integer(..) = number {
+        if precision >= 0 {
+            return Ok(number);
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    if let Value::Decimal(d) = number {
+        if let Some(rounded) = super::decimal::round(d, precision) {
+            return Ok(Value::decimal(context.arena, rounded));
+        }
+    }
+
     let num = multiply_by_pow10(number.as_f64(), precision)?;
     let num = num.round_ties_even();
     let num = multiply_by_pow10(num, -precision)?;
@@ -1215,8 +1655,565 @@ pub fn fn_round<'a>(
 // We need to do this multiplication by powers of 10 in a string to avoid
 // floating point precision errors which will affect the rounding algorithm
 fn multiply_by_pow10(num: f64, pow: isize) -> Result<f64> {
-    let num_str = format!("{}e{}", num.to_string(), pow);
+    let num_str = format!("{}e{}", num, pow);
     num_str
         .parse::<f64>()
         .map_err(|e| Error::D3137Error(e.to_string()))
 }
+
+/// Joins two arrays of objects on a key, merging each pair of objects whose keys are equal. The
+/// key is either the name of a field, or a function which computes it from each object. The join
+/// is `"inner"` by default, `"left"` to keep the left objects without a match, or `"outer"` to
+/// keep the objects without a match from both sides. Objects without a key never match.
+///
+/// The right array is hashed by key, so the join takes time proportional to the size of the
+/// arrays and the result rather than their product.
+pub fn fn_join_on<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    min_args!(context, args, 3);
+    max_args!(context, args, 4);
+
+    let objects = |arg: &'a Value<'a>| -> Vec<&'a Value<'a>> {
+        if arg.is_undefined() {
+            vec![]
+        } else if arg.is_array() {
+            arg.members().collect()
+        } else {
+            vec![arg]
+        }
+    };
+
+    let left = objects(&args[0]);
+    let right = objects(&args[1]);
+    let key = &args[2];
+    let join_type = &args[3];
+
+    assert_array_of_type!(
+        left.iter().all(|item| item.is_object()),
+        context,
+        1,
+        "object"
+    );
+    assert_array_of_type!(
+        right.iter().all(|item| item.is_object()),
+        context,
+        2,
+        "object"
+    );
+    assert_arg!(key.is_string() || key.is_function(), context, 3);
+
+    let (keep_left, keep_right) = if join_type.is_undefined() {
+        (false, false)
+    } else {
+        assert_arg!(join_type.is_string(), context, 4);
+        match &*join_type.as_str() {
+            "inner" => (false, false),
+            "left" => (true, false),
+            "outer" => (true, true),
+            _ => bad_arg!(context, 4),
+        }
+    };
+
+    // Keys are compared by their JSON, so that `1` and `"1"` are different keys
+    let key_of = |item: &'a Value<'a>| -> Result<Option<String>> {
+        let key = evaluate_key(&context, key, item)?;
+        Ok((!key.is_undefined()).then(|| key.serialize(false)))
+    };
+
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (position, item) in right.iter().enumerate() {
+        if let Some(key) = key_of(item)? {
+            index.entry(key).or_default().push(position);
+        }
+    }
+
+    let mut matched = vec![false; right.len()];
+    let result = Value::array(context.arena, ArrayFlags::empty());
+
+    for item in left {
+        let matches = match key_of(item)? {
+            Some(key) => index.get(&key).map(Vec::as_slice).unwrap_or_default(),
+            None => &[],
+        };
+
+        for &position in matches {
+            matched[position] = true;
+            let merged = Value::object(context.arena);
+            for (key, value) in item.entries().chain(right[position].entries()) {
+                merged.insert(key, value);
+            }
+            result.push(merged);
+        }
+
+        if matches.is_empty() && keep_left {
+            result.push(item);
+        }
+    }
+
+    if keep_right {
+        for (item, matched) in right.into_iter().zip(matched) {
+            if !matched {
+                result.push(item);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Builds an object for looking up the members of an array by key. The key is either the name of
+/// a field, or a function which computes it from each member, and members without a key are left
+/// out. When members have the same key, the one kept depends on `collisions`: `"last"` (the
+/// default) or `"first"` keeps one of them, `"array"` keeps all of them in an array, and `"error"`
+/// fails with `D1009`.
+pub fn fn_index<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    min_args!(context, args, 2);
+    max_args!(context, args, 3);
+
+    let array = &args[0];
+    let key = &args[1];
+    let collisions = &args[2];
+
+    if array.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    let array = Value::wrap_in_array_if_needed(context.arena, array, ArrayFlags::empty());
+    assert_arg!(key.is_string() || key.is_function(), context, 2);
+    assert_arg!(
+        collisions.is_undefined() || collisions.is_string(),
+        context,
+        3
+    );
+
+    let collisions = if collisions.is_undefined() {
+        "last".into()
+    } else {
+        collisions.as_str()
+    };
+    if !matches!(&*collisions, "first" | "last" | "array" | "error") {
+        bad_arg!(context, 3);
+    }
+
+    let mut index: IndexMap<String, Vec<&'a Value<'a>>> = IndexMap::new();
+    for item in array.members() {
+        let item_key = evaluate_key(&context, key, item)?;
+        if item_key.is_undefined() {
+            continue;
+        }
+        if !item_key.is_string() {
+            return Err(Error::T1003NonStringKey(
+                context.char_index,
+                item_key.serialize(false),
+            ));
+        }
+
+        let items = index.entry(item_key.as_str().into_owned()).or_default();
+        if !items.is_empty() && collisions == "error" {
+            return Err(Error::D1009MultipleKeys(
+                context.char_index,
+                item_key.as_str().into_owned(),
+            ));
+        }
+        items.push(item);
+    }
+
+    let result = Value::object_with_capacity(context.arena, index.len());
+    for (key, items) in index {
+        let value = match &*collisions {
+            "first" => items[0],
+            "array" => {
+                let array =
+                    Value::array_with_capacity(context.arena, items.len(), ArrayFlags::empty());
+                items.into_iter().for_each(|item| array.push(item));
+                array
+            }
+            _ => items[items.len() - 1],
+        };
+        result.insert(&key, value);
+    }
+
+    Ok(result)
+}
+
+/// Returns a version of a function which remembers its results, so it's only evaluated once for
+/// each set of arguments during an evaluation. Only pure functions of their arguments should be
+/// memoized, and calls with function arguments aren't remembered.
+#[cfg(feature = "higher-order")]
+pub fn fn_memoize<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    max_args!(context, args, 1);
+
+    let func = &args[0];
+    if func.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    match context.evaluator.memoize(func) {
+        Some(memoized) => Ok(memoized),
+        None => bad_arg!(context, 1),
+    }
+}
+
+/// Validates a value against a JSON Schema, returning an array of the ways it doesn't conform,
+/// which is empty if it does. Each is an object with the `instanceLocation` of the invalid part of
+/// the value and the `keywordLocation` of the part of the schema it breaks, both JSON pointers, and
+/// an `error` message, as in the basic output format of JSON Schema. Schemas are draft 2020-12
+/// unless they say otherwise with `$schema`, and references to remote schemas aren't resolved.
+#[cfg(feature = "json-schema")]
+pub fn fn_validate<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    use super::value::transcode::Serializable;
+
+    max_args!(context, args, 2);
+
+    let value = &args[0];
+    let schema = &args[1];
+
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+
+    assert_arg!(schema.is_object() || schema.is_bool(), context, 2);
+
+    let to_json = |value: &'a Value<'a>| {
+        serde_json::to_value(Serializable(value)).expect("Values can be converted to JSON")
+    };
+    let validator = jsonschema::validator_for(&to_json(schema))
+        .map_err(|e| Error::H0701InvalidSchema(context.char_index, e.to_string()))?;
+
+    let errors = Value::array(context.arena, ArrayFlags::empty());
+    for error in validator.iter_errors(&to_json(value)) {
+        let object = Value::object_with_capacity(context.arena, 3);
+        object.insert(
+            "instanceLocation",
+            Value::string(context.arena, error.instance_path().to_string()),
+        );
+        object.insert(
+            "keywordLocation",
+            Value::string(context.arena, error.evaluation_path().to_string()),
+        );
+        object.insert("error", Value::string(context.arena, error.to_string()));
+        errors.push(object);
+    }
+
+    Ok(errors)
+}
+
+/// Generates a random UUID, of version 4 unless 7 is asked for, which starts with the current time
+/// so that UUIDs generated later sort after it. The random bits come from the operating system,
+/// unless the host has set a source of random numbers or a seed, so that a seeded evaluation
+/// generates the same version 4 UUIDs each time.
+#[cfg(feature = "uuid")]
+pub fn fn_uuid<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use uuid::{Builder, Uuid};
+
+    max_args!(context, args, 1);
+
+    let version = &args[0];
+    assert_arg!(version.is_undefined() || version.is_number(), context, 1);
+
+    let v7 = match version {
+        Value::Undefined => false,
+        version if version.as_f64() == 4.0 => false,
+        version if version.as_f64() == 7.0 => true,
+        _ => bad_arg!(context, 1),
+    };
+
+    // The evaluation's own source of random numbers only has 64 bits of state, far fewer than
+    // the 122 random bits of a UUID, so it's only used when the host has given one
+    if !context.evaluator.has_host_random() {
+        let uuid = if v7 { Uuid::now_v7() } else { Uuid::new_v4() };
+        return Ok(Value::string(context.arena, uuid.hyphenated().to_string()));
+    }
+
+    let mut bytes = [0; 16];
+    for chunk in bytes.chunks_mut(4) {
+        let bits = (context.evaluator.random() * (1_u64 << 32) as f64) as u32;
+        chunk.copy_from_slice(&bits.to_be_bytes());
+    }
+
+    let builder = if v7 {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis() as u64);
+        let random: &[u8; 10] = bytes[..10].try_into().expect("There are 16 bytes");
+        Builder::from_unix_timestamp_millis(millis, random)
+    } else {
+        Builder::from_random_bytes(bytes)
+    };
+    let uuid = builder.into_uuid();
+    Ok(Value::string(context.arena, uuid.hyphenated().to_string()))
+}
+
+// Evaluates the key of an item for functions that take either the name of a field or a function
+fn evaluate_key<'a>(
+    context: &FunctionContext<'a, '_>,
+    key: &'a Value<'a>,
+    item: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    if key.is_function() {
+        let args = Value::array(context.arena, ArrayFlags::empty());
+        args.push(item);
+        context.evaluate_function(key, args)
+    } else {
+        Ok(fn_lookup_internal(context.clone(), item, &key.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    #[cfg(feature = "higher-order")]
+    use crate::Value;
+    use crate::{JsonAta, StringUnits};
+
+    const INPUT: &str = r#"{
+        "orders": [
+            {"id": 1, "customer": "a", "total": 10},
+            {"id": 2, "customer": "b", "total": 20},
+            {"id": 3, "customer": "a", "total": 30},
+            {"id": 4, "customer": "z", "total": 40}
+        ],
+        "customers": [
+            {"customer": "a", "name": "Ada"},
+            {"customer": "b", "name": "Bob"},
+            {"customer": "c", "name": "Cy"}
+        ]
+    }"#;
+
+    fn evaluate(expr: &str) -> Result<String, String> {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata
+            .evaluate(Some(INPUT), None)
+            .map(|result| result.serialize(false))
+            .map_err(|error| error.code().to_string())
+    }
+
+    #[test_case("$joinOn(orders, customers, 'customer').id", "[1,2,3]")]
+    #[test_case(
+        "$joinOn(orders, customers, 'customer', 'inner')[id = 3].name",
+        r#""Ada""#
+    )]
+    #[test_case("$joinOn(orders, customers, 'customer', 'left').id", "[1,2,3,4]")]
+    #[test_case("$joinOn(orders, customers, 'customer', 'left')[id = 4].name", "")]
+    #[test_case(
+        "$joinOn(orders, customers, 'customer', 'outer').(id ? id : name)",
+        r#"[1,2,3,4,"Cy"]"#
+    )]
+    #[test_case(
+        "$joinOn(customers, orders, function($v) { $v.customer }).total",
+        "[10,30,20]"
+    )]
+    #[test_case("$joinOn(orders, customers, 'missing')", "[]")]
+    #[test_case("$joinOn(orders[0], customers, 'customer').name", r#""Ada""#)]
+    #[test_case(
+        "$joinOn(nothing, customers, 'customer', 'outer').name",
+        r#"["Ada","Bob","Cy"]"#
+    )]
+    #[test_case(
+        "$joinOn([{'k': 1}], [{'k': '1'}, {'k': 1, 'v': true}], 'k')",
+        r#"[{"k":1,"v":true}]"#
+    )]
+    fn join_on(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("$index(customers, 'customer').b.name", r#""Bob""#)]
+    #[test_case("$keys($index(orders, 'customer'))", r#"["a","b","z"]"#)]
+    #[test_case("$index(orders, 'customer').a.id", "3")]
+    #[test_case("$index(orders, 'customer', 'first').a.id", "1")]
+    #[test_case("$index(orders, 'customer', 'array').a.id", "[1,3]")]
+    #[test_case(
+        "$index(orders, 'customer', 'array').b",
+        r#"[{"id":2,"customer":"b","total":20}]"#
+    )]
+    #[test_case("$index(orders, function($o) { 'order' & $o.id }).order4.total", "40")]
+    #[test_case("$index(orders, 'missing')", "{}")]
+    #[test_case("$index(nothing, 'customer')", "")]
+    #[test_case(
+        "($i := $index(customers, 'customer'); orders.$lookup($i, customer).name)",
+        r#"["Ada","Bob","Ada"]"#
+    )]
+    fn index(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("$index(orders, 'customer', 'error')", "D1009")]
+    #[test_case("$index(orders, 'id')", "T1003")]
+    #[test_case("$index(orders, 'customer', 'sometimes')", "T0410")]
+    fn index_errors(expr: &str, code: &str) {
+        assert_eq!(evaluate(expr).unwrap_err(), code);
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test_case("$validate(orders, {'type': 'array'})", "[]")]
+    #[test_case(
+        "$validate(orders, {'items': {'properties': {'total': {'maximum': 30}}}}).instanceLocation",
+        r#""/3/total""#
+    )]
+    #[test_case(
+        "$validate(orders[0], {'required': ['id', 'name']}).keywordLocation",
+        r#""/required""#
+    )]
+    #[test_case(
+        "$count($validate(orders[0], {'properties': {'id': {'type': 'string'}, 'total': {'minimum': 50}}}))",
+        "2"
+    )]
+    #[test_case("$validate(customers[0], {'$ref': '#/$defs/c', '$defs': {'c': {'maxProperties': 1}}}).keywordLocation", r#""/$ref/maxProperties""#)]
+    #[test_case("$validate(nothing, false)", "")]
+    #[test_case("$validate(1, false).error", r#""False schema does not allow 1""#)]
+    fn validate(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test_case("$uuid()", 4)]
+    #[test_case("$uuid(4)", 4)]
+    #[test_case("$uuid(7)", 7)]
+    fn uuid(expr: &str, version: usize) {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(&format!("[{expr}, {expr}]"), &arena).unwrap();
+        let result = jsonata.evaluate(None, None).unwrap();
+        let [a, b] = [0, 1].map(|index| ::uuid::Uuid::parse_str(&result[index].as_str()).unwrap());
+        assert_ne!(a, b);
+        assert_eq!(a.get_version_num(), version);
+        assert_eq!(a.get_variant(), ::uuid::Variant::RFC4122);
+        if version == 7 {
+            assert!(a.get_timestamp().is_some());
+        }
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_seeded() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$uuid()", &arena).unwrap();
+        jsonata.set_random_seed(7);
+        let first = jsonata.evaluate(None, None).unwrap().as_str().into_owned();
+        assert_eq!(jsonata.evaluate(None, None).unwrap().as_str(), first);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test_case("$uuid(1)", "T0410")]
+    #[test_case("$uuid('7')", "T0410")]
+    fn uuid_errors(expr: &str, code: &str) {
+        assert_eq!(evaluate(expr).unwrap_err(), code);
+    }
+
+    #[cfg(feature = "json-schema")]
+    #[test_case("$validate(orders, 'array')", "T0410")]
+    #[test_case("$validate(orders, {'type': 5})", "H0701")]
+    fn validate_errors(expr: &str, code: &str) {
+        assert_eq!(evaluate(expr).unwrap_err(), code);
+    }
+
+    #[test_case(
+        "$distinct([1, 1.0, '1', [1, 2], [1, 2], {'a': 1, 'b': 2}, {'b': 2, 'a': 1}])",
+        r#"[1,"1",[1,2],{"a":1,"b":2}]"#
+    )]
+    #[test_case("$distinct([[1], [[1]]])", "[[1],[[1]]]")]
+    fn distinct(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test_case(
+        "($fib := $memoize(function($n) { $n < 2 ? $n : $fib($n - 1) + $fib($n - 2) }); $fib(90))",
+        "2880067194370816120"
+    )]
+    #[test_case(
+        "($f := $memoize(function($a, $b) { $a & $b }); [$f('a', 'b'), $f('a', 'c')])",
+        r#"["ab","ac"]"#
+    )]
+    #[test_case("$map([1, 2, 1], $memoize($string))", r#"["1","2","1"]"#)]
+    #[test_case("$memoize(nothing)", "")]
+    fn memoize(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn memoize_evaluates_once() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            "($f := $memoize(function($x) { $count($x) + $calls() }); [$f([1]), $f([1]), $f([1, 2])])",
+            &arena,
+        )
+        .unwrap();
+        thread_local!(static CALLS: std::cell::Cell<f64> = const { std::cell::Cell::new(0.0) });
+        jsonata.register_function("calls", 0, |context, _| {
+            let calls = CALLS.with(|calls| {
+                calls.set(calls.get() + 1.0);
+                calls.get()
+            });
+            Ok(Value::number(context.arena, calls))
+        });
+
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), "[2,2,4]");
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn memoize_not_a_function() {
+        assert_eq!(evaluate("$memoize(1)").unwrap_err(), "T0410");
+    }
+
+    #[test_case("$joinOn(orders, customers, 'customer', 'sideways')")]
+    #[test_case("$joinOn(orders, customers, 1)")]
+    #[test_case("$joinOn(orders.id, customers, 'customer')")]
+    #[test_case("$joinOn(orders, customers)")]
+    fn join_on_errors(expr: &str) {
+        assert!(evaluate(expr).is_err());
+    }
+
+    #[test_case("$substring('😀abc', 1)", r#""abc""#)]
+    #[test_case("$substring('😀abc', -2, 5)", r#""bc""#)]
+    #[test_case("$substring('abc', 5)", r#""""#)]
+    #[test_case("$substring('abc', 1, 0)", r#""""#)]
+    #[test_case("$substringBefore('hello world', ' ')", r#""hello""#)]
+    #[test_case("$substringAfter('hello world', ' ')", r#""world""#)]
+    #[test_case("$substringAfter('hello world', 'x')", r#""hello world""#)]
+    #[test_case("orders[0].customer.$substringBefore('a')", r#""""#)]
+    #[test_case("'😀@example.com' ~> $substringAfter('@')", r#""example.com""#)]
+    #[test_case("$substringBefore(nothing, 'a')", "")]
+    fn substrings(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("$length('cafe\\u0301')", "5", "4")]
+    #[test_case("$length('🇬🇧')", "2", "1")]
+    #[test_case("$substring('🇬🇧🇫🇷', 1)", r#""🇧🇫🇷""#, r#""🇫🇷""#)]
+    #[test_case("$substring('cafe\\u0301s', 3, 1)", "\"e\"", "\"e\u{301}\"")]
+    #[test_case(
+        "$substringBefore('cafe\\u0301 or cafe', 'cafe')",
+        "\"\"",
+        "\"cafe\u{301} or \""
+    )]
+    #[test_case("$pad('é', -3, '#')", "\"##é\"", "\"##é\"")]
+    #[test_case("$pad('e\\u0301', 3)", "\"e\u{301} \"", "\"e\u{301}  \"")]
+    fn string_units(expr: &str, code_points: &str, graphemes: &str) {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), code_points, "code points");
+
+        jsonata.set_string_units(StringUnits::Graphemes);
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), graphemes, "graphemes");
+    }
+}
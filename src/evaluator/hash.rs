@@ -0,0 +1,144 @@
+//! Hashing functions, for deduplication keys and for checking the signatures of webhooks inside
+//! expressions. Strings are hashed as UTF-8, and digests are returned as lowercase hex.
+
+use std::fmt::Write;
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use super::functions::FunctionContext;
+use super::value::Value;
+use crate::{Error, Result};
+
+/// Lowercase hex, as digests are usually written.
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+/// The string argument `index` of a hashing function, or `None` if it's undefined.
+fn string_arg<'a>(
+    context: &FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+    index: usize,
+) -> Result<Option<&'a Value<'a>>> {
+    let arg = &args[index - 1];
+    if arg.is_undefined() {
+        Ok(None)
+    } else if arg.is_string() {
+        Ok(Some(arg))
+    } else {
+        Err(Error::T0410ArgumentNotValid(
+            context.char_index,
+            index,
+            context.name.to_string(),
+        ))
+    }
+}
+
+/// The SHA-256 digest of a string.
+pub fn fn_sha256<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    let Some(string) = string_arg(&context, args, 1)? else {
+        return Ok(Value::undefined());
+    };
+    let digest = Sha256::digest(string.as_str().as_bytes());
+    Ok(Value::string(context.arena, hex(&digest)))
+}
+
+/// The HMAC-SHA256 of a string with a key, which is how most webhooks are signed.
+pub fn fn_hmac_sha256<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    let Some(string) = string_arg(&context, args, 1)? else {
+        return Ok(Value::undefined());
+    };
+    let Some(key) = string_arg(&context, args, 2)? else {
+        return Err(Error::T0410ArgumentNotValid(
+            context.char_index,
+            2,
+            context.name.to_string(),
+        ));
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_str().as_bytes())
+        .expect("HMAC takes keys of any length");
+    mac.update(string.as_str().as_bytes());
+    Ok(Value::string(
+        context.arena,
+        hex(&mac.finalize().into_bytes()),
+    ))
+}
+
+/// The MD5 digest of a string, which is fine for deduplication but not for security.
+pub fn fn_md5<'a>(context: FunctionContext<'a, '_>, args: &'a Value<'a>) -> Result<&'a Value<'a>> {
+    let Some(string) = string_arg(&context, args, 1)? else {
+        return Ok(Value::undefined());
+    };
+    let digest = Md5::digest(string.as_str().as_bytes());
+    Ok(Value::string(context.arena, hex(&digest)))
+}
+
+/// The CRC-32 checksum of a string, as eight hex digits.
+pub fn fn_crc32<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    let Some(string) = string_arg(&context, args, 1)? else {
+        return Ok(Value::undefined());
+    };
+    let checksum = crc32fast::hash(string.as_str().as_bytes());
+    Ok(Value::string(context.arena, format!("{:08x}", checksum)))
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use crate::JsonAta;
+
+    fn evaluate(expr: &str) -> Result<String, String> {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata
+            .evaluate(None, None)
+            .map(|result| result.serialize(false))
+            .map_err(|error| error.code().to_string())
+    }
+
+    #[test_case(
+        "$sha256('')",
+        r#""e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855""#
+    )]
+    #[test_case(
+        "$sha256('abc')",
+        r#""ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad""#
+    )]
+    #[test_case(
+        "$hmacSha256('The quick brown fox jumps over the lazy dog', 'key')",
+        r#""f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8""#
+    )]
+    #[test_case("$md5('abc')", r#""900150983cd24fb0d6963f7d28e17f72""#)]
+    #[test_case("$crc32('123456789')", r#""cbf43926""#)]
+    #[test_case("$crc32('')", r#""00000000""#)]
+    #[test_case("'abc' ~> $md5()", r#""900150983cd24fb0d6963f7d28e17f72""#)]
+    #[test_case("$sha256(nothing)", "")]
+    fn digests(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("$sha256(1)")]
+    #[test_case("$md5({'a': 1})")]
+    #[test_case("$hmacSha256('abc')")]
+    #[test_case("$hmacSha256('abc', 1)")]
+    fn errors(expr: &str) {
+        assert_eq!(evaluate(expr).unwrap_err(), "T0410");
+    }
+}
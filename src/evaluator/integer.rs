@@ -0,0 +1,219 @@
+//! Integers, which keep numbers written without a fraction or exponent exact across the whole
+//! range of `i64` and `u64`, so that an ID like `9007199254740993` isn't rounded to the nearest
+//! `f64` on its way from the input to a result.
+//!
+//! Integers are promoted as needed: arithmetic with an integer operand stays exact as long as the
+//! other operand and the result are integers in range, and otherwise falls back to `f64`s. An
+//! integral `f64`, such as the result of `$count`, counts as an integer operand.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::value::Value;
+use crate::parser::ast::BinaryOp;
+
+/// An integer in the range of either `i64` or `u64`. Only integers above `i64::MAX` are
+/// `Unsigned`, so each integer has one representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Integer {
+    Signed(i64),
+    Unsigned(u64),
+}
+
+impl Integer {
+    /// Returns `None` if `n` doesn't fit in either an `i64` or a `u64`.
+    pub fn new(n: i128) -> Option<Self> {
+        i64::try_from(n)
+            .map(Integer::Signed)
+            .or_else(|_| u64::try_from(n).map(Integer::Unsigned))
+            .ok()
+    }
+
+    pub fn get(self) -> i128 {
+        match self {
+            Integer::Signed(n) => n as i128,
+            Integer::Unsigned(n) => n as i128,
+        }
+    }
+
+    /// Converts to the nearest `f64`.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Integer::Signed(n) => n as f64,
+            Integer::Unsigned(n) => n as f64,
+        }
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(n: i64) -> Self {
+        Integer::Signed(n)
+    }
+}
+
+impl From<u64> for Integer {
+    fn from(n: u64) -> Self {
+        Integer::new(n as i128).expect("u64s are in range")
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(&other.get())
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integer::Signed(n) => write!(f, "{}", n),
+            Integer::Unsigned(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Parses the text of a number if it's an integer, optionally negative, without a fraction or
+/// exponent, which is in range.
+pub fn parse(text: &str) -> Option<Integer> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Integer::new(text.parse().ok()?)
+}
+
+/// The value of a number as an integer, if it's integral.
+fn to_integer(value: &Value) -> Option<i128> {
+    match value {
+        Value::Integer(n) => Some(n.get()),
+        // Every integral `f64` within the range of an `i128` is exactly representable by one
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < i128::MAX as f64 => Some(*n as i128),
+        _ => None,
+    }
+}
+
+fn is_integer(value: &Value) -> bool {
+    matches!(value, Value::Integer(..))
+}
+
+/// Applies an arithmetic operator if either operand is an integer. Returns `None` if neither is,
+/// or the result isn't an integer in range, in which case the operator should fall back to
+/// `f64`s.
+pub fn arithmetic(op: &BinaryOp, lhs: &Value, rhs: &Value) -> Option<Integer> {
+    if !is_integer(lhs) && !is_integer(rhs) {
+        return None;
+    }
+    let (lhs, rhs) = (to_integer(lhs)?, to_integer(rhs)?);
+    let result = match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Subtract => lhs.checked_sub(rhs),
+        BinaryOp::Multiply => lhs.checked_mul(rhs),
+        BinaryOp::Divide if lhs.checked_rem(rhs)? == 0 => lhs.checked_div(rhs),
+        BinaryOp::Modulus => lhs.checked_rem(rhs),
+        _ => None,
+    }?;
+    Integer::new(result)
+}
+
+/// Negates an integer, returning `None` if the result is out of range.
+pub fn negate(n: Integer) -> Option<Integer> {
+    Integer::new(-n.get())
+}
+
+/// Compares two numbers exactly if either is an integer and both are integral.
+pub fn compare(lhs: &Value, rhs: &Value) -> Option<Ordering> {
+    if !is_integer(lhs) && !is_integer(rhs) {
+        return None;
+    }
+    Some(to_integer(lhs)?.cmp(&to_integer(rhs)?))
+}
+
+/// Sums an array of numbers if any of them is an integer and the sum is an integer in range.
+pub fn sum<'a>(numbers: &'a Value<'a>) -> Option<Integer> {
+    if !numbers.members().any(is_integer) {
+        return None;
+    }
+    let sum = numbers
+        .members()
+        .try_fold(0_i128, |sum, n| sum.checked_add(to_integer(n)?))?;
+    Integer::new(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::JsonAta;
+
+    #[test]
+    fn parses_integers_in_range() {
+        assert_eq!(parse("42"), Some(Integer::Signed(42)));
+        assert_eq!(parse("-42"), Some(Integer::Signed(-42)));
+        assert_eq!(
+            parse("18446744073709551615"),
+            Some(Integer::Unsigned(u64::MAX))
+        );
+        assert_eq!(parse("18446744073709551616"), None);
+        assert_eq!(parse("1.0"), None);
+        assert_eq!(parse("1e2"), None);
+        assert_eq!(parse("-"), None);
+    }
+
+    #[test]
+    fn promotion() {
+        let arena = Bump::new();
+        let big = Value::integer(&arena, 9007199254740993_i64);
+        let one = Value::integer(&arena, 1_i64);
+        let two = Value::number(&arena, 2);
+        let half = Value::number(&arena, 0.5);
+
+        assert_eq!(
+            arithmetic(&BinaryOp::Add, big, one),
+            Some(Integer::Signed(9007199254740994))
+        );
+        assert_eq!(
+            arithmetic(&BinaryOp::Multiply, big, two),
+            Some(Integer::Signed(18014398509481986))
+        );
+        assert_eq!(arithmetic(&BinaryOp::Add, big, half), None);
+        assert_eq!(arithmetic(&BinaryOp::Divide, one, two), None);
+        assert_eq!(arithmetic(&BinaryOp::Add, two, two), None);
+
+        let max = Value::integer(&arena, u64::MAX);
+        assert_eq!(arithmetic(&BinaryOp::Add, max, one), None);
+        assert_eq!(
+            arithmetic(&BinaryOp::Subtract, max, one),
+            Some(Integer::Unsigned(u64::MAX - 1))
+        );
+    }
+
+    #[test_case("id", "9007199254740993")]
+    #[test_case("big", "18446744073709551615")]
+    #[test_case(
+        "{ 'id': id, 'next': id + 1 }",
+        r#"{"id":9007199254740993,"next":9007199254740994}"#
+    )]
+    #[test_case("$sum([id, 1, -1])", "9007199254740993")]
+    #[test_case("$max([id, 9007199254740992])", "9007199254740993")]
+    #[test_case("id = 9007199254740992", "false")]
+    #[test_case("$string(-id)", r#""-9007199254740993""#)]
+    #[test_case("$number('9007199254740993')", "9007199254740993")]
+    #[test_case("id / 3", "3002399751580331")]
+    #[test_case("id / 2", "4503599627370500")]
+    #[test_case("big + 1 > big", "true")]
+    fn round_trip(expr: &str, expected: &str) {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        let input = r#"{"id": 9007199254740993, "big": 18446744073709551615}"#;
+        let result = jsonata.evaluate(Some(input), None).unwrap();
+        assert_eq!(result.serialize(false), expected);
+    }
+}
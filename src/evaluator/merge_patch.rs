@@ -0,0 +1,82 @@
+//! `$mergePatch(original, updated)`, which describes how a value changed as an
+//! [RFC 7386](https://www.rfc-editor.org/rfc/rfc7386) JSON Merge Patch.
+
+use super::functions::FunctionContext;
+use super::value::Value;
+use crate::Result;
+
+/// The merge patch which turns `original` into `updated`: an object of the members which were
+/// added or changed, with `null` for the ones which were removed, and the patches of objects which
+/// are in both nested within it. A value which isn't an object in both is replaced as a whole.
+///
+/// Merge patches can't set a member to `null`, so a member which became `null` is removed by the
+/// patch instead.
+pub fn fn_merge_patch<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    Ok(merge_patch(&context, &args[0], &args[1]))
+}
+
+fn merge_patch<'a>(
+    context: &FunctionContext<'a, '_>,
+    original: &'a Value<'a>,
+    updated: &'a Value<'a>,
+) -> &'a Value<'a> {
+    if !original.is_object() || !updated.is_object() {
+        return updated;
+    }
+
+    let patch = Value::object(context.arena);
+    for (key, value) in original.entries() {
+        if updated.get_entry(key).is_undefined() && !value.is_undefined() {
+            patch.insert(key, Value::null(context.arena));
+        }
+    }
+    for (key, value) in updated.entries() {
+        let before = original.get_entry(key);
+        if value.is_undefined() || before == *value {
+            continue;
+        }
+        patch.insert(key, merge_patch(context, before, value));
+    }
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use crate::JsonAta;
+
+    fn evaluate(expr: &str) -> String {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.evaluate(None, None).unwrap().serialize(false)
+    }
+
+    #[test_case("$mergePatch({'a': 1, 'b': 2}, {'a': 1, 'b': 3})", r#"{"b":3}"#)]
+    #[test_case("$mergePatch({'a': 1, 'b': 2}, {'a': 1})", r#"{"b":null}"#)]
+    #[test_case("$mergePatch({'a': 1}, {'a': 1, 'c': [1, 2]})", r#"{"c":[1,2]}"#)]
+    #[test_case(
+        "$mergePatch({'a': {'b': 1, 'c': 2}}, {'a': {'b': 1, 'c': 3, 'd': 4}})",
+        r#"{"a":{"c":3,"d":4}}"#;
+        "nested objects"
+    )]
+    #[test_case("$mergePatch({'a': [1, 2]}, {'a': [1]})", r#"{"a":[1]}"#; "arrays are replaced")]
+    #[test_case("$mergePatch({'a': {'b': 1}}, {'a': 'b'})", r#"{"a":"b"}"#)]
+    #[test_case("$mergePatch({'a': 1}, {'a': null})", r#"{"a":null}"#; "null removes")]
+    #[test_case("$mergePatch({'a': 1}, {'a': 1})", "{}")]
+    #[test_case("$mergePatch([1], {'a': 1})", r#"{"a":1}"#)]
+    #[test_case("$mergePatch({'a': 1}, 'a')", r#""a""#)]
+    #[test_case("$mergePatch({'a': 1}, missing)", "")]
+    #[test_case(
+        "($o := {'order': {'status': 'new'}}; $mergePatch($o, $o ~> |order|{'status': 'paid'}|))",
+        r#"{"order":{"status":"paid"}}"#;
+        "transformed copy"
+    )]
+    fn merge_patches(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr), expected);
+    }
+}
@@ -0,0 +1,269 @@
+//! Evaluating the steps of paths against the items of large arrays on several threads, see
+//! [`crate::JsonAta::set_parallel_threshold`].
+//!
+//! Values live in an arena which only one thread can allocate in, and functions hold on to the
+//! frame they were created in, so each thread evaluates its share of the items with an arena, a
+//! frame and an evaluator of its own, and the results are copied into the evaluation's arena once
+//! they're all done. That's only sound for values which are plain data, without functions, so
+//! the steps which are evaluated in parallel don't create any and their items and results are
+//! checked for them, falling back to evaluating the step as usual.
+
+use std::collections::HashSet;
+
+use bumpalo::Bump;
+use rayon::prelude::*;
+
+use super::frame::Frame;
+use super::functions::bind_builtins;
+use super::value::Value;
+use super::Evaluator;
+use crate::parser::ast::Ast;
+use crate::Result;
+
+/// The steps of an expression which can be evaluated in parallel, and when to.
+pub struct Parallel {
+    /// The least number of items a step is evaluated against in parallel
+    pub threshold: usize,
+
+    /// The addresses of the steps which only depend on their item, and don't create functions
+    steps: HashSet<usize>,
+}
+
+impl Parallel {
+    /// Finds the steps of `ast` which can be evaluated in parallel. The AST mustn't move while
+    /// it's used.
+    pub fn new(ast: &Ast, threshold: usize) -> Self {
+        Self {
+            threshold,
+            steps: super::step_memo::pure_steps(ast, false),
+        }
+    }
+}
+
+/// Shares values in an arena with other threads, for reading only. Sound for plain data, which
+/// has no interior mutability and isn't allocated as it's read, unlike functions and ranges.
+struct Shared<T>(T);
+
+unsafe impl<T> Send for Shared<T> {}
+unsafe impl<T> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    // Closures capture the wrapper through this, rather than the value it wraps
+    fn get(&self) -> &T {
+        &self.0
+    }
+}
+
+/// The results of evaluating a step against a chunk of the items, in the arena they're in.
+type Chunk = Shared<(Bump, Result<Vec<*const Value<'static>>>)>;
+
+impl<'a> Evaluator<'a> {
+    /// Evaluates `step` against each of the `items` on the threads of rayon's pool, if the step
+    /// and the items allow it, returning the result for each item in order.
+    pub(super) fn evaluate_step_in_parallel(
+        &self,
+        step: &Ast,
+        items: &'a Value<'a>,
+    ) -> Result<Option<Vec<&'a Value<'a>>>> {
+        let Some((ref parallel, ref policy)) = self.parallel else {
+            return Ok(None);
+        };
+        if items.len() < parallel.threshold
+            || !parallel.steps.contains(&(step as *const Ast as usize))
+            || !items.members().all(is_plain_data)
+        {
+            return Ok(None);
+        }
+
+        let items: Vec<_> = items.members().map(Shared).collect();
+        let chunk_size = items.len().div_ceil(rayon::current_num_threads() * 4);
+        let step = Shared(step);
+        let chain_ast = Shared(&self.chain_ast);
+        let string_units = self.string_units;
+        #[cfg(feature = "icu")]
+        let reload_locale = self.locale.as_ref().map(|locale| locale.reload());
+        #[cfg(feature = "decimal")]
+        let decimal = self.decimal;
+
+        let chunks: Vec<Chunk> = items
+            .par_chunks(chunk_size)
+            .map(|items| {
+                let arena = Bump::new();
+                let evaluator = Evaluator::new((*chain_ast.get()).clone(), &arena, None, None)
+                    .with_string_units(string_units);
+                #[cfg(feature = "icu")]
+                let evaluator =
+                    evaluator.with_locale(reload_locale.as_ref().map(|reload| reload()));
+                #[cfg(feature = "decimal")]
+                let evaluator = evaluator.with_decimal(decimal);
+                let frame = Frame::new();
+                bind_builtins(&frame, &arena, policy);
+
+                let results = items
+                    .iter()
+                    .map(|item| {
+                        // SAFETY: the item is plain data, which doesn't depend on the lifetime of
+                        // its arena, and outlives the evaluation
+                        let item: &Value = unsafe { std::mem::transmute(*item.get()) };
+                        let result = evaluator.evaluate_step_item(step.get(), item, &frame)?;
+                        Ok((result as *const Value).cast::<Value<'static>>())
+                    })
+                    .collect();
+                drop(evaluator);
+                drop(frame);
+                Shared((arena, results))
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(items.len());
+        for Shared((_arena, chunk)) in chunks {
+            for result in chunk? {
+                // SAFETY: the result is in the chunk's arena, which is still alive, or is one of
+                // the items
+                let result = unsafe { &*result };
+                if !is_plain_data(result) {
+                    return Ok(None);
+                }
+                results.push(copy_into(result, self.arena));
+            }
+        }
+        Ok(Some(results))
+    }
+}
+
+fn is_plain_data<'a>(value: &'a Value<'a>) -> bool {
+    match value {
+        Value::Array(..) => value.members().all(is_plain_data),
+        Value::Object(..) => value.entries().all(|(_, value)| is_plain_data(value)),
+        Value::Range(..) | Value::Lambda { .. } | Value::NativeFn { .. } => false,
+        Value::Transformer { .. } => false,
+        _ => true,
+    }
+}
+
+/// Copies plain data into `arena`, along with all the values within it.
+fn copy_into<'a>(value: &Value<'_>, arena: &'a Bump) -> &'a Value<'a> {
+    match value {
+        Value::Undefined => Value::undefined(),
+        Value::Null => Value::null(arena),
+        Value::Bool(b) => Value::bool(arena, *b),
+        Value::Number(n) => Value::number(arena, *n),
+        Value::Integer(n) => Value::integer(arena, *n),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => Value::decimal(arena, *d),
+        Value::String(s) => Value::string(arena, s.as_ref()),
+        Value::Array(members, flags) => {
+            let array = Value::array_with_capacity(arena, members.len(), flags.clone());
+            for member in members.iter() {
+                array.push(copy_into(member, arena));
+            }
+            array
+        }
+        Value::Object(entries) => {
+            let object = Value::object_with_capacity(arena, entries.len());
+            for (key, value) in entries.iter() {
+                object.insert(key, copy_into(value, arena));
+            }
+            object
+        }
+        _ => unreachable!("Only plain data is copied"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::Parallel;
+    use crate::parser::ast::{Ast, AstKind};
+    use crate::{FunctionPolicy, JsonAta};
+
+    const INPUT: &str = r#"{
+        "orders": [1..500].{
+            "id": $,
+            "items": [{"name": "a" & $, "price": $ % 7}, {"name": "b" & $, "price": $ % 3}]
+        }
+    }"#;
+
+    /// Evaluates `expr` against the input with and without a parallel threshold.
+    fn evaluate(expr: &str, input: &str, policy: FunctionPolicy) -> [Result<String, String>; 2] {
+        let arena = Bump::new();
+        let input = JsonAta::new(input, &arena)
+            .unwrap()
+            .evaluate(None, None)
+            .unwrap();
+        [None, Some(100)].map(|threshold| {
+            let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+            jsonata.set_function_policy(policy.clone());
+            jsonata.set_parallel_threshold(threshold);
+            jsonata
+                .evaluate_value(input)
+                .map(|result| result.serialize(false))
+                .map_err(|error| error.to_string())
+        })
+    }
+
+    #[test_case("orders.{'id': id, 'total': $sum(items.price)}")]
+    #[test_case("orders.items[price > 3].name")]
+    #[test_case("orders.items.price")]
+    #[test_case("orders[id % 2 = 0].(items.price ~> $sum())")]
+    #[test_case("orders^(>id).id")]
+    #[test_case("orders.$string(id)")]
+    #[test_case("($x := 2; orders.(id * $x))")]
+    #[test_case("orders.$map(items, function($i) { $i.price })")]
+    #[test_case("orders.(id > 100 ? $error('too big: ' & $string(id)) : id)")]
+    fn matches_sequential(expr: &str) {
+        let [sequential, parallel] = evaluate(expr, INPUT, FunctionPolicy::AllowAll);
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn first_error() {
+        let expr = "orders.(id > 100 ? $error('too big: ' & $string(id)) : id)";
+        let [_, parallel] = evaluate(expr, INPUT, FunctionPolicy::AllowAll);
+        assert!(parallel.unwrap_err().contains("too big: 101"));
+    }
+
+    #[test]
+    fn function_policy() {
+        let policy = FunctionPolicy::Deny(HashSet::from(["string".to_string()]));
+        let [sequential, parallel] = evaluate("orders.$string(id)", INPUT, policy);
+        assert!(parallel.is_err());
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn functions_in_items() {
+        let input = "{'orders': [1..500].{'id': $, 'f': function() { $ }}}";
+        let [sequential, parallel] = evaluate("orders.{'id': id}", input, FunctionPolicy::AllowAll);
+        assert_eq!(parallel, sequential);
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn locale() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("orders.$uppercase('i' & id)", &arena).unwrap();
+        jsonata.set_locale(Some("tr")).unwrap();
+        jsonata.set_parallel_threshold(Some(100));
+        let result = jsonata.evaluate(Some(INPUT), None).unwrap();
+        assert_eq!(result[499].as_str(), "İ500");
+    }
+
+    #[test_case("orders.{'id': id, 'total': $sum(items.price)}", true)]
+    #[test_case("orders.id", true)]
+    #[test_case("orders.(id * $x)", false)]
+    #[test_case("orders.$map(items, function($i) { $i.price })", false)]
+    #[test_case("orders.(items ~> |$|{'seen': true}|)", false)]
+    fn last_step(expr: &str, expected: bool) {
+        let ast = crate::parser::parse(expr).unwrap();
+        let AstKind::Path(ref steps) = ast.kind else {
+            panic!("Not a path");
+        };
+        let step = steps.last().unwrap() as *const Ast as usize;
+        assert_eq!(Parallel::new(&ast, 0).steps.contains(&step), expected);
+    }
+}
@@ -0,0 +1,157 @@
+//! [JSON Pointers](https://www.rfc-editor.org/rfc/rfc6901), for addressing members whose names are
+//! awkward in paths, such as ones containing dots, and for working with the pointers JSON Schema
+//! and JSON Patch use.
+
+use std::collections::HashMap;
+
+use super::functions::FunctionContext;
+use super::value::Value;
+use crate::{Error, Result};
+
+/// The reference tokens of a pointer, unescaped, or `None` if it isn't a valid pointer. The empty
+/// pointer, with no tokens, refers to the whole value.
+pub(crate) fn parse(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(vec![]);
+    }
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|token| {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                unescaped.push(match c {
+                    '~' => match chars.next() {
+                        Some('0') => '~',
+                        Some('1') => '/',
+                        _ => return None,
+                    },
+                    c => c,
+                });
+            }
+            Some(unescaped)
+        })
+        .collect()
+}
+
+/// `token` escaped to be a reference token of a pointer.
+pub(crate) fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// The pointer to each object and array within `value`, including itself, keyed by its address,
+/// for finding where the values a path matched are.
+pub(crate) fn index<'a>(value: &'a Value<'a>) -> HashMap<usize, String> {
+    fn add<'a>(value: &'a Value<'a>, pointer: String, pointers: &mut HashMap<usize, String>) {
+        match value {
+            Value::Object(..) => {
+                for (key, member) in value.entries() {
+                    add(member, format!("{}/{}", pointer, escape(key)), pointers);
+                }
+            }
+            Value::Array(..) => {
+                for (index, member) in value.members().enumerate() {
+                    add(member, format!("{}/{}", pointer, index), pointers);
+                }
+            }
+            _ => return,
+        }
+        pointers.insert(value as *const Value as usize, pointer);
+    }
+
+    let mut pointers = HashMap::new();
+    add(value, String::new(), &mut pointers);
+    pointers
+}
+
+/// The index of an array a reference token refers to, which is written in decimal without leading
+/// zeros.
+pub(crate) fn array_index(token: &str) -> Option<usize> {
+    if token.starts_with('0') && token.len() > 1 || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// The value `tokens` refer to within `value`, or undefined if there isn't one.
+pub(crate) fn resolve<'a>(value: &'a Value<'a>, tokens: &[String]) -> &'a Value<'a> {
+    tokens.iter().fold(value, |value, token| match value {
+        Value::Object(..) => value.get_entry(token),
+        Value::Array(..) | Value::Range(..) => match array_index(token) {
+            Some(index) if index < value.len() => value.get_member(index),
+            _ => Value::undefined(),
+        },
+        _ => Value::undefined(),
+    })
+}
+
+/// `$pointer(value, pointer)`, the member of `value` at a JSON Pointer such as `/a/b~1c/0`.
+pub fn fn_pointer<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    let value = &args[0];
+    let pointer = &args[1];
+    if !pointer.is_string() {
+        return Err(Error::T0410ArgumentNotValid(
+            context.char_index,
+            2,
+            context.name.to_string(),
+        ));
+    }
+    if value.is_undefined() {
+        return Ok(Value::undefined());
+    }
+    let pointer = pointer.as_str();
+    let tokens = parse(&pointer)
+        .ok_or_else(|| Error::H0703InvalidPointer(context.char_index, pointer.to_string()))?;
+    Ok(resolve(value, &tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::JsonAta;
+
+    const INPUT: &str = r#"{
+        "a.b": {"c/d": [10, 20, {"~e": true}]},
+        "": "empty",
+        "n": null
+    }"#;
+
+    fn evaluate(expr: &str) -> Result<String> {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena)?;
+        Ok(jsonata.evaluate(Some(INPUT), None)?.serialize(false))
+    }
+
+    #[test_case("$pointer($, '/a.b/c~1d/1')", "20")]
+    #[test_case("$pointer($, '/a.b/c~1d/2/~0e')", "true")]
+    #[test_case("$pointer($, '/')", r#""empty""#)]
+    #[test_case("$pointer($, '/n')", "null")]
+    #[test_case(
+        "$pointer($, '')",
+        r#"{"a.b":{"c/d":[10,20,{"~e":true}]},"":"empty","n":null}"#
+    )]
+    #[test_case("$pointer([1..3], '/2')", "3")]
+    #[test_case("$pointer($, '/missing/0')", "")]
+    #[test_case("$pointer($, '/a.b/c~1d/3')", "")]
+    #[test_case("$pointer($, '/a.b/c~1d/01')", ""; "leading zero")]
+    #[test_case("$pointer($, '/a.b/c~1d/-')", ""; "past the end")]
+    #[test_case("$pointer(missing, '/a')", "")]
+    fn pointers(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("$pointer($, 'a.b')", "H0703")]
+    #[test_case("$pointer($, '/a~2')", "H0703")]
+    #[test_case("$pointer($, '/a~')", "H0703")]
+    #[test_case("$pointer($, 1)", "T0410")]
+    fn invalid(expr: &str, code: &str) {
+        assert_eq!(evaluate(expr).unwrap_err().code(), code);
+    }
+}
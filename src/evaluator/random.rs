@@ -0,0 +1,55 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A source of random numbers for the functions which need them, such as `$shuffle`, which can
+/// be replaced with [`crate::JsonAta::set_random_source`].
+///
+/// Closures returning an `f64` are sources, so a test can use a fixed sequence of numbers.
+pub trait RandomSource {
+    /// Returns a number in the range `[0, 1)`.
+    fn next_f64(&mut self) -> f64;
+}
+
+impl<F: FnMut() -> f64> RandomSource for F {
+    fn next_f64(&mut self) -> f64 {
+        self()
+    }
+}
+
+/// The source used unless another is set: a xorshift generator, seeded with the random keys the
+/// standard library generates for each `HashMap`, or with a seed of the host's choosing.
+pub struct DefaultRandom(u64);
+
+impl DefaultRandom {
+    pub fn new() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        // Xorshift never leaves zero
+        Self(seed.max(1))
+    }
+
+    /// A generator which always produces the same numbers for the same seed.
+    pub fn with_seed(seed: u64) -> Self {
+        // Mixed with a step of SplitMix64, so that similar seeds, such as 1 and 2, don't start
+        // with similar numbers
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((state ^ (state >> 31)).max(1))
+    }
+}
+
+impl Default for DefaultRandom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomSource for DefaultRandom {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        // The top 53 bits fill the mantissa of an `f64` in `[0, 1)`
+        (self.0 >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
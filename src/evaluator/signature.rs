@@ -0,0 +1,365 @@
+//! Function signatures, such as `<s-n?:s>`, which declare the types of a lambda's parameters.
+//!
+//! Each parameter is one of `b` (boolean), `n` (number), `s` (string), `l` (null), `a` (array),
+//! `o` (object), `f` (function), `j` (any JSON value) or `x` (anything), or a choice like `(ns)`.
+//! A parameter can be followed by `?` if it's optional, `+` if it can be repeated, or `-` if the
+//! context is used when it's missing. Arrays and functions can have a type parameter, like `a<n>`.
+//! The return type, after the `:`, isn't checked.
+//!
+//! Arguments are checked against the signature each time the lambda is called, like in reference
+//! JSONata: an undefined argument matches any type, an argument which isn't an array is wrapped in
+//! one for an `a` parameter, and missing `-` arguments are filled in with the context.
+
+use bumpalo::Bump;
+
+use super::value::{ArrayFlags, Value};
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantifier {
+    One,
+    Optional,
+    OneOrMore,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Param {
+    /// The symbols of the types of argument the parameter accepts, see `symbol`.
+    types: String,
+    quantifier: Quantifier,
+    array: bool,
+    context: bool,
+    subtype: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    definition: String,
+    params: Vec<Param>,
+}
+
+impl Signature {
+    /// Parses a signature, including its angle brackets. `char_index` is where it starts in the
+    /// expression, for errors.
+    pub fn parse(definition: &str, char_index: usize) -> Result<Signature> {
+        let chars: Vec<char> = definition.chars().collect();
+        let mut params: Vec<Param> = Vec::new();
+        let mut position = 1;
+
+        while position < chars.len() {
+            let types = match chars[position] {
+                // The return type isn't checked
+                ':' => break,
+                symbol @ ('s' | 'n' | 'b' | 'l' | 'o') => Some(format!("{}m", symbol)),
+                'a' | 'x' => Some(String::from("asnblfom")),
+                'f' => Some(String::from("f")),
+                'j' => Some(String::from("asnblom")),
+                '(' => {
+                    let end = closing_bracket(&chars, position, '(', ')');
+                    let choice: String = chars[position + 1..end].iter().collect();
+                    if choice.contains('<') {
+                        return Err(Error::S0402ChoiceGroupParameterized(char_index + position));
+                    }
+                    position = end;
+                    Some(format!("{}m", choice))
+                }
+                '-' | '?' | '+' => {
+                    if let Some(param) = params.last_mut() {
+                        match chars[position] {
+                            '-' => {
+                                param.context = true;
+                                param.quantifier = Quantifier::Optional;
+                            }
+                            '?' => param.quantifier = Quantifier::Optional,
+                            _ => param.quantifier = Quantifier::OneOrMore,
+                        }
+                    }
+                    None
+                }
+                '<' => {
+                    let end = closing_bracket(&chars, position, '<', '>');
+                    match params.last_mut() {
+                        Some(param) if param.array || param.types == "f" => {
+                            param.subtype = Some(chars[position + 1..end].iter().collect());
+                        }
+                        _ => {
+                            return Err(Error::S0401TypeParametersNotAllowed(char_index + position))
+                        }
+                    }
+                    position = end;
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(types) = types {
+                params.push(Param {
+                    array: chars[position] == 'a',
+                    types,
+                    quantifier: Quantifier::One,
+                    context: false,
+                    subtype: None,
+                });
+            }
+            position += 1;
+        }
+
+        Ok(Signature {
+            definition: definition.to_string(),
+            params,
+        })
+    }
+
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    /// Checks the arguments of a call against the signature, returning the arguments the lambda
+    /// should be called with.
+    pub fn validate<'a>(
+        &self,
+        arena: &'a Bump,
+        args: &'a Value<'a>,
+        context: &'a Value<'a>,
+        char_index: usize,
+        name: &str,
+    ) -> Result<&'a Value<'a>> {
+        let symbols: Vec<char> = args.members().map(symbol).collect();
+        let counts = self.counts(&symbols, char_index, name)?;
+
+        let validated = Value::array_with_capacity(arena, args.len(), ArrayFlags::empty());
+        let mut index = 0;
+        for (param, count) in self.params.iter().zip(counts) {
+            if count == 0 {
+                if param.context {
+                    if !param.types.contains(symbol(context)) {
+                        return Err(Error::T0411ContextNotCompatible(
+                            char_index,
+                            index + 1,
+                            name.to_string(),
+                        ));
+                    }
+                    validated.push(context);
+                } else {
+                    validated.push(Value::undefined());
+                }
+                continue;
+            }
+
+            for _ in 0..count {
+                let arg = args.get_member(index);
+                if param.array && !arg.is_undefined() {
+                    if !param.is_array_of_subtype(arg) {
+                        let subtype = param.subtype.as_deref().unwrap_or_default();
+                        return Err(Error::T0412ArgumentMustBeArrayOfType(
+                            char_index,
+                            index + 1,
+                            name.to_string(),
+                            array_type_name(subtype).to_string(),
+                        ));
+                    }
+                    validated.push(if arg.is_array() {
+                        arg
+                    } else {
+                        Value::wrap_in_array(arena, arg, ArrayFlags::empty())
+                    });
+                } else {
+                    validated.push(arg);
+                }
+                index += 1;
+            }
+        }
+
+        Ok(validated)
+    }
+
+    /// Checks the types of the arguments of a call, given their symbols, as
+    /// [`Signature::validate`] would, apart from the context and the items of arrays, which
+    /// aren't known before the call is evaluated.
+    pub(crate) fn check_symbols(
+        &self,
+        symbols: &[char],
+        char_index: usize,
+        name: &str,
+    ) -> Result<()> {
+        let counts = self.counts(symbols, char_index, name)?;
+        let mut index = 0;
+        for (param, count) in self.params.iter().zip(counts) {
+            for symbol in &symbols[index..index + count] {
+                index += 1;
+                let Some(ref subtype) = param.subtype else {
+                    continue;
+                };
+                if param.array
+                    && !matches!(symbol, 'a' | 'm')
+                    && !(subtype.len() == 1 && subtype.starts_with(*symbol))
+                {
+                    return Err(Error::T0412ArgumentMustBeArrayOfType(
+                        char_index,
+                        index,
+                        name.to_string(),
+                        array_type_name(subtype).to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// How many parameters there are, counting each which can be repeated once.
+    pub(crate) fn param_count(&self) -> usize {
+        self.params.len()
+    }
+
+    /// How many of the arguments, given their symbols, each parameter takes.
+    fn counts(&self, symbols: &[char], char_index: usize, name: &str) -> Result<Vec<usize>> {
+        if let Some(counts) = self.matches(&self.params, symbols, true) {
+            return Ok(counts);
+        }
+
+        // The first argument which doesn't match is the one after the longest prefix of the
+        // arguments which match a prefix of the parameters
+        let mut good_to = 0;
+        for end in 1..=self.params.len() {
+            match self.matches(&self.params[..end], symbols, false) {
+                Some(counts) => good_to = counts.iter().sum(),
+                None => break,
+            }
+        }
+        Err(Error::T0410ArgumentNotValid(
+            char_index,
+            good_to + 1,
+            name.to_string(),
+        ))
+    }
+
+    /// Matches the symbols of the arguments against the parameters, greedily like a regular
+    /// expression, returning how many arguments each parameter takes. If `whole` is false, only a
+    /// prefix of the arguments needs to match.
+    fn matches(&self, params: &[Param], symbols: &[char], whole: bool) -> Option<Vec<usize>> {
+        let Some((param, rest)) = params.split_first() else {
+            return (!whole || symbols.is_empty()).then(Vec::new);
+        };
+
+        let matching = symbols
+            .iter()
+            .take_while(|symbol| param.types.contains(**symbol))
+            .count();
+        let (min, max) = match param.quantifier {
+            Quantifier::One => (1, 1),
+            Quantifier::Optional => (0, 1),
+            Quantifier::OneOrMore => (1, usize::MAX),
+        };
+
+        (min..=max.min(matching)).rev().find_map(|count| {
+            let mut counts = self.matches(rest, &symbols[count..], whole)?;
+            counts.insert(0, count);
+            Some(counts)
+        })
+    }
+}
+
+impl Param {
+    // Only the first level of a type parameter is checked, like `a` in `a<a<n>>`
+    fn is_array_of_subtype<'a>(&self, arg: &'a Value<'a>) -> bool {
+        let Some(subtype) = self.subtype.as_deref() else {
+            return true;
+        };
+        if !arg.is_array() {
+            return subtype.len() == 1 && subtype.starts_with(symbol(arg));
+        }
+        let mut members = arg.members();
+        match members.next() {
+            None => true,
+            Some(first) => {
+                let item = symbol(first);
+                subtype.starts_with(item) && members.all(|member| symbol(member) == item)
+            }
+        }
+    }
+}
+
+/// The symbol for the type of a value, as used in signatures, with `m` for missing.
+fn symbol(value: &Value) -> char {
+    match value {
+        Value::Undefined => 'm',
+        Value::Null => 'l',
+        Value::Bool(..) => 'b',
+        Value::String(..) => 's',
+        Value::Array(..) | Value::Range(..) => 'a',
+        Value::Object(..) => 'o',
+        Value::Number(..) | Value::Integer(..) => 'n',
+        #[cfg(feature = "decimal")]
+        Value::Decimal(..) => 'n',
+        Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => 'f',
+    }
+}
+
+fn array_type_name(subtype: &str) -> &'static str {
+    match subtype.chars().next() {
+        Some('a') => "arrays",
+        Some('b') => "booleans",
+        Some('f') => "functions",
+        Some('n') => "numbers",
+        Some('o') => "objects",
+        Some('s') => "strings",
+        _ => "values",
+    }
+}
+
+fn closing_bracket(chars: &[char], start: usize, open: char, close: char) -> usize {
+    let mut depth = 0;
+    for (position, &c) in chars.iter().enumerate().skip(start) {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return position;
+            }
+        }
+    }
+    chars.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::JsonAta;
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Signature::parse("<(sa<n>)>", 0),
+            Err(Error::S0402ChoiceGroupParameterized(1))
+        );
+        assert_eq!(
+            Signature::parse("<s<n>>", 0),
+            Err(Error::S0401TypeParametersNotAllowed(2))
+        );
+    }
+
+    #[test_case("$f('a', 1)", r#""a1""#)]
+    #[test_case("$f('a')", r#""a""#)]
+    #[test_case("['a'].$f()", r#""a""#)]
+    #[test_case("$f('a', 'b')", "T0410")]
+    #[test_case("[1].$f()", "T0411")]
+    #[test_case("$g(1)", "[1]")]
+    #[test_case("$g([1, 'a'])", "T0412")]
+    fn validate(expr: &str, expected: &str) {
+        let arena = Bump::new();
+        let expr = format!(
+            "($f := function($s, $n)<s-n?:s> {{ $s & $string($n) }}; \
+             $g := function($a)<a<n>> {{ $a }}; {})",
+            expr
+        );
+        let jsonata = JsonAta::new(&expr, &arena).unwrap();
+        match jsonata.evaluate(None, None) {
+            Ok(result) => assert_eq!(result.serialize(false), expected),
+            Err(error) => assert_eq!(error.code(), expected),
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use super::value::Value;
+use crate::parser::ast::{Ast, AstKind, BinaryOp};
+use crate::parser::visit::{walk_node, Visitor};
+use crate::Result;
+
+/// The built-in functions whose results only depend on their arguments, which a step can call
+/// and still be memoized. Functions which aren't listed, such as `$shuffle` and the functions
+/// the host registers, might not be.
+const PURE_FUNCTIONS: &[&str] = &[
+    "abs",
+    "append",
+    "assert",
+    "base64decode",
+    "base64encode",
+    "boolean",
+    "ceil",
+    "contains",
+    "count",
+    "crc32",
+    "distinct",
+    "each",
+    "error",
+    "exists",
+    "filter",
+    "floor",
+    "hmacSha256",
+    "join",
+    "index",
+    "joinOn",
+    "keys",
+    "length",
+    "lookup",
+    "lowercase",
+    "map",
+    "max",
+    "md5",
+    "memoize",
+    "merge",
+    "mergePatch",
+    "min",
+    "not",
+    "number",
+    "pad",
+    "pointer",
+    "power",
+    "replace",
+    "reverse",
+    "round",
+    "sha256",
+    "sort",
+    "split",
+    "spread",
+    "sqrt",
+    "string",
+    "substring",
+    "substringAfter",
+    "substringBefore",
+    "sum",
+    "trim",
+    "uppercase",
+    "validate",
+    "zip",
+];
+
+/// The results of the steps of paths evaluated against each item of their input, kept from one
+/// evaluation to the next, see [`crate::JsonAta::set_incremental`].
+///
+/// Results are keyed by the addresses of the step and the item. Values aren't changed once
+/// they're in the arena, nor freed until it is, so an item at the same address is the same value,
+/// and a step which only depends on the item it's evaluated against has the same result. A result
+/// is dropped after an evaluation which doesn't use it, so the memo holds the results for the
+/// latest input rather than for every input it has seen.
+pub struct StepMemo<'a> {
+    /// The addresses of the steps which only depend on their item
+    pure_steps: HashSet<usize>,
+
+    /// The results used by the last evaluation
+    previous: RefCell<HashMap<(usize, usize), &'a Value<'a>>>,
+
+    /// The results used by the evaluation in progress
+    current: RefCell<HashMap<(usize, usize), &'a Value<'a>>>,
+}
+
+impl<'a> StepMemo<'a> {
+    /// Creates a memo for the steps of `ast`, which mustn't move while it's used.
+    pub fn new(ast: &Ast) -> Self {
+        Self {
+            pure_steps: pure_steps(ast, true),
+            previous: RefCell::default(),
+            current: RefCell::default(),
+        }
+    }
+
+    pub fn is_pure(&self, step: &Ast) -> bool {
+        self.pure_steps.contains(&(step as *const Ast as usize))
+    }
+
+    /// The result of `step` against `item` from this evaluation or the last one, or else the
+    /// result of `evaluate`, which is remembered if it succeeds.
+    pub fn get_or_evaluate(
+        &self,
+        step: &Ast,
+        item: &'a Value<'a>,
+        evaluate: impl FnOnce() -> Result<&'a Value<'a>>,
+    ) -> Result<&'a Value<'a>> {
+        let key = (step as *const Ast as usize, item as *const Value as usize);
+
+        if let Some(result) = self.current.borrow().get(&key) {
+            return Ok(result);
+        }
+        let previous = self.previous.borrow_mut().remove(&key);
+        // Evaluating the step can evaluate other memoized steps, so nothing is borrowed meanwhile
+        let result = match previous {
+            Some(result) => result,
+            None => evaluate()?,
+        };
+        self.current.borrow_mut().insert(key, result);
+        Ok(result)
+    }
+
+    /// Drops the results the evaluation which has just finished didn't use.
+    pub fn finish_evaluation(&self) {
+        let current = self.current.take();
+        *self.previous.borrow_mut() = current;
+    }
+}
+
+/// The addresses of the steps of `ast` which only depend on the item they're evaluated against,
+/// as they don't refer to variables other than `$`, the ones they bind themselves and pure
+/// built-in functions, nor use the parent operator or bind the focus or index of a step. Steps
+/// with lambdas or transforms, whose values hold on to the frame they were created in, are only
+/// included if `allow_functions` is set.
+pub(crate) fn pure_steps(ast: &Ast, allow_functions: bool) -> HashSet<usize> {
+    let mut bound = Bound::default();
+    bound.visit_node(ast);
+
+    let mut steps = PureSteps {
+        bound: bound.0,
+        allow_functions,
+        pure_steps: HashSet::new(),
+    };
+    steps.visit_node(ast);
+    steps.pure_steps
+}
+
+struct PureSteps {
+    /// The variables bound anywhere in the expression, which can hide the built-in functions
+    bound: HashSet<String>,
+    allow_functions: bool,
+    pure_steps: HashSet<usize>,
+}
+
+impl Visitor for PureSteps {
+    fn visit_node(&mut self, ast: &Ast) {
+        if let AstKind::Path(ref steps) = ast.kind {
+            for step in steps {
+                let mut purity = Purity {
+                    bound: &self.bound,
+                    allow_functions: self.allow_functions,
+                    scopes: vec![vec![]],
+                    is_pure: true,
+                };
+                purity.visit_node(step);
+                if purity.is_pure {
+                    self.pure_steps.insert(step as *const Ast as usize);
+                }
+            }
+        }
+        walk_node(self, ast);
+    }
+}
+
+struct Purity<'p> {
+    bound: &'p HashSet<String>,
+    allow_functions: bool,
+    /// The variables bound within the step so far, in each enclosing block or lambda
+    scopes: Vec<Vec<String>>,
+    is_pure: bool,
+}
+
+impl Visitor for Purity<'_> {
+    fn visit_node(&mut self, ast: &Ast) {
+        if !self.is_pure {
+            return;
+        }
+        if ast.tuple || ast.focus.is_some() || ast.index.is_some() {
+            self.is_pure = false;
+            return;
+        }
+
+        match ast.kind {
+            AstKind::Parent => self.is_pure = false,
+            AstKind::Lambda { .. } | AstKind::Transform { .. } if !self.allow_functions => {
+                self.is_pure = false
+            }
+            // `$` is the item, anything else needs to be bound within the step or be a function
+            // that can't have been rebound
+            AstKind::Var(ref name) => {
+                let is_local = self.scopes.iter().flatten().any(|bound| bound == name);
+                let is_pure_function =
+                    PURE_FUNCTIONS.contains(&name.as_str()) && !self.bound.contains(name);
+                if !(name.is_empty() || is_local || is_pure_function) {
+                    self.is_pure = false;
+                }
+            }
+            AstKind::Block(..) => {
+                self.scopes.push(vec![]);
+                walk_node(self, ast);
+                self.scopes.pop();
+                return;
+            }
+            AstKind::Lambda { ref args, .. } => {
+                let params = args
+                    .iter()
+                    .filter_map(|arg| match arg.kind {
+                        AstKind::Var(ref name) => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                self.scopes.push(params);
+                walk_node(self, ast);
+                self.scopes.pop();
+                return;
+            }
+            AstKind::Binary(BinaryOp::Bind, ref lhs, ref rhs) => {
+                // Bound before the value is visited, so that recursive functions can call
+                // themselves
+                if let AstKind::Var(ref name) = lhs.kind {
+                    self.scopes.last_mut().unwrap().push(name.clone());
+                }
+                self.visit_node(rhs);
+                return;
+            }
+            _ => {}
+        }
+        walk_node(self, ast);
+    }
+}
+
+/// Collects the variables bound by an expression, by `:=` or as the parameters of a lambda.
+#[derive(Default)]
+struct Bound(HashSet<String>);
+
+impl Visitor for Bound {
+    fn visit_node(&mut self, ast: &Ast) {
+        match ast.kind {
+            AstKind::Binary(BinaryOp::Bind, ref lhs, _) => {
+                if let AstKind::Var(ref name) = lhs.kind {
+                    self.0.insert(name.clone());
+                }
+            }
+            AstKind::Lambda { ref args, .. } => {
+                for arg in args {
+                    if let AstKind::Var(ref name) = arg.kind {
+                        self.0.insert(name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_node(self, ast);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use crate::{JsonAta, Value};
+
+    const INPUT: &str = r#"{
+        "orders": [
+            {"id": 1, "items": [{"name": "a", "price": 2}, {"name": "b", "price": 8}]},
+            {"id": 2, "items": [{"name": "c", "price": 6}, {"name": "d", "price": 1}]},
+            {"id": 3, "items": [{"name": "e", "price": 9}]}
+        ]
+    }"#;
+
+    const EXPR: &str =
+        "orders.{'id': id, 'total': $sum(items.price), 'big': items[price > 5].name}";
+
+    /// Evaluates `expr` incrementally against the input, then again after each edit, checking
+    /// that the results match evaluating it from scratch. Returns the number of nodes evaluated
+    /// each time.
+    fn evaluate_edits(expr: &str, edits: &[(&[&str], &str)]) -> Vec<usize> {
+        let arena = Bump::new();
+        let nodes = Rc::new(Cell::new(0));
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_incremental(true);
+        let counter = nodes.clone();
+        jsonata.set_trace(move |_| counter.set(counter.get() + 1));
+
+        let mut input = crate::parse_value(INPUT, &arena).unwrap();
+        let mut evaluated = vec![];
+        for edit in std::iter::once(None).chain(edits.iter().map(Some)) {
+            if let Some((path, value)) = edit {
+                let value = crate::parse_value(value, &arena).unwrap();
+                input = input.replace_at(&arena, path, value).unwrap();
+            }
+
+            nodes.set(0);
+            let result = jsonata.evaluate_value(input).unwrap();
+            evaluated.push(nodes.get());
+
+            let expected = JsonAta::new(expr, &arena)
+                .unwrap()
+                .evaluate_value(input)
+                .unwrap();
+            assert_eq!(result, expected);
+        }
+        evaluated
+    }
+
+    #[test]
+    fn only_edited_items_are_evaluated_again() {
+        let evaluated = evaluate_edits(
+            EXPR,
+            &[
+                (&["orders", "1", "items", "0", "price"], "4"),
+                (
+                    &["orders", "2", "items", "1"],
+                    r#"{"name": "f", "price": 7}"#,
+                ),
+            ],
+        );
+        // The root and the edited order are evaluated again, the other orders aren't
+        assert!(evaluated[1] < evaluated[0] / 2, "{:?}", evaluated);
+        assert!(evaluated[2] < evaluated[0] / 2, "{:?}", evaluated);
+    }
+
+    #[test]
+    fn unchanged_input_is_not_evaluated_again() {
+        let evaluated = evaluate_edits(EXPR, &[(&[], INPUT)]);
+        // A new input from scratch shares nothing with the last one
+        assert_eq!(evaluated[1], evaluated[0]);
+
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(EXPR, &arena).unwrap();
+        jsonata.set_incremental(true);
+        let input = crate::parse_value(INPUT, &arena).unwrap();
+        let first = jsonata.evaluate_value(input).unwrap();
+        let second = jsonata.evaluate_value(input).unwrap();
+        // The steps' results are reused rather than equal
+        assert!(std::ptr::eq(first.get_member(1), second.get_member(1)));
+    }
+
+    #[test_case("orders.items.(price * $rate)" ; "free variable")]
+    #[test_case("orders.(id * $$.orders[0].id)" ; "root")]
+    #[test_case("orders@$o.items.($o.id)" ; "focus")]
+    #[test_case("($sum := function($a) { 0 }; orders.$sum(items.price))" ; "rebound function")]
+    #[test_case("orders.items.($y := price; $f := function($x) { $x + $y }; $f(1))" ; "local bindings")]
+    fn steps_depending_on_more_than_their_item(expr: &str) {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_incremental(true);
+        jsonata.assign_var("rate", Value::number(&arena, 2));
+
+        // An edit the steps can't see, which they must not have been memoized across
+        let input = crate::parse_value(INPUT, &arena).unwrap();
+        jsonata.evaluate_value(input).unwrap();
+        jsonata.assign_var("rate", Value::number(&arena, 3));
+        let edited = input
+            .replace_at(&arena, &["orders", "0", "id"], Value::number(&arena, 7))
+            .unwrap();
+        let result = jsonata.evaluate_value(edited).unwrap();
+
+        let expected = JsonAta::new(expr, &arena).unwrap();
+        expected.assign_var("rate", Value::number(&arena, 3));
+        assert_eq!(result, expected.evaluate_value(edited).unwrap());
+    }
+
+    #[test_case(&["orders", "5"] ; "index out of range")]
+    #[test_case(&["orders", "x", "id"] ; "not an index")]
+    #[test_case(&["orders", "0", "id", "x"] ; "not an object or array")]
+    #[test_case(&["missing", "id"] ; "missing member")]
+    fn invalid_paths(path: &[&str]) {
+        let arena = Bump::new();
+        let input = crate::parse_value(INPUT, &arena).unwrap();
+        let error = input
+            .replace_at(&arena, path, Value::null(&arena))
+            .unwrap_err();
+        assert_eq!(error.code(), "H0501");
+    }
+
+    #[test]
+    fn replace_at_shares_the_rest() {
+        let arena = Bump::new();
+        let input = crate::parse_value(INPUT, &arena).unwrap();
+        let edited = input
+            .replace_at(&arena, &["orders", "3"], Value::null(&arena))
+            .unwrap();
+        let edited = edited
+            .replace_at(&arena, &["orders", "0", "note"], Value::bool(&arena, true))
+            .unwrap();
+
+        assert_eq!(edited["orders"].len(), 4);
+        assert_eq!(edited["orders"][0]["note"], true);
+        assert_eq!(input["orders"].len(), 3);
+        assert!(input["orders"][0]["note"].is_undefined());
+        assert!(std::ptr::eq(
+            &input["orders"][1]["items"],
+            &edited["orders"][1]["items"]
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+
+use super::frame::Frame;
+use super::value::Value;
+use crate::parser::ast::{Ast, AstKind, UnaryOp};
+
+/// The longest summary of a value, in chars, before it's cut short.
+const SUMMARY_LENGTH: usize = 80;
+
+/// A node that has been evaluated, passed to the trace callback.
+#[derive(Debug, Clone)]
+pub struct TraceStep<'a> {
+    /// What the node is, such as `path`, `name`, `variable`, `function call` or `binary`
+    pub kind: &'static str,
+
+    /// The index in the chars of the expression of the node
+    pub char_index: usize,
+
+    /// How deeply the node is nested in the evaluation, which is 1 for the whole expression, so
+    /// that a debugger can rebuild the tree of steps.
+    pub depth: usize,
+
+    /// The context value the node was evaluated against
+    pub input: &'a Value<'a>,
+
+    /// The value the node evaluated to
+    pub output: &'a Value<'a>,
+
+    frame: Frame<'a>,
+}
+
+impl<'a> TraceStep<'a> {
+    /// The variables in scope where the node was evaluated, by name without the `$`, including
+    /// the input as `$` (for `$$`) and functions defined by the expression, but not native
+    /// functions such as the built-ins.
+    pub fn variables(&self) -> Vec<(String, &'a Value<'a>)> {
+        self.frame
+            .visible_bindings()
+            .into_iter()
+            .filter(|(name, value)| name != "@" && !matches!(value, Value::NativeFn { .. }))
+            .collect()
+    }
+
+    /// The input as compact JSON, cut short if it's long.
+    pub fn input_summary(&self) -> String {
+        summarize(self.input)
+    }
+
+    /// The output as compact JSON, cut short if it's long.
+    pub fn output_summary(&self) -> String {
+        summarize(self.output)
+    }
+}
+
+fn summarize<'a>(value: &'a Value<'a>) -> String {
+    if value.is_undefined() {
+        return "undefined".to_string();
+    }
+    if value.is_function() {
+        return "function".to_string();
+    }
+    let json = value.serialize(false);
+    match json.char_indices().nth(SUMMARY_LENGTH) {
+        Some((end, _)) => format!("{}…", &json[..end]),
+        None => json,
+    }
+}
+
+type Callback<'a> = Box<dyn FnMut(&TraceStep<'a>) + 'a>;
+
+/// A callback invoked after each node is evaluated.
+pub struct Tracer<'a> {
+    callback: RefCell<Callback<'a>>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(callback: impl FnMut(&TraceStep<'a>) + 'a) -> Self {
+        Self {
+            callback: RefCell::new(Box::new(callback)),
+        }
+    }
+
+    pub fn step(
+        &self,
+        node: &Ast,
+        depth: usize,
+        input: &'a Value<'a>,
+        output: &'a Value<'a>,
+        frame: &Frame<'a>,
+    ) {
+        let step = TraceStep {
+            kind: kind(node),
+            char_index: node.char_index,
+            depth,
+            input,
+            output,
+            frame: frame.clone(),
+        };
+        (self.callback.borrow_mut())(&step)
+    }
+}
+
+fn kind(node: &Ast) -> &'static str {
+    match node.kind {
+        AstKind::Empty => "empty",
+        AstKind::Null => "null",
+        AstKind::Bool(..) => "bool",
+        AstKind::String(..) => "string",
+        AstKind::Number(..) | AstKind::Integer(..) => "number",
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(..) => "number",
+        AstKind::Name(..) => "name",
+        AstKind::Var(..) => "variable",
+        AstKind::Wildcard => "wildcard",
+        AstKind::Descendent => "descendants",
+        AstKind::Parent => "parent",
+        AstKind::PartialArg => "placeholder",
+        AstKind::Index(..) => "index",
+        AstKind::Unary(UnaryOp::Minus(..)) => "negate",
+        AstKind::Unary(UnaryOp::ArrayConstructor(..)) => "array",
+        AstKind::Unary(UnaryOp::ObjectConstructor(..)) => "object",
+        AstKind::Binary(..) => "binary",
+        AstKind::GroupBy(..) => "group by",
+        AstKind::OrderBy(..) | AstKind::Sort(..) => "sort",
+        AstKind::Block(..) => "block",
+        AstKind::Path(..) => "path",
+        AstKind::Filter(..) => "filter",
+        AstKind::Function { .. } => "function call",
+        AstKind::Lambda { .. } => "lambda",
+        AstKind::Ternary { .. } => "condition",
+        AstKind::Transform { .. } => "transform",
+    }
+}
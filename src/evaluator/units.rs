@@ -0,0 +1,86 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What the string functions which count, such as `$length`, `$substring` and `$pad`, count
+/// strings in, which can be changed with [`crate::JsonAta::set_string_units`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StringUnits {
+    /// Unicode code points, as jsonata.js counts them, so an emoji is one character but an
+    /// accented letter made of a letter and a combining accent is two.
+    #[default]
+    CodePoints,
+
+    /// Extended grapheme clusters, which are what a reader would count as characters, so an
+    /// accented letter, or an emoji made of several code points such as a flag, is one.
+    Graphemes,
+}
+
+impl StringUnits {
+    /// The number of units in `string`.
+    pub fn count(self, string: &str) -> usize {
+        match self {
+            StringUnits::CodePoints => string.chars().count(),
+            StringUnits::Graphemes => string.graphemes(true).count(),
+        }
+    }
+
+    /// The units of `string`, in order.
+    pub fn split(self, string: &str) -> Vec<&str> {
+        match self {
+            StringUnits::CodePoints => string
+                .char_indices()
+                .map(|(index, ch)| &string[index..index + ch.len_utf8()])
+                .collect(),
+            StringUnits::Graphemes => string.graphemes(true).collect(),
+        }
+    }
+
+    /// The byte offset of the first occurrence of `pattern` in `string` which starts and ends
+    /// between units, so a search in graphemes doesn't find an `e` which is part of an `é`.
+    pub fn find(self, string: &str, pattern: &str) -> Option<usize> {
+        match self {
+            StringUnits::CodePoints => string.find(pattern),
+            StringUnits::Graphemes => {
+                let is_boundary = |index: usize| {
+                    index == string.len()
+                        || string
+                            .grapheme_indices(true)
+                            .any(|(boundary, _)| boundary == index)
+                };
+                string
+                    .match_indices(pattern)
+                    .map(|(index, _)| index)
+                    .find(|&index| is_boundary(index) && is_boundary(index + pattern.len()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::StringUnits::{self, *};
+
+    #[test_case(CodePoints, "abc", 3)]
+    #[test_case(CodePoints, "😀", 1)]
+    #[test_case(CodePoints, "e\u{301}", 2)]
+    #[test_case(CodePoints, "🇬🇧", 2)]
+    #[test_case(Graphemes, "abc", 3)]
+    #[test_case(Graphemes, "e\u{301}", 1)]
+    #[test_case(Graphemes, "🇬🇧", 1)]
+    #[test_case(Graphemes, "👩‍👩‍👧", 1)]
+    #[test_case(Graphemes, "नमस्ते", 3)]
+    fn count(units: StringUnits, string: &str, expected: usize) {
+        assert_eq!(units.count(string), expected);
+        assert_eq!(units.split(string).concat(), string);
+    }
+
+    #[test_case(CodePoints, "cafe\u{301}s", "e", Some(3))]
+    #[test_case(Graphemes, "cafe\u{301}s", "e", None)]
+    #[test_case(Graphemes, "cafe\u{301}se", "e", Some(7))]
+    #[test_case(Graphemes, "cafe\u{301}s", "e\u{301}", Some(3))]
+    #[test_case(Graphemes, "abc", "", Some(0))]
+    fn find(units: StringUnits, string: &str, pattern: &str, expected: Option<usize>) {
+        assert_eq!(units.find(string, pattern), expected);
+    }
+}
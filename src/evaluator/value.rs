@@ -1,22 +1,26 @@
 use std::borrow::Cow;
-use std::collections::{hash_map, HashMap};
 
 use bitflags::bitflags;
 use bumpalo::boxed::Box;
 use bumpalo::Bump;
+use indexmap::IndexMap;
 
 use super::frame::Frame;
 use super::functions::FunctionContext;
+use super::integer::Integer;
 use crate::parser::ast::{Ast, AstKind};
 use crate::{Error, Result};
 
+#[cfg(feature = "serde")]
+mod deserialize;
 pub mod impls;
 pub mod iterator;
 mod range;
 pub mod serialize;
+pub(crate) mod transcode;
 
 use self::range::Range;
-use self::serialize::{DumpFormatter, PrettyFormatter, Serializer};
+use self::serialize::{DumpFormatter, PrettyFormatter, SerializeOptions, Serializer};
 pub use iterator::MemberIterator;
 
 bitflags! {
@@ -38,14 +42,28 @@ pub const UNDEFINED: Value = Value::Undefined;
 ///
 /// Values are all allocated in a Bump arena, making them contiguous in memory and further avoiding
 /// heap allocations for every one.
+///
+/// Objects keep their keys in insertion order, as in JavaScript, so iterating over an object and
+/// serializing it are deterministic.
+///
+/// More kinds of value are added by features, such as `Value::Decimal` by `decimal`, so matches
+/// outside the crate need a wildcard arm.
+#[non_exhaustive]
 pub enum Value<'a> {
     Undefined,
     Null,
     Number(f64),
+    /// A number written without a fraction or exponent, which is kept exact rather than rounded to
+    /// the nearest `f64`.
+    Integer(Integer),
+    /// An exact decimal number, used instead of `Number` in decimal mode, see
+    /// [`crate::JsonAta::set_decimal`].
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
     Bool(bool),
-    String(String),
+    String(Cow<'a, str>),
     Array(Box<'a, Vec<&'a Value<'a>>>, ArrayFlags),
-    Object(Box<'a, HashMap<String, &'a Value<'a>>>),
+    Object(Box<'a, IndexMap<String, &'a Value<'a>>>),
     Range(Range<'a>),
     Lambda {
         ast: Box<'a, Ast>,
@@ -72,23 +90,38 @@ impl<'a> Value<'a> {
         unsafe { std::mem::transmute::<&Value<'static>, &'a Value<'a>>(&UNDEFINED) }
     }
 
-    pub fn null(arena: &Bump) -> &mut Value {
+    pub fn null(arena: &Bump) -> &mut Value<'_> {
         arena.alloc(Value::Null)
     }
 
-    pub fn bool(arena: &Bump, value: bool) -> &mut Value {
+    pub fn bool(arena: &Bump, value: bool) -> &mut Value<'_> {
         arena.alloc(Value::Bool(value))
     }
 
-    pub fn number(arena: &Bump, value: impl Into<f64>) -> &mut Value {
+    pub fn number(arena: &Bump, value: impl Into<f64>) -> &mut Value<'_> {
         arena.alloc(Value::Number(value.into()))
     }
 
-    pub fn string(arena: &Bump, value: impl Into<String>) -> &mut Value {
-        arena.alloc(Value::String(value.into()))
+    pub fn integer(arena: &Bump, value: impl Into<Integer>) -> &mut Value<'_> {
+        arena.alloc(Value::Integer(value.into()))
+    }
+
+    #[cfg(feature = "decimal")]
+    pub fn decimal(arena: &Bump, value: rust_decimal::Decimal) -> &mut Value<'_> {
+        arena.alloc(Value::Decimal(value))
+    }
+
+    pub fn string(arena: &Bump, value: impl Into<String>) -> &mut Value<'_> {
+        arena.alloc(Value::String(Cow::Owned(value.into())))
+    }
+
+    /// A string which borrows its text rather than copying it, such as a slice of an input which
+    /// outlives the arena.
+    pub fn borrowed_string(arena: &'a Bump, value: &'a str) -> &'a mut Value<'a> {
+        arena.alloc(Value::String(Cow::Borrowed(value)))
     }
 
-    pub fn array(arena: &Bump, flags: ArrayFlags) -> &mut Value {
+    pub fn array(arena: &Bump, flags: ArrayFlags) -> &mut Value<'_> {
         arena.alloc(Value::Array(Box::new_in(Vec::new(), arena), flags))
     }
 
@@ -104,19 +137,19 @@ impl<'a> Value<'a> {
         result
     }
 
-    pub fn array_with_capacity(arena: &Bump, capacity: usize, flags: ArrayFlags) -> &mut Value {
+    pub fn array_with_capacity(arena: &Bump, capacity: usize, flags: ArrayFlags) -> &mut Value<'_> {
         arena.alloc(Value::Array(
             Box::new_in(Vec::with_capacity(capacity), arena),
             flags,
         ))
     }
 
-    pub fn object(arena: &Bump) -> &mut Value {
-        arena.alloc(Value::Object(Box::new_in(HashMap::new(), arena)))
+    pub fn object(arena: &Bump) -> &mut Value<'_> {
+        arena.alloc(Value::Object(Box::new_in(IndexMap::new(), arena)))
     }
 
     pub fn object_from(
-        hash: &HashMap<String, &'a Value<'a>>,
+        hash: &IndexMap<String, &'a Value<'a>>,
         arena: &'a Bump,
     ) -> &'a mut Value<'a> {
         let result = Value::object_with_capacity(arena, hash.len());
@@ -126,13 +159,41 @@ impl<'a> Value<'a> {
         result
     }
 
-    pub fn object_with_capacity(arena: &Bump, capacity: usize) -> &mut Value {
+    pub fn object_with_capacity(arena: &Bump, capacity: usize) -> &mut Value<'_> {
         arena.alloc(Value::Object(Box::new_in(
-            HashMap::with_capacity(capacity),
+            IndexMap::with_capacity(capacity),
             arena,
         )))
     }
 
+    /// Copies a JSON value into the arena. Integers are kept exact, as they are when parsing.
+    pub fn from_json(arena: &'a Bump, json: &serde_json::Value) -> &'a mut Value<'a> {
+        match json {
+            serde_json::Value::Null => Value::null(arena),
+            serde_json::Value::Bool(b) => Value::bool(arena, *b),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Value::integer(arena, i),
+                (_, Some(u)) => Value::integer(arena, u),
+                _ => Value::number(arena, n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(s) => Value::string(arena, s.as_str()),
+            serde_json::Value::Array(items) => {
+                let array = Value::array_with_capacity(arena, items.len(), ArrayFlags::empty());
+                for item in items {
+                    array.push(Value::from_json(arena, item));
+                }
+                array
+            }
+            serde_json::Value::Object(entries) => {
+                let object = Value::object_with_capacity(arena, entries.len());
+                for (key, value) in entries {
+                    object.insert(key, Value::from_json(arena, value));
+                }
+                object
+            }
+        }
+    }
+
     pub fn lambda(
         arena: &'a Bump,
         node: &Ast,
@@ -193,7 +254,12 @@ impl<'a> Value<'a> {
     }
 
     pub fn is_number(&self) -> bool {
-        matches!(&self, Value::Number(..))
+        match self {
+            Value::Number(..) | Value::Integer(..) => true,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => true,
+            _ => false,
+        }
     }
 
     pub fn is_integer(&self) -> bool {
@@ -207,6 +273,9 @@ impl<'a> Value<'a> {
                     n - mantissa == 0.0
                 }
             },
+            Value::Integer(..) => true,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => d.fract().is_zero(),
             _ => false,
         }
     }
@@ -250,6 +319,9 @@ impl<'a> Value<'a> {
                     Ok(true)
                 }
             }
+            Value::Integer(..) => Ok(true),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => Ok(true),
             _ => Ok(false),
         }
     }
@@ -261,6 +333,9 @@ impl<'a> Value<'a> {
     pub fn is_finite(&self) -> bool {
         match self {
             Value::Number(n) => n.is_finite(),
+            Value::Integer(..) => true,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => true,
             _ => false,
         }
     }
@@ -289,6 +364,9 @@ impl<'a> Value<'a> {
             Value::Undefined => false,
             Value::Null => false,
             Value::Number(n) => n != 0.0,
+            Value::Integer(n) => n.get() != 0,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => !d.is_zero(),
             Value::Bool(ref b) => *b,
             Value::String(ref s) => !s.is_empty(),
             Value::Array(ref a, _) => match a.len() {
@@ -326,7 +404,7 @@ impl<'a> Value<'a> {
         }
     }
 
-    pub fn entries(&self) -> hash_map::Iter<'_, String, &'a Value> {
+    pub fn entries(&self) -> indexmap::map::Iter<'_, String, &'a Value<'a>> {
         match self {
             Value::Object(map) => map.iter(),
             _ => panic!("Not an object"),
@@ -358,14 +436,32 @@ impl<'a> Value<'a> {
     pub fn as_f64(&self) -> f64 {
         match *self {
             Value::Number(n) => n,
+            Value::Integer(n) => n.to_f64(),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(ref d) => crate::evaluator::decimal::to_f64(d),
             _ => panic!("Not a number"),
         }
     }
 
+    /// Compares two numbers, exactly if either of them is a decimal or an integer.
+    pub fn compare_numbers(&self, other: &Value) -> Option<std::cmp::Ordering> {
+        #[cfg(feature = "decimal")]
+        if let Some(ordering) = crate::evaluator::decimal::compare(self, other) {
+            return Some(ordering);
+        }
+        if let Some(ordering) = crate::evaluator::integer::compare(self, other) {
+            return Some(ordering);
+        }
+        self.as_f64().partial_cmp(&other.as_f64())
+    }
+
     // TODO(math): Completely unchecked, audit usage
     pub fn as_usize(&self) -> usize {
         match *self {
             Value::Number(n) => n as usize,
+            Value::Integer(..) => self.as_f64() as usize,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => self.as_f64() as usize,
             _ => panic!("Not a number"),
         }
     }
@@ -374,13 +470,16 @@ impl<'a> Value<'a> {
     pub fn as_isize(&self) -> isize {
         match *self {
             Value::Number(n) => n as isize,
+            Value::Integer(..) => self.as_f64() as isize,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => self.as_f64() as isize,
             _ => panic!("Not a number"),
         }
     }
 
     pub fn as_str(&self) -> Cow<'_, str> {
         match *self {
-            Value::String(ref s) => Cow::from(s),
+            Value::String(ref s) => Cow::from(s.as_ref()),
             _ => panic!("Not a string"),
         }
     }
@@ -413,7 +512,7 @@ impl<'a> Value<'a> {
 
     pub fn remove_entry(&mut self, key: &str) {
         match *self {
-            Value::Object(ref mut map) => map.remove(key),
+            Value::Object(ref mut map) => map.shift_remove(key),
             _ => panic!("Not an object"),
         };
     }
@@ -436,7 +535,7 @@ impl<'a> Value<'a> {
 
     pub fn remove(&mut self, key: &str) {
         match *self {
-            Value::Object(ref mut map) => map.remove(key),
+            Value::Object(ref mut map) => map.shift_remove(key),
             _ => panic!("Not an object"),
         };
     }
@@ -460,6 +559,34 @@ impl<'a> Value<'a> {
         flattened
     }
 
+    /// Returns the members of an array between `offset` and `offset + limit`. Ranges are sliced
+    /// without materializing their members.
+    pub fn slice(&'a self, arena: &'a Bump, offset: usize, limit: usize) -> &'a Value<'a> {
+        match *self {
+            Value::Range(ref range) => {
+                let end = usize::min(offset.saturating_add(limit), range.len());
+                if offset >= end {
+                    Value::array(arena, ArrayFlags::SEQUENCE)
+                } else {
+                    Value::range(
+                        arena,
+                        range.start() + offset as isize,
+                        range.start() + end as isize - 1,
+                    )
+                }
+            }
+            Value::Array(..) => {
+                let result = Value::array(arena, self.get_flags());
+                self.members()
+                    .skip(offset)
+                    .take(limit)
+                    .for_each(|member| result.push(member));
+                result
+            }
+            _ => panic!("Not an array"),
+        }
+    }
+
     pub fn wrap_in_array(
         arena: &'a Bump,
         value: &'a Value<'a>,
@@ -499,8 +626,11 @@ impl<'a> Value<'a> {
             Self::Undefined => arena.alloc(Value::Undefined),
             Self::Null => Value::null(arena),
             Self::Number(n) => Value::number(arena, *n),
+            Self::Integer(n) => Value::integer(arena, *n),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => Value::decimal(arena, *d),
             Self::Bool(b) => Value::bool(arena, *b),
-            Self::String(s) => Value::string(arena, s),
+            Self::String(s) => arena.alloc(Value::String(s.clone())),
             Self::Array(a, f) => Value::array_from(a, arena, f.clone()),
             Self::Object(o) => Value::object_from(o, arena),
             Self::Lambda { ast, input, frame } => Value::lambda(arena, ast, input, frame.clone()),
@@ -514,6 +644,28 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// A copy of the value along with the arrays and objects within it, so that changing them
+    /// doesn't change the original. Other values are shared, as they can't be changed.
+    pub fn clone_deep(&'a self, arena: &'a Bump) -> &'a mut Value<'a> {
+        match self {
+            Self::Array(members, flags) => {
+                let array = Value::array_with_capacity(arena, members.len(), flags.clone());
+                for member in members.iter() {
+                    array.push(member.clone_deep(arena));
+                }
+                array
+            }
+            Self::Object(entries) => {
+                let object = Value::object_with_capacity(arena, entries.len());
+                for (key, value) in entries.iter() {
+                    object.insert(key, value.clone_deep(arena));
+                }
+                object
+            }
+            _ => self.clone(arena),
+        }
+    }
+
     pub fn clone_array_with_flags(&self, arena: &'a Bump, flags: ArrayFlags) -> &'a mut Value<'a> {
         match *self {
             Value::Array(ref array, _) => arena.alloc(Value::Array(
@@ -534,6 +686,102 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Serializes the value as JSON written as `options` say, see [`SerializeOptions`].
+    pub fn serialize_with(&'a self, options: &SerializeOptions) -> String {
+        options.to_string(self, None)
+    }
+
+    /// Serializes the value like [`Value::serialize_with`], writing it to `writer` as it goes
+    /// rather than building a `String` of all of it first, so large results aren't held in memory
+    /// twice.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::{JsonAta, SerializeOptions};
+    /// let arena = Bump::new();
+    /// let result = JsonAta::new("[1..3].{'n': $}", &arena)?.evaluate(None, None)?;
+    ///
+    /// let mut output = vec![];
+    /// result.serialize_to(&mut output, &SerializeOptions::default())?;
+    /// assert_eq!(output, br#"[{"n":1},{"n":2},{"n":3}]"#);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn serialize_to(
+        &'a self,
+        writer: &mut impl std::io::Write,
+        options: &SerializeOptions,
+    ) -> std::io::Result<()> {
+        options.to_writer(self, None, writer)
+    }
+
+    /// Deserializes the value into any type implementing [`serde::Deserialize`]. Strings can be
+    /// borrowed from the value rather than copied.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::Deserialize<'a>>(&'a self) -> Result<T> {
+        T::deserialize(self)
+    }
+
+    /// Returns a copy of the value with the value at `path` replaced by `value`, for editing an
+    /// input which is evaluated incrementally, see [`crate::JsonAta::set_incremental`]. Only the
+    /// objects and arrays along the path are copied, and the rest is shared with this value.
+    ///
+    /// Each segment of the path is the name of a member of an object, or the index of a member of
+    /// an array. The last segment can also add a member to an object, or append to an array with
+    /// the index one past its end. Fails with `H0501` if there's no such member to replace.
+    pub fn replace_at(
+        &'a self,
+        arena: &'a Bump,
+        path: &[&str],
+        value: &'a Value<'a>,
+    ) -> Result<&'a Value<'a>> {
+        let invalid = || Error::H0501InvalidPath(format!("/{}", path.join("/")));
+        let Some((segment, rest)) = path.split_first() else {
+            return Ok(value);
+        };
+        let replace = |member: &'a Value<'a>| {
+            if rest.is_empty() {
+                Ok(value)
+            } else if member.is_undefined() {
+                Err(invalid())
+            } else {
+                member.replace_at(arena, rest, value).map_err(|_| invalid())
+            }
+        };
+
+        match self {
+            Value::Object(..) => {
+                let replaced = replace(self.get_entry(segment))?;
+                let object = Value::object_with_capacity(arena, self.entries().len() + 1);
+                for (key, member) in self.entries() {
+                    object.insert(key, member);
+                }
+                object.insert(segment, replaced);
+                Ok(object)
+            }
+            Value::Array(..) | Value::Range(..) => {
+                let index = segment
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index <= self.len())
+                    .ok_or_else(invalid)?;
+                let replaced = replace(self.get_member(index))?;
+                let flags = match self {
+                    Value::Array(_, flags) => flags.clone(),
+                    _ => ArrayFlags::empty(),
+                };
+                let array = Value::array_with_capacity(arena, self.len() + 1, flags);
+                for (i, member) in self.members().enumerate() {
+                    array.push(if i == index { replaced } else { member });
+                }
+                if index == self.len() {
+                    array.push(replaced);
+                }
+                Ok(array)
+            }
+            _ => Err(invalid()),
+        }
+    }
+
     // TODO: I don't have a good way to make modifications to values right now, so here's this absolutely
     // no good, very bad, shouldn't exist reference transmuter :(
     //
@@ -551,3 +799,39 @@ impl<'a> Value<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+
+    use serde_json::json;
+
+    use crate::{JsonAta, Value};
+
+    #[test]
+    fn from_json() {
+        let arena = Bump::new();
+        let value = Value::from_json(
+            &arena,
+            &json!({"id": 9007199254740993u64, "n": [-1, 1.5, null, true], "s": "a"}),
+        );
+        assert!(value.get_entry("id").is_integer());
+        assert_eq!(
+            value.serialize(false),
+            r#"{"id":9007199254740993,"n":[-1,1.5,null,true],"s":"a"}"#
+        );
+    }
+
+    #[test]
+    fn transforms_leave_their_input_unchanged() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("[$ ~> |a|{'b': 2}|, $]", &arena).unwrap();
+        let result = jsonata
+            .evaluate(Some(r#"{"a": {"b": 1}, "c": [{"d": 1}]}"#), None)
+            .unwrap();
+        assert_eq!(
+            result.serialize(false),
+            r#"[{"a":{"b":2},"c":[{"d":1}]},{"a":{"b":1},"c":[{"d":1}]}]"#
+        );
+    }
+}
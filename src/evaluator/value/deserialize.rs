@@ -0,0 +1,269 @@
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer},
+    DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+};
+
+use super::transcode::MAX_SAFE_INTEGER;
+use super::Value;
+use crate::evaluator::integer::Integer;
+use crate::Error;
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::H0201Deserialize(msg.to_string())
+    }
+}
+
+impl<'a> de::Deserializer<'a> for &'a Value<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Undefined | Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+                if *n < 0.0 {
+                    visitor.visit_i64(*n as i64)
+                } else {
+                    visitor.visit_u64(*n as u64)
+                }
+            }
+            Value::Number(n) => visitor.visit_f64(*n),
+            Value::Integer(Integer::Signed(n)) => visitor.visit_i64(*n),
+            Value::Integer(Integer::Unsigned(n)) => visitor.visit_u64(*n),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => match (u64::try_from(*d), i64::try_from(*d)) {
+                // Decimals are exact, so integers of any size that fits are integers
+                _ if !d.fract().is_zero() => visitor.visit_f64(self.as_f64()),
+                (Ok(n), _) => visitor.visit_u64(n),
+                (_, Ok(n)) => visitor.visit_i64(n),
+                _ => visitor.visit_f64(self.as_f64()),
+            },
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Array(..) | Value::Range(..) => {
+                let mut seq = SeqDeserializer::new(self.members());
+                let result = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(result)
+            }
+            Value::Object(..) => {
+                let mut map = MapDeserializer::new(self.entries().map(|(k, v)| (k.as_str(), *v)));
+                let result = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(result)
+            }
+            Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => Err(
+                Error::H0201Deserialize("a function cannot be deserialized".to_string()),
+            ),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Undefined | Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'a>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'a>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(s) => visitor.visit_enum(s.as_ref().into_deserializer()),
+            Value::Object(o) if o.len() == 1 => {
+                let (variant, value) = self.entries().next().unwrap();
+                visitor.visit_enum(Enum { variant, value })
+            }
+            _ => Err(Error::H0201Deserialize(
+                "expected a string or an object with a single key for an enum".to_string(),
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        <W: Visitor<'a>>
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'a> IntoDeserializer<'a, Error> for &'a Value<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct Enum<'a> {
+    variant: &'a str,
+    value: &'a Value<'a>,
+}
+
+impl<'a> EnumAccess<'a> for Enum<'a> {
+    type Error = Error;
+    type Variant = &'a Value<'a>;
+
+    fn variant_seed<S: DeserializeSeed<'a>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self.value))
+    }
+}
+
+impl<'a> VariantAccess<'a> for &'a Value<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'a>>(self, seed: S) -> Result<S::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'a>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'a>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bumpalo::Bump;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::JsonAta;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Order<'a> {
+        id: u32,
+        customer: &'a str,
+        total: f64,
+        notes: Option<String>,
+        status: Status,
+        lines: Vec<Line>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Open,
+        Shipped { tracking: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Line {
+        sku: String,
+        qty: i64,
+    }
+
+    #[test]
+    fn struct_from_result() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"{
+                "id": id,
+                "customer": customer.name,
+                "total": $sum(items.(price * qty)),
+                "status": {"shipped": {"tracking": tracking}},
+                "lines": items.{"sku": sku, "qty": qty}
+            }"#,
+            &arena,
+        )
+        .unwrap();
+
+        let result = jsonata
+            .evaluate(
+                Some(
+                    r#"{
+                        "id": 7,
+                        "customer": {"name": "Acme"},
+                        "tracking": "1Z999",
+                        "items": [
+                            {"sku": "a", "price": 2.5, "qty": 2},
+                            {"sku": "b", "price": 1, "qty": 3}
+                        ]
+                    }"#,
+                ),
+                None,
+            )
+            .unwrap();
+
+        let order: Order = result.deserialize().unwrap();
+
+        assert_eq!(
+            order,
+            Order {
+                id: 7,
+                customer: "Acme",
+                total: 8.0,
+                notes: None,
+                status: Status::Shipped {
+                    tracking: "1Z999".to_string()
+                },
+                lines: vec![
+                    Line {
+                        sku: "a".to_string(),
+                        qty: 2
+                    },
+                    Line {
+                        sku: "b".to_string(),
+                        qty: 3
+                    }
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn scalars_and_collections() {
+        let arena = Bump::new();
+
+        let range = Value::range(&arena, 1, 3);
+        assert_eq!(range.deserialize::<Vec<u8>>().unwrap(), vec![1, 2, 3]);
+
+        let status = Value::string(&arena, "open");
+        assert_eq!(status.deserialize::<Status>().unwrap(), Status::Open);
+
+        let object = Value::object(&arena);
+        object.insert("a", Value::number(&arena, -1));
+        let object: &Value = object;
+        assert_eq!(
+            object.deserialize::<HashMap<String, i32>>().unwrap(),
+            HashMap::from([("a".to_string(), -1)])
+        );
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let arena = Bump::new();
+        let value = Value::string(&arena, "not a number");
+        assert_eq!(value.deserialize::<u32>().unwrap_err().code(), "H0201");
+    }
+}
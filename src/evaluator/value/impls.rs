@@ -8,6 +8,9 @@ impl<'a> PartialEq<Value<'a>> for Value<'a> {
             (Value::Undefined, Value::Undefined) => true,
             (Value::Null, Value::Null) => true,
             (Value::Number(l), Value::Number(r)) => *l == *r,
+            (l, r) if l.is_number() && r.is_number() => {
+                self.compare_numbers(other) == Some(std::cmp::Ordering::Equal)
+            }
             (Value::Bool(l), Value::Bool(r)) => *l == *r,
             (Value::String(l), Value::String(r)) => *l == *r,
             (Value::Array(l, ..), Value::Array(r, ..)) => *l == *r,
@@ -30,7 +33,9 @@ impl PartialEq<bool> for Value<'_> {
 impl PartialEq<usize> for Value<'_> {
     fn eq(&self, other: &usize) -> bool {
         match self {
-            Value::Number(..) => self.as_usize() == *other,
+            Value::Number(..) | Value::Integer(..) => self.as_usize() == *other,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => self.as_usize() == *other,
             _ => false,
         }
     }
@@ -39,7 +44,9 @@ impl PartialEq<usize> for Value<'_> {
 impl PartialEq<isize> for Value<'_> {
     fn eq(&self, other: &isize) -> bool {
         match self {
-            Value::Number(..) => self.as_isize() == *other,
+            Value::Number(..) | Value::Integer(..) => self.as_isize() == *other,
+            #[cfg(feature = "decimal")]
+            Value::Decimal(..) => self.as_isize() == *other,
             _ => false,
         }
     }
@@ -82,6 +89,9 @@ impl std::fmt::Debug for Value<'_> {
             Self::Undefined => write!(f, "undefined"),
             Self::Null => write!(f, "null"),
             Self::Number(n) => n.fmt(f),
+            Self::Integer(n) => std::fmt::Display::fmt(n, f),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => d.normalize().fmt(f),
             Self::Bool(b) => b.fmt(f),
             Self::String(s) => s.fmt(f),
             Self::Array(a, _) => a.fmt(f),
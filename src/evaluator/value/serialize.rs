@@ -6,7 +6,8 @@
 //
 // The original code is licensed in the same way as this crate.
 
-use std::io::Write;
+use std::collections::HashMap;
+use std::io::{self, Write};
 
 use super::Value;
 use crate::Result;
@@ -69,12 +70,22 @@ impl Formatter for DumpFormatter {
 
 pub struct PrettyFormatter {
     dent: u16,
-    spaces: u16,
+    indent: String,
+}
+
+impl PrettyFormatter {
+    /// Indents each level of nesting with `indent`.
+    pub fn new(indent: &str) -> Self {
+        Self {
+            dent: 0,
+            indent: indent.to_string(),
+        }
+    }
 }
 
 impl Default for PrettyFormatter {
     fn default() -> Self {
-        Self { dent: 0, spaces: 2 }
+        Self::new("  ")
     }
 }
 
@@ -86,8 +97,8 @@ impl Formatter for PrettyFormatter {
 
     fn new_line(&self, output: &mut Vec<u8>) {
         output.push(b'\n');
-        for _ in 0..(self.dent * self.spaces) {
-            output.push(b' ');
+        for _ in 0..self.dent {
+            output.extend_from_slice(self.indent.as_bytes());
         }
     }
 
@@ -100,21 +111,141 @@ impl Formatter for PrettyFormatter {
     }
 }
 
-pub struct Serializer<T: Formatter> {
+/// How values are written as JSON, see [`Value::serialize_with`]. The default writes them on one
+/// line, like `serialize(false)`.
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{JsonAta, SerializeOptions};
+/// let arena = Bump::new();
+/// let result = JsonAta::new(r#"{"name": "Zoë", "id": 1}"#, &arena)?.evaluate(None, None)?;
+///
+/// let options = SerializeOptions {
+///     indent: Some("\t".to_string()),
+///     sort_keys: true,
+///     ascii: true,
+///     trailing_newline: true,
+/// };
+/// assert_eq!(result.serialize_with(&options), "{\n\t\"id\": 1,\n\t\"name\": \"Zo\\u00eb\"\n}\n");
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerializeOptions {
+    /// What each level of nesting is indented with, such as two spaces or a tab, with each member
+    /// of an array or object on a line of its own. `None` writes everything on one line.
+    pub indent: Option<String>,
+
+    /// Writes the members of objects in the order of their keys, rather than the order they were
+    /// added in.
+    pub sort_keys: bool,
+
+    /// Writes characters outside of ASCII as `\u` escapes, with a surrogate pair for those which
+    /// need one.
+    pub ascii: bool,
+
+    /// Ends the output with a newline, unless there's no output as the value is undefined.
+    pub trailing_newline: bool,
+}
+
+impl SerializeOptions {
+    /// Indents each level of nesting by two spaces, like `serialize(true)`.
+    pub fn pretty() -> Self {
+        Self {
+            indent: Some("  ".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Serializes `value` to a string, writing the numbers in `lexemes` with their original text.
+    pub(crate) fn to_string<'a>(
+        &self,
+        value: &'a Value<'a>,
+        lexemes: Option<&HashMap<usize, &str>>,
+    ) -> String {
+        let mut output = match self.indent {
+            Some(ref indent) => Serializer::new(PrettyFormatter::new(indent), false)
+                .with_options(self)
+                .with_lexemes(lexemes)
+                .serialize(value),
+            None => Serializer::new(DumpFormatter, false)
+                .with_options(self)
+                .with_lexemes(lexemes)
+                .serialize(value),
+        }
+        .expect("Shouldn't fail");
+        if self.trailing_newline && !value.is_undefined() {
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Serializes `value` to `sink` as it goes, like [`SerializeOptions::to_string`].
+    pub(crate) fn to_writer<'a>(
+        &self,
+        value: &'a Value<'a>,
+        lexemes: Option<&HashMap<usize, &str>>,
+        sink: &mut dyn Write,
+    ) -> io::Result<()> {
+        match self.indent {
+            Some(ref indent) => Serializer::new(PrettyFormatter::new(indent), false)
+                .with_options(self)
+                .with_lexemes(lexemes)
+                .serialize_to(value, sink)?,
+            None => Serializer::new(DumpFormatter, false)
+                .with_options(self)
+                .with_lexemes(lexemes)
+                .serialize_to(value, sink)?,
+        }
+        if self.trailing_newline && !value.is_undefined() {
+            sink.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+// When writing to a sink, the output is passed on whenever this much of it has been buffered
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct Serializer<'l, T: Formatter> {
     output: Vec<u8>,
     formatter: T,
     fail_on_invalid_numbers: bool,
+    sort_keys: bool,
+    ascii: bool,
+    lexemes: Option<&'l HashMap<usize, &'l str>>,
+    sink: Option<&'l mut dyn Write>,
+    sink_error: Option<io::Error>,
 }
 
-impl<T: Formatter> Serializer<T> {
+impl<'l, T: Formatter> Serializer<'l, T> {
     pub fn new(formatter: T, fail_on_invalid_numbers: bool) -> Self {
         Serializer {
             output: Vec::with_capacity(1024),
             formatter,
             fail_on_invalid_numbers,
+            sort_keys: false,
+            ascii: false,
+            lexemes: None,
+            sink: None,
+            sink_error: None,
         }
     }
 
+    /// Sorts keys and escapes characters outside of ASCII as `options` say. The formatter decides
+    /// the indentation.
+    pub fn with_options(mut self, options: &SerializeOptions) -> Self {
+        self.sort_keys = options.sort_keys;
+        self.ascii = options.ascii;
+        self
+    }
+
+    /// Writes the numbers in `lexemes`, keyed by their address, with their original text rather
+    /// than formatting them.
+    pub fn with_lexemes(mut self, lexemes: Option<&'l HashMap<usize, &'l str>>) -> Self {
+        self.lexemes = lexemes;
+        self
+    }
+
     pub fn serialize<'a>(mut self, value: &'a Value<'a>) -> Result<String> {
         self.write_json(value)?;
 
@@ -123,6 +254,42 @@ impl<T: Formatter> Serializer<T> {
         Ok(unsafe { String::from_utf8_unchecked(self.output) })
     }
 
+    /// Writes the value to `sink` a chunk at a time as it's serialized, rather than building all
+    /// of it in memory. Stops at the first error writing to the sink. An invalid number is
+    /// reported as an error of kind `Other` wrapping the [`crate::Error`].
+    pub fn serialize_to<'a>(
+        mut self,
+        value: &'a Value<'a>,
+        sink: &'l mut dyn Write,
+    ) -> io::Result<()> {
+        self.sink = Some(sink);
+        self.write_json(value).map_err(io::Error::other)?;
+        self.flush();
+        match self.sink_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Passes the output buffered so far on to the sink, if there is one and it's grown large
+    /// enough. Returns whether to carry on, which is until writing to the sink fails.
+    #[inline(always)]
+    fn flush_if_full(&mut self) -> bool {
+        if self.output.len() >= CHUNK_SIZE {
+            self.flush();
+        }
+        self.sink_error.is_none()
+    }
+
+    fn flush(&mut self) {
+        if let Some(ref mut sink) = self.sink {
+            if self.sink_error.is_none() {
+                self.sink_error = sink.write_all(&self.output).err();
+            }
+            self.output.clear();
+        }
+    }
+
     #[inline(always)]
     fn write(&mut self, slice: &[u8]) {
         self.output.extend_from_slice(slice);
@@ -153,8 +320,34 @@ impl<T: Formatter> Serializer<T> {
         self.write_char(b'"');
     }
 
+    #[inline(never)]
+    fn write_string_ascii(&mut self, string: &str) {
+        self.write_char(b'"');
+        let mut units = [0; 2];
+        for ch in string.chars() {
+            if ch.is_ascii() {
+                let escape = ESCAPED[ch as usize];
+                match escape {
+                    0 => self.write_char(ch as u8),
+                    b'u' => write!(self.output, "\\u{:04x}", ch as u32).unwrap(),
+                    _ => self.write(&[b'\\', escape]),
+                }
+            } else {
+                for unit in ch.encode_utf16(&mut units) {
+                    write!(self.output, "\\u{:04x}", unit).unwrap();
+                }
+            }
+        }
+        self.write_char(b'"');
+    }
+
     #[inline(always)]
     fn write_string(&mut self, string: &str) {
+        if self.ascii && !string.is_ascii() {
+            self.write_string_ascii(string);
+            return;
+        }
+
         self.write_char(b'"');
 
         for (index, ch) in string.bytes().enumerate() {
@@ -170,46 +363,8 @@ impl<T: Formatter> Serializer<T> {
 
     #[inline(always)]
     fn write_number(&mut self, number: f64) {
-        const MAX_SIGNIFICANT_DIGITS: usize = 15;
-
         if number.is_finite() {
-            let mut buffer = dtoa::Buffer::new();
-            let formatted = buffer.format_finite(number).as_bytes();
-
-            // JSONata uses JSON.stringify with Number.toPrecision(15) to format numbers.
-            //
-            // dtoa gets us close to the behaviour of JSON.stringify, in particular for
-            // switching to scientific notation (which Rust format! doesn't do), but dtoa
-            // doesn't support specifying a number of significant digits.
-            //
-            // This craziness limits the number of significant digits and trims off trailing
-            // zeroes in the fraction by doing string manipulation.
-            //
-            // It's not pretty, and I'm sure there's a better way to do this.
-            let mut split_iter = formatted.split(|b| *b == b'.');
-            let whole = split_iter.next();
-            let fraction = split_iter.next();
-            if let Some(whole) = whole {
-                self.write(whole);
-                if whole.len() < MAX_SIGNIFICANT_DIGITS {
-                    if let Some(fraction) = fraction {
-                        let fraction_length =
-                            usize::min(MAX_SIGNIFICANT_DIGITS - whole.len(), fraction.len());
-                        if fraction_length > 0 {
-                            let fraction = unsafe {
-                                std::str::from_utf8_unchecked(&fraction[0..fraction_length])
-                                    .trim_end_matches('0')
-                            };
-                            if !fraction.is_empty() {
-                                self.write_char(b'.');
-                                self.write(fraction.as_bytes());
-                            }
-                        }
-                    }
-                }
-            } else {
-                self.write(formatted);
-            }
+            write_js_number(&mut self.output, number);
         } else {
             self.write(b"null");
         }
@@ -217,8 +372,24 @@ impl<T: Formatter> Serializer<T> {
 
     #[inline(always)]
     fn write_object<'a>(&mut self, object: &'a Value<'a>) -> Result<()> {
+        if self.sort_keys {
+            let mut entries: Vec<_> = object.entries().collect();
+            entries.sort_unstable_by_key(|(a, _)| *a);
+            self.write_entries(entries.into_iter())
+        } else {
+            self.write_entries(object.entries())
+        }
+    }
+
+    #[inline(always)]
+    fn write_entries<'a, 'e>(
+        &mut self,
+        mut iter: impl Iterator<Item = (&'e String, &'e &'a Value<'a>)>,
+    ) -> Result<()>
+    where
+        'a: 'e,
+    {
         self.write_char(b'{');
-        let mut iter = object.entries();
 
         if let Some((key, value)) = iter.next() {
             self.formatter.indent();
@@ -232,6 +403,9 @@ impl<T: Formatter> Serializer<T> {
         }
 
         for (key, value) in iter {
+            if !self.flush_if_full() {
+                return Ok(());
+            }
             self.write_char(b',');
             self.formatter.new_line(&mut self.output);
             self.write_string(key);
@@ -261,6 +435,9 @@ impl<T: Formatter> Serializer<T> {
         }
 
         for item in iter {
+            if !self.flush_if_full() {
+                return Ok(());
+            }
             self.write_char(b',');
             self.formatter.new_line(&mut self.output);
             self.write_json(item)?;
@@ -282,7 +459,24 @@ impl<T: Formatter> Serializer<T> {
                 if self.fail_on_invalid_numbers {
                     value.is_valid_number()?;
                 }
-                self.write_number(*n);
+                let lexeme = self
+                    .lexemes
+                    .and_then(|lexemes| lexemes.get(&(value as *const Value as usize)));
+                match lexeme {
+                    Some(lexeme) => self.write(lexeme.as_bytes()),
+                    None => self.write_number(*n),
+                }
+            }
+            Value::Integer(n) => self.write(n.to_string().as_bytes()),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => {
+                let lexeme = self
+                    .lexemes
+                    .and_then(|lexemes| lexemes.get(&(value as *const Value as usize)));
+                match lexeme {
+                    Some(lexeme) => self.write(lexeme.as_bytes()),
+                    None => self.write(d.normalize().to_string().as_bytes()),
+                }
             }
             Value::Bool(true) => self.write(b"true"),
             Value::Bool(false) => self.write(b"false"),
@@ -296,3 +490,278 @@ impl<T: Formatter> Serializer<T> {
         Ok(())
     }
 }
+
+/// jsonata.js rounds numbers to this many significant digits, with `Number.toPrecision(15)`,
+/// before formatting them.
+const MAX_SIGNIFICANT_DIGITS: usize = 15;
+
+/// Writes a finite number the way jsonata.js formats it: rounded to 15 significant digits, then
+/// with JavaScript's `Number.prototype.toString`, which writes the fewest digits that read back
+/// as the same number, switching to exponential notation from 1e21 up and below 1e-6.
+fn write_js_number(output: &mut Vec<u8>, number: f64) {
+    if number == 0.0 {
+        // Including -0, which JavaScript writes as 0
+        output.push(b'0');
+        return;
+    }
+    if number < 0.0 {
+        output.push(b'-');
+    }
+
+    // Rust also writes the fewest digits that read back as the same number
+    let (mut digits, mut exponent) = split_exponential(&format!("{:e}", number.abs()));
+    if digits.len() > MAX_SIGNIFICANT_DIGITS {
+        (digits, exponent) = round_to_precision(number.abs());
+    }
+
+    // The digits are d.ddd × 10^exponent, and JavaScript's rules are in terms of 0.dddd × 10^n
+    let length = digits.len() as i32;
+    let n = exponent + 1;
+    if length <= n && n <= 21 {
+        output.extend_from_slice(&digits);
+        output.resize(output.len() + (n - length) as usize, b'0');
+    } else if 0 < n && n <= 21 {
+        output.extend_from_slice(&digits[..n as usize]);
+        output.push(b'.');
+        output.extend_from_slice(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        output.extend_from_slice(b"0.");
+        output.resize(output.len() + (-n) as usize, b'0');
+        output.extend_from_slice(&digits);
+    } else {
+        output.push(digits[0]);
+        if length > 1 {
+            output.push(b'.');
+            output.extend_from_slice(&digits[1..]);
+        }
+        let sign = if exponent < 0 { '-' } else { '+' };
+        write!(output, "e{}{}", sign, exponent.abs()).unwrap();
+    }
+}
+
+/// Rounds a positive number to 15 significant digits, rounding halves up as `Number.toPrecision`
+/// does rather than to even as Rust does, and returns its digits without trailing zeros and its
+/// exponent.
+fn round_to_precision(number: f64) -> (Vec<u8>, i32) {
+    // Two more digits are enough to round by, unless they're 50, which may have been rounded up
+    // from 49.. or be an exact half, so then take every digit, of which a double has at most 767
+    let mut formatted = format!("{:.*e}", MAX_SIGNIFICANT_DIGITS + 1, number);
+    if formatted[..MAX_SIGNIFICANT_DIGITS + 3].ends_with("50") {
+        formatted = format!("{:.766e}", number);
+    }
+
+    let (mut digits, mut exponent) = split_exponential(&formatted);
+    let round_up = digits[MAX_SIGNIFICANT_DIGITS] >= b'5';
+    digits.truncate(MAX_SIGNIFICANT_DIGITS);
+    if round_up {
+        match digits.iter().rposition(|&digit| digit != b'9') {
+            Some(index) => {
+                digits[index] += 1;
+                digits.truncate(index + 1);
+            }
+            None => {
+                digits = vec![b'1'];
+                exponent += 1;
+            }
+        }
+    }
+    while digits.len() > 1 && digits.last() == Some(&b'0') {
+        digits.pop();
+    }
+    (digits, exponent)
+}
+
+/// Splits a number Rust has formatted in exponential notation into its digits and exponent.
+fn split_exponential(formatted: &str) -> (Vec<u8>, i32) {
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .expect("The number is in exponential notation");
+    let digits = mantissa.bytes().filter(|&b| b != b'.').collect();
+    (
+        digits,
+        exponent.parse().expect("The exponent is an integer"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::JsonAta;
+
+    /// Fails once more than `limit` bytes have been written to it.
+    struct Limited {
+        written: usize,
+        limit: usize,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written += buf.len();
+            if self.written > self.limit {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn large_value(arena: &Bump) -> &Value<'_> {
+        JsonAta::new(
+            "[1..20000].{'id': $, 'name': 'item \"' & $, 'tags': ['a', 'b']}",
+            arena,
+        )
+        .unwrap()
+        .evaluate(None, None)
+        .unwrap()
+    }
+
+    #[test_case(SerializeOptions::default())]
+    #[test_case(SerializeOptions::pretty())]
+    #[test_case(SerializeOptions { indent: Some("\t".to_string()), sort_keys: true, ascii: true, trailing_newline: true })]
+    fn serialize_to_matches_serialize_with(options: SerializeOptions) {
+        let arena = Bump::new();
+        let value = large_value(&arena);
+        let mut output = vec![];
+        value.serialize_to(&mut output, &options).unwrap();
+        assert!(output.len() > 4 * CHUNK_SIZE);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            value.serialize_with(&options)
+        );
+    }
+
+    #[test_case(false)]
+    #[test_case(true)]
+    fn options_match_serialize(pretty: bool) {
+        let arena = Bump::new();
+        let value = large_value(&arena);
+        let options = if pretty {
+            SerializeOptions::pretty()
+        } else {
+            SerializeOptions::default()
+        };
+        assert_eq!(value.serialize_with(&options), value.serialize(pretty));
+    }
+
+    fn serialize_with(json: &str, options: SerializeOptions) -> String {
+        let arena = Bump::new();
+        let value = JsonAta::new(json, &arena)
+            .unwrap()
+            .evaluate(None, None)
+            .unwrap();
+        value.serialize_with(&options)
+    }
+
+    #[test_case(
+        r#"{"b": 1, "a": {"d": [{"f": 1, "e": 2}], "c": 3}}"#,
+        r#"{"a":{"c":3,"d":[{"e":2,"f":1}]},"b":1}"#
+    )]
+    #[test_case(r#"{"B": 1, "a": 2, "é": 3, "_": 4}"#, r#"{"B":1,"_":4,"a":2,"é":3}"#)]
+    fn sort_keys(json: &str, expected: &str) {
+        let options = SerializeOptions {
+            sort_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(json, options), expected);
+    }
+
+    #[test_case(r#""plain""#, r#""plain""#)]
+    #[test_case(r#""Zoë""#, r#""Zo\u00eb""#)]
+    #[test_case(r#""😀 and \n""#, r#""\ud83d\ude00 and \n""#)]
+    #[test_case(r#"{"ключ": "€"}"#, r#"{"\u043a\u043b\u044e\u0447":"\u20ac"}"#)]
+    fn ascii(json: &str, expected: &str) {
+        let options = SerializeOptions {
+            ascii: true,
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(json, options), expected);
+    }
+
+    #[test_case(Some("\t"), "{\n\t\"a\": [\n\t\t1\n\t]\n}")]
+    #[test_case(Some("    "), "{\n    \"a\": [\n        1\n    ]\n}")]
+    #[test_case(Some(""), "{\n\"a\": [\n1\n]\n}")]
+    #[test_case(None, r#"{"a":[1]}"#)]
+    fn indent(indent: Option<&str>, expected: &str) {
+        let options = SerializeOptions {
+            indent: indent.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(r#"{"a": [1]}"#, options), expected);
+    }
+
+    #[test_case("[1, 2]", "[1,2]\n")]
+    #[test_case("nothing", "")]
+    fn trailing_newline(json: &str, expected: &str) {
+        let options = SerializeOptions {
+            trailing_newline: true,
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(json, options), expected);
+    }
+
+    // As formatted by `JSON.stringify(Number(n.toPrecision(15)))`
+    #[test_case(0.1 + 0.2, "0.3")]
+    #[test_case(1.0 / 3.0, "0.333333333333333")]
+    #[test_case(2.0 / 3.0, "0.666666666666667")]
+    #[test_case(-1.5, "-1.5")]
+    #[test_case(-0.0, "0")]
+    #[test_case(12345678.9, "12345678.9")]
+    #[test_case(1e20, "100000000000000000000")]
+    #[test_case(123456789012345680000.0, "123456789012346000000")]
+    #[test_case(1e21, "1e+21")]
+    #[test_case(1.5e300, "1.5e+300")]
+    #[test_case(0.000001, "0.000001")]
+    #[test_case(0.0000012345, "0.0000012345")]
+    #[test_case(1e-7, "1e-7")]
+    #[test_case(-1.5e-10, "-1.5e-10")]
+    #[test_case(5e-324, "5e-324")]
+    #[test_case(100000000000000.5, "100000000000001"; "half rounded up")]
+    #[test_case(100000000000000.47, "100000000000000")]
+    #[test_case(999999999999999.9, "1000000000000000")]
+    fn js_numbers(number: f64, expected: &str) {
+        let arena = Bump::new();
+        assert_eq!(Value::number(&arena, number).serialize(false), expected);
+    }
+
+    #[test_case("$string(0.1 + 0.2)", "0.3")]
+    #[test_case("$string(1e21)", "1e+21")]
+    #[test_case("'n = ' & 1/3e7", "n = 3.33333333333333e-8")]
+    fn js_number_strings(expr: &str, expected: &str) {
+        let arena = Bump::new();
+        let result = JsonAta::new(expr, &arena)
+            .unwrap()
+            .evaluate(None, None)
+            .unwrap();
+        assert_eq!(result.as_str(), expected);
+    }
+
+    #[test]
+    fn stops_at_write_error() {
+        let arena = Bump::new();
+        let mut sink = Limited {
+            written: 0,
+            limit: 1024,
+        };
+        let error = large_value(&arena)
+            .serialize_to(&mut sink, &SerializeOptions::default())
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        // The first chunk fails, and nothing is written after it
+        assert!(sink.written < 2 * CHUNK_SIZE);
+    }
+
+    #[test]
+    fn undefined() {
+        let mut output = vec![];
+        Value::undefined()
+            .serialize_to(&mut output, &SerializeOptions::default())
+            .unwrap();
+        assert!(output.is_empty());
+    }
+}
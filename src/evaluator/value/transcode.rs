@@ -0,0 +1,250 @@
+//! Converts values to and from formats other than JSON through serde, such as MessagePack, without
+//! going through JSON text on the way.
+
+// Only the borrowing seed is used to parse input without the `serde` feature, the rest is used by
+// MessagePack and the functions which pass values to other crates
+#![cfg_attr(not(feature = "serde"), allow(dead_code))]
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use base64::Engine;
+use bumpalo::Bump;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::{ArrayFlags, Value};
+use crate::evaluator::integer::Integer;
+
+// Integral f64s are handed to the visitor as integers too, to allow them to be deserialized into
+// integer types.
+pub(super) const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Deserializes a value of any format into the arena. Byte strings, which JSON doesn't have,
+/// become base64 strings.
+pub(crate) struct ValueSeed<'a, S = Copied>(&'a Bump, PhantomData<S>);
+
+impl<'a> ValueSeed<'a> {
+    pub fn new(arena: &'a Bump) -> Self {
+        Self(arena, PhantomData)
+    }
+}
+
+impl<'a> ValueSeed<'a, Borrowed> {
+    /// Borrows strings from what's deserialized, which has to outlive the arena, when the format
+    /// hands them over as they are, such as strings without escapes in JSON.
+    pub fn borrowing(arena: &'a Bump) -> Self {
+        Self(arena, PhantomData)
+    }
+}
+
+/// How strings the deserializer can lend for `'de` end up in values in the arena.
+pub(crate) trait Strings<'de, 'a> {
+    fn string(arena: &'a Bump, value: &'de str) -> &'a Value<'a>;
+}
+
+/// Strings are always copied.
+pub(crate) struct Copied;
+
+impl<'de, 'a> Strings<'de, 'a> for Copied {
+    fn string(arena: &'a Bump, value: &'de str) -> &'a Value<'a> {
+        Value::string(arena, value)
+    }
+}
+
+/// Strings are borrowed when they can be.
+pub(crate) struct Borrowed;
+
+impl<'a> Strings<'a, 'a> for Borrowed {
+    fn string(arena: &'a Bump, value: &'a str) -> &'a Value<'a> {
+        Value::borrowed_string(arena, value)
+    }
+}
+
+impl<'de, 'a, S: Strings<'de, 'a>> DeserializeSeed<'de> for ValueSeed<'a, S> {
+    type Value = &'a Value<'a>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a, S: Strings<'de, 'a>> Visitor<'de> for ValueSeed<'a, S> {
+    type Value = &'a Value<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Value::null(self.0))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Value::null(self.0))
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::bool(self.0, v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::integer(self.0, v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::integer(self.0, v))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<Self::Value, E> {
+        // Going through the shortest text that reads back as the same `f32` keeps 0.1 as 0.1,
+        // rather than the `f64` that's nearest to the `f32`
+        Ok(Value::number(
+            self.0,
+            v.to_string().parse::<f64>().unwrap_or(v as f64),
+        ))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::number(self.0, v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::string(self.0, v))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(S::string(self.0, v))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::string(self.0, v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        Ok(Value::string(self.0, base64.encode(v)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let array = Value::array_with_capacity(
+            self.0,
+            seq.size_hint().unwrap_or_default(),
+            ArrayFlags::empty(),
+        );
+        while let Some(member) = seq.next_element_seed(ValueSeed::<S>(self.0, PhantomData))? {
+            array.push(member);
+        }
+        Ok(array)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let object = Value::object_with_capacity(self.0, map.size_hint().unwrap_or_default());
+        while let Some(key) = map.next_key_seed(Key)? {
+            let value = map.next_value_seed(ValueSeed::<S>(self.0, PhantomData))?;
+            object.insert(&key, value);
+        }
+        Ok(object)
+    }
+}
+
+/// Deserializes a key of a map, which may be a number or another scalar in formats other than
+/// JSON, into a string.
+struct Key;
+
+impl<'de> DeserializeSeed<'de> for Key {
+    type Value = String;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Visitor<'_> for Key {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string, number or boolean key")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<String, E> {
+        Ok(v.to_string())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<String, E> {
+        Ok(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<String, E> {
+        Ok(String::from_utf8_lossy(v).into_owned())
+    }
+}
+
+/// Serializes a value in any format. Integral numbers are written as integers, which are more
+/// compact in binary formats, and functions as empty strings, as they are in JSON.
+pub(crate) struct Serializable<'a>(pub &'a Value<'a>);
+
+impl Serialize for Serializable<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Undefined | Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+                serializer.serialize_i64(*n as i64)
+            }
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Integer(Integer::Signed(n)) => serializer.serialize_i64(*n),
+            Value::Integer(Integer::Unsigned(n)) => serializer.serialize_u64(*n),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => match i64::try_from(*d) {
+                Ok(n) if d.fract().is_zero() => serializer.serialize_i64(n),
+                _ => serializer.serialize_f64(self.0.as_f64()),
+            },
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(..) | Value::Range(..) => {
+                serializer.collect_seq(self.0.members().map(Serializable))
+            }
+            Value::Object(..) => serializer.collect_map(
+                self.0
+                    .entries()
+                    .map(|(key, value)| (key, Serializable(value))),
+            ),
+            Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+                serializer.serialize_str("")
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// A snapshot of how far an evaluation has got, passed to the watchdog callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of AST nodes evaluated so far.
+    pub nodes: u64,
+
+    /// The current evaluation depth.
+    pub depth: usize,
+
+    /// The time since evaluation started.
+    pub elapsed: Duration,
+}
+
+/// Returned by a watchdog callback to decide whether evaluation should carry on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAction {
+    Continue,
+    Cancel,
+}
+
+type Callback<'a> = Box<dyn FnMut(&Progress) -> WatchdogAction + 'a>;
+
+/// A callback invoked every `interval` evaluated nodes.
+pub struct Watchdog<'a> {
+    interval: u64,
+    callback: RefCell<Callback<'a>>,
+}
+
+impl<'a> Watchdog<'a> {
+    pub fn new(interval: u64, callback: impl FnMut(&Progress) -> WatchdogAction + 'a) -> Self {
+        Self {
+            interval: interval.max(1),
+            callback: RefCell::new(Box::new(callback)),
+        }
+    }
+
+    /// Invokes the callback if `progress` has reached the next interval. An evaluation which the
+    /// callback itself starts with the same watchdog isn't watched, rather than calling it again
+    /// while it's running.
+    pub fn tick(&self, progress: &Progress) -> WatchdogAction {
+        if !progress.nodes.is_multiple_of(self.interval) {
+            return WatchdogAction::Continue;
+        }
+        match self.callback.try_borrow_mut() {
+            Ok(mut callback) => callback(progress),
+            Err(..) => WatchdogAction::Continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, OnceCell};
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn reentrant_tick() {
+        let progress = Progress {
+            nodes: 1,
+            depth: 0,
+            elapsed: Duration::ZERO,
+        };
+        let watchdog: Rc<OnceCell<Watchdog>> = Rc::new(OnceCell::new());
+        let calls = Rc::new(Cell::new(0));
+        let callback = {
+            let watchdog = watchdog.clone();
+            let calls = calls.clone();
+            move |progress: &Progress| {
+                calls.set(calls.get() + 1);
+                watchdog.get().unwrap().tick(progress)
+            }
+        };
+        assert!(watchdog.set(Watchdog::new(1, callback)).is_ok());
+
+        let action = watchdog.get().unwrap().tick(&progress);
+        assert_eq!(action, WatchdogAction::Continue);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt::Write;
+
+use crate::parser::ast::{Ast, AstKind, Object, UnaryOp};
+use crate::{CompiledExpression, JsonAta};
+
+impl CompiledExpression {
+    /// Describes how the expression is evaluated, as a tree of the steps of its paths and the
+    /// filters, sorts and group-bys applied to them, to help understand why it's slow or why its
+    /// result has the shape it does. The format is meant for people, and may change.
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("orders[total > 100].customer")?;
+    /// assert_eq!(
+    ///     expression.explain(),
+    ///     "\
+    /// path
+    /// ├─ step `orders`
+    /// │  └─ filter
+    /// │     └─ >
+    /// │        ├─ path
+    /// │        │  └─ step `total`
+    /// │        └─ 100
+    /// └─ step `customer`
+    /// "
+    /// );
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn explain(&self) -> String {
+        explain(&self.ast)
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::explain`].
+    pub fn explain(&self) -> String {
+        explain(&self.ast)
+    }
+}
+
+fn explain(ast: &Ast) -> String {
+    let mut out = String::new();
+    tree(ast, false).render(&mut out, "", "");
+    out
+}
+
+/// A line of the explanation, and the lines nested under it.
+struct Tree {
+    label: String,
+    children: Vec<Tree>,
+}
+
+impl Tree {
+    fn new(label: impl Into<String>, children: Vec<Tree>) -> Tree {
+        Tree {
+            label: label.into(),
+            children,
+        }
+    }
+
+    fn render(&self, out: &mut String, first: &str, rest: &str) {
+        let _ = writeln!(out, "{}{}", first, self.label);
+        for (i, child) in self.children.iter().enumerate() {
+            if i + 1 == self.children.len() {
+                child.render(out, &format!("{}└─ ", rest), &format!("{}   ", rest));
+            } else {
+                child.render(out, &format!("{}├─ ", rest), &format!("{}│  ", rest));
+            }
+        }
+    }
+}
+
+/// The tree for a node, which is labelled as a step if it's one of the steps of a path.
+fn tree(ast: &Ast, is_step: bool) -> Tree {
+    let mut children = vec![];
+
+    let mut label = match ast.kind {
+        AstKind::Empty => "empty".to_string(),
+        AstKind::Null => "null".to_string(),
+        AstKind::Bool(b) => b.to_string(),
+        AstKind::String(ref s) => format!("{:?}", s),
+        AstKind::Number(n) => n.to_string(),
+        AstKind::Integer(n) => n.to_string(),
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(_, d) => d.to_string(),
+        AstKind::Name(ref name) => format!("`{}`", name),
+        AstKind::Var(ref name) => format!("${}", name),
+        AstKind::Wildcard => "*".to_string(),
+        AstKind::Descendent => "**".to_string(),
+        AstKind::Parent => "%".to_string(),
+        AstKind::PartialArg => "?".to_string(),
+        AstKind::Index(ref name) => format!("index ${}", name),
+        AstKind::Unary(UnaryOp::Minus(ref value)) => {
+            children.push(tree(value, false));
+            "negate".to_string()
+        }
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref items)) => {
+            children.extend(items.iter().map(|item| tree(item, false)));
+            "array".to_string()
+        }
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref object)) => {
+            children.extend(pairs(object));
+            "object".to_string()
+        }
+        AstKind::Binary(ref op, ref lhs, ref rhs) => {
+            children.push(tree(lhs, false));
+            children.push(tree(rhs, false));
+            op.to_string()
+        }
+        AstKind::GroupBy(ref lhs, ref object) => {
+            children.push(tree(lhs, false));
+            children.push(Tree::new("group by", pairs(object)));
+            "group".to_string()
+        }
+        AstKind::OrderBy(ref lhs, ref terms) => {
+            children.push(tree(lhs, false));
+            children.push(sort(terms));
+            "order".to_string()
+        }
+        AstKind::Sort(ref terms) => return sort(terms),
+        AstKind::Block(ref exprs) => {
+            children.extend(exprs.iter().map(|expr| tree(expr, false)));
+            "block".to_string()
+        }
+        AstKind::Path(ref steps) => {
+            children.extend(steps.iter().map(|step| tree(step, true)));
+            "path".to_string()
+        }
+        AstKind::Filter(ref predicate) => {
+            children.push(tree(predicate, false));
+            "filter".to_string()
+        }
+        AstKind::Function {
+            ref proc,
+            ref args,
+            is_partial,
+            ..
+        } => {
+            children.extend(args.iter().map(|arg| tree(arg, false)));
+            let name = match proc.kind {
+                AstKind::Var(ref name) => format!("${}", name),
+                _ => {
+                    children.insert(0, Tree::new("function", vec![tree(proc, false)]));
+                    "function".to_string()
+                }
+            };
+            if is_partial {
+                format!("partially apply {}", name)
+            } else {
+                format!("call {}", name)
+            }
+        }
+        AstKind::Lambda {
+            ref args, ref body, ..
+        } => {
+            children.push(tree(body, false));
+            let args: Vec<String> = args.iter().map(|arg| tree(arg, false).label).collect();
+            format!("function({})", args.join(", "))
+        }
+        AstKind::Ternary {
+            ref cond,
+            ref truthy,
+            ref falsy,
+        } => {
+            children.push(Tree::new("if", vec![tree(cond, false)]));
+            children.push(Tree::new("then", vec![tree(truthy, false)]));
+            if let Some(falsy) = falsy {
+                children.push(Tree::new("else", vec![tree(falsy, false)]));
+            }
+            "condition".to_string()
+        }
+        AstKind::Transform {
+            ref pattern,
+            ref update,
+            ref delete,
+        } => {
+            children.push(Tree::new("pattern", vec![tree(pattern, false)]));
+            children.push(Tree::new("update", vec![tree(update, false)]));
+            if let Some(delete) = delete {
+                children.push(Tree::new("delete", vec![tree(delete, false)]));
+            }
+            "transform".to_string()
+        }
+    };
+
+    if is_step {
+        label = format!("step {}", label);
+    }
+    if let Some(ref focus) = ast.focus {
+        label.push_str(&format!(" @${}", focus));
+    }
+    if let Some(ref index) = ast.index {
+        label.push_str(&format!(" #${}", index));
+    }
+    if ast.keep_array {
+        label.push_str(" (keep array)");
+    }
+    if ast.cons_array {
+        label.push_str(" (construct array)");
+    }
+
+    children.extend(
+        ast.predicates
+            .iter()
+            .chain(&ast.stages)
+            .flatten()
+            .map(|stage| tree(stage, false)),
+    );
+    if let Some((_, ref object)) = ast.group_by {
+        children.push(Tree::new("group by", pairs(object)));
+    }
+
+    Tree::new(label, children)
+}
+
+fn pairs(object: &Object) -> Vec<Tree> {
+    object
+        .iter()
+        .map(|(key, value)| Tree::new("pair", vec![tree(key, false), tree(value, false)]))
+        .collect()
+}
+
+fn sort(terms: &[(Ast, bool)]) -> Tree {
+    let terms = terms
+        .iter()
+        .map(|(term, descending)| {
+            let order = if *descending {
+                "descending"
+            } else {
+                "ascending"
+            };
+            Tree::new(order, vec![tree(term, false)])
+        })
+        .collect();
+    Tree::new("sort", terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explained(expr: &str) -> String {
+        CompiledExpression::new(expr).unwrap().explain()
+    }
+
+    #[test]
+    fn sort_and_group_by() {
+        assert_eq!(
+            explained("items^(>price){category: $sum(price)}"),
+            "\
+path
+├─ step `items`
+├─ sort
+│  └─ descending
+│     └─ path
+│        └─ step `price`
+└─ group by
+   └─ pair
+      ├─ path
+      │  └─ step `category`
+      └─ call $sum
+         └─ path
+            └─ step `price`
+"
+        );
+    }
+
+    #[test]
+    fn bindings_and_functions() {
+        assert_eq!(
+            explained("orders@$o#$i.($f := function($x) { $x * 2 }; $f($i))"),
+            "\
+path
+├─ step `orders` @$o #$i
+└─ step block
+   ├─ :=
+   │  ├─ $f
+   │  └─ function($x)
+   │     └─ *
+   │        ├─ $x
+   │        └─ 2
+   └─ call $f
+      └─ $i
+"
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        assert_eq!(
+            explained("a ? [1, 2] : -b"),
+            "\
+condition
+├─ if
+│  └─ path
+│     └─ step `a`
+├─ then
+│  └─ array
+│     ├─ 1
+│     └─ 2
+└─ else
+   └─ negate
+      └─ path
+         └─ step `b`
+"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! The `$fetch(url [, options])` function, for looking up data in other services from within an
+//! expression, see [`JsonAta::allow_fetch`].
+//!
+//! An expression can only request the URLs the host allows, whatever its function policy, and
+//! fails with `H0401` if the host allows none. The response must be JSON, which is parsed into the
+//! result, and a status other than 2xx is an `H0702` error, as is a request which fails.
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use serde::de::DeserializeSeed;
+use ureq::http::{Method, Request};
+use ureq::Agent;
+
+use crate::evaluator::functions::FunctionContext;
+use crate::evaluator::value::serialize::{DumpFormatter, Serializer};
+use crate::evaluator::value::transcode::ValueSeed;
+use crate::{Error, JsonAta, Result, Value};
+
+/// How long a request can take, from connecting to reading the whole response.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The URLs an expression can request, and the client it requests them with.
+pub(crate) struct Fetch {
+    prefixes: Vec<String>,
+    agent: Agent,
+}
+
+impl Fetch {
+    /// Whether `url` starts with one of the allowed prefixes, at the end of a segment of its path,
+    /// so that `https://example.com/api` allows `https://example.com/api/items` but neither
+    /// `https://example.com/apis` nor `https://example.com.evil.org`.
+    fn allows(&self, url: &str) -> bool {
+        self.prefixes.iter().any(|prefix| {
+            url.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+                rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?', '#'])
+            })
+        })
+    }
+}
+
+impl JsonAta<'_> {
+    /// Allows `$fetch` to request URLs starting with any of `prefixes`, such as
+    /// `https://users.internal/api`, in every subsequent evaluation. Until it's called, `$fetch`
+    /// fails with `H0401`, as if the function policy didn't allow it, and only the URLs allowed by
+    /// the latest call can be requested.
+    ///
+    /// Requests are made while evaluating, blocking the thread, and time out after 30 seconds.
+    pub fn allow_fetch<S: Into<String>>(&mut self, prefixes: impl IntoIterator<Item = S>) {
+        let config = Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .http_status_as_error(false)
+            .build();
+        self.fetch = Some(Rc::new(Fetch {
+            prefixes: prefixes.into_iter().map(Into::into).collect(),
+            agent: Agent::new_with_config(config),
+        }));
+    }
+}
+
+/// Requests a URL and parses the JSON in the response. The options are an object with the
+/// `method`, `GET` by default, an object of `headers`, and a `body`, which is sent as it is if
+/// it's a string and as JSON otherwise.
+pub(crate) fn fn_fetch<'a>(
+    context: FunctionContext<'a, '_>,
+    args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    let Some(fetch) = context.evaluator.fetch() else {
+        return Err(Error::H0401FunctionDenied(
+            context.char_index,
+            context.name.to_string(),
+        ));
+    };
+
+    let url = &args[0];
+    let options = &args[1];
+    if url.is_undefined() {
+        return Ok(Value::undefined());
+    }
+    let bad_arg =
+        |index| Error::T0410ArgumentNotValid(context.char_index, index, context.name.to_string());
+    if !url.is_string() {
+        return Err(bad_arg(1));
+    }
+    if !options.is_undefined() && !options.is_object() {
+        return Err(bad_arg(2));
+    }
+
+    let url = url.as_str();
+    if !fetch.allows(&url) {
+        return Err(Error::H0402FetchNotAllowed(
+            context.char_index,
+            url.into_owned(),
+        ));
+    }
+    let failed = |message: String| Error::H0702FetchFailed(context.char_index, message);
+    let option = |key| match options {
+        Value::Object(..) => options.get_entry(key),
+        _ => Value::undefined(),
+    };
+
+    let method = match option("method") {
+        Value::Undefined => Method::GET,
+        method if method.is_string() => {
+            Method::from_bytes(method.as_str().to_uppercase().as_bytes()).map_err(|_| bad_arg(2))?
+        }
+        _ => return Err(bad_arg(2)),
+    };
+    let mut request = Request::builder().method(method).uri(url.as_ref());
+    let mut has_content_type = false;
+    match option("headers") {
+        Value::Undefined => {}
+        headers if headers.is_object() => {
+            for (name, value) in headers.entries() {
+                if !value.is_string() {
+                    return Err(bad_arg(2));
+                }
+                has_content_type |= name.eq_ignore_ascii_case("content-type");
+                request = request.header(name.as_str(), value.as_str().as_ref());
+            }
+        }
+        _ => return Err(bad_arg(2)),
+    }
+    let body = match option("body") {
+        Value::Undefined => String::new(),
+        body if body.is_string() => body.as_str().into_owned(),
+        body => {
+            if !has_content_type {
+                request = request.header("content-type", "application/json");
+            }
+            Serializer::new(DumpFormatter, true).serialize(body)?
+        }
+    };
+    let request = request
+        .body(body)
+        .map_err(|error| failed(error.to_string()))?;
+
+    let mut response = fetch
+        .agent
+        .run(request)
+        .map_err(|error| failed(error.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(failed(format!("{} responded with {}", url, status)));
+    }
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|error| failed(error.to_string()))?;
+    if body.is_empty() {
+        return Ok(Value::undefined());
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(&body);
+    ValueSeed::new(context.arena)
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|()| value))
+        .map_err(|error| failed(format!("{} didn't respond with JSON: {}", url, error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use crate::{FunctionPolicy, JsonAta};
+
+    /// Serves one request, responding with `status` and `body`, and returns the address to request
+    /// and a handle which returns the request it received, as text.
+    fn serve(status: &'static str, body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length: ") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut content = vec![0; content_length];
+            reader.read_exact(&mut content).unwrap();
+            request.push_str(&String::from_utf8(content).unwrap());
+
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            request
+        });
+        (address, handle)
+    }
+
+    fn evaluate(expr: &str, allowed: Option<&str>) -> Result<String, String> {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        if let Some(allowed) = allowed {
+            jsonata.allow_fetch([allowed]);
+        }
+        jsonata
+            .evaluate(None, None)
+            .map(|result| result.serialize(false))
+            .map_err(|error| error.code().to_string())
+    }
+
+    #[test]
+    fn get() {
+        let (address, server) = serve("200 OK", r#"{"id": 1, "name": "Ada"}"#);
+        let expr = format!("$fetch('{address}/users/1').name");
+        assert_eq!(evaluate(&expr, Some(&address)).unwrap(), r#""Ada""#);
+        assert!(server.join().unwrap().starts_with("GET /users/1 HTTP/1.1"));
+    }
+
+    #[test]
+    fn post() {
+        let (address, server) = serve("201 Created", r#"{"created": true}"#);
+        let expr = format!(
+            "$fetch('{address}/users', {{'method': 'post', 'headers': {{'x-token': 'secret'}}, 'body': {{'name': 'Ada'}}}})"
+        );
+        assert_eq!(
+            evaluate(&expr, Some(&address)).unwrap(),
+            r#"{"created":true}"#
+        );
+        let request = server.join().unwrap().to_lowercase();
+        assert!(request.starts_with("post /users http/1.1"));
+        assert!(request.contains("x-token: secret"));
+        assert!(request.contains("content-type: application/json"));
+        assert!(request.ends_with(r#"{"name":"ada"}"#));
+    }
+
+    #[test_case("404 Not Found", "{}")]
+    #[test_case("200 OK", "not json")]
+    fn failures(status: &'static str, body: &'static str) {
+        let (address, server) = serve(status, body);
+        let expr = format!("$fetch('{address}/users/2')");
+        assert_eq!(evaluate(&expr, Some(&address)).unwrap_err(), "H0702");
+        server.join().unwrap();
+    }
+
+    #[test_case("http://localhost:1/api", None, "H0401")]
+    #[test_case("http://localhost:1/other", Some("http://localhost:1/api"), "H0402")]
+    #[test_case("http://localhost:1/apis", Some("http://localhost:1/api"), "H0402")]
+    #[test_case("http://localhost:1.evil.org", Some("http://localhost:1"), "H0402")]
+    #[test_case(
+        "http://localhost:1/api/items",
+        Some("http://localhost:1/api"),
+        "H0702"
+    )]
+    fn allowed_urls(url: &str, allowed: Option<&str>, code: &str) {
+        assert_eq!(
+            evaluate(&format!("$fetch('{url}')"), allowed).unwrap_err(),
+            code
+        );
+    }
+
+    #[test]
+    fn function_policy() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$fetch('http://localhost:1')", &arena).unwrap();
+        jsonata.allow_fetch(["http://localhost:1"]);
+        jsonata.set_function_policy(FunctionPolicy::deny(["fetch"]));
+        let error = jsonata.evaluate(None, None).unwrap_err();
+        assert_eq!(error.code(), "H0401");
+    }
+
+    #[test_case("$fetch(1)")]
+    #[test_case("$fetch('http://localhost:1', 'GET')")]
+    #[test_case("$fetch('http://localhost:1', {'headers': {'x-count': 1}})")]
+    fn bad_arguments(expr: &str) {
+        assert_eq!(
+            evaluate(expr, Some("http://localhost:1")).unwrap_err(),
+            "T0410"
+        );
+    }
+}
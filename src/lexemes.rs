@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::evaluator::value::serialize::{
+    DumpFormatter, PrettyFormatter, SerializeOptions, Serializer,
+};
+use crate::parser;
+use crate::{JsonAta, Result, Value};
+
+/// The original text of numbers parsed from inputs, keyed by the address of their value, so they
+/// can be recognised when they're passed through to a result unchanged.
+pub(crate) type Lexemes<'a> = RefCell<HashMap<usize, &'a str>>;
+
+impl<'a> JsonAta<'a> {
+    /// Keeps the text of the numbers in inputs, such as `1.10` or `1e2`, so that numbers which are
+    /// passed through to a result unchanged are written exactly as they were by
+    /// [`JsonAta::serialize`]. Numbers computed by the expression are formatted as usual.
+    pub fn set_preserve_numbers(&mut self, preserve: bool) {
+        self.lexemes = preserve.then(Lexemes::default);
+    }
+
+    /// Serializes a result of the expression like [`Value::serialize`], except that numbers taken
+    /// from the input are written with their original text when
+    /// [`JsonAta::set_preserve_numbers`] is enabled.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new("{ 'price': price, 'total': price * 2 }", &arena)?;
+    /// jsonata.set_preserve_numbers(true);
+    ///
+    /// let result = jsonata.evaluate(Some(r#"{ "price": 1.10 }"#), None)?;
+    /// assert_eq!(jsonata.serialize(result, false), r#"{"price":1.10,"total":2.2}"#);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn serialize(&self, value: &'a Value<'a>, pretty: bool) -> String {
+        let lexemes = self.lexemes.as_ref().map(RefCell::borrow);
+        let lexemes = lexemes.as_deref();
+        if pretty {
+            Serializer::new(PrettyFormatter::default(), false)
+                .with_lexemes(lexemes)
+                .serialize(value)
+                .expect("Shouldn't fail")
+        } else {
+            Serializer::new(DumpFormatter, false)
+                .with_lexemes(lexemes)
+                .serialize(value)
+                .expect("Shouldn't fail")
+        }
+    }
+
+    /// Serializes a result of the expression like [`JsonAta::serialize`], written as `options`
+    /// say, see [`SerializeOptions`].
+    pub fn serialize_with(&self, value: &'a Value<'a>, options: &SerializeOptions) -> String {
+        let lexemes = self.lexemes.as_ref().map(RefCell::borrow);
+        options.to_string(value, lexemes.as_deref())
+    }
+
+    /// Serializes a result of the expression like [`JsonAta::serialize_with`], writing it to
+    /// `writer` as it goes, like [`Value::serialize_to`].
+    pub fn serialize_to(
+        &self,
+        value: &'a Value<'a>,
+        writer: &mut impl std::io::Write,
+        options: &SerializeOptions,
+    ) -> std::io::Result<()> {
+        let lexemes = self.lexemes.as_ref().map(RefCell::borrow);
+        options.to_writer(value, lexemes.as_deref(), writer)
+    }
+
+    /// Records the text of the numbers in `value`, which was parsed from `source`.
+    pub(crate) fn record_lexemes(&self, source: &str, value: &'a Value<'a>) -> Result<()> {
+        let Some(ref lexemes) = self.lexemes else {
+            return Ok(());
+        };
+
+        let mut numbers = vec![];
+        collect_numbers(value, &mut numbers);
+        let texts = parser::number_lexemes(source)?;
+
+        // An input that computes anything, like `1 + 2` or `[1..3]`, doesn't have a number for
+        // every lexeme, and there's no telling which is which
+        if numbers.len() != texts.len() {
+            return Ok(());
+        }
+
+        let mut lexemes = lexemes.borrow_mut();
+        for (number, text) in numbers.into_iter().zip(texts) {
+            if text.parse::<f64>().ok() == Some(number.as_f64()) {
+                lexemes.insert(number as *const Value as usize, self.arena.alloc_str(text));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Objects keep their keys in insertion order, so the numbers are in the order they were written
+fn collect_numbers<'a>(value: &'a Value<'a>, numbers: &mut Vec<&'a Value<'a>>) {
+    match value {
+        _ if value.is_number() => numbers.push(value),
+        Value::Array(..) => value
+            .members()
+            .for_each(|member| collect_numbers(member, numbers)),
+        Value::Object(..) => value
+            .entries()
+            .for_each(|(_, value)| collect_numbers(value, numbers)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn preserved(expr: &str, input: &str) -> String {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_preserve_numbers(true);
+        let result = jsonata.evaluate(Some(input), None).unwrap();
+        jsonata.serialize(result, false)
+    }
+
+    #[test_case(
+        "$",
+        r#"{"a": 1.10, "b": [1e2, -0.50, 3]}"#,
+        r#"{"a":1.10,"b":[1e2,-0.50,3]}"#
+    )]
+    #[test_case("b[0]", r#"{"b": [1E+2]}"#, "1E+2")]
+    #[test_case("b", r#"{"b": [1.0, 2.00]}"#, "[1.0,2.00]")]
+    #[test_case("a + 0", r#"{"a": 1.10}"#, "1.1")]
+    #[test_case("[a, a * 10]", r#"{"a": 1.10}"#, "[1.10,11]")]
+    #[test_case("$", "[1.50, 2 + 1.50]", "[1.5,3.5]")]
+    #[test_case("$", "- 1.50", "-1.5")]
+    fn passes_through(expr: &str, input: &str, expected: &str) {
+        assert_eq!(preserved(expr, input), expected);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+        let result = jsonata.evaluate(Some("[1.10, 1e2]"), None).unwrap();
+        assert_eq!(jsonata.serialize(result, false), "[1.1,100]");
+    }
+
+    #[test]
+    fn pretty() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$", &arena).unwrap();
+        jsonata.set_preserve_numbers(true);
+        let result = jsonata.evaluate(Some(r#"{"a": 1.10}"#), None).unwrap();
+        assert_eq!(jsonata.serialize(result, true), "{\n  \"a\": 1.10\n}");
+    }
+}
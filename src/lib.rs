@@ -1,44 +1,347 @@
 #![cfg_attr(not(doctest), doc = include_str!("../README.md"))]
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
 
 use bumpalo::Bump;
 
+mod ast_json;
+mod batch;
+mod builder;
+mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "js-compat")]
+pub mod compat;
+pub mod conformance;
+mod cost;
+mod cursor;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod documents;
 mod errors;
 mod evaluator;
+mod explain;
+#[cfg(feature = "fetch")]
+mod fetch;
+mod lexemes;
+mod library;
+mod lint;
+#[cfg(feature = "icu")]
+mod locale;
+#[cfg(feature = "serde")]
+pub mod msgpack;
+mod optimize;
+mod owned;
 mod parser;
+mod patch;
+mod paths;
+mod policy;
+pub mod prelude;
+mod profile;
+mod provider;
+mod query;
+#[cfg(feature = "simd-json")]
+mod simd;
+mod snapshot;
+mod stream;
+mod strict;
+#[cfg(feature = "test-suite")]
+pub mod test_suite;
+mod tokens;
+mod typecheck;
+mod warnings;
+#[cfg(feature = "xml")]
+pub mod xml;
 
+/// The AST produced by the parser, exposed for tooling. This isn't part of the stable API, see
+/// [`prelude`].
+#[cfg(feature = "unstable-ast")]
+pub mod ast {
+    pub use crate::parser::ast::*;
+    pub use crate::parser::visit::{walk_node, walk_node_mut, Visitor, VisitorMut};
+    pub use crate::parser::{parse, parse_recovering, parse_with_operators};
+}
+
+pub use batch::Batch;
+pub use builder::JsonAtaBuilder;
+pub use cache::ExpressionCache;
+pub use cost::Cost;
+pub use cursor::{Checkpoint, NdjsonCursor};
+pub use documents::Documents;
 pub use errors::Error;
+pub use evaluator::bytecode::Backend;
 pub use evaluator::functions::FunctionContext;
+pub use evaluator::integer::Integer;
+pub use evaluator::random::RandomSource;
+pub use evaluator::trace::TraceStep;
+pub use evaluator::units::StringUnits;
+pub use evaluator::value::serialize::SerializeOptions;
 pub use evaluator::value::{ArrayFlags, Value};
+pub use evaluator::watchdog::{Progress, WatchdogAction};
+pub use library::Library;
+pub use lint::{Diagnostic, DiagnosticKind};
+pub use owned::OwnedValue;
+pub use parser::incremental::IncrementalParser;
+pub use parser::operators::{OperatorFn, Operators};
+pub use patch::Patched;
+pub use policy::FunctionPolicy;
+pub use profile::{DeterministicProfile, Metered};
+pub use provider::{Member, ValueProvider};
+pub use query::query;
+#[cfg(feature = "serde")]
+pub use query::query_value;
+pub use snapshot::Snapshot;
+pub use stream::StreamItems;
+pub use tokens::{tokenize, Span, Token, Tokens};
+pub use warnings::{PrecedenceWarnings, Warning, WarningKind};
 
-use evaluator::{frame::Frame, functions::*, Evaluator};
-use parser::ast::Ast;
+use evaluator::{
+    bytecode, frame::Frame, functions::*, random::DefaultRandom, step_memo::StepMemo,
+    trace::Tracer, value::transcode::ValueSeed, watchdog::Watchdog, Evaluator,
+};
+use parser::ast::{Ast, AstKind};
+use serde::de::DeserializeSeed;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A window into the result of an expression, as returned by [`JsonAta::evaluate_page`].
+#[derive(Debug)]
+pub struct Page<'a> {
+    /// The members of the result that fall within the requested window, always an array.
+    pub items: &'a Value<'a>,
+
+    /// The offset of the first item in the window.
+    pub offset: usize,
+
+    /// The total number of items in the result, if it's known without evaluating the items after
+    /// the window.
+    pub total: Option<usize>,
+}
+
+/// A parsed expression that doesn't borrow an arena, so it can be shared between threads or kept
+/// in an application-wide cache. Each evaluation allocates its values in an arena provided by the
+/// caller.
+#[derive(Debug, Clone)]
+pub struct CompiledExpression {
+    ast: Arc<Ast>,
+    program: Option<Arc<bytecode::Program>>,
+}
+
+impl CompiledExpression {
+    pub fn new(expr: &str) -> Result<CompiledExpression> {
+        Ok(Self {
+            ast: Arc::new(parser::parse(expr)?),
+            program: None,
+        })
+    }
+
+    /// Parses an expression, recovering from syntax errors rather than stopping at the first, and
+    /// returns what could be parsed of it along with every syntax error, such as for an editor to
+    /// show them all at once. The expression is only meant to be evaluated if there are none.
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let (expression, errors) = CompiledExpression::new_recovering("[a, (b + ), c");
+    /// let codes: Vec<_> = errors.iter().map(|error| error.code()).collect();
+    /// assert_eq!(codes, ["S0211", "S0203"]);
+    /// assert_eq!(expression.ast_json()["expressions"].as_array().unwrap().len(), 3);
+    /// ```
+    pub fn new_recovering(expr: &str) -> (CompiledExpression, Vec<Error>) {
+        let (ast, errors) = parser::parse_recovering(expr);
+        let expression = Self {
+            ast: Arc::new(ast),
+            program: None,
+        };
+        (expression, errors)
+    }
+
+    /// Selects the backend used to evaluate the expression, compiling it to bytecode if needed.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.program = match backend {
+            Backend::TreeWalker => None,
+            Backend::Bytecode => bytecode::compile(&self.ast).map(Arc::new),
+        };
+        self
+    }
+
+    /// The backend that will actually be used, which is the tree-walker if the bytecode backend
+    /// was selected but the expression couldn't be compiled.
+    pub fn backend(&self) -> Backend {
+        if self.program.is_some() {
+            Backend::Bytecode
+        } else {
+            Backend::TreeWalker
+        }
+    }
+
+    /// The parsed expression, which isn't part of the stable API, see [`ast`].
+    #[cfg(feature = "unstable-ast")]
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Creates a [`JsonAta`] for evaluating the expression in `arena`, without parsing it again.
+    pub fn bind<'a>(&self, arena: &'a Bump) -> JsonAta<'a> {
+        JsonAta::from_ast(self.ast.clone(), self.program.clone(), arena)
+    }
+
+    pub fn evaluate<'a>(&self, input: Option<&str>, arena: &'a Bump) -> Result<&'a Value<'a>> {
+        self.bind(arena).evaluate(input, None)
+    }
+
+    /// Evaluates the expression in `arena` after resetting it, and passes the result to `f`. This
+    /// is how to evaluate in a loop without the arena growing with each evaluation or being
+    /// allocated again: the memory of the previous evaluation is reused, which is safe as the
+    /// result can't outlive `f`.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("$sum(items.price)")?;
+    /// let mut arena = Bump::new();
+    ///
+    /// let mut totals = vec![];
+    /// for input in [r#"{"items": [{"price": 1}]}"#, r#"{"items": [{"price": 2}, {"price": 3}]}"#] {
+    ///     totals.push(expression.evaluate_in(Some(input), &mut arena, |total| total.as_f64())?);
+    /// }
+    /// assert_eq!(totals, [1.0, 5.0]);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Anything else allocated in the arena is reset too, so it should be dedicated to these
+    /// evaluations.
+    pub fn evaluate_in<R>(
+        &self,
+        input: Option<&str>,
+        arena: &mut Bump,
+        f: impl for<'a> FnOnce(&'a Value<'a>) -> R,
+    ) -> Result<R> {
+        arena.reset();
+        let result = self.evaluate(input, arena)?;
+        Ok(f(result))
+    }
+}
+
 pub struct JsonAta<'a> {
-    ast: Ast,
+    ast: Arc<Ast>,
+    program: Option<Arc<bytecode::Program>>,
     frame: Frame<'a>,
     arena: &'a Bump,
+    watchdog: Option<Rc<Watchdog<'a>>>,
+    tracer: Option<Rc<Tracer<'a>>>,
+    random: Option<Rc<RefCell<dyn RandomSource + 'a>>>,
+    random_seed: Option<u64>,
+    policy: FunctionPolicy,
+    max_depth: Option<usize>,
+    time_limit: Option<usize>,
+    gas_limit: Option<u64>,
+    step_memo: Option<Rc<StepMemo<'a>>>,
+    #[cfg(feature = "parallel")]
+    parallel: Option<Rc<evaluator::parallel::Parallel>>,
+    string_units: StringUnits,
+    #[cfg(feature = "icu")]
+    locale: Option<Rc<locale::Locale>>,
+    #[cfg(feature = "fetch")]
+    fetch: Option<Rc<fetch::Fetch>>,
+    lexemes: Option<lexemes::Lexemes<'a>>,
+    #[cfg(feature = "decimal")]
+    decimal: bool,
 }
 
 impl<'a> JsonAta<'a> {
     pub fn new(expr: &str, arena: &'a Bump) -> Result<JsonAta<'a>> {
-        Ok(Self {
-            ast: parser::parse(expr)?,
+        Ok(Self::from_ast(Arc::new(parser::parse(expr)?), None, arena))
+    }
+
+    /// Parses `expr` with the registered `operators` as well as the built-in ones, binding the
+    /// function of each operator for it to call, see [`Operators`].
+    pub fn with_operators(
+        expr: &str,
+        arena: &'a Bump,
+        operators: &Operators,
+    ) -> Result<JsonAta<'a>> {
+        let ast = parser::parse_with_operators(expr, operators)?;
+        let jsonata = Self::from_ast(Arc::new(ast), None, arena);
+        for (symbol, arity, implementation) in operators.implementations() {
+            jsonata.register_function(symbol, arity, implementation);
+        }
+        Ok(jsonata)
+    }
+
+    fn from_ast(
+        ast: Arc<Ast>,
+        program: Option<Arc<bytecode::Program>>,
+        arena: &'a Bump,
+    ) -> JsonAta<'a> {
+        Self {
+            ast,
+            program,
             frame: Frame::new(),
             arena,
-        })
+            watchdog: None,
+            tracer: None,
+            random: None,
+            random_seed: None,
+            policy: FunctionPolicy::AllowAll,
+            max_depth: None,
+            time_limit: None,
+            gas_limit: None,
+            step_memo: None,
+            #[cfg(feature = "parallel")]
+            parallel: None,
+            string_units: StringUnits::default(),
+            #[cfg(feature = "icu")]
+            locale: None,
+            #[cfg(feature = "fetch")]
+            fetch: None,
+            lexemes: None,
+            #[cfg(feature = "decimal")]
+            decimal: false,
+        }
     }
 
+    /// The parsed expression, which isn't part of the stable API, see [`ast`].
+    #[cfg(feature = "unstable-ast")]
     pub fn ast(&self) -> &Ast {
         &self.ast
     }
 
+    /// Selects the backend used to evaluate the expression, see [`CompiledExpression::with_backend`].
+    ///
+    /// The bytecode backend doesn't track evaluation depth, time, gas or progress, so evaluations
+    /// with a depth, time or gas limit, a watchdog or a trace callback, always use the tree-walker.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.program = match backend {
+            Backend::TreeWalker => None,
+            Backend::Bytecode => bytecode::compile(&self.ast).map(Arc::new),
+        };
+    }
+
+    /// The backend that will be used to evaluate the expression.
+    pub fn backend(&self) -> Backend {
+        if self.program.is_some() {
+            Backend::Bytecode
+        } else {
+            Backend::TreeWalker
+        }
+    }
+
+    /// Binds a variable, without its `$`, so that host code can pass parameters to the expression
+    /// rather than building them into its text. Bindings last for every later evaluation.
     pub fn assign_var(&self, name: &str, value: &'a Value<'a>) {
         self.frame.bind(name, value)
     }
 
+    /// Binds a variable to a JSON value, converting it into the arena.
+    pub fn assign_json(&self, name: &str, value: &serde_json::Value) {
+        self.assign_var(name, Value::from_json(self.arena, value))
+    }
+
     pub fn register_function(
         &self,
         name: &str,
@@ -51,31 +354,164 @@ impl<'a> JsonAta<'a> {
         );
     }
 
-    fn json_value_to_value(&self, json_value: &serde_json::Value) -> &'a mut Value<'a> {
-        return match json_value {
-            serde_json::Value::Null => Value::null(self.arena),
-            serde_json::Value::Bool(b) => Value::bool(self.arena, *b),
-            serde_json::Value::Number(n) => Value::number(self.arena, n.as_f64().unwrap()),
-            serde_json::Value::String(s) => Value::string(self.arena, s),
+    /// Registers a callback which is invoked every `interval` evaluated nodes with the progress of
+    /// the evaluation so far. Returning [`WatchdogAction::Cancel`] stops the evaluation with an
+    /// `H0301` error. The callback can evaluate with the same [`JsonAta`], but isn't invoked for
+    /// that evaluation.
+    pub fn set_watchdog(
+        &mut self,
+        interval: u64,
+        callback: impl FnMut(&Progress) -> WatchdogAction + 'a,
+    ) {
+        self.watchdog = Some(Rc::new(Watchdog::new(interval, callback)));
+    }
+
+    /// Registers a callback which is invoked with each node of the expression once it has been
+    /// evaluated, with the values it was evaluated against and to, for building debuggers on. A
+    /// node which fails to evaluate isn't reported, the error says where it failed instead.
+    ///
+    /// Steps are reported as their evaluation finishes, so a node comes after the nodes within it,
+    /// which have a greater [`TraceStep::depth`].
+    pub fn set_trace(&mut self, callback: impl FnMut(&TraceStep<'a>) + 'a) {
+        self.tracer = Some(Rc::new(Tracer::new(callback)));
+    }
 
-            serde_json::Value::Array(a) => {
-                let array = Value::array_with_capacity(self.arena, a.len(), ArrayFlags::empty());
-                for v in a.iter() {
-                    array.push(self.json_value_to_value(v))
-                }
+    /// Replaces the source of the random numbers used by functions such as `$random` and
+    /// `$shuffle`, for every subsequent evaluation.
+    pub fn set_random_source(&mut self, random: impl RandomSource + 'a) {
+        self.random = Some(Rc::new(RefCell::new(random)));
+        self.random_seed = None;
+    }
 
-                return array;
-            }
-            serde_json::Value::Object(o) => {
-                let object = Value::object_with_capacity(self.arena, o.len());
-                for (k, v) in o.iter() {
-                    object.insert(k, self.json_value_to_value(v));
-                }
-                return object;
-            }
-        };
+    /// Seeds the random numbers used by functions such as `$random` and `$shuffle`, so that each
+    /// subsequent evaluation of the same input has the same result, whatever was evaluated before
+    /// it. Every evaluation starts again from the seed.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new("[$random(), $shuffle([1..10])]", &arena)?;
+    /// jsonata.set_random_seed(42);
+    /// let first = jsonata.evaluate(None, None)?.serialize(false);
+    /// assert_eq!(jsonata.evaluate(None, None)?.serialize(false), first);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.random = None;
+        self.random_seed = Some(seed);
     }
 
+    /// Sets what `$length`, `$substring`, `$substringBefore`, `$substringAfter` and `$pad` count
+    /// the characters of strings in, for every subsequent evaluation. By default they count code
+    /// points, as jsonata.js does, so `$length("e\u0301")` is 2, whereas it's 1 in graphemes.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::{JsonAta, StringUnits};
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new(r#"$length("🇬🇧")"#, &arena)?;
+    /// assert_eq!(jsonata.evaluate(None, None)?.as_f64(), 2.0);
+    ///
+    /// jsonata.set_string_units(StringUnits::Graphemes);
+    /// assert_eq!(jsonata.evaluate(None, None)?.as_f64(), 1.0);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn set_string_units(&mut self, string_units: StringUnits) {
+        self.string_units = string_units;
+    }
+
+    /// Restricts the built-in functions the expression can call in subsequent evaluations, see
+    /// [`FunctionPolicy`].
+    pub fn set_function_policy(&mut self, policy: FunctionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Limits how deeply each subsequent evaluation can nest, failing with `U1001` once it's
+    /// evaluating more than `max_depth` nodes of the expression within each other, unless it's
+    /// given a limit of its own with [`JsonAta::evaluate_timeboxed`].
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Limits how long each subsequent evaluation can take, to the millisecond, failing with
+    /// `U1001` once it has taken longer than `timeout`, unless it's given a limit of its own with
+    /// [`JsonAta::evaluate_timeboxed`].
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.time_limit = timeout.map(|timeout| timeout.as_millis() as usize);
+    }
+
+    /// Limits the steps each subsequent evaluation can take, failing with `H0302` once it has used
+    /// more than `gas_limit` units of gas. Unlike a time limit, which can be combined with it, the
+    /// limit is reached at the same point on any machine. See [`DeterministicProfile`] for how gas
+    /// is charged.
+    pub fn set_gas_limit(&mut self, gas_limit: Option<u64>) {
+        self.gas_limit = gas_limit;
+    }
+
+    /// Remembers the result of each step of a path against each item it's evaluated against, so
+    /// that later evaluations against the same items reuse them rather than evaluating the step
+    /// again. This suits re-evaluating an expression as parts of a large input are edited, such
+    /// as in a UI, with the edits made by [`Value::replace_at`], which shares what isn't edited
+    /// with the previous input. Steps which depend on anything other than their item, such as
+    /// variables which aren't bound within them, are always evaluated.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::{JsonAta, Value};
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new("orders.$sum(items.price)", &arena)?;
+    /// jsonata.set_incremental(true);
+    ///
+    /// let input = r#"{"orders": [{"items": [{"price": 2}]}, {"items": [{"price": 3}]}]}"#;
+    /// let input = JsonAta::new("$", &arena)?.evaluate(Some(input), None)?;
+    /// assert_eq!(jsonata.evaluate_value(input)?.serialize(false), "[2,3]");
+    ///
+    /// // Only the second order is summed again
+    /// let input = input.replace_at(&arena, &["orders", "1", "items", "0", "price"], Value::number(&arena, 5))?;
+    /// assert_eq!(jsonata.evaluate_value(input)?.serialize(false), "[2,5]");
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Values are never changed once they're in the arena, which is what makes a result for the
+    /// same item valid, so they mustn't be changed by other means. The remembered results are
+    /// only dropped once an evaluation doesn't use them.
+    pub fn set_incremental(&mut self, incremental: bool) {
+        self.step_memo = incremental.then(|| Rc::new(StepMemo::new(&self.ast)));
+    }
+
+    /// Evaluates the steps of paths against inputs of at least `threshold` items on the threads of
+    /// rayon's global pool, or always on the calling thread if it's `None`, which is the default.
+    /// Only steps which depend on nothing but their item and don't create functions are evaluated
+    /// in parallel, such as `orders.{"id": id, "total": $sum(items.price)}`, and only when none of
+    /// the items are functions. Each thread evaluates in an arena of its own and the results are
+    /// copied into this one, so it's worth it for steps which are expensive compared to the size
+    /// of their results.
+    ///
+    /// The steps are evaluated as usual when the evaluation is limited, traced or incremental, as
+    /// those need to see each step as it's evaluated.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new("$.($ * $)", &arena)?;
+    /// jsonata.set_parallel_threshold(Some(1000));
+    ///
+    /// let input = JsonAta::new("[1..10000]", &arena)?.evaluate(None, None)?;
+    /// let result = jsonata.evaluate_value(input)?;
+    /// assert_eq!(result.len(), 10000);
+    /// assert_eq!(result.get_member(9999).as_f64(), 1e8);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel_threshold(&mut self, threshold: Option<usize>) {
+        self.parallel = threshold
+            .map(|threshold| Rc::new(evaluator::parallel::Parallel::new(&self.ast, threshold)));
+    }
+
+    /// Evaluates the expression against the JSON `input`, if there is one, after binding each of
+    /// `bindings` like [`JsonAta::assign_json`].
     pub fn evaluate(
         &self,
         input: Option<&str>,
@@ -83,93 +519,486 @@ impl<'a> JsonAta<'a> {
     ) -> Result<&'a Value<'a>> {
         if let Some(bindings) = bindings {
             for (key, json_value) in bindings.iter() {
-                let value = self.json_value_to_value(json_value);
-                self.assign_var(key, value);
+                self.assign_json(key, json_value);
             }
         };
 
         self.evaluate_timeboxed(input, None, None)
     }
 
+    /// Evaluates the expression against a value which is already in the arena, such as the result
+    /// of another expression, without serializing and parsing it again.
+    pub fn evaluate_value(&self, input: &'a Value<'a>) -> Result<&'a Value<'a>> {
+        self.evaluate_input(input, None, None)
+    }
+
+    /// Evaluates the expression against the JSON `input` like [`JsonAta::evaluate`], but with the
+    /// strings in it which have no escapes borrowed from it rather than copied, which saves memory
+    /// on large inputs which are mostly passed through. The input has to outlive the arena.
+    ///
+    /// Only strict JSON is borrowed from, with a repeated key replacing the earlier one rather
+    /// than being an error. Other inputs, and inputs in decimal mode, are parsed as they are by
+    /// `evaluate`.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// // Read before the arena is created, so it outlives it
+    /// let input = String::from(r#"{"orders": [{"id": "a1"}, {"id": "b2"}]}"#);
+    ///
+    /// let arena = Bump::new();
+    /// let jsonata = JsonAta::new("orders.id", &arena)?;
+    /// let result = jsonata.evaluate_borrowed(&input)?;
+    /// assert_eq!(result.serialize(false), r#"["a1","b2"]"#);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn evaluate_borrowed(&self, input: &'a str) -> Result<&'a Value<'a>> {
+        let input = self.parse_input_borrowed(input)?;
+        self.evaluate_input(input, None, None)
+    }
+
+    /// Evaluates the expression and returns at most `limit` items of the result, starting at
+    /// `offset`. A result that isn't an array is treated as a sequence of one item, and an
+    /// undefined result as an empty sequence.
+    ///
+    /// A path such as `orders.{"id": id}` is evaluated one member of its first step at a time,
+    /// and stops once the window is full, so nothing after the window is evaluated; the members
+    /// before it still are, to find where it starts. The total is then only given if the window
+    /// reaches the end of the result. Any other expression is evaluated in full and the window
+    /// taken from its result.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let jsonata = JsonAta::new(r#"[1..1000].{"n": $}"#, &arena)?;
+    /// let page = jsonata.evaluate_page(None, 20, 2)?;
+    /// assert_eq!(page.items.serialize(false), r#"[{"n":21},{"n":22}]"#);
+    /// assert_eq!(page.total, None);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn evaluate_page(
+        &self,
+        input: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<'a>> {
+        if let Some((head, tail)) = split_windowed_path(&self.ast) {
+            let input = self.parse_input(input)?;
+            let evaluator = self.evaluator(None, None)?;
+            let page = self.evaluate_path_page(&evaluator, input, &head, &tail, offset, limit);
+            if let Some(ref step_memo) = self.step_memo {
+                step_memo.finish_evaluation();
+            }
+            return page;
+        }
+
+        let result = self.evaluate_timeboxed(input, None, None)?;
+        Ok(self.page_of(result, offset, limit))
+    }
+
+    /// Evaluates `tail` against each member of the result of `head` in turn, collecting the items
+    /// in the window and stopping once it's full.
+    fn evaluate_path_page(
+        &self,
+        evaluator: &Evaluator<'a>,
+        input: &'a Value<'a>,
+        head: &Ast,
+        tail: &Ast,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Page<'a>> {
+        let input = self.bind_input(input);
+        let members = evaluator.evaluate(head, input, &self.frame)?;
+        let members: &Value = if members.is_undefined() {
+            Value::array(self.arena, ArrayFlags::SEQUENCE)
+        } else {
+            Value::wrap_in_array_if_needed(self.arena, members, ArrayFlags::SEQUENCE)
+        };
+
+        let items = Value::array(self.arena, ArrayFlags::SEQUENCE);
+        let mut first = None;
+        let mut count = 0;
+
+        for member in members.members() {
+            // A single array item is spread if it turns out to be the only one, so it doesn't fill
+            // the window until there's another item after it
+            let single_array = count == 1 && first.is_some_and(Value::is_array);
+            if count >= offset.saturating_add(limit) && !single_array {
+                return Ok(Page {
+                    items,
+                    offset,
+                    total: None,
+                });
+            }
+
+            // Each member is evaluated as a single input, even if it's an array
+            let member = if member.is_array() {
+                Value::wrap_in_array(self.arena, member, ArrayFlags::WRAPPED)
+            } else {
+                member
+            };
+
+            let result = evaluator.evaluate(tail, member, &self.frame)?;
+            let result: &Value = if result.is_undefined() {
+                continue;
+            } else if result.is_array() && !result.has_flags(ArrayFlags::CONS) {
+                result
+            } else {
+                Value::wrap_in_array(self.arena, result, ArrayFlags::SEQUENCE)
+            };
+
+            for item in result.members() {
+                if count >= offset && count < offset.saturating_add(limit) {
+                    items.push(item);
+                }
+                first = first.or(Some(item));
+                count += 1;
+            }
+        }
+
+        // A path whose only item is an array results in that array rather than a sequence of it
+        match first {
+            Some(first) if count == 1 && first.is_array() => Ok(self.page_of(first, offset, limit)),
+            _ => Ok(Page {
+                items,
+                offset,
+                total: Some(count),
+            }),
+        }
+    }
+
+    /// Takes the window from a result which has been evaluated in full.
+    fn page_of(&self, result: &'a Value<'a>, offset: usize, limit: usize) -> Page<'a> {
+        let result: &Value = if result.is_undefined() {
+            Value::array(self.arena, ArrayFlags::SEQUENCE)
+        } else {
+            Value::wrap_in_array_if_needed(self.arena, result, ArrayFlags::SEQUENCE)
+        };
+
+        Page {
+            items: result.slice(self.arena, offset, limit),
+            offset,
+            total: Some(result.len()),
+        }
+    }
+
     pub fn evaluate_timeboxed(
         &self,
         input: Option<&str>,
         max_depth: Option<usize>,
         time_limit: Option<usize>,
     ) -> Result<&'a Value<'a>> {
-        let input = match input {
+        let input = self.parse_input(input)?;
+        self.evaluate_input(input, max_depth, time_limit)
+    }
+
+    fn parse_input(&self, input: Option<&str>) -> Result<&'a Value<'a>> {
+        match input {
             Some(input) => {
-                let input_ast = parser::parse(input)?;
+                #[cfg(feature = "simd-json")]
+                if let Some(value) = self.parse_large_input(input) {
+                    self.record_lexemes(input, value)?;
+                    return Ok(value);
+                }
+
                 let evaluator = Evaluator::new(None, self.arena, None, None);
-                evaluator.evaluate(&input_ast, Value::undefined(), &Frame::new())?
+                #[cfg(feature = "decimal")]
+                let evaluator = evaluator.with_decimal(self.decimal);
+                let value = parse_value_with(input, &evaluator)?;
+                self.record_lexemes(input, value)?;
+                Ok(value)
             }
-            None => Value::undefined(),
+            None => Ok(Value::undefined()),
+        }
+    }
+
+    fn parse_input_borrowed(&self, input: &'a str) -> Result<&'a Value<'a>> {
+        #[cfg(feature = "decimal")]
+        if self.decimal {
+            return self.parse_input(Some(input));
+        }
+
+        let mut deserializer = serde_json::Deserializer::from_str(input);
+        let value = ValueSeed::borrowing(self.arena)
+            .deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|()| value));
+        match value {
+            Ok(value) => {
+                self.record_lexemes(input, value)?;
+                Ok(value)
+            }
+            Err(..) => self.parse_input(Some(input)),
+        }
+    }
+
+    fn evaluate_input(
+        &self,
+        input: &'a Value<'a>,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> Result<&'a Value<'a>> {
+        let evaluator = self.evaluator(max_depth, time_limit)?;
+        self.evaluate_with(&evaluator, input)
+    }
+
+    /// Binds the built-in functions and creates an evaluator for the expression.
+    fn evaluator(
+        &self,
+        max_depth: Option<usize>,
+        time_limit: Option<usize>,
+    ) -> Result<Evaluator<'a>> {
+        bind_builtins(&self.frame, self.arena, &self.policy);
+
+        let chain_ast = Some(parser::parse(
+            "function($f, $g) { function($x){ $g($f($x)) } }",
+        )?);
+        let evaluator = Evaluator::new(
+            chain_ast,
+            self.arena,
+            max_depth.or(self.max_depth),
+            time_limit.or(self.time_limit),
+        )
+        .with_watchdog(self.watchdog.clone())
+        .with_tracer(self.tracer.clone())
+        .with_random(self.random_source())
+        .with_gas_limit(self.gas_limit)
+        .with_step_memo(self.step_memo.clone())
+        .with_string_units(self.string_units);
+        #[cfg(feature = "icu")]
+        let evaluator = evaluator.with_locale(self.locale.clone());
+        #[cfg(feature = "fetch")]
+        let evaluator = evaluator.with_fetch(self.fetch.clone());
+        #[cfg(feature = "decimal")]
+        let evaluator = evaluator.with_decimal(self.decimal);
+        #[cfg(feature = "parallel")]
+        let evaluator = match self.parallel {
+            Some(ref parallel) if !evaluator.is_limited() => {
+                evaluator.with_parallel(Some((parallel.clone(), self.policy.clone())))
+            }
+            _ => evaluator,
         };
+        Ok(evaluator)
+    }
+
+    /// The source of random numbers for an evaluation, which starts again from the seed if one
+    /// was set.
+    fn random_source(&self) -> Option<Rc<RefCell<dyn RandomSource + 'a>>> {
+        match self.random_seed {
+            Some(seed) => Some(Rc::new(RefCell::new(DefaultRandom::with_seed(seed)))),
+            None => self.random.clone(),
+        }
+    }
+
+    fn evaluate_with(
+        &self,
+        evaluator: &Evaluator<'a>,
+        input: &'a Value<'a>,
+    ) -> Result<&'a Value<'a>> {
+        let input = self.bind_input(input);
+
+        // The bytecode only has `f64` literals, so decimal mode needs the tree-walker
+        #[cfg(feature = "decimal")]
+        let program = self.program.as_ref().filter(|_| !self.decimal);
+        #[cfg(not(feature = "decimal"))]
+        let program = self.program.as_ref();
+
+        let result = match program {
+            Some(program) if !evaluator.is_limited() => {
+                bytecode::run(evaluator, program, input, &self.frame)
+            }
+            _ => evaluator.evaluate(&self.ast, input, &self.frame),
+        };
+        if let Some(ref step_memo) = self.step_memo {
+            step_memo.finish_evaluation();
+        }
+        result
+    }
 
+    /// Binds the input to `$`, returning it as it should be passed to the evaluator.
+    fn bind_input(&self, input: &'a Value<'a>) -> &'a Value<'a> {
         // If the input is an array, wrap it in an array so that it gets treated as a single input
         let input = if input.is_array() {
             Value::wrap_in_array(self.arena, input, ArrayFlags::WRAPPED)
         } else {
             input
         };
+        self.frame.bind("$", input);
+        input
+    }
+}
 
-        macro_rules! bind_native {
-            ($name:literal, $arity:literal, $fn:ident) => {
-                self.frame
-                    .bind($name, Value::nativefn(&self.arena, $name, $arity, $fn));
-            };
-        }
+/// Parses an input or document into `arena`.
+fn parse_value<'a>(source: &str, arena: &'a Bump) -> Result<&'a Value<'a>> {
+    parse_value_with(source, &Evaluator::new(None, arena, None, None))
+}
 
-        self.frame.bind("$", input);
-        bind_native!("abs", 1, fn_abs);
-        bind_native!("append", 2, fn_append);
-        bind_native!("assert", 2, fn_assert);
-        bind_native!("base64decode", 1, fn_base64_decode);
-        bind_native!("base64encode", 1, fn_base64_encode);
-        bind_native!("boolean", 1, fn_boolean);
-        bind_native!("ceil", 1, fn_ceil);
-        bind_native!("contains", 2, fn_contains);
-        bind_native!("count", 1, fn_count);
-        bind_native!("each", 2, fn_each);
-        bind_native!("error", 1, fn_error);
-        bind_native!("exists", 1, fn_exists);
-        bind_native!("filter", 2, fn_filter);
-        bind_native!("floor", 1, fn_floor);
-        bind_native!("join", 2, fn_join);
-        bind_native!("keys", 1, fn_keys);
-        bind_native!("length", 1, fn_length);
-        bind_native!("lookup", 2, fn_lookup);
-        bind_native!("lowercase", 1, fn_lowercase);
-        bind_native!("map", 2, fn_map);
-        bind_native!("max", 1, fn_max);
-        bind_native!("merge", 1, fn_merge);
-        bind_native!("min", 1, fn_min);
-        bind_native!("not", 1, fn_not);
-        bind_native!("number", 1, fn_number);
-        bind_native!("power", 2, fn_power);
-        bind_native!("replace", 4, fn_replace);
-        bind_native!("reverse", 1, fn_reverse);
-        bind_native!("round", 2, fn_round);
-        bind_native!("sort", 2, fn_sort);
-        bind_native!("split", 3, fn_split);
-        bind_native!("sqrt", 1, fn_sqrt);
-        bind_native!("string", 1, fn_string);
-        bind_native!("substring", 3, fn_substring);
-        bind_native!("sum", 1, fn_sum);
-        bind_native!("trim", 1, fn_trim);
-        bind_native!("uppercase", 1, fn_uppercase);
+fn parse_value_with<'a>(source: &str, evaluator: &Evaluator<'a>) -> Result<&'a Value<'a>> {
+    let ast = parser::parse(source)?;
+    evaluator.evaluate(&ast, Value::undefined(), &Frame::new())
+}
 
-        let chain_ast = Some(parser::parse(
-            "function($f, $g) { function($x){ $g($f($x)) } }",
-        )?);
-        let evaluator = Evaluator::new(chain_ast, self.arena, max_depth, time_limit);
-        evaluator.evaluate(&self.ast, input, &self.frame)
+/// Splits a path whose steps each map one item to its own results into its first step and the
+/// rest of it, so [`JsonAta::evaluate_page`] can evaluate the rest against one member at a time.
+fn split_windowed_path(ast: &Ast) -> Option<(Ast, Ast)> {
+    let AstKind::Path(ref steps) = ast.kind else {
+        return None;
+    };
+
+    let steps_are_independent = steps.iter().all(|step| {
+        !step.tuple
+            && step.index.is_none()
+            && step.focus.is_none()
+            && !matches!(step.kind, AstKind::Sort(..))
+    });
+    // The first step of a path is evaluated once against its whole input if it's an array
+    // constructor, so the second can't become the first step of the rest
+    if steps.len() < 2
+        || steps[1].cons_array
+        || !steps_are_independent
+        || ast.predicates.is_some()
+        || ast.group_by.is_some()
+        || ast.keep_singleton_array
+        || ast.keep_array
+    {
+        return None;
     }
+
+    let head = Ast::new(AstKind::Path(steps[..1].to_vec()), ast.char_index);
+    let tail = Ast::new(AstKind::Path(steps[1..].to_vec()), ast.char_index);
+    Some((head, tail))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn evaluate_borrowed() {
+        let input = r#"{"name": "plain", "quoted": "\"escaped\"", "tags": ["a", "b"], "n": 1}"#;
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$", &arena).unwrap();
+        let result = jsonata.evaluate_borrowed(input).unwrap();
+        assert_eq!(
+            result.serialize(false),
+            jsonata
+                .evaluate(Some(input), None)
+                .unwrap()
+                .serialize(false)
+        );
+
+        let is_borrowed = |value: &Value| match value {
+            Value::String(std::borrow::Cow::Borrowed(s)) => {
+                input.as_bytes().as_ptr_range().contains(&s.as_ptr())
+            }
+            _ => false,
+        };
+        assert!(is_borrowed(result.get_entry("name")));
+        assert!(is_borrowed(result.get_entry("tags").get_member(1)));
+        assert!(!is_borrowed(result.get_entry("quoted")));
+    }
+
+    #[test]
+    fn evaluate_borrowed_falls_back_to_the_parser() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$sum($)", &arena).unwrap();
+        assert_eq!(jsonata.evaluate_borrowed("[1..4]").unwrap().as_f64(), 10.0);
+        assert!(matches!(
+            jsonata.evaluate_borrowed("[1, 2"),
+            Err(Error::S0203ExpectedTokenBeforeEnd(..))
+        ));
+    }
+
+    #[test]
+    fn evaluate_in_reuses_the_arena() {
+        let expression =
+            CompiledExpression::new("items.{'name': name, 'total': price * 2}").unwrap();
+        let input = r#"{"items": [{"name": "a", "price": 1}, {"name": "b", "price": 2}]}"#;
+        let mut arena = Bump::new();
+        let evaluate = |arena: &mut Bump| {
+            expression
+                .evaluate_in(Some(input), arena, |result| result.serialize(false))
+                .unwrap()
+        };
+
+        // The arena keeps its largest chunk when it's reset, so it takes a few evaluations to
+        // settle on one large enough for a whole evaluation
+        let expected = evaluate(&mut arena);
+        for _ in 0..3 {
+            evaluate(&mut arena);
+        }
+        let capacity = arena.allocated_bytes();
+        for _ in 0..1000 {
+            assert_eq!(evaluate(&mut arena), expected);
+        }
+        assert_eq!(arena.allocated_bytes(), capacity);
+    }
+
+    #[test]
+    fn evaluate_value_chains_results() {
+        let arena = Bump::new();
+        let first = JsonAta::new("items[price > 1]", &arena).unwrap();
+        let second = JsonAta::new("$sum($.price)", &arena).unwrap();
+
+        let input = r#"{"items": [{"price": 1}, {"price": 2}, {"price": 3}]}"#;
+        let result = first.evaluate(Some(input), None).unwrap();
+        assert_eq!(second.evaluate_value(result).unwrap().as_f64(), 5.0);
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn partial_application_in_pipelines() {
+        let arena = Bump::new();
+        let evaluate = |expr: &str| {
+            let jsonata = JsonAta::new(expr, &arena).unwrap();
+            let input = r#"{"names": ["alpha", "beta"]}"#;
+            jsonata
+                .evaluate(Some(input), None)
+                .unwrap()
+                .serialize(false)
+        };
+
+        assert_eq!(
+            evaluate("'hello world' ~> $substring(?, 0, 5) ~> $uppercase()"),
+            r#""HELLO""#
+        );
+        assert_eq!(
+            evaluate("names ~> $map(?, $substring(?, 0, 2)) ~> $join(?, '-')"),
+            r#""al-be""#
+        );
+        assert_eq!(
+            evaluate("($x := 10; $add := function($a, $b) { $a + $b + $x }; $inc := $add(?, 1); $inc(2))"),
+            "13"
+        );
+    }
+
+    #[test]
+    fn order_by_missing_first_key() {
+        let arena = Bump::new();
+        let evaluate = |expr: &str| {
+            let jsonata = JsonAta::new(expr, &arena).unwrap();
+            let input = r#"[{"b": 1}, {"a": 1, "b": 2}, {"a": 1, "b": 0}]"#;
+            jsonata
+                .evaluate(Some(input), None)
+                .unwrap()
+                .serialize(false)
+        };
+
+        // The first term decides, later ones only break ties, and a missing key goes last
+        assert_eq!(
+            evaluate("$^(a, b)"),
+            r#"[{"a":1,"b":0},{"a":1,"b":2},{"b":1}]"#
+        );
+        assert_eq!(
+            evaluate("$^(>a, b)"),
+            r#"[{"a":1,"b":0},{"a":1,"b":2},{"b":1}]"#
+        );
+        assert_eq!(
+            evaluate("$^(>a, >b)"),
+            r#"[{"a":1,"b":2},{"a":1,"b":0},{"b":1}]"#
+        );
+    }
 
     #[test]
     fn register_function_simple() {
@@ -195,13 +1024,14 @@ mod tests {
         assert_eq!(result.unwrap(), Value::string(&arena, "time for tea"));
     }
 
+    #[cfg(feature = "higher-order")]
     #[test]
     fn register_function_map_squareroot() {
         let arena = Bump::new();
         let jsonata = JsonAta::new("$map([1,4,9,16], $squareroot)", &arena).unwrap();
         jsonata.register_function("squareroot", 1, |ctx, args| {
             let num = &args[0];
-            return Ok(Value::number(ctx.arena, (num.as_f64()).sqrt()));
+            Ok(Value::number(ctx.arena, (num.as_f64()).sqrt()))
         });
 
         let result = jsonata.evaluate(Some(r#"anything"#), None);
@@ -216,13 +1046,14 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "higher-order")]
     #[test]
     fn register_function_filter_even() {
         let arena = Bump::new();
         let jsonata = JsonAta::new("$filter([1,4,9,16], $even)", &arena).unwrap();
         jsonata.register_function("even", 1, |ctx, args| {
             let num = &args[0];
-            return Ok(Value::bool(ctx.arena, (num.as_f64()) % 2.0 == 0.0));
+            Ok(Value::bool(ctx.arena, (num.as_f64()) % 2.0 == 0.0))
         });
 
         let result = jsonata.evaluate(Some(r#"anything"#), None);
@@ -237,6 +1068,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn assign_json() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$config.scale * $sum($config.values)", &arena).unwrap();
+        jsonata.assign_json(
+            "config",
+            &serde_json::json!({"scale": 2, "values": [1, 2, 3]}),
+        );
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap().as_f64(), 12.0);
+    }
+
+    #[test]
+    fn assign_json_parameter() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("orders[total > $config.threshold].id", &arena).unwrap();
+        jsonata.assign_json("config", &serde_json::json!({"threshold": 100}));
+
+        let input = r#"{"orders": [{"id": 1, "total": 50}, {"id": 2, "total": 150}]}"#;
+        let result = jsonata.evaluate(Some(input), None).unwrap();
+        assert_eq!(result.serialize(false), "2");
+
+        // Bindings last for later evaluations, and can be replaced
+        jsonata.assign_json("config", &serde_json::json!({"threshold": 0}));
+        let result = jsonata.evaluate(Some(input), None).unwrap();
+        assert_eq!(result.serialize(false), "[1,2]");
+    }
+
     #[test]
     fn evaluate_with_bindings_simple() {
         let arena = Bump::new();
@@ -279,4 +1140,319 @@ mod tests {
 
         assert_eq!(result.unwrap().as_f64(), 3.0);
     }
+
+    #[test]
+    fn evaluate_page_array() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("items.name", &arena).unwrap();
+
+        let page = jsonata
+            .evaluate_page(
+                Some(r#"{"items": [{"name": "a"}, {"name": "b"}, {"name": "c"}]}"#),
+                1,
+                5,
+            )
+            .unwrap();
+
+        assert_eq!(page.offset, 1);
+        assert_eq!(page.total, Some(3));
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0], "b");
+        assert_eq!(page.items[1], "c");
+    }
+
+    #[test]
+    fn evaluate_page_range() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("[1..1000000]", &arena).unwrap();
+
+        let page = jsonata.evaluate_page(None, 10, 3).unwrap();
+
+        assert_eq!(page.total, Some(1000000));
+        assert_eq!(
+            page.items
+                .members()
+                .map(|v| v.as_f64())
+                .collect::<Vec<f64>>(),
+            vec![11.0, 12.0, 13.0]
+        );
+    }
+
+    #[test]
+    fn evaluate_page_singleton_and_undefined() {
+        let arena = Bump::new();
+
+        let jsonata = JsonAta::new("name", &arena).unwrap();
+        let page = jsonata
+            .evaluate_page(Some(r#"{"name": "a"}"#), 0, 10)
+            .unwrap();
+        assert_eq!(page.total, Some(1));
+        assert_eq!(page.items[0], "a");
+
+        let page = jsonata.evaluate_page(Some(r#"{}"#), 0, 10).unwrap();
+        assert_eq!(page.total, Some(0));
+        assert!(page.items.is_empty());
+    }
+
+    #[test]
+    fn evaluate_page_stops_after_window() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            r#"orders.(id = 4 ? $error("evaluated past the window") : {"n": id})"#,
+            &arena,
+        )
+        .unwrap();
+        let input = r#"{"orders": [{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]}"#;
+
+        let page = jsonata.evaluate_page(Some(input), 1, 2).unwrap();
+        assert_eq!(page.total, None);
+        assert_eq!(page.items.serialize(false), r#"[{"n":2},{"n":3}]"#);
+
+        assert!(jsonata.evaluate_page(Some(input), 1, 3).is_err());
+    }
+
+    #[test_case("orders.items.name")]
+    #[test_case("orders.items")]
+    #[test_case("orders.items[0].name")]
+    #[test_case("orders.[id]")]
+    #[test_case("orders[0].[id, 0]")]
+    #[test_case("orders.(items.[name])")]
+    #[test_case("orders[0].items.[name, $$.owner]")]
+    #[test_case("orders.$count(items)")]
+    #[test_case("$.orders.id")]
+    #[test_case("orders.items.$split(name, '')")]
+    fn evaluate_page_matches_evaluate(expr: &str) {
+        let input = r#"{
+            "owner": "z",
+            "orders": [
+                {"id": 1, "items": [{"name": "ab"}, {"name": "cd"}]},
+                {"id": 2, "items": []},
+                {"id": 3, "items": [{"name": "ef"}]},
+                {"id": 4, "items": {"name": "gh"}}
+            ]
+        }"#;
+
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        let whole = jsonata.evaluate(Some(input), None).unwrap();
+        let whole = jsonata.page_of(whole, 0, usize::MAX);
+        let expected: Vec<String> = whole.items.members().map(|v| v.serialize(false)).collect();
+
+        for offset in 0..=expected.len() {
+            for limit in 0..=expected.len() {
+                let page = jsonata.evaluate_page(Some(input), offset, limit).unwrap();
+                let items: Vec<String> = page.items.members().map(|v| v.serialize(false)).collect();
+                let end = expected.len().min(offset + limit);
+                assert_eq!(items, expected[offset.min(end)..end], "{offset} {limit}");
+                if end == expected.len() {
+                    assert_eq!(page.total, Some(expected.len()), "{offset} {limit}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compiled_expression_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CompiledExpression>();
+    }
+
+    #[test]
+    fn compiled_expression_concurrent_evaluation() {
+        let compiled = CompiledExpression::new("$sum(values) * factor").unwrap();
+
+        let results = std::thread::scope(|scope| {
+            let handles = (1..=4)
+                .map(|factor| {
+                    let compiled = &compiled;
+                    scope.spawn(move || {
+                        let arena = Bump::new();
+                        let input = format!(r#"{{"values": [1, 2, 3], "factor": {}}}"#, factor);
+                        compiled.evaluate(Some(&input), &arena).unwrap().as_f64()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<f64>>()
+        });
+
+        assert_eq!(results, vec![6.0, 12.0, 18.0, 24.0]);
+    }
+
+    #[test]
+    fn random_source() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$shuffle([1..4])", &arena).unwrap();
+        jsonata.set_random_source(|| 0.0);
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), "[4,1,2,3]");
+
+        let mut numbers = [0.5, 0.99, 0.0].into_iter().cycle();
+        jsonata.set_random_source(move || numbers.next().unwrap());
+        let result = jsonata.evaluate(None, None).unwrap();
+        assert_eq!(result.serialize(false), "[3,2,4,1]");
+    }
+
+    #[test]
+    fn random_seed() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("[$random(), $shuffle([1..10])]", &arena).unwrap();
+        let evaluate = |jsonata: &JsonAta| jsonata.evaluate(None, None).unwrap().serialize(false);
+
+        jsonata.set_random_seed(1);
+        let first = evaluate(&jsonata);
+        assert_eq!(evaluate(&jsonata), first);
+
+        jsonata.set_random_seed(2);
+        assert_ne!(evaluate(&jsonata), first);
+
+        // A source replaces the seed
+        jsonata.set_random_source(|| 0.0);
+        assert_eq!(evaluate(&jsonata), "[0,10,1,2,3,4,5,6,7,8,9]");
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn watchdog_reports_progress() {
+        let arena = Bump::new();
+        let mut calls = vec![];
+        {
+            let mut jsonata =
+                JsonAta::new("$map([1..100], function($x) { $x * 2 })", &arena).unwrap();
+            jsonata.set_watchdog(50, |progress| {
+                calls.push(progress.nodes);
+                WatchdogAction::Continue
+            });
+
+            let result = jsonata.evaluate(None, None).unwrap();
+            assert_eq!(result.len(), 100);
+        }
+
+        assert!(calls.len() > 1);
+        assert_eq!(calls[0], 50);
+        assert_eq!(calls[1], 100);
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn watchdog_cancels() {
+        let arena = Bump::new();
+        let mut jsonata =
+            JsonAta::new("$map([1..100000], function($x) { $x * 2 })", &arena).unwrap();
+        jsonata.set_watchdog(1000, |progress| {
+            if progress.nodes >= 5000 {
+                WatchdogAction::Cancel
+            } else {
+                WatchdogAction::Continue
+            }
+        });
+
+        let result = jsonata.evaluate(None, None);
+
+        assert_eq!(result.unwrap_err(), Error::H0301Cancelled(5000));
+    }
+
+    #[test]
+    fn trace_reports_each_step() {
+        let arena = Bump::new();
+        let mut steps = vec![];
+        {
+            let mut jsonata = JsonAta::new("a.b + 1", &arena).unwrap();
+            jsonata.set_backend(Backend::Bytecode);
+            jsonata.set_trace(|step| {
+                steps.push((
+                    step.kind,
+                    step.char_index,
+                    step.depth,
+                    step.input_summary(),
+                    step.output_summary(),
+                ))
+            });
+            let result = jsonata.evaluate(Some(r#"{"a": {"b": 2}}"#), None).unwrap();
+            assert_eq!(result.serialize(false), "3");
+        }
+
+        let input = r#"{"a":{"b":2}}"#.to_string();
+        assert_eq!(
+            steps,
+            [
+                ("name", 0, 3, input.clone(), r#"{"b":2}"#.to_string()),
+                ("name", 2, 3, r#"{"b":2}"#.to_string(), "2".to_string()),
+                ("path", 0, 2, input.clone(), "2".to_string()),
+                ("number", 6, 2, input.clone(), "1".to_string()),
+                ("binary", 4, 1, input, "3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_variables() {
+        let arena = Bump::new();
+        let mut variables = vec![];
+        {
+            let mut jsonata =
+                JsonAta::new("($x := 2; $f := function($y) { $y * $x }; $f(3))", &arena).unwrap();
+            jsonata.set_trace(|step| {
+                if step.kind == "binary" && step.output_summary() == "6" {
+                    variables = step
+                        .variables()
+                        .into_iter()
+                        .map(|(name, value)| (name, value.serialize(false)))
+                        .collect();
+                }
+            });
+            jsonata.evaluate(None, None).unwrap();
+        }
+
+        assert_eq!(
+            variables,
+            [
+                ("$".to_string(), "".to_string()),
+                ("f".to_string(), r#""""#.to_string()),
+                ("x".to_string(), "2".to_string()),
+                ("y".to_string(), "3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_summaries_are_cut_short() {
+        let arena = Bump::new();
+        let mut summaries = vec![];
+        {
+            let mut jsonata = JsonAta::new("[1..100]", &arena).unwrap();
+            jsonata.set_trace(|step| summaries.push(step.output_summary()));
+            jsonata.evaluate(None, None).unwrap();
+        }
+
+        let summary = summaries.last().unwrap();
+        assert_eq!(summary.chars().count(), 81);
+        assert!(summary.starts_with("[1,2,3,"));
+        assert!(summary.ends_with('…'));
+    }
+
+    #[test]
+    fn prelude() {
+        use crate::prelude::*;
+
+        // The prelude doesn't shadow the standard library's `Result`
+        let evaluate = |expr: &str| -> std::result::Result<f64, crate::Error> {
+            let arena = Bump::new();
+            let result = CompiledExpression::new(expr)?.evaluate(Some(r#"{"a": 1}"#), &arena)?;
+            Ok(result.as_f64())
+        };
+        assert_eq!(evaluate("a + 2"), Ok(3.0));
+
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("a + 1", &arena).unwrap();
+        assert_eq!(
+            jsonata
+                .evaluate(Some(r#"{"a": 1}"#), None)
+                .unwrap()
+                .as_f64(),
+            2.0
+        );
+    }
 }
@@ -0,0 +1,194 @@
+//! Libraries of functions written in JSONata, which the host parses once and imports into the
+//! expressions which use them, see [`Library`].
+
+use std::sync::Arc;
+
+use crate::evaluator::bytecode;
+use crate::evaluator::frame::Frame;
+use crate::optimize::has_extras;
+use crate::parser::ast::{Ast, AstKind};
+use crate::parser::visit::{walk_node_mut, VisitorMut};
+use crate::{parser, Error, JsonAta, Result, Value};
+
+/// A library of functions written in JSONata, such as helpers shared by many expressions. Its
+/// source is an expression which evaluates to an object of functions, and which can define other
+/// values for them first:
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{JsonAta, Library};
+/// let phone = Library::new(r#"
+///     (
+///         $digits := function($s) { $join($split($s, "-")) };
+///         {
+///             "normalize": function($p) { "+1" & $digits($p) },
+///             "isValid": function($p) { $length($digits($p)) = 10 }
+///         }
+///     )
+/// "#)?;
+///
+/// let arena = Bump::new();
+/// let mut jsonata = JsonAta::new("contacts.$phone.normalize(phone)", &arena)?;
+/// jsonata.import_library("phone", &phone)?;
+/// let result = jsonata.evaluate(Some(r#"{"contacts": [{"phone": "555-123-4567"}]}"#), None)?;
+/// assert_eq!(result.as_str(), "+15551234567");
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+///
+/// A library is parsed once, and can be shared between threads and imported into any number of
+/// expressions.
+#[derive(Debug, Clone)]
+pub struct Library {
+    ast: Arc<Ast>,
+}
+
+impl Library {
+    pub fn new(source: &str) -> Result<Self> {
+        Ok(Self {
+            ast: Arc::new(parser::parse(source)?),
+        })
+    }
+}
+
+impl JsonAta<'_> {
+    /// Binds `$name` to the object of functions `library` evaluates to, for every subsequent
+    /// evaluation. The functions can call each other, and the built-in functions, as the
+    /// expression can, and fail with `H0503` if the library isn't an object.
+    ///
+    /// Unlike for other objects, the arguments of a call such as `$name.normalize(phone)` are
+    /// evaluated against the context of the call rather than against the library, so it works as
+    /// a function call would anywhere else in a path.
+    pub fn import_library(&mut self, name: &str, library: &Library) -> Result<()> {
+        let mut ast = (*library.ast).clone();
+        Calls { library: name }.visit_node_mut(&mut ast);
+
+        let evaluator = self.evaluator(None, None)?;
+        let frame = Frame::new_with_parent(&self.frame);
+        let functions = evaluator.evaluate(&ast, Value::undefined(), &frame)?;
+        if !functions.is_object() {
+            return Err(Error::H0503InvalidLibrary(name.to_string()));
+        }
+        self.assign_var(name, functions);
+
+        Calls { library: name }.visit_node_mut(Arc::make_mut(&mut self.ast));
+        if self.program.is_some() {
+            self.program = bytecode::compile(&self.ast).map(Arc::new);
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites the calls of the functions of a library, from a step which is the library followed by
+/// a step which calls a function by name, to a step which calls the path to the function, so that
+/// its arguments are evaluated against the same context as the library was.
+struct Calls<'n> {
+    library: &'n str,
+}
+
+impl VisitorMut for Calls<'_> {
+    fn visit_node_mut(&mut self, node: &mut Ast) {
+        walk_node_mut(self, node);
+
+        let AstKind::Path(ref mut steps) = node.kind else {
+            return;
+        };
+        let mut index = 0;
+        while index + 1 < steps.len() {
+            let is_library = !has_extras(&steps[index])
+                && matches!(steps[index].kind, AstKind::Var(ref name) if name == self.library);
+            if is_library && function_path(&mut steps[index + 1]).is_some() {
+                let library = steps.remove(index);
+                if let Some(function) = function_path(&mut steps[index]) {
+                    function.insert(0, library);
+                }
+            }
+            index += 1;
+        }
+    }
+}
+
+/// The path to the function a step calls, if it calls one by its name, as in `name(args)`.
+fn function_path(step: &mut Ast) -> Option<&mut Vec<Ast>> {
+    let AstKind::Function { ref mut proc, .. } = step.kind else {
+        return None;
+    };
+    match proc.kind {
+        AstKind::Path(ref mut path)
+            if matches!(
+                path[..],
+                [Ast {
+                    kind: AstKind::Name(..),
+                    ..
+                }]
+            ) =>
+        {
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::Backend;
+
+    const STRINGS: &str = r#"
+        (
+            $clean := function($s) { $trim($s) };
+            {
+                "shout": function($s) { $uppercase($strings.tidy($s)) & "!" },
+                "tidy": function($s) { $clean($s) },
+                "separator": ", "
+            }
+        )
+    "#;
+
+    fn evaluate(expr: &str, backend: Backend) -> Result<String> {
+        let strings = Library::new(STRINGS)?;
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena)?;
+        jsonata.set_backend(backend);
+        jsonata.import_library("strings", &strings)?;
+        let input = r#"{"names": [" ada ", "grace "], "name": " alan"}"#;
+        Ok(jsonata.evaluate(Some(input), None)?.serialize(false))
+    }
+
+    #[test_case("$strings.shout(name)", r#""ALAN!""#)]
+    #[test_case("names.$strings.tidy($)", r#"["ada","grace"]"#)]
+    #[test_case("names.($strings.shout($))", r#"["ADA!","GRACE!"]"#)]
+    #[test_case("$join(names.$strings.tidy($), $strings.separator)", r#""ada, grace""#)]
+    #[test_case("$exists($strings.tidy)", "true")]
+    #[test_case("$strings.shout(name) & $strings.tidy(name)", r#""ALAN!alan""#)]
+    #[test_case("$strings.missing(name)", "T1006"; "missing function")]
+    fn calls(expr: &str, expected: &str) {
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            let result = evaluate(expr, backend).unwrap_or_else(|e| e.code().to_string());
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn shared_between_expressions() {
+        let strings = Library::new(STRINGS).unwrap();
+        let arena = Bump::new();
+        for (expr, expected) in [("$strings.tidy(' a ')", "a"), ("$strings.shout('b')", "B!")] {
+            let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+            jsonata.import_library("strings", &strings).unwrap();
+            assert_eq!(jsonata.evaluate(None, None).unwrap().as_str(), expected);
+        }
+    }
+
+    #[test_case("[1, 2]", "H0503")]
+    #[test_case("$error('broken')", "D3137")]
+    fn invalid(library: &str, code: &str) {
+        let library = Library::new(library).unwrap();
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$lib", &arena).unwrap();
+        let error = jsonata.import_library("lib", &library).unwrap_err();
+        assert_eq!(error.code(), code);
+    }
+}
@@ -0,0 +1,300 @@
+use bumpalo::Bump;
+
+use crate::parser::ast::{Ast, AstKind, BinaryOp};
+use crate::parser::visit::{walk_node, Visitor};
+use crate::warnings::is_comparison_op;
+use crate::{CompiledExpression, JsonAta, Result};
+
+/// A kind of issue in an expression which doesn't stop it from being evaluated, but which is
+/// probably a mistake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A path compared with a variable that isn't bound by the expression, a built-in function or
+    /// the host, such as `price > $treshold`, which is always false as the variable is undefined.
+    UndefinedVariable,
+
+    /// A variable bound by a block or a parameter of a lambda which hides a variable of the same
+    /// name bound in an enclosing block.
+    ShadowedVariable,
+
+    /// A condition that's a literal, such as `true ? a : b`, so one of the branches is never
+    /// evaluated.
+    UnreachableBranch,
+
+    /// A predicate that's a literal, such as `items[true]`, so it keeps every item or none of
+    /// them. Number literals are indexes rather than predicates, and aren't reported.
+    ConstantPredicate,
+}
+
+/// An issue found by [`CompiledExpression::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+
+    /// The index in the source of the node the diagnostic is about
+    pub char_index: usize,
+
+    pub message: String,
+}
+
+impl CompiledExpression {
+    /// Parses an expression, also returning the diagnostics from linting it.
+    pub fn new_with_diagnostics(expr: &str) -> Result<(CompiledExpression, Vec<Diagnostic>)> {
+        let compiled = CompiledExpression::new(expr)?;
+        let diagnostics = compiled.lint();
+        Ok((compiled, diagnostics))
+    }
+
+    /// Looks for issues which don't stop the expression from being evaluated, but are probably
+    /// mistakes. Variables are only known to be bound if the expression or a built-in function
+    /// binds them, so comparisons with variables the host will assign are reported too; lint with
+    /// [`JsonAta::lint`] after assigning them to avoid that.
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let arena = Bump::new();
+        let diagnostics = self.bind(&arena).lint();
+        diagnostics
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::lint`]. The variables assigned to this expression and the
+    /// functions registered with it are known to be bound.
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        // Binds the built-in functions
+        let _ = self.evaluator(None, None);
+        let is_bound = |name: &str| self.frame.lookup(name).is_some();
+
+        let mut linter = Linter {
+            scopes: vec![vec![]],
+            is_bound: &is_bound,
+            diagnostics: vec![],
+        };
+        linter.visit_node(&self.ast);
+        linter.diagnostics
+    }
+}
+
+struct Linter<'l> {
+    /// The variables bound by the expression in each enclosing block or lambda
+    scopes: Vec<Vec<String>>,
+    is_bound: &'l dyn Fn(&str) -> bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_node(&mut self, ast: &Ast) {
+        match ast.kind {
+            AstKind::Block(..) => {
+                self.scopes.push(vec![]);
+                walk_node(self, ast);
+                self.scopes.pop();
+                return;
+            }
+            AstKind::Lambda { ref args, .. } => {
+                self.scopes.push(vec![]);
+                for arg in args {
+                    if let AstKind::Var(ref name) = arg.kind {
+                        self.bind(name, arg.char_index);
+                    }
+                }
+                walk_node(self, ast);
+                self.scopes.pop();
+                return;
+            }
+            AstKind::Binary(BinaryOp::Bind, ref lhs, _) => {
+                // Bound before the value is visited, so that recursive functions can call
+                // themselves
+                if let AstKind::Var(ref name) = lhs.kind {
+                    self.bind(name, lhs.char_index);
+                }
+            }
+            AstKind::Binary(ref op, ref lhs, ref rhs) if is_comparison_op(op) => {
+                self.check_comparison(lhs, rhs);
+                self.check_comparison(rhs, lhs);
+            }
+            AstKind::Ternary { ref cond, .. } => {
+                if let Some(truthy) = literal_truthiness(cond) {
+                    self.report(
+                        DiagnosticKind::UnreachableBranch,
+                        cond.char_index,
+                        format!(
+                            "The condition is always {}, so the {} branch is never evaluated",
+                            truthy,
+                            if truthy { "false" } else { "true" }
+                        ),
+                    );
+                }
+            }
+            AstKind::Filter(ref predicate) if !is_number(predicate) => {
+                if let Some(truthy) = literal_truthiness(predicate) {
+                    self.report(
+                        DiagnosticKind::ConstantPredicate,
+                        predicate.char_index,
+                        format!(
+                            "The predicate is always {}, so {} kept",
+                            truthy,
+                            if truthy {
+                                "every item is"
+                            } else {
+                                "no items are"
+                            }
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        // Focus and index variables are bound for the rest of the path
+        for name in [&ast.focus, &ast.index].into_iter().flatten() {
+            self.scopes.last_mut().unwrap().push(name.clone());
+        }
+
+        walk_node(self, ast);
+    }
+}
+
+impl Linter<'_> {
+    fn bind(&mut self, name: &str, char_index: usize) {
+        let (scope, enclosing) = self.scopes.split_last().unwrap();
+        if scope.iter().any(|bound| bound == name) {
+            return;
+        }
+        if enclosing.iter().flatten().any(|bound| bound == name) {
+            self.report(
+                DiagnosticKind::ShadowedVariable,
+                char_index,
+                format!(
+                    "`${}` hides the variable of the same name bound in an enclosing block",
+                    name
+                ),
+            );
+        }
+        self.scopes.last_mut().unwrap().push(name.to_string());
+    }
+
+    fn check_comparison(&mut self, var: &Ast, path: &Ast) {
+        let AstKind::Var(ref name) = var.kind else {
+            return;
+        };
+        if !matches!(path.kind, AstKind::Path(..) | AstKind::Name(..)) || self.is_known(name) {
+            return;
+        }
+        self.report(
+            DiagnosticKind::UndefinedVariable,
+            var.char_index,
+            format!(
+                "`${}` isn't bound, so it's undefined and the comparison is always false",
+                name
+            ),
+        );
+    }
+
+    fn is_known(&self, name: &str) -> bool {
+        // `$` and `$$` are the context and the input
+        name.is_empty()
+            || name == "$"
+            || self.scopes.iter().flatten().any(|bound| bound == name)
+            || (self.is_bound)(name)
+    }
+
+    fn report(&mut self, kind: DiagnosticKind, char_index: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            char_index,
+            message,
+        });
+    }
+}
+
+fn is_number(ast: &Ast) -> bool {
+    match ast.kind {
+        AstKind::Number(..) | AstKind::Integer(..) => true,
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(..) => true,
+        _ => false,
+    }
+}
+
+/// Whether a literal is truthy, or `None` if the node isn't a literal.
+fn literal_truthiness(ast: &Ast) -> Option<bool> {
+    match ast.kind {
+        AstKind::Bool(b) => Some(b),
+        AstKind::Null => Some(false),
+        AstKind::String(ref s) => Some(!s.is_empty()),
+        AstKind::Number(n) => Some(n != 0.0),
+        AstKind::Integer(n) => Some(n.get() != 0),
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(n, _) => Some(n != 0.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::Value;
+
+    fn kinds(expr: &str) -> Vec<DiagnosticKind> {
+        CompiledExpression::new(expr)
+            .unwrap()
+            .lint()
+            .into_iter()
+            .map(|diagnostic| diagnostic.kind)
+            .collect()
+    }
+
+    #[test_case("price > $threshold", &[DiagnosticKind::UndefinedVariable])]
+    #[test_case("$threshold = a.b", &[DiagnosticKind::UndefinedVariable])]
+    #[test_case("($threshold := 5; price > $threshold)", &[])]
+    #[test_case("price > $", &[])]
+    #[test_case("items[price > $$.limit]", &[])]
+    #[test_case("$f := function($limit) { price > $limit }", &[])]
+    #[test_case("items@$i[price > $i.min]", &[])]
+    #[test_case("$x > $y", &[])]
+    #[test_case("$count(a) = $sum", &[])]
+    #[test_case("($x := 1; ($x := 2))", &[DiagnosticKind::ShadowedVariable])]
+    #[test_case("($x := 1; $f := function($x) { $x })", &[DiagnosticKind::ShadowedVariable])]
+    #[test_case("($x := 1; $x := $x + 1)", &[])]
+    #[test_case("(($x := 1); ($x := 2))", &[])]
+    #[test_case("true ? a : b", &[DiagnosticKind::UnreachableBranch])]
+    #[test_case("'' ? a", &[DiagnosticKind::UnreachableBranch])]
+    #[test_case("a ? b : c", &[])]
+    #[test_case("items[true]", &[DiagnosticKind::ConstantPredicate])]
+    #[test_case("items[null].name", &[DiagnosticKind::ConstantPredicate])]
+    #[test_case("items[0]", &[])]
+    #[test_case("items[price > 5]", &[])]
+    fn diagnostics(expr: &str, expected: &[DiagnosticKind]) {
+        assert_eq!(kinds(expr), expected);
+    }
+
+    #[test]
+    fn assigned_variables_are_bound() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("price > $threshold", &arena).unwrap();
+        assert_eq!(jsonata.lint().len(), 1);
+        jsonata.assign_var("threshold", Value::number(&arena, 5));
+        assert_eq!(jsonata.lint(), vec![]);
+    }
+
+    #[test]
+    fn alongside_the_compiled_expression() {
+        let (compiled, diagnostics) =
+            CompiledExpression::new_with_diagnostics("a[false] ? b : c").unwrap();
+        assert_eq!(
+            compiled.evaluate(None, &Bump::new()).unwrap(),
+            Value::undefined()
+        );
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                kind: DiagnosticKind::ConstantPredicate,
+                char_index: 2,
+                message: "The predicate is always false, so no items are kept".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Locale-aware casing and collation with [ICU4X](https://github.com/unicode-org/icu4x), see
+//! [`JsonAta::set_locale`].
+//!
+//! Without a locale, `$uppercase` and `$lowercase` use the default Unicode case mappings and
+//! strings are sorted by their code points, as in jsonata.js. With one, the case mappings of its
+//! language are used, such as the dotted and dotless i of Turkish, and strings are sorted in the
+//! order of its collation, so that `"apple"` comes before `"Banana"` and `"é"` next to `"e"`.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use icu_casemap::CaseMapper;
+use icu_collator::{Collator, CollatorOptions};
+
+use crate::{Error, JsonAta, Result};
+
+/// The case mappings and collation of a locale.
+pub struct Locale {
+    locale: icu_locid::Locale,
+    case_mapper: CaseMapper,
+    collator: Collator,
+}
+
+impl Locale {
+    /// Loads the data for a BCP 47 language tag, such as `tr` or `de-u-co-phonebk`.
+    pub fn new(tag: &str) -> Result<Self> {
+        let invalid = || Error::H0502InvalidLocale(tag.to_string());
+        let locale: icu_locid::Locale = tag.parse().map_err(|_| invalid())?;
+        Self::from_locale(locale).ok_or_else(invalid)
+    }
+
+    fn from_locale(locale: icu_locid::Locale) -> Option<Self> {
+        let collator = Collator::try_new(&(&locale).into(), CollatorOptions::new()).ok()?;
+        Some(Self {
+            locale,
+            case_mapper: CaseMapper::new(),
+            collator,
+        })
+    }
+
+    pub fn uppercase(&self, string: &str) -> String {
+        self.case_mapper
+            .uppercase_to_string(string, &self.locale.id)
+    }
+
+    pub fn lowercase(&self, string: &str) -> String {
+        self.case_mapper
+            .lowercase_to_string(string, &self.locale.id)
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator.compare(a, b)
+    }
+
+    /// Loads the data for the same locale again, on other threads, as it can't be shared.
+    pub fn reload(&self) -> impl Fn() -> Rc<Locale> + Send + Sync {
+        let locale = self.locale.clone();
+        move || Rc::new(Self::from_locale(locale.clone()).expect("The locale has loaded before"))
+    }
+}
+
+impl JsonAta<'_> {
+    /// Sets the locale whose case mappings `$uppercase` and `$lowercase` use and whose collation
+    /// `$sort` and the order-by operator sort strings in, for every subsequent evaluation, or
+    /// goes back to the locale-independent behaviour of jsonata.js with `None`. The locale is a
+    /// BCP 47 language tag, such as `tr` or `de-u-co-phonebk`, and anything else is an `H0502`
+    /// error.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let mut jsonata = JsonAta::new(r#"$uppercase("istanbul")"#, &arena)?;
+    /// jsonata.set_locale(Some("tr"))?;
+    /// assert_eq!(jsonata.evaluate(None, None)?.as_str(), "İSTANBUL");
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn set_locale(&mut self, locale: Option<&str>) -> Result<()> {
+        self.locale = locale.map(Locale::new).transpose()?.map(Rc::new);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use crate::JsonAta;
+
+    fn evaluate(expr: &str, locale: Option<&str>) -> String {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_locale(locale).unwrap();
+        jsonata.evaluate(None, None).unwrap().serialize(false)
+    }
+
+    #[test_case(r#"$uppercase("istanbul")"#, None, r#""ISTANBUL""#)]
+    #[test_case(r#"$uppercase("istanbul")"#, Some("tr"), r#""İSTANBUL""#; "turkish uppercase")]
+    #[test_case(r#"$lowercase("DIYARBAKIR")"#, Some("tr"), r#""dıyarbakır""#)]
+    #[test_case(r#"$lowercase("DIYARBAKIR")"#, Some("en"), r#""diyarbakir""#)]
+    #[test_case(r#"$uppercase("straße")"#, Some("de"), r#""STRASSE""#)]
+    fn casing(expr: &str, locale: Option<&str>, expected: &str) {
+        assert_eq!(evaluate(expr, locale), expected);
+    }
+
+    const WORDS: &str = r#"["zebra", "Äpfel", "apple", "Banana", "éclair", "egg"]"#;
+
+    #[test_case(None, r#"["Banana","apple","egg","zebra","Äpfel","éclair"]"#)]
+    #[test_case(Some("en"), r#"["Äpfel","apple","Banana","éclair","egg","zebra"]"#; "english")]
+    #[test_case(Some("sv"), r#"["apple","Banana","éclair","egg","zebra","Äpfel"]"#; "swedish")]
+    fn sort(locale: Option<&str>, expected: &str) {
+        assert_eq!(evaluate(&format!("$sort({WORDS})"), locale), expected);
+        assert_eq!(evaluate(&format!("{WORDS}^($)"), locale), expected);
+    }
+
+    #[test]
+    fn invalid_locale() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$", &arena).unwrap();
+        let error = jsonata.set_locale(Some("not a locale")).unwrap_err();
+        assert_eq!(error.code(), "H0502");
+    }
+}
@@ -0,0 +1,99 @@
+//! Reads and writes [MessagePack](https://msgpack.org), converting it directly to and from values
+//! in the arena rather than going through JSON text:
+//!
+//! ```
+//! # use bumpalo::Bump;
+//! # use jsonata_rs::JsonAta;
+//! let arena = Bump::new();
+//! // {"price": 2, "quantity": 3}
+//! let input = jsonata_rs::msgpack::from_slice(b"\x82\xa5price\x02\xa8quantity\x03", &arena)?;
+//!
+//! let jsonata = JsonAta::new("{'total': price * quantity}", &arena)?;
+//! let result = jsonata.evaluate_value(input)?;
+//! assert_eq!(jsonata_rs::msgpack::to_vec(result), b"\x81\xa5total\x06");
+//! # Ok::<(), jsonata_rs::Error>(())
+//! ```
+//!
+//! Binary data, which JSON doesn't have, is read as a base64 string, and extension types as an
+//! array of their type and their data. Keys of maps which aren't strings become strings.
+
+use bumpalo::Bump;
+use serde::de::DeserializeSeed;
+
+use crate::evaluator::value::transcode::{Serializable, ValueSeed};
+use crate::{Error, Result, Value};
+
+/// Reads a MessagePack value into `arena`. Fails if there's anything after the value.
+pub fn from_slice<'a>(bytes: &[u8], arena: &'a Bump) -> Result<&'a Value<'a>> {
+    let mut rest = bytes;
+    let mut deserializer = rmp_serde::Deserializer::new(&mut rest);
+    let value = ValueSeed::new(arena)
+        .deserialize(&mut deserializer)
+        .map_err(|e| Error::H0105MalformedMsgpack(e.to_string()))?;
+    if !rest.is_empty() {
+        return Err(Error::H0105MalformedMsgpack(format!(
+            "{} bytes after the value",
+            rest.len()
+        )));
+    }
+    Ok(value)
+}
+
+/// Writes a value, such as the result of an expression, as MessagePack. Undefined, which
+/// MessagePack can't represent, is written as nothing at all.
+pub fn to_vec<'a>(value: &'a Value<'a>) -> Vec<u8> {
+    if value.is_undefined() {
+        return vec![];
+    }
+    rmp_serde::to_vec(&Serializable(value)).expect("Values can be written as MessagePack")
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(r#"{"a":[1,-2,1.5,"x",true,null],"b":{}}"#)]
+    #[test_case("18446744073709551615")]
+    #[test_case(r#""é""#)]
+    #[test_case("[]")]
+    fn round_trip(json: &str) {
+        let arena = Bump::new();
+        let value = crate::parse_value(json, &arena).unwrap();
+        let bytes = to_vec(value);
+        assert_eq!(from_slice(&bytes, &arena).unwrap().serialize(false), json);
+    }
+
+    #[test]
+    fn compact_numbers() {
+        let arena = Bump::new();
+        assert_eq!(to_vec(Value::number(&arena, 3.0)), [0x03]);
+        assert_eq!(to_vec(Value::number(&arena, -1.0)), [0xff]);
+        assert_eq!(to_vec(Value::number(&arena, 0.5))[0], 0xcb);
+    }
+
+    #[test]
+    fn other_types() {
+        let arena = Bump::new();
+        // {1: bin [1, 2, 3], "f": 0.1 as f32, "e": ext type 5 [0xaa]}
+        let bytes = b"\x83\x01\xc4\x03\x01\x02\x03\xa1f\xca\x3d\xcc\xcc\xcd\xa1e\xd4\x05\xaa";
+        assert_eq!(
+            from_slice(bytes, &arena).unwrap().serialize(false),
+            r#"{"1":"AQID","f":0.1,"e":[5,"qg=="]}"#
+        );
+    }
+
+    #[test]
+    fn undefined() {
+        assert!(to_vec(Value::undefined()).is_empty());
+    }
+
+    #[test_case(b"\x92\x01")]
+    #[test_case(b"\x01\x02")]
+    #[test_case(b"")]
+    fn malformed(bytes: &[u8]) {
+        let arena = Bump::new();
+        assert_eq!(from_slice(bytes, &arena).unwrap_err().code(), "H0105");
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use bumpalo::Bump;
+
+use crate::evaluator::bytecode;
+use crate::evaluator::frame::Frame;
+use crate::evaluator::value::Value;
+use crate::evaluator::Evaluator;
+use crate::parser::ast::{Ast, AstKind, BinaryOp, UnaryOp};
+use crate::parser::visit::{walk_node_mut, VisitorMut};
+use crate::{CompiledExpression, JsonAta};
+
+impl CompiledExpression {
+    /// Folds the subexpressions which only involve literals into the value they evaluate to, so
+    /// `2 * 60 * 60` becomes `7200`, `"a" & "b"` becomes `"ab"`, and a condition which is a
+    /// literal selects its branch. Evaluating the optimized expression gives the same results,
+    /// including errors, which are left to be raised when it's evaluated.
+    ///
+    /// With the `decimal` feature, only integers are folded, so that decimal mode gives the same
+    /// results too.
+    pub fn optimized(mut self) -> Self {
+        fold_constants(Arc::make_mut(&mut self.ast));
+        if self.program.is_some() {
+            self.program = bytecode::compile(&self.ast).map(Arc::new);
+        }
+        self
+    }
+}
+
+impl JsonAta<'_> {
+    /// Folds constant subexpressions, see [`CompiledExpression::optimized`].
+    pub fn optimize(&mut self) {
+        fold_constants(Arc::make_mut(&mut self.ast));
+        if self.program.is_some() {
+            self.program = bytecode::compile(&self.ast).map(Arc::new);
+        }
+    }
+}
+
+fn fold_constants(ast: &mut Ast) {
+    let arena = Bump::new();
+    Folder {
+        evaluator: Evaluator::new(None, &arena, None, None),
+    }
+    .visit_node_mut(ast);
+}
+
+struct Folder<'a> {
+    evaluator: Evaluator<'a>,
+}
+
+impl VisitorMut for Folder<'_> {
+    fn visit_node_mut(&mut self, node: &mut Ast) {
+        // Children first, so that folding works its way up from the literals
+        walk_node_mut(self, node);
+
+        if has_extras(node) {
+            return;
+        }
+        match node.kind {
+            // Parentheses around a literal
+            AstKind::Block(ref mut exprs) if exprs.len() == 1 && is_literal(&exprs[0]) => {
+                *node = exprs.pop().unwrap();
+            }
+            AstKind::Unary(UnaryOp::Minus(ref value)) if is_literal(value) => self.fold(node),
+            AstKind::Binary(ref op, ref lhs, ref rhs)
+                if is_foldable_op(op) && is_literal(lhs) && is_literal(rhs) =>
+            {
+                self.fold(node)
+            }
+            AstKind::Ternary {
+                ref mut cond,
+                ref mut truthy,
+                ref mut falsy,
+            } if is_literal(cond) => {
+                let Some(cond) = self.evaluate(cond) else {
+                    return;
+                };
+                let branch = if cond.is_truthy() {
+                    std::mem::take(&mut **truthy)
+                } else if let Some(falsy) = falsy {
+                    std::mem::take(&mut **falsy)
+                } else {
+                    // An empty block is undefined
+                    Ast::new(AstKind::Block(vec![]), node.char_index)
+                };
+                *node = branch;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Folder<'a> {
+    /// Replaces a node with the literal it evaluates to, if it evaluates to one without an error.
+    fn fold(&self, node: &mut Ast) {
+        let kind = match self.evaluate(node) {
+            Some(Value::Null) => AstKind::Null,
+            Some(Value::Bool(b)) => AstKind::Bool(*b),
+            Some(Value::String(s)) => AstKind::String(s.to_string()),
+            // Decimal mode would evaluate the operands as decimals, and give a different number
+            #[cfg(not(feature = "decimal"))]
+            Some(Value::Number(n)) => AstKind::Number(*n),
+            Some(Value::Integer(n)) => AstKind::Integer(*n),
+            _ => return,
+        };
+        *node = Ast::new(kind, node.char_index);
+    }
+
+    fn evaluate(&self, node: &Ast) -> Option<&'a Value<'a>> {
+        self.evaluator
+            .evaluate(node, Value::undefined(), &Frame::new())
+            .ok()
+    }
+}
+
+/// Whether anything other than the kind of a node affects how it's evaluated.
+pub(crate) fn has_extras(node: &Ast) -> bool {
+    node.keep_array
+        || node.cons_array
+        || node.keep_singleton_array
+        || node.group_by.is_some()
+        || node.predicates.is_some()
+        || node.stages.is_some()
+        || node.tuple
+        || node.index.is_some()
+        || node.focus.is_some()
+}
+
+fn is_literal(node: &Ast) -> bool {
+    if has_extras(node) {
+        return false;
+    }
+    match node.kind {
+        AstKind::Null | AstKind::Bool(..) | AstKind::String(..) | AstKind::Integer(..) => true,
+        // Only integers are exact in both modes
+        #[cfg(not(feature = "decimal"))]
+        AstKind::Number(..) => true,
+        _ => false,
+    }
+}
+
+fn is_foldable_op(op: &BinaryOp) -> bool {
+    use BinaryOp::*;
+
+    // Ranges are arrays, and the others bind, map or call something
+    matches!(
+        op,
+        Add | Subtract
+            | Multiply
+            | Divide
+            | Modulus
+            | Equal
+            | NotEqual
+            | LessThan
+            | GreaterThan
+            | LessThanEqual
+            | GreaterThanEqual
+            | Concat
+            | And
+            | Or
+            | In
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn folded(expr: &str) -> AstKind {
+        CompiledExpression::new(expr)
+            .unwrap()
+            .optimized()
+            .ast
+            .kind
+            .clone()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert!(matches!(folded("2 * 60 * 60"), AstKind::Integer(n) if n.get() == 7200));
+        assert!(matches!(folded("-(1 + 2)"), AstKind::Integer(n) if n.get() == -3));
+    }
+
+    #[test]
+    fn strings_and_booleans() {
+        assert!(matches!(folded(r#""a" & "b" & 1"#), AstKind::String(ref s) if s == "ab1"));
+        assert!(matches!(folded("1 < 2 and 'a' = 'a'"), AstKind::Bool(true)));
+    }
+
+    #[test]
+    fn ternaries() {
+        assert!(matches!(folded("1 > 2 ? a : b"), AstKind::Path(ref steps) if steps.len() == 1));
+        assert!(matches!(folded("'x' ? 1 : a"), AstKind::Integer(n) if n.get() == 1));
+        assert!(matches!(folded("null ? a"), AstKind::Block(ref exprs) if exprs.is_empty()));
+    }
+
+    #[test]
+    fn leaves_errors_for_evaluation() {
+        assert!(matches!(folded("1 + 'a'"), AstKind::Binary(..)));
+        let arena = Bump::new();
+        let error = CompiledExpression::new("1 + 'a'")
+            .unwrap()
+            .optimized()
+            .evaluate(None, &arena)
+            .unwrap_err();
+        assert_eq!(error.code(), "T2002");
+    }
+
+    #[test_case("a.b + 2 * 3", "8")]
+    #[test_case("[1..2 + 1]", "[1,2,3]")]
+    #[test_case("a.(1 = 1 ? b : 'x')", "2")]
+    #[test_case("false ? a", "")]
+    #[test_case("$string(60 * 60) & 's'", r#""3600s""#)]
+    fn same_results(expr: &str, expected: &str) {
+        let arena = Bump::new();
+        let input = Some(r#"{"a": {"b": 2}}"#);
+        let compiled = CompiledExpression::new(expr).unwrap();
+        let unoptimized = compiled.evaluate(input, &arena).unwrap();
+        let optimized = compiled.optimized().evaluate(input, &arena).unwrap();
+        assert_eq!(optimized, unoptimized);
+        if expected.is_empty() {
+            assert!(optimized.is_undefined());
+        } else {
+            assert_eq!(optimized.serialize(false), expected);
+        }
+    }
+
+    #[test]
+    fn bytecode() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("a.b * (60 * 60)", &arena).unwrap();
+        jsonata.set_backend(crate::Backend::Bytecode);
+        jsonata.optimize();
+        assert!(matches!(
+            jsonata.ast.kind,
+            AstKind::Binary(BinaryOp::Multiply, _, ref rhs) if matches!(rhs.kind, AstKind::Integer(..))
+        ));
+        let result = jsonata.evaluate(Some(r#"{"a": {"b": 2}}"#), None).unwrap();
+        assert_eq!(result.serialize(false), "7200");
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+
+use bumpalo::Bump;
+use indexmap::IndexMap;
+
+use crate::{ArrayFlags, CompiledExpression, Integer, Result, Value};
+
+/// A value which owns what it's made of, rather than borrowing it from an arena, so that it can
+/// outlive the evaluation it's the result of. See [`CompiledExpression::evaluate_str`].
+///
+/// Like [`Value`], it has more kinds of value with some features, so matches outside the crate
+/// need a wildcard arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum OwnedValue {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    /// A number which is kept exact, see [`Value::Integer`].
+    Integer(Integer),
+    /// An exact decimal number, see [`crate::JsonAta::set_decimal`].
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+    String(String),
+    Array(Vec<OwnedValue>),
+    Object(IndexMap<String, OwnedValue>),
+    /// A function, which can only be called during the evaluation which created it, so only
+    /// records that there was one. It's serialized as an empty string, as in JSON.
+    Function,
+}
+
+impl CompiledExpression {
+    /// Evaluates the expression against the JSON `input` in an arena of its own, returning the
+    /// result as an [`OwnedValue`], for hosts which don't need to manage arenas themselves:
+    ///
+    /// ```
+    /// # use jsonata_rs::{CompiledExpression, OwnedValue};
+    /// let expression = CompiledExpression::new("orders.{'id': id, 'total': price * quantity}")?;
+    /// let result = expression.evaluate_str(r#"{"orders": [{"id": "a1", "price": 2, "quantity": 3}]}"#)?;
+    /// assert_eq!(result["total"].as_f64(), Some(6.0));
+    /// assert_eq!(result.serialize(false), r#"{"id":"a1","total":6}"#);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Results are copied out of the arena, so evaluating many times is faster with an arena
+    /// which is reused, see [`CompiledExpression::evaluate_in`].
+    pub fn evaluate_str(&self, input: &str) -> Result<OwnedValue> {
+        let arena = Bump::new();
+        let result = self.evaluate(Some(input), &arena)?;
+        Ok(OwnedValue::from(result))
+    }
+
+    /// Evaluates the expression against `input`, or without any input if it's undefined, like
+    /// [`CompiledExpression::evaluate_str`].
+    pub fn evaluate_owned(&self, input: &OwnedValue) -> Result<OwnedValue> {
+        let arena = Bump::new();
+        let jsonata = self.bind(&arena);
+        let result = jsonata.evaluate_value(input.to_value(&arena))?;
+        Ok(OwnedValue::from(result))
+    }
+}
+
+impl OwnedValue {
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, OwnedValue::Undefined)
+    }
+
+    /// The number, if it's any kind of number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OwnedValue::Number(n) => Some(n),
+            OwnedValue::Integer(Integer::Signed(n)) => Some(n as f64),
+            OwnedValue::Integer(Integer::Unsigned(n)) => Some(n as f64),
+            #[cfg(feature = "decimal")]
+            OwnedValue::Decimal(d) => Some(rust_decimal::prelude::ToPrimitive::to_f64(&d)?),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OwnedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Serializes the value to JSON, in the same way as [`Value::serialize`].
+    pub fn serialize(&self, prettify: bool) -> String {
+        let arena = Bump::new();
+        self.to_value(&arena).serialize(prettify)
+    }
+
+    /// Copies the value into `arena`, such as to pass it to another expression. A function
+    /// becomes undefined, as it can't be called any more.
+    pub fn to_value<'a>(&self, arena: &'a Bump) -> &'a Value<'a> {
+        match *self {
+            OwnedValue::Undefined | OwnedValue::Function => Value::undefined(),
+            OwnedValue::Null => Value::null(arena),
+            OwnedValue::Bool(b) => Value::bool(arena, b),
+            OwnedValue::Number(n) => Value::number(arena, n),
+            OwnedValue::Integer(n) => Value::integer(arena, n),
+            #[cfg(feature = "decimal")]
+            OwnedValue::Decimal(d) => Value::decimal(arena, d),
+            OwnedValue::String(ref s) => Value::string(arena, s),
+            OwnedValue::Array(ref members) => {
+                let array = Value::array_with_capacity(arena, members.len(), ArrayFlags::empty());
+                for member in members {
+                    array.push(member.to_value(arena));
+                }
+                array
+            }
+            OwnedValue::Object(ref entries) => {
+                let object = Value::object_with_capacity(arena, entries.len());
+                for (key, value) in entries {
+                    object.insert(key, value.to_value(arena));
+                }
+                object
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Value<'a>> for OwnedValue {
+    fn from(value: &'a Value<'a>) -> Self {
+        match *value {
+            Value::Undefined => OwnedValue::Undefined,
+            Value::Null => OwnedValue::Null,
+            Value::Bool(b) => OwnedValue::Bool(b),
+            Value::Number(n) => OwnedValue::Number(n),
+            Value::Integer(n) => OwnedValue::Integer(n),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => OwnedValue::Decimal(d),
+            Value::String(ref s) => OwnedValue::String(s.to_string()),
+            Value::Array(..) | Value::Range(..) => {
+                OwnedValue::Array(value.members().map(OwnedValue::from).collect())
+            }
+            Value::Object(..) => OwnedValue::Object(
+                value
+                    .entries()
+                    .map(|(key, value)| (key.clone(), OwnedValue::from(*value)))
+                    .collect(),
+            ),
+            Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+                OwnedValue::Function
+            }
+        }
+    }
+}
+
+/// Members of objects by key, which are undefined if there's no such member or it isn't an
+/// object, as in a path.
+impl std::ops::Index<&str> for OwnedValue {
+    type Output = OwnedValue;
+
+    fn index(&self, key: &str) -> &OwnedValue {
+        match self {
+            OwnedValue::Object(entries) => entries.get(key).unwrap_or(&OwnedValue::Undefined),
+            _ => &OwnedValue::Undefined,
+        }
+    }
+}
+
+/// Members of arrays by index, which are undefined if there's no such member or it isn't an
+/// array.
+impl std::ops::Index<usize> for OwnedValue {
+    type Output = OwnedValue;
+
+    fn index(&self, index: usize) -> &OwnedValue {
+        match self {
+            OwnedValue::Array(members) => members.get(index).unwrap_or(&OwnedValue::Undefined),
+            _ => &OwnedValue::Undefined,
+        }
+    }
+}
+
+impl fmt::Display for OwnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serialize(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("$", "[1,2.5,\"a\",null,true,{\"b\":[]}]")]
+    #[test_case("$[0]", "1")]
+    #[test_case("[1..3]", "[1,2,3]"; "range")]
+    #[test_case("$[9]", ""; "undefined")]
+    #[test_case("18446744073709551615", "18446744073709551615"; "integer")]
+    fn round_trip(expr: &str, expected: &str) {
+        let input = r#"[1, 2.5, "a", null, true, {"b": []}]"#;
+        let expression = CompiledExpression::new(expr).unwrap();
+        let result = expression.evaluate_str(input).unwrap();
+        assert_eq!(result.serialize(false), expected);
+        let identity = CompiledExpression::new("$").unwrap();
+        assert_eq!(identity.evaluate_owned(&result).unwrap(), result);
+    }
+
+    #[test]
+    fn outlives_the_arena() {
+        let result = {
+            let expression = CompiledExpression::new("{'name': name, 'tags': tags}").unwrap();
+            expression
+                .evaluate_str(r#"{"name": "a", "tags": ["x", "y"]}"#)
+                .unwrap()
+        };
+        assert_eq!(result["name"].as_str(), Some("a"));
+        assert_eq!(result["tags"][1], OwnedValue::String("y".to_string()));
+        assert!(result["missing"][0].is_undefined());
+    }
+
+    #[test]
+    fn functions() {
+        let expression = CompiledExpression::new("function($x) { $x }").unwrap();
+        let result = expression.evaluate_str("{}").unwrap();
+        assert_eq!(result, OwnedValue::Function);
+        assert!(OwnedValue::Function.to_value(&Bump::new()).is_undefined());
+    }
+
+    #[test]
+    fn errors() {
+        let expression = CompiledExpression::new("$error('x')").unwrap();
+        assert_eq!(expression.evaluate_str("{}").unwrap_err().code(), "D3137");
+        let expression = CompiledExpression::new("$").unwrap();
+        assert_eq!(expression.evaluate_str("{").unwrap_err().code(), "S0211");
+    }
+}
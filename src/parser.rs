@@ -1,26 +1,88 @@
 pub mod ast;
+pub mod incremental;
+pub mod operators;
 mod process;
 mod symbol;
-mod tokenizer;
+pub(crate) mod tokenizer;
+pub mod visit;
 
 use crate::{Error, Result};
 
 use ast::*;
+use incremental::Lexed;
+use operators::Operators;
 use symbol::Symbol;
 use tokenizer::*;
 
 #[derive(Debug)]
 pub struct Parser<'a> {
-    pub tokenizer: Tokenizer<'a>,
+    source: &'a str,
+    tokens: Tokens<'a>,
     pub token: Token,
+
+    /// Operators registered on top of the built-in ones
+    operators: Option<&'a Operators>,
+
+    /// The syntax errors found so far, when recovering from them, see [`parse_recovering`]
+    errors: Option<Vec<Error>>,
+}
+
+#[derive(Debug)]
+enum Tokens<'a> {
+    /// Tokens are read from the source as they're needed.
+    Tokenizer(Tokenizer<'a>),
+
+    /// Tokens were read ahead of time by an [`incremental::IncrementalParser`], which can also
+    /// reuse the subtrees of a previous parse.
+    Lexed(Lexed<'a>),
+
+    /// The tokenizer failed while recovering from errors, so the rest of the source is skipped.
+    Ended,
 }
 
 impl<'a> Parser<'a> {
     fn new(source: &'a str) -> Result<Self> {
         let mut tokenizer = Tokenizer::new(source);
         Ok(Self {
+            source,
+            token: tokenizer.next_token()?,
+            tokens: Tokens::Tokenizer(tokenizer),
+            operators: None,
+            errors: None,
+        })
+    }
+
+    fn recovering(source: &'a str) -> Self {
+        let mut parser = Self {
+            source,
+            token: end_of(source),
+            tokens: Tokens::Tokenizer(Tokenizer::new(source)),
+            operators: None,
+            errors: Some(vec![]),
+        };
+        // Errors are recorded rather than returned while recovering
+        let _ = parser.next_token();
+        parser
+    }
+
+    fn with_operators(source: &'a str, operators: &'a Operators) -> Result<Self> {
+        let mut tokenizer = Tokenizer::with_operators(source, operators);
+        Ok(Self {
+            source,
             token: tokenizer.next_token()?,
-            tokenizer,
+            tokens: Tokens::Tokenizer(tokenizer),
+            operators: Some(operators),
+            errors: None,
+        })
+    }
+
+    fn lexed(source: &'a str, mut lexed: Lexed<'a>) -> Result<Self> {
+        Ok(Self {
+            source,
+            token: lexed.next_token()?,
+            tokens: Tokens::Lexed(lexed),
+            operators: None,
+            errors: None,
         })
     }
 
@@ -29,41 +91,111 @@ impl<'a> Parser<'a> {
     }
 
     pub fn next_token(&mut self) -> Result<()> {
-        self.token = self.tokenizer.next_token()?;
+        let token = match self.tokens {
+            Tokens::Tokenizer(ref mut tokenizer) => tokenizer.next_token(),
+            Tokens::Lexed(ref mut lexed) => lexed.next_token(),
+            Tokens::Ended => Ok(end_of(self.source)),
+        };
+        self.token = match token {
+            Ok(token) => token,
+            Err(error) => {
+                self.recover(error)?;
+                self.tokens = Tokens::Ended;
+                end_of(self.source)
+            }
+        };
         Ok(())
     }
 
-    pub fn expect(&mut self, expected: TokenKind) -> Result<()> {
-        if self.token.kind == TokenKind::End {
-            return Err(Error::S0203ExpectedTokenBeforeEnd(
-                self.token.byte_index,
-                expected.to_string(),
-            ));
+    /// Records `error` and carries on if recovering from errors, or returns it otherwise.
+    fn recover(&mut self, error: Error) -> Result<()> {
+        let Some(ref mut errors) = self.errors else {
+            return Err(error);
+        };
+        // An error at the same position as the one before is usually a consequence of it, as is
+        // every error after the tokenizer failed
+        let is_consequence = matches!(self.tokens, Tokens::Ended)
+            || errors.last().map(Error::char_index) == Some(error.char_index());
+        if !is_consequence {
+            errors.push(error);
         }
+        Ok(())
+    }
+
+    pub fn string_from_token(&self, token: &Token) -> String {
+        String::from(self.token_text(token))
+    }
 
-        if self.token.kind != expected {
-            return Err(Error::S0202UnexpectedToken(
+    pub fn token_text(&self, token: &Token) -> &str {
+        &self.source[token.byte_index..token.byte_index + token.len]
+    }
+
+    pub fn expect(&mut self, expected: TokenKind) -> Result<()> {
+        let error = if self.token.kind == TokenKind::End {
+            Error::S0203ExpectedTokenBeforeEnd(self.token.byte_index, expected.to_string())
+        } else if self.token.kind != expected {
+            Error::S0202UnexpectedToken(
                 self.token.char_index,
                 expected.to_string(),
                 self.token.kind.to_string(),
-            ));
-        }
+            )
+        } else {
+            return self.next_token();
+        };
 
-        self.next_token()?;
-
-        Ok(())
+        // When recovering, the missing token is taken to be there
+        self.recover(error)
     }
 
     pub fn expression(&mut self, bp: u32) -> Result<Ast> {
+        // Only whole expressions are reused, as where they end doesn't depend on what they're
+        // part of
+        if bp == 0 {
+            if let Tokens::Lexed(ref mut lexed) = self.tokens {
+                if let Some((ast, token)) = lexed.reuse(&self.token) {
+                    self.token = token;
+                    return Ok(ast);
+                }
+            }
+        }
+
+        // When recovering, a missing operand is left empty, and the token after it is left for
+        // whatever it ends
+        if self.errors.is_some() && ends_expression(&self.token.kind) {
+            let error =
+                Error::S0211InvalidUnary(self.token.char_index, self.token.kind.to_string());
+            self.recover(error)?;
+            return Ok(Ast::new(AstKind::Empty, self.token.char_index));
+        }
+
+        let start = self.token.clone();
         let mut last = self.token.clone();
         self.next_token()?;
 
-        let mut left = last.null_denotation(self)?;
+        let mut left = match last.null_denotation(self) {
+            Ok(left) => left,
+            Err(error) => {
+                self.recover(error)?;
+                Ast::new(AstKind::Empty, last.char_index)
+            }
+        };
 
         while bp < self.token.left_binding_power() {
             last = self.token.clone();
             self.next_token()?;
-            left = last.left_denotation(self, left)?;
+            left = match last.left_denotation(self, left) {
+                Ok(left) => left,
+                Err(error) => {
+                    self.recover(error)?;
+                    Ast::new(AstKind::Empty, last.char_index)
+                }
+            };
+        }
+
+        if bp == 0 {
+            if let Tokens::Lexed(ref mut lexed) = self.tokens {
+                lexed.remember(&start, &self.token, &left);
+            }
         }
 
         Ok(left)
@@ -71,15 +203,107 @@ impl<'a> Parser<'a> {
 }
 
 pub fn parse(source: &str) -> Result<Ast> {
-    let mut parser = Parser::new(source)?;
+    parse_with(&mut Parser::new(source)?)
+}
+
+/// Parses `source`, recovering from syntax errors to carry on parsing the rest of it, and returns
+/// a best-effort AST along with every error found, for editors.
+///
+/// A missing token, such as a closing bracket, is taken to be there, a missing operand is left
+/// empty, and a token which can't start an operand is skipped. Anything after an expression which
+/// is complete, or after an error in tokenizing, is skipped. The errors are in the order they
+/// were found, which is usually the order of their positions.
+pub fn parse_recovering(source: &str) -> (Ast, Vec<Error>) {
+    let mut parser = Parser::recovering(source);
+    let result = parse_unprocessed(&mut parser);
+    let mut errors = parser.errors.take().unwrap_or_default();
+    let ast = match result {
+        Ok(ast) => match ast.clone().process() {
+            Ok(processed) => processed,
+            Err(error) => {
+                errors.push(error);
+                ast
+            }
+        },
+        Err(error) => {
+            errors.push(error);
+            Ast::default()
+        }
+    };
+    (ast, errors)
+}
+
+/// Parses `source` with the registered `operators` as well as the built-in ones.
+pub fn parse_with_operators(source: &str, operators: &Operators) -> Result<Ast> {
+    parse_with(&mut Parser::with_operators(source, operators)?)
+}
+
+/// The text of each number in `source`, in the order they appear. A minus sign directly before a
+/// number is included.
+pub fn number_lexemes(source: &str) -> Result<Vec<&str>> {
+    let mut tokenizer = Tokenizer::new(source);
+    let mut lexemes = vec![];
+    let mut minus = None;
+    loop {
+        let token = tokenizer.next_token()?;
+        match token.kind {
+            TokenKind::End => break,
+            TokenKind::Minus => {
+                minus = Some(token.byte_index);
+                continue;
+            }
+            TokenKind::Number(..) => {
+                let start = match minus {
+                    Some(minus) if minus + 1 == token.byte_index => minus,
+                    _ => token.byte_index,
+                };
+                lexemes.push(&source[start..token.byte_index + token.len]);
+            }
+            _ => {}
+        }
+        minus = None;
+    }
+    Ok(lexemes)
+}
+
+fn parse_with(parser: &mut Parser) -> Result<Ast> {
+    parse_unprocessed(parser)?.process()
+}
+
+fn parse_unprocessed(parser: &mut Parser) -> Result<Ast> {
     let ast = parser.expression(0)?;
     if !matches!(parser.token().kind, TokenKind::End) {
-        return Err(Error::S0201SyntaxError(
+        let error = Error::S0201SyntaxError(
             parser.token().byte_index,
-            parser.tokenizer.string_from_token(parser.token()),
-        ));
+            parser.string_from_token(parser.token()),
+        );
+        parser.recover(error)?;
+    }
+    Ok(ast)
+}
+
+/// Whether a token ends an operand rather than starting one, such as a closing bracket.
+fn ends_expression(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::End
+            | TokenKind::RightParen
+            | TokenKind::RightBracket
+            | TokenKind::RightBrace
+            | TokenKind::Comma
+            | TokenKind::SemiColon
+            | TokenKind::Colon
+    )
+}
+
+/// The token at the end of `source`.
+fn end_of(source: &str) -> Token {
+    Token {
+        kind: TokenKind::End,
+        char_index: source.chars().count(),
+        byte_index: source.len(),
+        len: 0,
     }
-    ast.process()
 }
 
 #[cfg(test)]
@@ -272,4 +496,40 @@ mod tests {
     fn parser_tests(source: &str) {
         let _ = parse(source);
     }
+
+    #[test_case("a.b", &[]; "valid")]
+    #[test_case("[a, (b + ), c", &[("S0211", 9), ("S0203", 13)]; "missing operand and bracket")]
+    #[test_case("{'a': 1 'b': 2}", &[("S0202", 8)]; "missing comma")]
+    #[test_case("f(1, 2 + ) and g(", &[("S0211", 9), ("S0211", 17)]; "two calls")]
+    #[test_case("(a; b c)", &[("S0202", 6)]; "missing semicolon")]
+    #[test_case("a b", &[("S0201", 2)]; "trailing tokens")]
+    #[test_case("a + 'b", &[("S0101", 4)]; "unterminated string")]
+    #[test_case("1 := 2", &[("S0212", 0), ("S0201", 5)]; "invalid binding")]
+    fn recovering(source: &str, expected: &[(&str, usize)]) {
+        let (_, errors) = parse_recovering(source);
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| (error.code(), error.char_index().unwrap()))
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test_case("[a, (b + ), c")]
+    #[test_case("f(1, 2 + ) and g(")]
+    #[test_case("a + 'b")]
+    #[test_case("$x := ")]
+    fn recovering_reports_the_error_parse_does(source: &str) {
+        let (_, errors) = parse_recovering(source);
+        assert!(errors.contains(&parse(source).unwrap_err()));
+    }
+
+    #[test]
+    fn recovering_keeps_what_parses() {
+        let (ast, _) = parse_recovering("{'total': $sum(items.price), 'count': }");
+        let AstKind::Unary(UnaryOp::ObjectConstructor(ref members)) = ast.kind else {
+            panic!("Expected an object constructor, not {:?}", ast.kind);
+        };
+        assert!(matches!(members[0].1.kind, AstKind::Function { .. }));
+        assert!(matches!(members[1].1.kind, AstKind::Empty));
+    }
 }
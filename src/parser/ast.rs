@@ -1,3 +1,6 @@
+use crate::evaluator::integer::Integer;
+use crate::evaluator::signature::Signature;
+
 // Object constructor, represented by tuples of (key, value)
 pub type Object = Vec<(Ast, Ast)>;
 
@@ -66,13 +69,21 @@ impl std::fmt::Display for BinaryOp {
     }
 }
 
+/// The kinds of node, which features can add to, such as decimal literals with `decimal`.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum AstKind {
     Empty,
     Null,
     Bool(bool),
     String(String),
     Number(f64),
+    /// A number written without a fraction or exponent, which is evaluated exactly.
+    Integer(Integer),
+    /// A number which can also be represented exactly as a decimal, which is evaluated to one in
+    /// decimal mode. Number literals are parsed into these when the `decimal` feature is enabled.
+    #[cfg(feature = "decimal")]
+    Decimal(f64, rust_decimal::Decimal),
     Name(String),
     Var(String),
     Unary(UnaryOp),
@@ -95,6 +106,7 @@ pub enum AstKind {
         args: Vec<Ast>,
         body: Box<Ast>,
         thunk: bool,
+        signature: Option<Signature>,
     },
     Ternary {
         cond: Box<Ast>,
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use super::ast::*;
+use super::tokenizer::{Token, TokenKind, Tokenizer};
+use super::{parse_with, Parser};
+use crate::{Error, Result};
+
+// The furthest past the end of a token that the tokenizer looks when deciding where it ends, which
+// is two characters, in bytes.
+const LOOKAHEAD: usize = 2 * 4;
+
+/// Parses an expression that's being edited, such as in an editor, keeping its tokens and the
+/// subtrees of its AST so that after an edit only the tokens around the edit are read again, and
+/// only the expressions containing it are parsed again.
+///
+/// ```
+/// # use jsonata_rs::IncrementalParser;
+/// let mut parser = IncrementalParser::new("($a := 1; $b := 2; $a + $b)");
+/// parser.edit(7..8, "10");
+///
+/// assert_eq!(parser.source(), "($a := 10; $b := 2; $a + $b)");
+/// assert!(parser.ast().is_ok());
+/// ```
+#[derive(Debug)]
+pub struct IncrementalParser {
+    source: String,
+    tokens: Vec<Token>,
+
+    /// The error which stopped tokenizing, after the last of the tokens
+    error: Option<Error>,
+
+    subtrees: Subtrees,
+    ast: Result<Ast>,
+}
+
+/// The unprocessed ASTs of the whole expressions in the source, by the byte index they start at.
+#[derive(Debug, Default)]
+pub(super) struct Subtrees {
+    map: BTreeMap<usize, Subtree>,
+    reused: usize,
+}
+
+#[derive(Debug)]
+struct Subtree {
+    /// The byte index of the token after the expression, which was looked at to end it
+    stop: usize,
+
+    /// The char index the expression started at when it was parsed, as the char indexes in the
+    /// AST are adjusted when it's reused somewhere else
+    char_index: usize,
+
+    ast: Ast,
+}
+
+impl IncrementalParser {
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let (tokens, error, _) = tokenize(&source, 0, 0, &[], 0);
+
+        let mut parser = Self {
+            source,
+            tokens,
+            error,
+            subtrees: Subtrees::default(),
+            ast: Ok(Ast::default()),
+        };
+        parser.parse();
+        parser
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The result of parsing the source as it currently is, which is the same as
+    /// [`parse`](super::parse) would return.
+    pub fn ast(&self) -> Result<&Ast> {
+        self.ast.as_ref().map_err(Clone::clone)
+    }
+
+    /// How many expressions the last parse reused from earlier ones, rather than parsing them
+    /// again.
+    pub fn reused(&self) -> usize {
+        self.subtrees.reused
+    }
+
+    /// Replaces the bytes in `range` with `text` and parses the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or doesn't start and end on char boundaries, like
+    /// [`String::replace_range`].
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> Result<&Ast> {
+        let Range { start, end } = range;
+        let removed_chars = self.source[start..end].chars().count() as isize;
+
+        // The tokens that end far enough before the edit that it can't have changed them
+        let kept = self
+            .tokens
+            .partition_point(|token| token.byte_index + token.len + LOOKAHEAD <= start);
+        let (restart_byte, restart_char) = match kept.checked_sub(1).map(|i| &self.tokens[i]) {
+            Some(token) => {
+                let text = &self.source[token.byte_index..token.byte_index + token.len];
+                (
+                    token.byte_index + token.len,
+                    token.char_index + text.chars().count(),
+                )
+            }
+            None => (0, 0),
+        };
+
+        // The tokens after the edit, which are kept if tokenizing lines up with one of them again.
+        // The tokens before an error aren't, as the error's position would be out of date.
+        let after = if self.error.is_some() {
+            self.tokens.len()
+        } else {
+            self.tokens.partition_point(|token| token.byte_index < end)
+        };
+
+        self.source.replace_range(start..end, text);
+        let byte_delta = text.len() as isize - (end - start) as isize;
+        let char_delta = text.chars().count() as isize - removed_chars;
+
+        let (relexed, error, synced) = tokenize(
+            &self.source,
+            restart_byte,
+            restart_char,
+            &self.tokens[after..],
+            byte_delta,
+        );
+        self.error = error;
+
+        let synced = synced.map(|synced| after + synced);
+        let synced_byte = synced.map(|synced| self.tokens[synced].byte_index);
+
+        let mut tail = match synced {
+            Some(synced) => self.tokens.split_off(synced),
+            None => vec![],
+        };
+        for token in tail.iter_mut() {
+            token.byte_index = offset(token.byte_index, byte_delta);
+            token.char_index = offset(token.char_index, char_delta);
+        }
+        self.tokens.truncate(kept);
+        self.tokens.extend(relexed);
+        self.tokens.extend(tail);
+
+        // Keep the subtrees made of tokens which weren't read again, moving those after the edit
+        let map = &mut self.subtrees.map;
+        let after_edit = match synced_byte {
+            Some(synced_byte) => map.split_off(&synced_byte),
+            None => BTreeMap::new(),
+        };
+        map.split_off(&restart_byte);
+        map.retain(|_, subtree| subtree.stop < restart_byte);
+        map.extend(after_edit.into_iter().map(|(start, mut subtree)| {
+            subtree.stop = offset(subtree.stop, byte_delta);
+            (offset(start, byte_delta), subtree)
+        }));
+
+        self.parse();
+        self.ast()
+    }
+
+    fn parse(&mut self) {
+        self.subtrees.reused = 0;
+        let lexed = Lexed {
+            tokens: &self.tokens,
+            error: self.error.clone(),
+            next: 0,
+            subtrees: &mut self.subtrees,
+        };
+        self.ast =
+            Parser::lexed(&self.source, lexed).and_then(|mut parser| parse_with(&mut parser));
+    }
+}
+
+/// Tokenizes `source` from a byte index where a token ended, until the end or until it lines up
+/// with one of `following`, which are tokens from before an edit, to be moved by `byte_delta`.
+///
+/// Returns the tokens up to the one that lines up, the error which stopped tokenizing if there
+/// was one, and the index in `following` of the token that lined up.
+fn tokenize(
+    source: &str,
+    byte_index: usize,
+    char_index: usize,
+    following: &[Token],
+    byte_delta: isize,
+) -> (Vec<Token>, Option<Error>, Option<usize>) {
+    let mut tokenizer = Tokenizer::starting_at(source, byte_index, char_index);
+    let mut tokens = vec![];
+    let mut candidate = 0;
+
+    loop {
+        let token = match tokenizer.next_token() {
+            Ok(token) => token,
+            Err(error) => return (tokens, Some(error), None),
+        };
+
+        while candidate < following.len()
+            && offset(following[candidate].byte_index, byte_delta) < token.byte_index
+        {
+            candidate += 1;
+        }
+        if candidate < following.len()
+            && offset(following[candidate].byte_index, byte_delta) == token.byte_index
+        {
+            return (tokens, None, Some(candidate));
+        }
+
+        let end = token.kind == TokenKind::End;
+        tokens.push(token);
+        if end {
+            return (tokens, None, None);
+        }
+    }
+}
+
+fn offset(index: usize, delta: isize) -> usize {
+    index
+        .checked_add_signed(delta)
+        .expect("Index moved before the start")
+}
+
+/// Tokens read ahead of time by an [`IncrementalParser`], for a [`Parser`].
+#[derive(Debug)]
+pub(super) struct Lexed<'a> {
+    tokens: &'a [Token],
+    error: Option<Error>,
+
+    /// The index of the token after the parser's current token
+    next: usize,
+
+    subtrees: &'a mut Subtrees,
+}
+
+impl Lexed<'_> {
+    pub(super) fn next_token(&mut self) -> Result<Token> {
+        match self.tokens.get(self.next) {
+            Some(token) => {
+                self.next += 1;
+                Ok(token.clone())
+            }
+            None => match self.error.take() {
+                Some(error) => Err(error),
+                // Like the tokenizer, keep returning the end once it's reached
+                None => Ok(self.tokens[self.tokens.len() - 1].clone()),
+            },
+        }
+    }
+
+    /// Returns the AST of the expression starting at `token` if it was parsed before, along with
+    /// the token after it, which the parser continues from.
+    pub(super) fn reuse(&mut self, token: &Token) -> Option<(Ast, Token)> {
+        let subtree = self.subtrees.map.get(&token.byte_index)?;
+        let stop = self
+            .tokens
+            .binary_search_by_key(&subtree.stop, |token| token.byte_index)
+            .ok()?;
+
+        let mut ast = subtree.ast.clone();
+        if subtree.char_index != token.char_index {
+            shift(
+                &mut ast,
+                token.char_index as isize - subtree.char_index as isize,
+            );
+        }
+
+        self.next = stop + 1;
+        self.subtrees.reused += 1;
+        Some((ast, self.tokens[stop].clone()))
+    }
+
+    /// Keeps the AST of the expression between `start` and `stop` for later parses.
+    pub(super) fn remember(&mut self, start: &Token, stop: &Token, ast: &Ast) {
+        self.subtrees.map.insert(
+            start.byte_index,
+            Subtree {
+                stop: stop.byte_index,
+                char_index: start.char_index,
+                ast: ast.clone(),
+            },
+        );
+    }
+}
+
+// Moves the char indexes of a reused AST to where it is now.
+fn shift(ast: &mut Ast, delta: isize) {
+    let shift_all = |asts: &mut [Ast]| asts.iter_mut().for_each(|ast| shift(ast, delta));
+    let shift_pairs = |pairs: &mut [(Ast, Ast)]| {
+        pairs.iter_mut().for_each(|(key, value)| {
+            shift(key, delta);
+            shift(value, delta);
+        })
+    };
+
+    ast.char_index = offset(ast.char_index, delta);
+
+    match ast.kind {
+        AstKind::Unary(UnaryOp::Minus(ref mut value)) => shift(value, delta),
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref mut items)) => shift_all(items),
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref mut object)) => shift_pairs(object),
+        AstKind::Binary(_, ref mut lhs, ref mut rhs) => {
+            shift(lhs, delta);
+            shift(rhs, delta);
+        }
+        AstKind::GroupBy(ref mut lhs, ref mut object) => {
+            shift(lhs, delta);
+            shift_pairs(object);
+        }
+        AstKind::OrderBy(ref mut lhs, ref mut terms) => {
+            shift(lhs, delta);
+            terms.iter_mut().for_each(|(term, _)| shift(term, delta));
+        }
+        AstKind::Sort(ref mut terms) => terms.iter_mut().for_each(|(term, _)| shift(term, delta)),
+        AstKind::Block(ref mut exprs) | AstKind::Path(ref mut exprs) => shift_all(exprs),
+        AstKind::Function {
+            ref mut proc,
+            ref mut args,
+            ..
+        } => {
+            shift(proc, delta);
+            shift_all(args);
+        }
+        AstKind::Lambda {
+            ref mut args,
+            ref mut body,
+            ..
+        } => {
+            shift_all(args);
+            shift(body, delta);
+        }
+        AstKind::Filter(ref mut body) => shift(body, delta),
+        AstKind::Ternary {
+            ref mut cond,
+            ref mut truthy,
+            ref mut falsy,
+        } => {
+            shift(cond, delta);
+            shift(truthy, delta);
+            if let Some(falsy) = falsy {
+                shift(falsy, delta);
+            }
+        }
+        AstKind::Transform {
+            ref mut pattern,
+            ref mut update,
+            ref mut delete,
+        } => {
+            shift(pattern, delta);
+            shift(update, delta);
+            if let Some(delete) = delete {
+                shift(delete, delta);
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(ref mut predicates) = ast.predicates {
+        shift_all(predicates);
+    }
+    if let Some(ref mut stages) = ast.stages {
+        shift_all(stages);
+    }
+    if let Some((ref mut char_index, ref mut object)) = ast.group_by {
+        *char_index = offset(*char_index, delta);
+        shift_pairs(object);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::super::parse;
+    use super::*;
+
+    // The ASTs are compared by their debug output, which includes the char indexes
+    fn assert_same_as_parse(parser: &IncrementalParser) {
+        assert_eq!(
+            format!("{:?}", parser.ast()),
+            format!("{:?}", parse(parser.source()).as_ref()),
+            "source: {}",
+            parser.source()
+        );
+    }
+
+    #[test_case("a.b", 1..1, "."; "merges tokens")]
+    #[test_case("a..b", 2..3, ""; "splits tokens")]
+    #[test_case("1 .. 2", 1..1, "2"; "extends a number")]
+    #[test_case("[1..2]", 2..2, "0"; "extends a number before a range")]
+    #[test_case("(a; b; c)", 4..5, "$x := 1"; "replaces a statement")]
+    #[test_case("(a; b; c)", 0..9, "d"; "replaces everything")]
+    #[test_case("(a; b; c)", 9..9, " + 1"; "appends")]
+    #[test_case("(a; b; c)", 0..0, "x."; "prepends")]
+    #[test_case("\"a b\" & c", 2..3, "\" & \""; "splits a string")]
+    #[test_case("a /* x */ + b", 5..6, "y"; "edits a comment")]
+    #[test_case("a + b + c", 2..2, "/* "; "opens a comment")]
+    #[test_case("a /* + b */ + c", 2..5, ""; "removes the start of a comment")]
+    #[test_case("\"ⵣⵓⵍ\" & a & b", 13..14, "bc"; "moves char indexes")]
+    #[test_case("$f(a, b", 7..7, ")"; "fixes a syntax error")]
+    #[test_case("$f(a, b)", 7..8, ""; "makes a syntax error")]
+    #[test_case("{\"a\": 1}", 2..3, "b"; "edits an object key")]
+    fn edit(source: &str, range: Range<usize>, text: &str) {
+        let mut parser = IncrementalParser::new(source);
+        assert_same_as_parse(&parser);
+
+        parser.edit(range, text).ok();
+        assert_same_as_parse(&parser);
+    }
+
+    #[test]
+    fn tokenizer_errors() {
+        let mut parser = IncrementalParser::new("a & \"b\" & c");
+        parser.edit(7..7, "\"").ok();
+        assert_eq!(parser.source(), "a & \"b\"\" & c");
+        assert_same_as_parse(&parser);
+        assert_eq!(parser.ast().unwrap_err().code(), "S0101");
+
+        parser.edit(7..8, "").ok();
+        assert_same_as_parse(&parser);
+
+        // A later error comes after the parse error, as it does when tokenizing on demand
+        parser.edit(0..0, ") \"").ok();
+        assert_same_as_parse(&parser);
+        assert_eq!(parser.ast().unwrap_err().code(), "S0211");
+    }
+
+    #[test]
+    fn sequence_of_edits() {
+        let mut parser = IncrementalParser::new("");
+        let mut typed = String::new();
+        for c in "($total := $sum(items.(price * qty)); $total > 100 ? \"big\" : \"small\")".chars()
+        {
+            parser
+                .edit(typed.len()..typed.len(), c.encode_utf8(&mut [0; 4]))
+                .ok();
+            typed.push(c);
+            assert_same_as_parse(&parser);
+        }
+
+        while !typed.is_empty() {
+            let start = typed.len() / 3;
+            let end = (start + 2).min(typed.len());
+            typed.replace_range(start..end, "");
+            parser.edit(start..end, "").ok();
+            assert_same_as_parse(&parser);
+        }
+    }
+
+    #[test]
+    fn reuses_unchanged_expressions() {
+        let statements: Vec<String> = (0..100)
+            .map(|i| format!("$f{i} := function($x) {{ $x.items[price > {i}].{{\"n\": name}} }}"))
+            .collect();
+        let source = format!("(\n{}\n)", statements.join(";\n"));
+        let mut parser = IncrementalParser::new(source.as_str());
+
+        let start = source.find("price > 50").unwrap();
+        parser.edit(start..start + 5, "cost").unwrap();
+        assert_same_as_parse(&parser);
+
+        // The other 99 statements are reused, along with the parameter, key and value of the
+        // edited one
+        assert_eq!(parser.reused(), 102);
+    }
+}
@@ -0,0 +1,260 @@
+//! Operators which aren't part of the language, which the host registers to try out extensions to
+//! it without changing the parser, see [`Operators`].
+
+use crate::{FunctionContext, Result, Value};
+
+/// The function an operator evaluates to, which is called with its operands as its arguments.
+pub type OperatorFn =
+    for<'a, 'e> fn(FunctionContext<'a, 'e>, &'a Value<'a>) -> Result<&'a Value<'a>>;
+
+/// The characters operators can be made of, which end names, so that `a??b` is `a ?? b`.
+const OPERATOR_CHARS: &str = ".@#:?+-*/%|=<>^&!~";
+
+/// The operators of the language, which can't be registered again, and which take precedence over
+/// registered operators which are no longer, so that a `!` operator doesn't split `!=`.
+const BUILT_IN: &[&str] = &[
+    ".", "@", "#", ":", "?", "+", "-", "*", "/", "%", "|", "=", "<", ">", "^", "&", "..", ":=",
+    "!=", ">=", "<=", "**", "~>",
+];
+
+/// Operators to parse expressions with, on top of the built-in ones, see
+/// [`crate::JsonAta::with_operators`].
+///
+/// An operator is made of the characters `.@#:?+-*/%|=<>^&!~`, and the longest operator which
+/// matches is used, so a `??` operator takes precedence over the `?` of conditions, but a `!`
+/// operator doesn't over `!=`. Applying an
+/// operator calls its function with the values of its operands, as for a function call, so both
+/// operands are evaluated first.
+///
+/// How tightly an operator binds to its operands is given by its binding power. Infix operators
+/// are left-associative, and the built-in ones have these binding powers:
+///
+/// | Operators                                        | Binding power |
+/// |--------------------------------------------------|---------------|
+/// | `:=`                                             | 10            |
+/// | `? :`                                            | 20            |
+/// | `or`                                             | 25            |
+/// | `and`                                            | 30            |
+/// | `=` `!=` `<` `<=` `>` `>=` `in` `~>`              | 40            |
+/// | `&` `+` `-`                                      | 50            |
+/// | `*` `/` `%`                                      | 60            |
+/// | unary `-`                                        | 70            |
+/// | `.`                                              | 75            |
+/// | `[]` `()` `@` `#`                                | 80            |
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{JsonAta, Operators, Value};
+/// let mut operators = Operators::new();
+/// // The value on the left, or the one on the right if it's undefined
+/// operators.infix("??", 15, |_, args| {
+///     Ok(if args[0].is_undefined() { &args[1] } else { &args[0] })
+/// });
+///
+/// let arena = Bump::new();
+/// let jsonata = JsonAta::with_operators("nickname ?? name", &arena, &operators)?;
+/// let result = jsonata.evaluate(Some(r#"{"name": "Ada"}"#), None)?;
+/// assert_eq!(result.as_str(), "Ada");
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Operators {
+    operators: Vec<Operator>,
+}
+
+#[derive(Debug, Clone)]
+struct Operator {
+    symbol: String,
+    prefix: bool,
+    binding_power: u32,
+    implementation: OperatorFn,
+}
+
+impl Operators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an operator between two operands, such as `a ?? b`, replacing any operator with
+    /// the same symbol.
+    ///
+    /// # Panics
+    ///
+    /// If the symbol is a built-in operator, starts a comment, or has characters operators can't
+    /// be made of.
+    pub fn infix(
+        &mut self,
+        symbol: &str,
+        binding_power: u32,
+        implementation: OperatorFn,
+    ) -> &mut Self {
+        self.register(symbol, false, binding_power, implementation)
+    }
+
+    /// Registers an operator before its operand, such as `!a`, replacing any operator with the
+    /// same symbol.
+    ///
+    /// # Panics
+    ///
+    /// If the symbol is a built-in operator, starts a comment, or has characters operators can't
+    /// be made of.
+    pub fn prefix(
+        &mut self,
+        symbol: &str,
+        binding_power: u32,
+        implementation: OperatorFn,
+    ) -> &mut Self {
+        self.register(symbol, true, binding_power, implementation)
+    }
+
+    fn register(
+        &mut self,
+        symbol: &str,
+        prefix: bool,
+        binding_power: u32,
+        implementation: OperatorFn,
+    ) -> &mut Self {
+        assert!(
+            !symbol.is_empty() && symbol.chars().all(|c| OPERATOR_CHARS.contains(c)),
+            "Operators are made of the characters {OPERATOR_CHARS}, not `{symbol}`"
+        );
+        assert!(
+            !BUILT_IN.contains(&symbol) && !symbol.starts_with("/*"),
+            "`{symbol}` is already part of the language"
+        );
+        self.operators.retain(|operator| operator.symbol != symbol);
+        self.operators.push(Operator {
+            symbol: symbol.to_string(),
+            prefix,
+            binding_power,
+            implementation,
+        });
+        self
+    }
+
+    /// The longest operator `input` starts with, and its binding power as an infix operator,
+    /// which is 0 for prefix operators, so they never take a left operand.
+    pub(crate) fn longest_match(&self, input: &str) -> Option<(&str, u32)> {
+        let built_in = BUILT_IN
+            .iter()
+            .filter(|symbol| input.starts_with(*symbol))
+            .map(|symbol| symbol.len())
+            .max()
+            .unwrap_or(0);
+        self.operators
+            .iter()
+            .filter(|operator| {
+                operator.symbol.len() > built_in && input.starts_with(&operator.symbol)
+            })
+            .max_by_key(|operator| operator.symbol.len())
+            .map(|operator| {
+                let binding_power = if operator.prefix {
+                    0
+                } else {
+                    operator.binding_power
+                };
+                (operator.symbol.as_str(), binding_power)
+            })
+    }
+
+    /// The binding power of a prefix operator, if `symbol` is one.
+    pub(crate) fn prefix_binding_power(&self, symbol: &str) -> Option<u32> {
+        self.operators
+            .iter()
+            .find(|operator| operator.prefix && operator.symbol == symbol)
+            .map(|operator| operator.binding_power)
+    }
+
+    /// The symbol, number of operands and function of each operator.
+    pub(crate) fn implementations(&self) -> impl Iterator<Item = (&str, usize, OperatorFn)> {
+        self.operators.iter().map(|operator| {
+            let arity = if operator.prefix { 1 } else { 2 };
+            (operator.symbol.as_str(), arity, operator.implementation)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{ArrayFlags, Error, JsonAta};
+
+    fn operators() -> Operators {
+        let mut operators = Operators::new();
+        operators
+            .infix("??", 15, |_, args| {
+                Ok(if args[0].is_undefined() {
+                    &args[1]
+                } else {
+                    &args[0]
+                })
+            })
+            .infix("|>", 40, |context, args| {
+                let arg = Value::array(context.arena, ArrayFlags::empty());
+                arg.push(&args[0]);
+                context.evaluate_function(&args[1], arg)
+            })
+            .infix("**?", 60, |context, args| {
+                Ok(Value::number(
+                    context.arena,
+                    args[0].as_f64().powf(args[1].as_f64()),
+                ))
+            })
+            .prefix("!", 70, |context, args| {
+                Ok(Value::bool(context.arena, !args[0].is_truthy()))
+            });
+        operators
+    }
+
+    fn evaluate(expr: &str) -> Result<String> {
+        let arena = Bump::new();
+        let jsonata = JsonAta::with_operators(expr, &arena, &operators())?;
+        let input = r#"{"name": "Ada", "scores": [3, 1, 2]}"#;
+        Ok(jsonata.evaluate(Some(input), None)?.serialize(false))
+    }
+
+    #[test_case("nickname ?? name", r#""Ada""#)]
+    #[test_case("name??nickname", r#""Ada""#)]
+    #[test_case("nickname ?? alias ?? 'anonymous'", r#""anonymous""#)]
+    #[test_case("nickname ?? name = 'Ada'", "true"; "binds more loosely than comparisons")]
+    #[test_case("scores |> $sort |> $reverse", "[3,2,1]")]
+    #[test_case("$count(scores) |> $string = '3'", "true"; "binds as tightly as comparisons")]
+    #[test_case("2 **? 3 * 2", "16"; "left associative at the same binding power")]
+    #[test_case("1 + 2 **? 2", "5")]
+    #[test_case("!nickname", "true")]
+    #[test_case("!name and true", "false")]
+    #[test_case("name ? 'yes' : 'no'", r#""yes""#; "built-in operators they start")]
+    #[test_case("$count(scores) != 3", "false")]
+    fn operators_evaluate(expr: &str, expected: &str) {
+        assert_eq!(evaluate(expr).unwrap(), expected);
+    }
+
+    #[test_case("?? name", "S0211")]
+    #[test_case("name !", "S0201")]
+    fn operators_in_the_wrong_place(expr: &str, code: &str) {
+        assert_eq!(evaluate(expr).unwrap_err().code(), code);
+    }
+
+    #[test]
+    fn only_where_registered() {
+        let arena = Bump::new();
+        let error = JsonAta::new("nickname ?? name", &arena).err().unwrap();
+        assert!(matches!(
+            error,
+            Error::S0202UnexpectedToken(..) | Error::S0211InvalidUnary(..)
+        ));
+    }
+
+    #[test_case("?")]
+    #[test_case("~>")]
+    #[test_case("/**")]
+    #[test_case("=>>a")]
+    #[test_case("")]
+    #[should_panic]
+    fn invalid_symbols(symbol: &str) {
+        Operators::new().infix(symbol, 10, |_, args| Ok(&args[0]));
+    }
+}
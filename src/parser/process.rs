@@ -73,10 +73,10 @@ fn process_ternary(node: Ast) -> Result<Ast> {
         ref mut falsy,
     } = node.kind
     {
-        *cond = Box::new(process_ast(take(cond))?);
-        *truthy = Box::new(process_ast(take(truthy))?);
+        **cond = process_ast(take(cond))?;
+        **truthy = process_ast(take(truthy))?;
         if let Some(ref mut falsy) = falsy {
-            *falsy = Box::new(process_ast(take(falsy))?);
+            **falsy = process_ast(take(falsy))?;
         }
     } else {
         unreachable!()
@@ -93,10 +93,10 @@ fn process_transform(node: Ast) -> Result<Ast> {
         ref mut delete,
     } = node.kind
     {
-        *pattern = Box::new(process_ast(take(pattern))?);
-        *update = Box::new(process_ast(take(update))?);
+        **pattern = process_ast(take(pattern))?;
+        **update = process_ast(take(update))?;
         if let Some(ref mut delete) = delete {
-            *delete = Box::new(process_ast(take(delete))?);
+            **delete = process_ast(take(delete))?;
         }
     }
 
@@ -115,6 +115,16 @@ fn process_unary(node: Ast) -> Result<Ast> {
                     *v = -*v;
                     Ok(result)
                 }
+                AstKind::Integer(n) => match crate::evaluator::integer::negate(n) {
+                    Some(n) => Ok(Ast::new(AstKind::Integer(n), result.char_index)),
+                    None => Ok(Ast::new(AstKind::Number(-n.to_f64()), result.char_index)),
+                },
+                #[cfg(feature = "decimal")]
+                AstKind::Decimal(ref mut v, ref mut d) => {
+                    *v = -*v;
+                    *d = -*d;
+                    Ok(result)
+                }
                 _ => Ok(Ast::new(
                     AstKind::Unary(UnaryOp::Minus(Box::new(result))),
                     node.char_index,
@@ -161,8 +171,8 @@ fn process_binary(node: Ast) -> Result<Ast> {
             process_index_bind(node.char_index, lhs, rhs)
         }
         AstKind::Binary(_, ref mut lhs, ref mut rhs) => {
-            *lhs = Box::new(process_ast(take(lhs))?);
-            *rhs = Box::new(process_ast(take(rhs))?);
+            **lhs = process_ast(take(lhs))?;
+            **rhs = process_ast(take(rhs))?;
             Ok(node)
         }
         _ => unreachable!(),
@@ -199,7 +209,11 @@ fn process_path(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>) -> Re
         for (step_index, step) in steps.iter_mut().enumerate() {
             match step.kind {
                 // Steps can't be literal values other than strings
-                AstKind::Number(..) | AstKind::Bool(..) | AstKind::Null => {
+                AstKind::Number(..) | AstKind::Integer(..) | AstKind::Bool(..) | AstKind::Null => {
+                    return Err(Error::S0213InvalidStep(step.char_index, "TODO".to_string()));
+                }
+                #[cfg(feature = "decimal")]
+                AstKind::Decimal(..) => {
                     return Err(Error::S0213InvalidStep(step.char_index, "TODO".to_string()));
                 }
 
@@ -209,10 +223,10 @@ fn process_path(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut Box<Ast>) -> Re
                 }
 
                 // If the first or last step is an array constructor, it shouldn't be flattened
-                AstKind::Unary(UnaryOp::ArrayConstructor(..)) => {
-                    if step_index == 0 || step_index == last_index {
-                        step.cons_array = true;
-                    }
+                AstKind::Unary(UnaryOp::ArrayConstructor(..))
+                    if step_index == 0 || step_index == last_index =>
+                {
+                    step.cons_array = true;
                 }
 
                 _ => (),
@@ -395,7 +409,7 @@ fn process_order_by(char_index: usize, lhs: &mut Box<Ast>, rhs: &mut SortTerms)
 }
 
 fn process_function(proc: &mut Box<Ast>, args: &mut [Ast]) -> Result<()> {
-    *proc = Box::new(process_ast(take(&mut *proc))?);
+    **proc = process_ast(take(&mut *proc))?;
     for arg in args.iter_mut() {
         *arg = process_ast(take(arg))?;
     }
@@ -405,7 +419,7 @@ fn process_function(proc: &mut Box<Ast>, args: &mut [Ast]) -> Result<()> {
 fn process_lambda(body: &mut Box<Ast>) -> Result<()> {
     let new_body = process_ast(take(body))?;
     let new_body = tail_call_optimize(new_body)?;
-    *body = Box::new(new_body);
+    **body = new_body;
     Ok(())
 }
 
@@ -419,6 +433,7 @@ fn tail_call_optimize(mut expr: Ast) -> Result<Ast> {
                     args: vec![],
                     thunk: true,
                     body: Box::new(expr),
+                    signature: None,
                 },
                 char_index,
             );
@@ -426,10 +441,9 @@ fn tail_call_optimize(mut expr: Ast) -> Result<Ast> {
             Ok(thunk)
         }
         AstKind::Ternary { truthy, falsy, .. } => {
-            *truthy = Box::new(tail_call_optimize(take(truthy))?);
-            match falsy {
-                Some(inner) => *falsy = Some(Box::new(tail_call_optimize(take(inner))?)),
-                None => {}
+            **truthy = tail_call_optimize(take(truthy))?;
+            if let Some(inner) = falsy {
+                *falsy = Some(Box::new(tail_call_optimize(take(inner))?))
             }
             Ok(expr)
         }
@@ -3,6 +3,7 @@ use crate::{Error, Result};
 use super::ast::*;
 use super::tokenizer::{Token, TokenKind};
 use super::Parser;
+use crate::evaluator::signature::Signature;
 
 pub trait Symbol {
     fn left_binding_power(&self) -> u32;
@@ -26,6 +27,7 @@ impl Symbol for Token {
             Period => 75,
             LeftBracket | LeftParen => 80,
             At | Hash => 80,
+            Operator(_, binding_power) => *binding_power,
             _ => 0,
         }
     }
@@ -35,7 +37,10 @@ impl Symbol for Token {
             TokenKind::Null => Ok(Ast::new(AstKind::Null, self.char_index)),
             TokenKind::Bool(ref v) => Ok(Ast::new(AstKind::Bool(*v), self.char_index)),
             TokenKind::Str(ref v) => Ok(Ast::new(AstKind::String(v.clone()), self.char_index)),
-            TokenKind::Number(v) => Ok(Ast::new(AstKind::Number(v), self.char_index)),
+            TokenKind::Number(v) => Ok(Ast::new(
+                number_literal(v, parser.token_text(self)),
+                self.char_index,
+            )),
             TokenKind::Name(ref v) => Ok(Ast::new(AstKind::Name(v.clone()), self.char_index)),
             TokenKind::Var(ref v) => Ok(Ast::new(AstKind::Var(v.clone()), self.char_index)),
             TokenKind::And => Ok(Ast::new(
@@ -51,6 +56,18 @@ impl Symbol for Token {
             TokenKind::Asterisk => Ok(Ast::new(AstKind::Wildcard, self.char_index)),
             TokenKind::Descendent => Ok(Ast::new(AstKind::Descendent, self.char_index)),
             TokenKind::PercentSign => Ok(Ast::new(AstKind::Parent, self.char_index)),
+            TokenKind::Operator(ref symbol, _) => {
+                match parser
+                    .operators
+                    .and_then(|o| o.prefix_binding_power(symbol))
+                {
+                    Some(binding_power) => {
+                        let operand = parser.expression(binding_power)?;
+                        Ok(operator_call(symbol, vec![operand], self.char_index))
+                    }
+                    None => Err(Error::S0211InvalidUnary(self.char_index, symbol.clone())),
+                }
+            }
 
             // Block of expressions
             TokenKind::LeftParen => {
@@ -177,6 +194,10 @@ impl Symbol for Token {
             TokenKind::Or => binary!(Or),
             TokenKind::In => binary!(In),
             TokenKind::Apply => binary!(Apply),
+            TokenKind::Operator(ref symbol, binding_power) => {
+                let right = parser.expression(binding_power)?;
+                Ok(operator_call(symbol, vec![left, right], self.char_index))
+            }
 
             // Function calls or lambda definitions
             TokenKind::LeftParen => {
@@ -223,12 +244,18 @@ impl Symbol for Token {
                         name.clone()
                     }
                     AstKind::Var(ref name) => name.clone(),
-                    _ => unreachable!(),
+                    // Calling the result of an expression, like a lambda defined in place
+                    _ => String::new(),
                 };
 
                 let func: Ast;
 
                 if is_lambda {
+                    let signature = if parser.token().kind == TokenKind::LeftAngleBracket {
+                        Some(parse_signature(parser)?)
+                    } else {
+                        None
+                    };
                     parser.expect(TokenKind::LeftBrace)?;
                     let body = Box::new(parser.expression(0)?);
                     func = Ast::new(
@@ -237,6 +264,7 @@ impl Symbol for Token {
                             args,
                             body,
                             thunk: false,
+                            signature,
                         },
                         self.char_index,
                     );
@@ -392,12 +420,52 @@ impl Symbol for Token {
 
             _ => Err(Error::S0201SyntaxError(
                 self.byte_index,
-                parser.tokenizer.string_from_token(self),
+                parser.string_from_token(self),
             )),
         }
     }
 }
 
+/// A registered operator, which is a call of the function it's bound to under its symbol, which
+/// no variable can have.
+fn operator_call(symbol: &str, operands: Vec<Ast>, char_index: usize) -> Ast {
+    Ast::new(
+        AstKind::Function {
+            name: symbol.to_string(),
+            proc: Box::new(Ast::new(AstKind::Var(symbol.to_string()), char_index)),
+            args: operands,
+            is_partial: false,
+        },
+        char_index,
+    )
+}
+
+/// Parses the signature of a lambda, from its opening angle bracket to the matching closing one.
+fn parse_signature(parser: &mut Parser) -> Result<Signature> {
+    let start = parser.token().clone();
+    let mut depth = 0;
+    loop {
+        let token = parser.token().clone();
+        match token.kind {
+            TokenKind::LeftAngleBracket => depth += 1,
+            TokenKind::RightAngleBracket => depth -= 1,
+            TokenKind::End => {
+                return Err(Error::S0203ExpectedTokenBeforeEnd(
+                    token.byte_index,
+                    TokenKind::RightAngleBracket.to_string(),
+                ))
+            }
+            _ => {}
+        }
+        parser.next_token()?;
+
+        if depth == 0 {
+            let definition = &parser.source[start.byte_index..token.byte_index + token.len];
+            return Signature::parse(definition, start.char_index);
+        }
+    }
+}
+
 /// Parses an object definition.
 fn parse_object(parser: &mut Parser) -> Result<Object> {
     let mut object: Object = Vec::new();
@@ -416,3 +484,15 @@ fn parse_object(parser: &mut Parser) -> Result<Object> {
     parser.expect(TokenKind::RightBrace)?;
     Ok(object)
 }
+
+/// Numbers are kept exact where they can be, see [`crate::evaluator::integer`].
+fn number_literal(value: f64, text: &str) -> AstKind {
+    if let Some(n) = crate::evaluator::integer::parse(text) {
+        return AstKind::Integer(n);
+    }
+    #[cfg(feature = "decimal")]
+    if let Some(decimal) = crate::evaluator::decimal::parse(text) {
+        return AstKind::Decimal(value, decimal);
+    }
+    AstKind::Number(value)
+}
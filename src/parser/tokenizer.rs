@@ -2,6 +2,7 @@ use std::char::decode_utf16;
 use std::str::Chars;
 use std::{char, str};
 
+use super::operators::Operators;
 use crate::{Error, Result};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -64,6 +65,9 @@ pub enum TokenKind {
     // Identifiers
     Name(String),
     Var(String),
+
+    // An operator registered with `Operators`, and its binding power as an infix operator
+    Operator(String, u32),
 }
 
 impl std::fmt::Display for TokenKind {
@@ -115,6 +119,7 @@ impl std::fmt::Display for TokenKind {
             Number(v) => write!(f, "{}", v),
             Name(v) => write!(f, "{}", v),
             Var(v) => write!(f, "${}", v),
+            Operator(v, _) => write!(f, "{}", v),
         }
     }
 }
@@ -147,6 +152,9 @@ pub struct Tokenizer<'a> {
 
     /// The starting char index of the current token being generated (used for errors)
     start_char_index: usize,
+
+    /// Operators registered on top of the built-in ones
+    operators: Option<&'a Operators>,
 }
 
 const NULL: char = '\0';
@@ -211,6 +219,25 @@ impl<'a> Tokenizer<'a> {
             char_index: 0,
             start_byte_index: 0,
             start_char_index: 0,
+            operators: None,
+        }
+    }
+
+    /// Tokenizes the registered `operators` as well as the built-in ones.
+    pub fn with_operators(input: &'a str, operators: &'a Operators) -> Self {
+        Self {
+            operators: Some(operators),
+            ..Self::new(input)
+        }
+    }
+
+    /// Starts tokenizing part of the way through the input, which must be where a token ended.
+    pub fn starting_at(input: &'a str, byte_index: usize, char_index: usize) -> Self {
+        Self {
+            chars: input[byte_index..].chars(),
+            byte_index,
+            char_index,
+            ..Self::new(input)
         }
     }
 
@@ -245,10 +272,6 @@ impl<'a> Tokenizer<'a> {
         String::from(&self.input[self.start_byte_index..self.byte_index])
     }
 
-    pub fn string_from_token(&self, token: &Token) -> String {
-        String::from(&self.input[token.byte_index..token.byte_index + token.len])
-    }
-
     fn get_hex_digit(&mut self) -> Result<u16> {
         let ch = self.bump();
         if ch.len_utf8() != 1 {
@@ -271,289 +294,309 @@ impl<'a> Tokenizer<'a> {
             | self.get_hex_digit()?)
     }
 
+    /// The next token for the parser, skipping whitespace and comments.
     pub fn next_token(&mut self) -> Result<Token> {
-        use TokenKind::*;
+        loop {
+            let token = self.next_lexeme()?;
+            if !matches!(token.kind, TokenKind::Whitespace | TokenKind::Comment) {
+                return Ok(token);
+            }
+        }
+    }
 
-        let kind = loop {
-            self.start_byte_index = self.byte_index;
-            self.start_char_index = self.char_index;
+    /// The next token, including whitespace and comments.
+    pub fn next_lexeme(&mut self) -> Result<Token> {
+        use TokenKind::*;
 
-            let kind = match self.bump() {
-                NULL => End,
+        self.start_byte_index = self.byte_index;
+        self.start_char_index = self.char_index;
 
-                c if is_whitespace(c) => {
-                    self.eat_while(is_whitespace);
-                    Whitespace
-                }
+        if let Some(kind) = self.registered_operator() {
+            return Ok(self.token(kind));
+        }
 
-                // Comments, forward-slashes or regexp
-                // TODO: Regexp
-                '/' => match self.peek() {
-                    '*' => {
-                        // Skip the *
-                        self.bump();
+        let kind = match self.bump() {
+            NULL => End,
 
-                        loop {
-                            // Eat until the next *
-                            self.eat_while(|c| c != '*');
+            c if is_whitespace(c) => {
+                self.eat_while(is_whitespace);
+                Whitespace
+            }
 
-                            // Skip the *
-                            self.bump();
+            // Comments, forward-slashes or regexp
+            // TODO: Regexp
+            '/' => match self.peek() {
+                '*' => {
+                    // Skip the *
+                    self.bump();
 
-                            // Check for unterminated comments
-                            if self.eof() {
-                                return Err(Error::S0106UnterminatedComment(self.start_char_index));
-                            }
+                    loop {
+                        // Eat until the next *
+                        self.eat_while(|c| c != '*');
 
-                            // Is this the end of the comment?
-                            if self.bump() == '/' {
-                                break;
-                            }
+                        // Check for unterminated comments
+                        if self.eof() {
+                            return Err(Error::S0106UnterminatedComment(self.start_char_index));
                         }
 
-                        Comment
-                    }
-                    _ => ForwardSlash,
-                },
-
-                '.' => match self.peek() {
-                    '.' => {
+                        // Skip the *
                         self.bump();
-                        Range
-                    }
-                    _ => Period,
-                },
 
-                ':' => match self.peek() {
-                    '=' => {
-                        self.bump();
-                        Bind
+                        // Is this the end of the comment? If not, the next character may
+                        // be another *, as in `**/`
+                        if self.peek() == '/' {
+                            self.bump();
+                            break;
+                        }
                     }
-                    _ => Colon,
-                },
 
-                '!' => match self.peek() {
-                    '=' => {
-                        self.bump();
-                        NotEqual
-                    }
-                    _ => ExclamationMark,
-                },
+                    Comment
+                }
+                _ => ForwardSlash,
+            },
 
-                '*' => match self.peek() {
-                    '*' => {
-                        self.bump();
-                        Descendent
-                    }
-                    _ => Asterisk,
-                },
+            '.' => match self.peek() {
+                '.' => {
+                    self.bump();
+                    Range
+                }
+                _ => Period,
+            },
 
-                '~' => match self.peek() {
-                    '>' => {
-                        self.bump();
-                        Apply
-                    }
-                    _ => Tilde,
-                },
+            ':' => match self.peek() {
+                '=' => {
+                    self.bump();
+                    Bind
+                }
+                _ => Colon,
+            },
 
-                '>' => match self.peek() {
-                    '=' => {
-                        self.bump();
-                        GreaterEqual
-                    }
-                    _ => RightAngleBracket,
-                },
+            '!' => match self.peek() {
+                '=' => {
+                    self.bump();
+                    NotEqual
+                }
+                _ => ExclamationMark,
+            },
 
-                '<' => match self.peek() {
-                    '=' => {
-                        self.bump();
-                        LessEqual
-                    }
-                    _ => LeftAngleBracket,
-                },
-
-                '[' => LeftBracket,
-                ']' => RightBracket,
-                '{' => LeftBrace,
-                '}' => RightBrace,
-                '(' => LeftParen,
-                ')' => RightParen,
-                ',' => Comma,
-                '@' => At,
-                '#' => Hash,
-                ';' => SemiColon,
-                '?' => QuestionMark,
-                '+' => Plus,
-                '-' => Minus,
-                '%' => PercentSign,
-                '|' => Pipe,
-                '=' => Equal,
-                '^' => Caret,
-                '&' => Ampersand,
-
-                // Backtick identifiers like a.`b`.c
-                '`' => {
-                    let start_byte_index = self.byte_index;
-
-                    // Eat until the next `
-                    self.eat_while(|c| c != '`');
-
-                    // Check for unterminated quotes
-                    if self.eof() {
-                        return Err(Error::S0105UnterminatedQuoteProp(self.start_char_index));
-                    }
+            '*' => match self.peek() {
+                '*' => {
+                    self.bump();
+                    Descendent
+                }
+                _ => Asterisk,
+            },
 
-                    let token = Name(String::from(&self.input[start_byte_index..self.byte_index]));
+            '~' => match self.peek() {
+                '>' => {
+                    self.bump();
+                    Apply
+                }
+                _ => Tilde,
+            },
 
-                    // Skip the final `
+            '>' => match self.peek() {
+                '=' => {
                     self.bump();
+                    GreaterEqual
+                }
+                _ => RightAngleBracket,
+            },
 
-                    token
+            '<' => match self.peek() {
+                '=' => {
+                    self.bump();
+                    LessEqual
+                }
+                _ => LeftAngleBracket,
+            },
+
+            '[' => LeftBracket,
+            ']' => RightBracket,
+            '{' => LeftBrace,
+            '}' => RightBrace,
+            '(' => LeftParen,
+            ')' => RightParen,
+            ',' => Comma,
+            '@' => At,
+            '#' => Hash,
+            ';' => SemiColon,
+            '?' => QuestionMark,
+            '+' => Plus,
+            '-' => Minus,
+            '%' => PercentSign,
+            '|' => Pipe,
+            '=' => Equal,
+            '^' => Caret,
+            '&' => Ampersand,
+
+            // Backtick identifiers like a.`b`.c
+            '`' => {
+                let start_byte_index = self.byte_index;
+
+                // Eat until the next `
+                self.eat_while(|c| c != '`');
+
+                // Check for unterminated quotes
+                if self.eof() {
+                    return Err(Error::S0105UnterminatedQuoteProp(self.start_char_index));
                 }
 
-                // String literals
-                quote @ ('\'' | '"') => {
-                    loop {
-                        match self.bump() {
-                            // Supported escape sequences
-                            '\\' => match self.bump() {
-                                '\\' => self.buffer.push('\\'),
-                                '"' => self.buffer.push('"'),
-                                'b' => self.buffer.push('\x08'),
-                                'f' => self.buffer.push('\x0c'),
-                                'n' => self.buffer.push('\n'),
-                                'r' => self.buffer.push('\r'),
-                                't' => self.buffer.push('\t'),
-
-                                // 2-byte hex UTF-16 escape like \u0010.
-                                // Note that UTF-16 surrogate pairs (for characters outside of the Basic Multilingual Plane)
-                                // are represented as two escape sequences which can't be directly converted to a UTF-8 char.
-                                // Example: \\uD83D\\uDE02 => 😂
-                                'u' => {
-                                    let codepoint = self.get_codepoint()?;
-
-                                    let unicode = match char::try_from(codepoint as u32) {
-                                        Ok(code) => code,
-                                        Err(_) => match (self.bump(), self.bump()) {
-                                            // The codepoint was not valid UTF-8, look for another one that could be part
-                                            // of a surrogate pair
-                                            ('\\', 'u') => {
-                                                match decode_utf16(
-                                                    [codepoint, self.get_codepoint()?]
-                                                        .iter()
-                                                        .copied(),
-                                                )
-                                                .next()
-                                                {
-                                                    Some(Ok(code)) => code,
-                                                    _ => {
-                                                        return Err(
-                                                            Error::S0104InvalidUnicodeEscape(
-                                                                self.start_char_index,
-                                                            ),
-                                                        )
-                                                    }
+                let token = Name(String::from(&self.input[start_byte_index..self.byte_index]));
+
+                // Skip the final `
+                self.bump();
+
+                token
+            }
+
+            // String literals
+            quote @ ('\'' | '"') => {
+                loop {
+                    match self.bump() {
+                        // Supported escape sequences
+                        '\\' => match self.bump() {
+                            '\\' => self.buffer.push('\\'),
+                            '"' => self.buffer.push('"'),
+                            'b' => self.buffer.push('\x08'),
+                            'f' => self.buffer.push('\x0c'),
+                            'n' => self.buffer.push('\n'),
+                            'r' => self.buffer.push('\r'),
+                            't' => self.buffer.push('\t'),
+
+                            // 2-byte hex UTF-16 escape like \u0010.
+                            // Note that UTF-16 surrogate pairs (for characters outside of the Basic Multilingual Plane)
+                            // are represented as two escape sequences which can't be directly converted to a UTF-8 char.
+                            // Example: \\uD83D\\uDE02 => 😂
+                            'u' => {
+                                let codepoint = self.get_codepoint()?;
+
+                                let unicode = match char::try_from(codepoint as u32) {
+                                    Ok(code) => code,
+                                    Err(_) => match (self.bump(), self.bump()) {
+                                        // The codepoint was not valid UTF-8, look for another one that could be part
+                                        // of a surrogate pair
+                                        ('\\', 'u') => {
+                                            match decode_utf16(
+                                                [codepoint, self.get_codepoint()?].iter().copied(),
+                                            )
+                                            .next()
+                                            {
+                                                Some(Ok(code)) => code,
+                                                _ => {
+                                                    return Err(Error::S0104InvalidUnicodeEscape(
+                                                        self.start_char_index,
+                                                    ))
                                                 }
                                             }
-                                            _ => {
-                                                return Err(Error::S0104InvalidUnicodeEscape(
-                                                    self.start_char_index,
-                                                ))
-                                            }
-                                        },
-                                    };
-
-                                    self.buffer.push(unicode);
-                                }
-                                c => {
-                                    return Err(Error::S0103UnsupportedEscape(
-                                        self.start_char_index,
-                                        c,
-                                    ));
-                                }
-                            },
-
-                            // End of string
-                            c if c == quote => {
-                                break;
+                                        }
+                                        _ => {
+                                            return Err(Error::S0104InvalidUnicodeEscape(
+                                                self.start_char_index,
+                                            ))
+                                        }
+                                    },
+                                };
+
+                                self.buffer.push(unicode);
                             }
-
                             c => {
-                                // Check for unterminated strings
-                                if self.eof() {
-                                    return Err(Error::S0101UnterminatedStringLiteral(
-                                        self.start_char_index,
-                                    ));
-                                }
-
-                                self.buffer.push(c);
+                                return Err(Error::S0103UnsupportedEscape(
+                                    self.start_char_index,
+                                    c,
+                                ));
                             }
+                        },
+
+                        // End of string
+                        c if c == quote => {
+                            break;
+                        }
+
+                        c => {
+                            // Check for unterminated strings
+                            if self.eof() {
+                                return Err(Error::S0101UnterminatedStringLiteral(
+                                    self.start_char_index,
+                                ));
+                            }
+
+                            self.buffer.push(c);
                         }
                     }
+                }
 
-                    let s = String::from_iter(self.buffer.clone());
-                    let token = Str(s);
+                let s = String::from_iter(self.buffer.clone());
+                let token = Str(s);
 
-                    // The buffer gets cleared for the next string
-                    self.buffer.clear();
+                // The buffer gets cleared for the next string
+                self.buffer.clear();
 
-                    token
-                }
+                token
+            }
 
-                // Numbers
-                '0' => {
-                    if self.eof() {
-                        Number(0.0)
-                    } else {
-                        self.scan_number()?
-                    }
+            // Numbers
+            '0' => {
+                if self.eof() {
+                    Number(0.0)
+                } else {
+                    self.scan_number()?
                 }
-                '1'..='9' => self.scan_number()?,
-
-                // Names
-                c if is_name_start(c) => {
-                    self.eat_while(|c| !(is_whitespace(c) || is_operator(c)));
-
-                    if c == '$' {
-                        Var(String::from(
-                            &self.input[self.start_byte_index + 1..self.byte_index],
-                        ))
-                    } else {
-                        match &self.input[self.start_byte_index..self.byte_index] {
-                            "or" => Or,
-                            "in" => In,
-                            "and" => And,
-                            "true" => Bool(true),
-                            "false" => Bool(false),
-                            "null" => Null,
-                            _ => Name(String::from(
-                                &self.input[self.start_byte_index..self.byte_index],
-                            )),
-                        }
+            }
+            '1'..='9' => self.scan_number()?,
+
+            // Names
+            c if is_name_start(c) => {
+                self.eat_while(|c| !(is_whitespace(c) || is_operator(c)));
+
+                if c == '$' {
+                    Var(String::from(
+                        &self.input[self.start_byte_index + 1..self.byte_index],
+                    ))
+                } else {
+                    match &self.input[self.start_byte_index..self.byte_index] {
+                        "or" => Or,
+                        "in" => In,
+                        "and" => And,
+                        "true" => Bool(true),
+                        "false" => Bool(false),
+                        "null" => Null,
+                        _ => Name(String::from(
+                            &self.input[self.start_byte_index..self.byte_index],
+                        )),
                     }
                 }
+            }
 
-                _ => {
-                    return Err(Error::S0204UnknownOperator(
-                        self.start_char_index,
-                        self.token_string(),
-                    ));
-                }
-            };
-
-            if !matches!(kind, Whitespace | Comment) {
-                break kind;
+            _ => {
+                return Err(Error::S0204UnknownOperator(
+                    self.start_char_index,
+                    self.token_string(),
+                ));
             }
         };
 
-        let token = Token {
+        Ok(self.token(kind))
+    }
+
+    /// The token from where the current one started to here.
+    fn token(&self, kind: TokenKind) -> Token {
+        Token {
             kind,
             char_index: self.start_char_index,
             byte_index: self.start_byte_index,
             len: self.byte_index - self.start_byte_index,
-        };
+        }
+    }
 
-        Ok(token)
+    /// A registered operator at the current position, which takes precedence over the built-in
+    /// operators it starts with, as `??` does over `?`.
+    fn registered_operator(&mut self) -> Option<TokenKind> {
+        let (symbol, binding_power) = self.operators?.longest_match(self.chars.as_str())?;
+        for _ in symbol.chars() {
+            self.bump();
+        }
+        Some(TokenKind::Operator(symbol.to_string(), binding_power))
     }
 
     fn scan_number(&mut self) -> Result<TokenKind> {
@@ -610,6 +653,34 @@ mod tests {
         assert!(matches!(t.next_token().unwrap().kind, TokenKind::End));
     }
 
+    #[test]
+    fn comments_with_stars() {
+        for source in ["/** doc */", "/* a **/", "/***/", "/* a * b */"] {
+            let mut t = Tokenizer::new(source);
+            assert!(matches!(t.next_token().unwrap().kind, TokenKind::End));
+        }
+    }
+
+    #[test]
+    fn unterminated_comments() {
+        for source in ["1 /* a", "1 /* a *", "1 /* a */ /*"] {
+            let mut t = Tokenizer::new(source);
+            let error = std::iter::from_fn(|| Some(t.next_token()))
+                .find_map(|token| token.err())
+                .unwrap();
+            assert_eq!(error.code(), "S0106");
+        }
+    }
+
+    #[test]
+    fn positions_after_comments() {
+        let mut t = Tokenizer::new("/* café\n */ name");
+        let token = t.next_token().unwrap();
+        assert!(matches!(token.kind, TokenKind::Name(ref name) if name == "name"));
+        assert_eq!(token.char_index, 12);
+        assert_eq!(token.byte_index, 13);
+    }
+
     #[test]
     fn operators() {
         let mut t = Tokenizer::new("@..[]{}()=^&,~>#+<=:=>=!=?-***");
@@ -0,0 +1,257 @@
+//! Walking the AST without matching every kind of node, so that tools keep working when kinds
+//! are added.
+
+use super::ast::{Ast, AstKind, UnaryOp};
+
+/// Visits the nodes of an AST. The default [`Visitor::visit_node`] visits the children of each
+/// node, so an implementation only needs to match the kinds it's interested in, and call
+/// [`walk_node`] to carry on into the children:
+///
+/// ```ignore
+/// # use jsonata_rs::ast::{parse, walk_node, Ast, AstKind, Visitor};
+/// #[derive(Default)]
+/// struct Variables(Vec<String>);
+///
+/// impl Visitor for Variables {
+///     fn visit_node(&mut self, node: &Ast) {
+///         if let AstKind::Var(ref name) = node.kind {
+///             self.0.push(name.clone());
+///         }
+///         walk_node(self, node);
+///     }
+/// }
+///
+/// let mut variables = Variables::default();
+/// variables.visit_node(&parse("$a + items[price > $b].($c)")?);
+/// assert_eq!(variables.0, ["a", "b", "c"]);
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+pub trait Visitor {
+    fn visit_node(&mut self, node: &Ast) {
+        walk_node(self, node);
+    }
+}
+
+/// Like [`Visitor`], for rewriting an AST in place.
+pub trait VisitorMut {
+    fn visit_node_mut(&mut self, node: &mut Ast) {
+        walk_node_mut(self, node);
+    }
+}
+
+/// Visits each child of `node`, followed by the predicates, stages and group-by of a step. The
+/// parameters of a lambda aren't visited, as they're bindings rather than expressions.
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &Ast) {
+    for_each_child(node, &mut |child| visitor.visit_node(child));
+}
+
+/// Visits each child of `node` mutably, see [`walk_node`].
+pub fn walk_node_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Ast) {
+    for_each_child_mut(node, &mut |child| visitor.visit_node_mut(child));
+}
+
+pub(crate) fn for_each_child(ast: &Ast, f: &mut impl FnMut(&Ast)) {
+    for_each_operand(ast, f);
+    for_each_stage(ast, f);
+}
+
+/// Calls `f` with each child of `node` that's part of its kind, such as the operands of a binary
+/// operator, the steps of a path or the body of a lambda.
+pub(crate) fn for_each_operand(ast: &Ast, f: &mut impl FnMut(&Ast)) {
+    match ast.kind {
+        AstKind::Unary(UnaryOp::Minus(ref value)) => f(value),
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref items)) => items.iter().for_each(&mut *f),
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref object)) => {
+            object.iter().for_each(|(key, value)| {
+                f(key);
+                f(value);
+            })
+        }
+        AstKind::Binary(_, ref lhs, ref rhs) => {
+            f(lhs);
+            f(rhs);
+        }
+        AstKind::GroupBy(ref lhs, ref object) => {
+            f(lhs);
+            object.iter().for_each(|(key, value)| {
+                f(key);
+                f(value);
+            });
+        }
+        AstKind::OrderBy(ref lhs, ref terms) => {
+            f(lhs);
+            terms.iter().for_each(|(term, _)| f(term));
+        }
+        AstKind::Sort(ref terms) => terms.iter().for_each(|(term, _)| f(term)),
+        AstKind::Block(ref exprs) | AstKind::Path(ref exprs) => exprs.iter().for_each(&mut *f),
+        AstKind::Function {
+            ref proc, ref args, ..
+        } => {
+            f(proc);
+            args.iter().for_each(&mut *f);
+        }
+        AstKind::Lambda { ref body, .. } | AstKind::Filter(ref body) => f(body),
+        AstKind::Ternary {
+            ref cond,
+            ref truthy,
+            ref falsy,
+        } => {
+            f(cond);
+            f(truthy);
+            if let Some(falsy) = falsy {
+                f(falsy);
+            }
+        }
+        AstKind::Transform {
+            ref pattern,
+            ref update,
+            ref delete,
+        } => {
+            f(pattern);
+            f(update);
+            if let Some(delete) = delete {
+                f(delete);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Calls `f` with each predicate, stage and group-by expression of a step, which are evaluated for
+/// each item the step produces.
+pub(crate) fn for_each_stage(ast: &Ast, f: &mut impl FnMut(&Ast)) {
+    ast.predicates.iter().flatten().for_each(&mut *f);
+    ast.stages.iter().flatten().for_each(&mut *f);
+    if let Some((_, ref object)) = ast.group_by {
+        object.iter().for_each(|(key, value)| {
+            f(key);
+            f(value);
+        });
+    }
+}
+
+pub(crate) fn for_each_child_mut(ast: &mut Ast, f: &mut impl FnMut(&mut Ast)) {
+    match ast.kind {
+        AstKind::Unary(UnaryOp::Minus(ref mut value)) => f(value),
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref mut items)) => {
+            items.iter_mut().for_each(&mut *f)
+        }
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref mut object)) => {
+            object.iter_mut().for_each(|(key, value)| {
+                f(key);
+                f(value);
+            })
+        }
+        AstKind::Binary(_, ref mut lhs, ref mut rhs) => {
+            f(lhs);
+            f(rhs);
+        }
+        AstKind::GroupBy(ref mut lhs, ref mut object) => {
+            f(lhs);
+            object.iter_mut().for_each(|(key, value)| {
+                f(key);
+                f(value);
+            });
+        }
+        AstKind::OrderBy(ref mut lhs, ref mut terms) => {
+            f(lhs);
+            terms.iter_mut().for_each(|(term, _)| f(term));
+        }
+        AstKind::Sort(ref mut terms) => terms.iter_mut().for_each(|(term, _)| f(term)),
+        AstKind::Block(ref mut exprs) | AstKind::Path(ref mut exprs) => {
+            exprs.iter_mut().for_each(&mut *f)
+        }
+        AstKind::Function {
+            ref mut proc,
+            ref mut args,
+            ..
+        } => {
+            f(proc);
+            args.iter_mut().for_each(&mut *f);
+        }
+        AstKind::Lambda { ref mut body, .. } | AstKind::Filter(ref mut body) => f(body),
+        AstKind::Ternary {
+            ref mut cond,
+            ref mut truthy,
+            ref mut falsy,
+        } => {
+            f(cond);
+            f(truthy);
+            if let Some(falsy) = falsy {
+                f(falsy);
+            }
+        }
+        AstKind::Transform {
+            ref mut pattern,
+            ref mut update,
+            ref mut delete,
+        } => {
+            f(pattern);
+            f(update);
+            if let Some(delete) = delete {
+                f(delete);
+            }
+        }
+        _ => {}
+    }
+
+    ast.predicates.iter_mut().flatten().for_each(&mut *f);
+    ast.stages.iter_mut().flatten().for_each(&mut *f);
+    if let Some((_, ref mut object)) = ast.group_by {
+        object.iter_mut().for_each(|(key, value)| {
+            f(key);
+            f(value);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_node(&mut self, node: &Ast) {
+            if let AstKind::Name(ref name) = node.kind {
+                self.0.push(name.clone());
+            }
+            walk_node(self, node);
+        }
+    }
+
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_node_mut(&mut self, node: &mut Ast) {
+            if let AstKind::Name(ref mut name) = node.kind {
+                *name = name.to_uppercase();
+            }
+            walk_node_mut(self, node);
+        }
+    }
+
+    fn names(ast: &Ast) -> Vec<String> {
+        let mut names = Names(vec![]);
+        names.visit_node(ast);
+        names.0
+    }
+
+    #[test]
+    fn visits_every_expression() {
+        let ast = parse("a.b[c > 1]{d: e}^(f) ~> $g(h, function($x) { i }) ? { 'k': j } : [l, -m]")
+            .unwrap();
+        assert_eq!(
+            names(&ast),
+            ["a", "b", "c", "f", "d", "e", "h", "i", "j", "l", "m"]
+        );
+    }
+
+    #[test]
+    fn rewrites() {
+        let mut ast = parse("a.b[c = 'x'] & $string(d)").unwrap();
+        Rename.visit_node_mut(&mut ast);
+        assert_eq!(names(&ast), ["A", "B", "C", "D"]);
+    }
+}
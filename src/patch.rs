@@ -0,0 +1,124 @@
+use crate::{ArrayFlags, JsonAta, Result, Value};
+
+/// The result of [`JsonAta::evaluate_patch`], along with the changes its transforms made.
+#[derive(Debug)]
+pub struct Patched<'a> {
+    pub result: &'a Value<'a>,
+
+    /// An array of [RFC 6902](https://www.rfc-editor.org/rfc/rfc6902) JSON Patch operations.
+    pub patch: &'a Value<'a>,
+}
+
+impl<'a> JsonAta<'a> {
+    /// Evaluates the expression, returning the result along with the changes the transforms in it
+    /// made, as a JSON Patch, such as for syncing them to another copy of the input:
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let jsonata = JsonAta::new("$ ~> |items[qty = 0]|{'status': 'gone'}, ['qty']|", &arena)?;
+    /// let patched = jsonata.evaluate_patch(Some(r#"{"items": [{"qty": 2}, {"qty": 0}]}"#))?;
+    /// assert_eq!(
+    ///     patched.patch.serialize(false),
+    ///     r#"[{"op":"add","path":"/items/1/status","value":"gone"},{"op":"remove","path":"/items/1/qty"}]"#
+    /// );
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Each member a transform's update sets is an `add` operation if the object didn't have it
+    /// and a `replace` if it did, and each one it deletes is a `remove`. The paths are pointers
+    /// into the input, so only transforms of the whole input, such as `$ ~> | ... |`, and of
+    /// their results are recorded. A transform of a value within it, such as `order ~> | ... |`
+    /// or one in a lambda which `$map` calls, isn't, and neither are changes to objects an update
+    /// created.
+    pub fn evaluate_patch(&self, input: Option<&str>) -> Result<Patched<'a>> {
+        let input = self.parse_input(input)?;
+        let evaluator = self.evaluator(None, None)?.with_patch(input);
+        // The threads have evaluators of their own, which wouldn't record anything
+        #[cfg(feature = "parallel")]
+        let evaluator = evaluator.with_parallel(None);
+
+        let result = self.evaluate_with(&evaluator, input)?;
+
+        let patch = Value::array(self.arena, ArrayFlags::empty());
+        for operation in evaluator.take_patch().unwrap_or_default() {
+            patch.push(operation);
+        }
+        Ok(Patched { result, patch })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::Backend;
+
+    const INPUT: &str = r#"{
+        "order": {"id": "a/1", "status": "new"},
+        "items": [{"sku": "x", "qty": 2}, {"sku": "y", "qty": 0}]
+    }"#;
+
+    #[test_case(
+        "$ ~> |order|{'status': 'paid'}|",
+        r#"[{"op":"replace","path":"/order/status","value":"paid"}]"#
+    )]
+    #[test_case(
+        "$ ~> |items[qty = 0]|{}, ['qty', 'missing']|",
+        r#"[{"op":"remove","path":"/items/1/qty"}]"#
+    )]
+    #[test_case(
+        "$ ~> |items|{'total': qty * 2}|",
+        r#"[{"op":"add","path":"/items/0/total","value":4},{"op":"add","path":"/items/1/total","value":0}]"#
+    )]
+    #[test_case(
+        "$ ~> |$|{'a~b': 1, 'c/d': 2}|",
+        r#"[{"op":"add","path":"/a~0b","value":1},{"op":"add","path":"/c~1d","value":2}]"#
+    )]
+    #[test_case(
+        "$ ~> |order|{'status': 'paid'}| ~> |order|{}, ['id']|",
+        r#"[{"op":"replace","path":"/order/status","value":"paid"},{"op":"remove","path":"/order/id"}]"#;
+        "chained transforms"
+    )]
+    #[test_case("$ ~> |missing|{'a': 1}|", "[]")]
+    #[test_case("order ~> |$|{'status': 'paid'}|", "[]"; "transform within the input")]
+    #[test_case(
+        "[$ ~> |order|{'status': 'paid'}|, items[0] ~> |$|{'qty': 1}|]",
+        r#"[{"op":"replace","path":"/order/status","value":"paid"}]"#;
+        "transforms of the input and within it"
+    )]
+    #[test_case(
+        "($o := $ ~> |order|{'status': 'paid'}|; $o.order ~> |$|{'id': 'b'}|; $o ~> |items|{}, ['sku']|)",
+        r#"[{"op":"replace","path":"/order/status","value":"paid"},{"op":"remove","path":"/items/0/sku"},{"op":"remove","path":"/items/1/sku"}]"#;
+        "transform of a transformed input"
+    )]
+    #[cfg_attr(
+        feature = "higher-order",
+        test_case("$map(items, function($i) { $i ~> |$|{'qty': 1}| })", "[]"; "transforms in map")
+    )]
+    #[test_case("order.status", "[]")]
+    fn patches(expr: &str, expected: &str) {
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            let arena = Bump::new();
+            let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+            jsonata.set_backend(backend);
+            let patched = jsonata.evaluate_patch(Some(INPUT)).unwrap();
+            assert_eq!(patched.patch.serialize(false), expected);
+        }
+    }
+
+    #[test]
+    fn result_alongside() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("($ ~> |order|{'status': 'paid'}|).order", &arena).unwrap();
+        let patched = jsonata.evaluate_patch(Some(INPUT)).unwrap();
+        assert_eq!(
+            patched.result.serialize(false),
+            r#"{"id":"a/1","status":"paid"}"#
+        );
+        assert_eq!(patched.patch.len(), 1);
+    }
+}
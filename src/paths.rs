@@ -0,0 +1,288 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::parser::ast::{Ast, AstKind, BinaryOp, UnaryOp};
+use crate::parser::visit::for_each_child;
+use crate::{CompiledExpression, JsonAta};
+
+impl CompiledExpression {
+    /// The paths into the input which the expression can read, such as `order.customer.email`,
+    /// for building projections or auditing which fields a transformation touches.
+    ///
+    /// A path is reported when its value is used as a whole, so `items[price > 5].name` reads
+    /// `items.price` and `items.name`, but not the rest of `items`. `$` means the whole input,
+    /// and a wildcard or descendant step is written `*` or `**`. Names which aren't plain
+    /// identifiers are quoted with backticks, so each path is itself a JSONata path.
+    ///
+    /// Paths are followed through variables bound with `:=`, but not into the arguments of
+    /// lambdas, so `$map(items, function($v) { $v.price })` reads all of `items`.
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("$sum(items[price > 5].(price * quantity))")?;
+    /// assert_eq!(
+    ///     expression.referenced_paths().into_iter().collect::<Vec<_>>(),
+    ///     ["items.price", "items.quantity"]
+    /// );
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn referenced_paths(&self) -> BTreeSet<String> {
+        referenced_paths(&self.ast)
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::referenced_paths`].
+    pub fn referenced_paths(&self) -> BTreeSet<String> {
+        referenced_paths(&self.ast)
+    }
+}
+
+fn referenced_paths(ast: &Ast) -> BTreeSet<String> {
+    referenced_segments(ast)
+        .iter()
+        .map(|path| format_path(path))
+        .collect()
+}
+
+/// A step of a path into the input.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Segment {
+    /// The member with a name, which can be `*` or `**` if it was quoted.
+    Name(String),
+    /// `*`, every member.
+    Wildcard,
+    /// `**`, every value below.
+    Descendants,
+}
+
+/// The paths `ast` reads, as their steps.
+pub(crate) fn referenced_segments(ast: &Ast) -> BTreeSet<Vec<Segment>> {
+    let mut collector = Collector {
+        scopes: vec![HashMap::new()],
+        paths: BTreeSet::new(),
+    };
+    collector.visit(ast, Some(&[]), true);
+    collector.paths
+}
+
+/// A path into the input, or `None` if an expression's value doesn't come from one.
+type Path = Option<Vec<Segment>>;
+
+struct Collector {
+    /// The paths bound to variables in each enclosing block
+    scopes: Vec<HashMap<String, Path>>,
+    paths: BTreeSet<Vec<Segment>>,
+}
+
+impl Collector {
+    /// Collects the paths read by `node` when its context is `context`, returning the path its
+    /// value comes from. If `record` is false, that path is being continued by a later step, so it
+    /// isn't reported itself.
+    fn visit(&mut self, node: &Ast, context: Option<&[Segment]>, record: bool) -> Path {
+        let extend = |segment: Segment| {
+            context.map(|context| {
+                let mut path = context.to_vec();
+                path.push(segment);
+                path
+            })
+        };
+
+        let mut path = match node.kind {
+            AstKind::Name(ref name) => extend(Segment::Name(name.clone())),
+            AstKind::Wildcard => extend(Segment::Wildcard),
+            AstKind::Descendent => extend(Segment::Descendants),
+            AstKind::Parent => context
+                .and_then(|context| context.split_last())
+                .map(|(_, parent)| parent.to_vec()),
+
+            // `$` is the context and `$$` is the input
+            AstKind::Var(ref name) if name.is_empty() => context.map(<[Segment]>::to_vec),
+            AstKind::Var(ref name) if name == "$" => Some(vec![]),
+            AstKind::Var(ref name) => self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .cloned()
+                .flatten(),
+
+            AstKind::Path(ref steps) => {
+                let mut path = context.map(<[Segment]>::to_vec);
+                for step in steps {
+                    let step_path = self.visit(step, path.as_deref(), false);
+                    match step.focus {
+                        // A focus binding doesn't change the context of the next step
+                        Some(ref focus) => self.bind(focus, step_path),
+                        None => path = step_path,
+                    }
+                    if let Some(ref index) = step.index {
+                        self.bind(index, None);
+                    }
+                }
+                path
+            }
+
+            AstKind::Block(ref exprs) => {
+                self.scopes.push(HashMap::new());
+                // The value of a block is the value of its last expression, and the values of the
+                // others aren't used
+                let mut path = None;
+                for expr in exprs {
+                    path = self.visit(expr, context, false);
+                }
+                self.scopes.pop();
+                path
+            }
+
+            AstKind::Binary(BinaryOp::Bind, ref lhs, ref rhs) => {
+                let path = self.visit(rhs, context, false);
+                if let AstKind::Var(ref name) = lhs.kind {
+                    self.bind(name, path.clone());
+                }
+                path
+            }
+
+            AstKind::Lambda {
+                ref args, ref body, ..
+            } => {
+                let scope = args
+                    .iter()
+                    .filter_map(|arg| match arg.kind {
+                        AstKind::Var(ref name) => Some((name.clone(), None)),
+                        _ => None,
+                    })
+                    .collect();
+                self.scopes.push(scope);
+                self.visit(body, context, true);
+                self.scopes.pop();
+                None
+            }
+
+            AstKind::Function {
+                ref proc, ref args, ..
+            } => {
+                self.visit(proc, context, false);
+                for arg in args {
+                    self.visit(arg, context, true);
+                }
+                None
+            }
+
+            AstKind::Filter(ref predicate) => {
+                self.visit(predicate, context, true);
+                context.map(<[Segment]>::to_vec)
+            }
+            AstKind::Sort(ref terms) => {
+                for (term, _) in terms {
+                    self.visit(term, context, true);
+                }
+                context.map(<[Segment]>::to_vec)
+            }
+            AstKind::OrderBy(ref lhs, ref terms) => {
+                let path = self.visit(lhs, context, false);
+                for (term, _) in terms {
+                    self.visit(term, path.as_deref(), true);
+                }
+                path
+            }
+            AstKind::GroupBy(ref lhs, ref object) => {
+                let path = self.visit(lhs, context, false);
+                self.visit_object(object, path.as_deref());
+                None
+            }
+            AstKind::Unary(UnaryOp::ObjectConstructor(ref object)) => {
+                self.visit_object(object, context);
+                None
+            }
+
+            _ => {
+                for_each_child(node, &mut |child| {
+                    self.visit(child, context, true);
+                });
+                return None;
+            }
+        };
+
+        // Predicates and stages filter the values, and a group-by makes an object of them
+        for stage in node.predicates.iter().chain(&node.stages).flatten() {
+            self.visit(stage, path.as_deref(), false);
+        }
+        if let Some((_, ref object)) = node.group_by {
+            self.visit_object(object, path.as_deref());
+            path = None;
+        }
+
+        if record {
+            if let Some(ref path) = path {
+                self.paths.insert(path.clone());
+            }
+        }
+        path
+    }
+
+    fn visit_object(&mut self, object: &[(Ast, Ast)], context: Option<&[Segment]>) {
+        for (key, value) in object {
+            self.visit(key, context, true);
+            self.visit(value, context, true);
+        }
+    }
+
+    fn bind(&mut self, name: &str, path: Path) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), path);
+    }
+}
+
+fn format_path(path: &[Segment]) -> String {
+    if path.is_empty() {
+        return "$".to_string();
+    }
+    let is_identifier = |name: &str| {
+        name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && !matches!(name, "and" | "or" | "in" | "true" | "false" | "null")
+    };
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Wildcard => "*".to_string(),
+            Segment::Descendants => "**".to_string(),
+            Segment::Name(name) if is_identifier(name) => name.to_string(),
+            Segment::Name(name) => format!("`{}`", name),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("order.customer.email", &["order.customer.email"])]
+    #[test_case("items[price > 5].name", &["items.name", "items.price"])]
+    #[test_case("$count(items) + $sum(items.price)", &["items", "items.price"])]
+    #[test_case("($o := order; $o.total * $o.tax)", &["order.tax", "order.total"])]
+    #[test_case("items{category: $sum(price)}", &["items.category", "items.price"])]
+    #[test_case("items^(>price).name", &["items.name", "items.price"])]
+    #[test_case("a.*.b", &["a.*.b"])]
+    #[test_case("**.email", &["**.email"])]
+    #[test_case("a.`*`.`**`", &["a.`*`.`**`"]; "quoted wildcards")]
+    #[test_case("$", &["$"])]
+    #[test_case("a.($$.b & c)", &["a.c", "b"])]
+    #[test_case("$map(items, function($v) { $v.price })", &["items"])]
+    #[test_case("`first name` & ' ' & `last.name`", &["`first name`", "`last.name`"])]
+    #[test_case("orders@$o.items[sku = $o.sku].qty", &["items.qty", "items.sku", "orders.sku"])]
+    #[test_case("{'total': $sum(lines.amount)}", &["lines.amount"])]
+    #[test_case("$x := a", &["a"])]
+    #[test_case("1 + 2", &[])]
+    fn paths(expr: &str, expected: &[&str]) {
+        let paths = CompiledExpression::new(expr).unwrap().referenced_paths();
+        assert_eq!(
+            paths.iter().map(String::as_str).collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+
+use crate::evaluator::functions::FunctionContext;
+use crate::{Error, Result, Value};
+
+/// Which of the built-in functions an expression can call, for evaluating expressions written by
+/// untrusted users, see [`JsonAta::set_function_policy`](crate::JsonAta::set_function_policy).
+/// Calling a function that isn't allowed fails with `H0401`. Functions registered with
+/// [`JsonAta::register_function`](crate::JsonAta::register_function) can always be called, as the
+/// host chose to provide them.
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{FunctionPolicy, JsonAta};
+/// let arena = Bump::new();
+/// let mut jsonata = JsonAta::new("$uppercase(name) & ' ' & $shuffle(tags)", &arena)?;
+/// jsonata.set_function_policy(FunctionPolicy::deny(["$shuffle", "$random"]));
+///
+/// let error = jsonata.evaluate(Some(r#"{"name": "a", "tags": [1, 2]}"#), None).unwrap_err();
+/// assert_eq!(error.code(), "H0401");
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FunctionPolicy {
+    /// Every built-in function can be called.
+    #[default]
+    AllowAll,
+
+    /// Only the named functions can be called, so functions added to later versions of the crate
+    /// can't be until they're named.
+    Allow(HashSet<String>),
+
+    /// Every built-in function apart from the named ones can be called.
+    Deny(HashSet<String>),
+}
+
+impl FunctionPolicy {
+    /// Allows only the functions named, with or without their `$`.
+    pub fn allow<S: AsRef<str>>(names: impl IntoIterator<Item = S>) -> Self {
+        FunctionPolicy::Allow(names.into_iter().map(function_name).collect())
+    }
+
+    /// Allows every function apart from the ones named, with or without their `$`.
+    pub fn deny<S: AsRef<str>>(names: impl IntoIterator<Item = S>) -> Self {
+        FunctionPolicy::Deny(names.into_iter().map(function_name).collect())
+    }
+
+    /// Whether the built-in function `name`, without its `$`, can be called.
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            FunctionPolicy::AllowAll => true,
+            FunctionPolicy::Allow(names) => names.contains(name),
+            FunctionPolicy::Deny(names) => !names.contains(name),
+        }
+    }
+}
+
+fn function_name(name: impl AsRef<str>) -> String {
+    let name = name.as_ref();
+    name.strip_prefix('$').unwrap_or(name).to_string()
+}
+
+/// Stands in for a built-in function that the policy doesn't allow, failing when it's called.
+pub(crate) fn fn_denied<'a>(
+    context: FunctionContext<'a, '_>,
+    _args: &'a Value<'a>,
+) -> Result<&'a Value<'a>> {
+    Err(Error::H0401FunctionDenied(
+        context.char_index,
+        context.name.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::JsonAta;
+
+    #[test_case(FunctionPolicy::AllowAll, "$string($count(xs))", Ok("\"2\""))]
+    #[test_case(FunctionPolicy::allow(["string", "$count"]), "$string($count(xs))", Ok("\"2\""))]
+    #[test_case(FunctionPolicy::allow(["string"]), "$string($count(xs))", Err("H0401"))]
+    #[test_case(FunctionPolicy::deny(["count"]), "$string($count(xs))", Err("H0401"))]
+    #[test_case(FunctionPolicy::deny(["count"]), "$string(xs)", Ok("\"[1,2]\""))]
+    #[cfg_attr(
+        feature = "higher-order",
+        test_case(FunctionPolicy::deny(["count"]), "$map(xs, $count)", Err("H0401"))
+    )]
+    #[test_case(FunctionPolicy::deny(["count"]), "($count := function($x) { 1 }; $count(xs))", Ok("1"))]
+    fn policies(policy: FunctionPolicy, expr: &str, expected: std::result::Result<&str, &str>) {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.set_function_policy(policy);
+        let result = jsonata.evaluate(Some(r#"{"xs": [1, 2]}"#), None);
+        match expected {
+            Ok(expected) => assert_eq!(result.unwrap().serialize(false), expected),
+            Err(code) => assert_eq!(result.unwrap_err().code(), code),
+        }
+    }
+
+    #[test]
+    fn denied_function_position() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("1 + $floor(1.5)", &arena).unwrap();
+        jsonata.set_function_policy(FunctionPolicy::deny(["floor"]));
+        let error = jsonata.evaluate(None, None).unwrap_err();
+        assert_eq!(error, Error::H0401FunctionDenied(4, "floor".to_string()));
+        assert_eq!(
+            error.to_string(),
+            "H0401 @ 4: The function $floor is not allowed"
+        );
+    }
+
+    #[test]
+    fn registered_functions_are_allowed() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$double(2)", &arena).unwrap();
+        jsonata.set_function_policy(FunctionPolicy::allow(Vec::<String>::new()));
+        jsonata.register_function("double", 1, |ctx, args| {
+            Ok(Value::number(ctx.arena, args[0].as_f64() * 2.0))
+        });
+        assert_eq!(jsonata.evaluate(None, None).unwrap().as_f64(), 4.0);
+    }
+
+    #[test]
+    fn policy_can_be_changed() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$abs(-1)", &arena).unwrap();
+        jsonata.set_function_policy(FunctionPolicy::deny(["abs"]));
+        assert!(jsonata.evaluate(None, None).is_err());
+        jsonata.set_function_policy(FunctionPolicy::AllowAll);
+        assert_eq!(jsonata.evaluate(None, None).unwrap().as_f64(), 1.0);
+    }
+}
@@ -0,0 +1,25 @@
+//! The stable public API of the crate.
+//!
+//! Everything exported here follows semantic versioning: once the crate reaches 1.0, breaking
+//! changes to these items only happen in a major release, and until then only in a minor release
+//! (for example 0.1 to 0.2, never 0.1.3 to 0.1.4). Downstream crates should prefer
+//! `use jsonata_rs::prelude::*` over reaching into the crate root.
+//!
+//! [`crate::Error`] and [`crate::Result`] are just as stable, but aren't exported here so that a
+//! glob import doesn't shadow `std::error::Error` and `std::result::Result`. Import them from the
+//! crate root, or use them as `jsonata_rs::Error` and `jsonata_rs::Result`.
+//!
+//! The AST and parser are deliberately not part of the stable API, as they change with almost every
+//! language feature. Tooling which needs them can enable the `unstable-ast` feature to get the
+//! [`crate::ast`] module, which carries no stability guarantees at all.
+
+#[cfg(feature = "serde")]
+pub use crate::query_value;
+pub use crate::{query, tokenize};
+pub use crate::{
+    Backend, Batch, Checkpoint, CompiledExpression, Cost, DeterministicProfile, Diagnostic,
+    DiagnosticKind, Documents, ExpressionCache, FunctionContext, FunctionPolicy, Integer, JsonAta,
+    JsonAtaBuilder, Library, Member, Metered, NdjsonCursor, OperatorFn, Operators, Page, Patched,
+    PrecedenceWarnings, Progress, RandomSource, SerializeOptions, Snapshot, Span, StreamItems,
+    StringUnits, Token, Tokens, TraceStep, Value, Warning, WarningKind, WatchdogAction,
+};
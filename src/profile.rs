@@ -0,0 +1,207 @@
+use crate::{JsonAta, Result, Value};
+
+/// A preset for evaluating untrusted expressions, such as those written by users of a plugin or
+/// workflow platform, so that the same expression and input always produce the same result and
+/// use the same amount of gas, on any machine.
+///
+/// # Metering
+///
+/// Gas is charged as follows:
+///
+/// - 1 for every evaluation of a node of the expression. A node which is evaluated more than
+///   once, such as a path step, a predicate or the body of a function, is charged every time.
+/// - 1 for every member of every array passed as an argument to a built-in or registered
+///   function, as these do work proportional to the size of their arguments.
+///
+/// So `$sum([1, 2, 3])` uses 9 gas: 1 for the function call, 1 for looking up `$sum`, 1 for the
+/// array constructor, 3 for its members and 3 for passing the array to `$sum`. Parsing the input
+/// isn't charged. Evaluation fails with `H0302` as soon as the gas used exceeds the limit.
+///
+/// Changes to this formula are treated as breaking changes. The same limit can be set on any
+/// evaluation with [`JsonAta::set_gas_limit`], such as to go with a time limit.
+///
+/// # Determinism
+///
+/// - There's no time limit, as whether it's reached depends on the machine. The gas limit takes
+///   its place.
+/// - Evaluation fails with `U1001` when it's nested more than `max_depth` deep.
+/// - The watchdog, if one is set, isn't called.
+/// - The tree-walking backend is always used, so the gas used doesn't depend on the backend.
+/// - Objects keep their keys in insertion order, so iterating over them is deterministic.
+///
+/// Registered functions are up to the host, and should be deterministic too. So should the random
+/// numbers for `$random` and `$shuffle`, which can be seeded with [`JsonAta::set_random_seed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeterministicProfile {
+    /// The most gas an evaluation can use.
+    pub gas_limit: u64,
+
+    /// The deepest evaluation can be nested.
+    pub max_depth: usize,
+}
+
+impl Default for DeterministicProfile {
+    fn default() -> Self {
+        Self {
+            gas_limit: 1_000_000,
+            max_depth: 500,
+        }
+    }
+}
+
+/// The result of [`JsonAta::evaluate_metered`], along with the gas it used.
+#[derive(Debug)]
+pub struct Metered<'a> {
+    pub result: &'a Value<'a>,
+    pub gas_used: u64,
+}
+
+impl<'a> JsonAta<'a> {
+    /// Evaluates the expression with a [`DeterministicProfile`], returning the result along with
+    /// the gas used.
+    pub fn evaluate_metered(
+        &self,
+        input: Option<&str>,
+        profile: &DeterministicProfile,
+    ) -> Result<Metered<'a>> {
+        let input = self.parse_input(input)?;
+        let evaluator = self
+            .evaluator(Some(profile.max_depth), None)?
+            .with_watchdog(None)
+            .with_gas_limit(Some(profile.gas_limit));
+
+        let result = self.evaluate_with(&evaluator, input)?;
+
+        Ok(Metered {
+            result,
+            gas_used: evaluator.gas_used(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::{Backend, WatchdogAction};
+
+    fn gas_used(expr: &str, input: Option<&str>) -> u64 {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata
+            .evaluate_metered(input, &DeterministicProfile::default())
+            .unwrap()
+            .gas_used
+    }
+
+    #[test_case("1", None, 1)]
+    #[test_case("1 + 2", None, 3)]
+    #[test_case("$sum([1, 2, 3])", None, 9)]
+    #[test_case("a.b", Some(r#"{"a": [{"b": 1}, {"b": 2}]}"#), 4)]
+    fn formula(expr: &str, input: Option<&str>, gas: u64) {
+        assert_eq!(gas_used(expr, input), gas);
+    }
+
+    #[test]
+    fn charges_for_array_arguments() {
+        assert_eq!(
+            gas_used("$count([1..20])", None) - gas_used("$count([1..10])", None),
+            10
+        );
+    }
+
+    #[test]
+    fn gas_exhausted() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$sum([1..1000])", &arena).unwrap();
+        let profile = DeterministicProfile {
+            gas_limit: 100,
+            ..Default::default()
+        };
+
+        let error = jsonata.evaluate_metered(None, &profile).unwrap_err();
+        assert_eq!(error.code(), "H0302");
+    }
+
+    #[test]
+    fn gas_limit_with_time_limit() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("$sum([1..100])", &arena).unwrap();
+        let gas = gas_used("$sum([1..100])", None);
+
+        jsonata.set_gas_limit(Some(gas));
+        let result = jsonata
+            .evaluate_timeboxed(None, None, Some(10_000))
+            .unwrap();
+        assert_eq!(result.as_f64(), 5050.0);
+
+        jsonata.set_gas_limit(Some(gas - 1));
+        let error = jsonata
+            .evaluate_timeboxed(None, None, Some(10_000))
+            .unwrap_err();
+        assert_eq!(error, crate::Error::H0302GasExhausted(gas - 1));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(
+            "($f := function($n) { $n = 0 ? 0 : 1 + $f($n - 1) }; $f(1000))",
+            &arena,
+        )
+        .unwrap();
+        let profile = DeterministicProfile {
+            max_depth: 50,
+            ..Default::default()
+        };
+
+        let error = jsonata.evaluate_metered(None, &profile).unwrap_err();
+        assert_eq!(error.code(), "U1001");
+    }
+
+    #[cfg(feature = "higher-order")]
+    #[test]
+    fn reproducible() {
+        let input = r#"{"z": 1, "a": 2, "m": {"y": 3, "b": 4}}"#;
+        let expr = r#"{"keys": $keys($), "values": *, "each": $each(m, function($v, $k) { $k })}"#;
+
+        let runs: Vec<(String, u64)> = (0..5)
+            .map(|_| {
+                let arena = Bump::new();
+                let jsonata = JsonAta::new(expr, &arena).unwrap();
+                let metered = jsonata
+                    .evaluate_metered(Some(input), &DeterministicProfile::default())
+                    .unwrap();
+                (metered.result.serialize(false), metered.gas_used)
+            })
+            .collect();
+
+        assert_eq!(
+            runs[0].0,
+            r#"{"keys":["z","a","m"],"values":[1,2,{"y":3,"b":4}],"each":["y","b"]}"#
+        );
+        assert!(runs.iter().all(|run| *run == runs[0]));
+    }
+
+    #[test]
+    fn ignores_backend_and_watchdog() {
+        let arena = Bump::new();
+        let mut jsonata = JsonAta::new("a.b + 1", &arena).unwrap();
+        let input = Some(r#"{"a": {"b": 1}}"#);
+        let tree_walker = jsonata
+            .evaluate_metered(input, &DeterministicProfile::default())
+            .unwrap()
+            .gas_used;
+
+        jsonata.set_backend(Backend::Bytecode);
+        jsonata.set_watchdog(1, |_| WatchdogAction::Cancel);
+        let metered = jsonata
+            .evaluate_metered(input, &DeterministicProfile::default())
+            .unwrap();
+
+        assert_eq!(metered.result.as_f64(), 2.0);
+        assert_eq!(metered.gas_used, tree_walker);
+    }
+}
@@ -0,0 +1,318 @@
+//! Evaluating expressions against data the host provides on demand, rather than against an input
+//! which is in the arena in its entirety, see [`ValueProvider`].
+
+use std::collections::BTreeMap;
+
+use bumpalo::Bump;
+
+use crate::paths::{referenced_segments, Segment};
+use crate::{ArrayFlags, JsonAta, Result, Value};
+
+/// An object whose members are read from a source of the host's, such as a struct, a column store
+/// or an index on disk, only when an expression reads them:
+///
+/// ```
+/// # use bumpalo::Bump;
+/// # use jsonata_rs::{JsonAta, Member, Value, ValueProvider};
+/// struct Order {
+///     id: u64,
+///     lines: Vec<(String, f64)>,
+/// }
+///
+/// struct Line<'o>(&'o (String, f64));
+///
+/// impl ValueProvider for Order {
+///     fn keys(&self) -> Vec<String> {
+///         vec!["id".to_string(), "lines".to_string()]
+///     }
+///
+///     fn member<'a>(&self, name: &str, arena: &'a Bump) -> Option<Member<'_, 'a>> {
+///         match name {
+///             "id" => Some(Member::Value(Value::number(arena, self.id as f64))),
+///             "lines" => Some(Member::Array(
+///                 self.lines.iter().map(|line| Member::Object(Box::new(Line(line)))).collect(),
+///             )),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// impl ValueProvider for Line<'_> {
+///     fn keys(&self) -> Vec<String> {
+///         vec!["sku".to_string(), "amount".to_string()]
+///     }
+///
+///     fn member<'a>(&self, name: &str, arena: &'a Bump) -> Option<Member<'_, 'a>> {
+///         match name {
+///             "sku" => Some(Member::Value(Value::string(arena, &self.0 .0))),
+///             "amount" => Some(Member::Value(Value::number(arena, self.0 .1))),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let order = Order { id: 7, lines: vec![("a".to_string(), 2.5), ("b".to_string(), 4.0)] };
+/// let arena = Bump::new();
+/// let jsonata = JsonAta::new("$sum(lines.amount)", &arena)?;
+/// // Only reads `lines` and the `amount` of each line
+/// assert_eq!(jsonata.evaluate_provider(&order)?.as_f64(), 6.5);
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+pub trait ValueProvider {
+    /// The names of the object's members, in order. Only called when an expression reads the
+    /// whole object, or all of its members with a wildcard or descendant step.
+    fn keys(&self) -> Vec<String>;
+
+    /// The member called `name`, or `None` if there's no such member. Values are allocated in
+    /// `arena`.
+    fn member<'a>(&self, name: &str, arena: &'a Bump) -> Option<Member<'_, 'a>>;
+}
+
+/// A member of a [`ValueProvider`].
+pub enum Member<'p, 'a> {
+    /// A value which is already in the arena, such as a string or a number.
+    Value(&'a Value<'a>),
+    /// An object whose members are also read on demand.
+    Object(Box<dyn ValueProvider + 'p>),
+    /// An array, whose members the steps reading it apply to.
+    Array(Vec<Member<'p, 'a>>),
+}
+
+impl<'a> JsonAta<'a> {
+    /// Evaluates the expression against the object `provider` gives the members of, reading only
+    /// the members on the paths the expression reads, see
+    /// [`crate::CompiledExpression::referenced_paths`]. Those members are read into the arena
+    /// before the evaluation starts, and the rest are never read, so a query of a few fields of a
+    /// large dataset only costs as much as those fields.
+    ///
+    /// Members which are read as a whole, such as by `$` or as the argument of a lambda, are read
+    /// in their entirety, with every key of each object they contain.
+    pub fn evaluate_provider(&self, provider: &dyn ValueProvider) -> Result<&'a Value<'a>> {
+        let mut projection = Projection::default();
+        for path in referenced_segments(&self.ast) {
+            projection.insert(&path);
+        }
+        let input = object(provider, &projection, self.arena);
+        self.evaluate_value(input)
+    }
+}
+
+/// The members of an object which are read, as a tree of their names.
+#[derive(Default, Clone)]
+struct Projection {
+    /// Whether the object is read as a whole, so all of its members are read in their entirety.
+    whole: bool,
+    /// What's read of every member, by a wildcard step.
+    any: Option<Box<Projection>>,
+    members: BTreeMap<String, Projection>,
+}
+
+impl Projection {
+    fn insert(&mut self, path: &[Segment]) {
+        match path.split_first() {
+            None => self.whole = true,
+            // Descendants can be anywhere below
+            Some((Segment::Descendants, _)) => self.whole = true,
+            Some((Segment::Wildcard, rest)) => self.any.get_or_insert_default().insert(rest),
+            Some((Segment::Name(name), rest)) => {
+                self.members.entry(name.clone()).or_default().insert(rest)
+            }
+        }
+    }
+
+    /// What's read of the member called `name`, if anything.
+    fn member(&self, name: &str) -> Option<Projection> {
+        if self.whole {
+            return Some(Projection {
+                whole: true,
+                ..Projection::default()
+            });
+        }
+        match (self.any.as_deref(), self.members.get(name)) {
+            (Some(wildcard), Some(named)) => {
+                let mut merged = wildcard.clone();
+                merged.merge(named);
+                Some(merged)
+            }
+            (Some(projection), None) | (None, Some(projection)) => Some(projection.clone()),
+            (None, None) => None,
+        }
+    }
+
+    fn merge(&mut self, other: &Projection) {
+        self.whole |= other.whole;
+        if let Some(ref any) = other.any {
+            self.any.get_or_insert_default().merge(any);
+        }
+        for (name, projection) in &other.members {
+            self.members
+                .entry(name.clone())
+                .or_default()
+                .merge(projection);
+        }
+    }
+}
+
+fn object<'a>(
+    provider: &dyn ValueProvider,
+    projection: &Projection,
+    arena: &'a Bump,
+) -> &'a Value<'a> {
+    let names = if projection.whole || projection.any.is_some() {
+        provider.keys()
+    } else {
+        projection.members.keys().cloned().collect()
+    };
+
+    let object = Value::object_with_capacity(arena, names.len());
+    for name in names {
+        let Some(projection) = projection.member(&name) else {
+            continue;
+        };
+        if let Some(member) = provider.member(&name, arena) {
+            object.insert(&name, value(member, &projection, arena));
+        }
+    }
+    object
+}
+
+fn value<'a>(member: Member<'_, 'a>, projection: &Projection, arena: &'a Bump) -> &'a Value<'a> {
+    match member {
+        Member::Value(value) => value,
+        Member::Object(provider) => object(&*provider, projection, arena),
+        Member::Array(members) => {
+            let array = Value::array_with_capacity(arena, members.len(), ArrayFlags::empty());
+            for member in members {
+                array.push(value(member, projection, arena));
+            }
+            array
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeSet;
+
+    use serde_json::{json, Value as Json};
+    use test_case::test_case;
+
+    use super::*;
+
+    /// Provides a JSON object, recording the members which are read.
+    struct JsonProvider<'j> {
+        json: &'j Json,
+        path: String,
+        read: &'j RefCell<Vec<String>>,
+    }
+
+    impl<'j> JsonProvider<'j> {
+        fn member_of<'a>(&self, json: &'j Json, path: String, arena: &'a Bump) -> Member<'j, 'a> {
+            match json {
+                Json::Object(..) => Member::Object(Box::new(JsonProvider {
+                    json,
+                    path,
+                    read: self.read,
+                })),
+                Json::Array(members) => Member::Array(
+                    members
+                        .iter()
+                        .map(|member| self.member_of(member, path.clone(), arena))
+                        .collect(),
+                ),
+                Json::Null => Member::Value(Value::null(arena)),
+                Json::Bool(b) => Member::Value(Value::bool(arena, *b)),
+                Json::Number(n) => Member::Value(match n.as_i64() {
+                    Some(n) => Value::integer(arena, n),
+                    None => Value::number(arena, n.as_f64().unwrap()),
+                }),
+                Json::String(s) => Member::Value(Value::string(arena, s)),
+            }
+        }
+    }
+
+    impl ValueProvider for JsonProvider<'_> {
+        fn keys(&self) -> Vec<String> {
+            self.json.as_object().unwrap().keys().cloned().collect()
+        }
+
+        fn member<'a>(&self, name: &str, arena: &'a Bump) -> Option<Member<'_, 'a>> {
+            let member = self.json.get(name)?;
+            let path = format!("{}/{}", self.path, name);
+            self.read.borrow_mut().push(path.clone());
+            Some(self.member_of(member, path, arena))
+        }
+    }
+
+    fn input() -> Json {
+        json!({
+            "order": {"id": 1, "customer": {"name": "a", "email": "a@example.com"}},
+            "items": [
+                {"sku": "x", "price": 2, "qty": 3, "tags": ["new"]},
+                {"sku": "y", "price": 10, "qty": 1, "tags": []}
+            ],
+            "large": {"unread": [1, 2, 3]},
+            "*": {"id": 2},
+            "**": "stars"
+        })
+    }
+
+    #[test_case("order.customer.email", &["/order", "/order/customer", "/order/customer/email"])]
+    #[test_case("$sum(items.(price * qty))", &["/items", "/items/price", "/items/qty"])]
+    #[test_case("items[price > 5].sku", &["/items", "/items/price", "/items/sku"])]
+    #[test_case("order.*.name", &["/order", "/order/customer", "/order/customer/name", "/order/id"])]
+    #[test_case("`*`.id", &["/*", "/*/id"]; "quoted wildcard")]
+    #[test_case("`**`", &["/**"]; "quoted descendants")]
+    #[test_case("1 + 2", &[])]
+    fn reads_only_referenced(expr: &str, expected: &[&str]) {
+        let json = input();
+        let read = RefCell::new(vec![]);
+        let provider = JsonProvider {
+            json: &json,
+            path: String::new(),
+            read: &read,
+        };
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        jsonata.evaluate_provider(&provider).unwrap();
+
+        let read: BTreeSet<String> = read.into_inner().into_iter().collect();
+        assert_eq!(
+            read.iter().map(String::as_str).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test_case("order.customer.email")]
+    #[test_case("$sum(items.(price * qty))")]
+    #[test_case("items[price > 5].sku")]
+    #[test_case("items{sku: $sum(price)}")]
+    #[test_case("items^(>price).tags")]
+    #[test_case("order.*.name")]
+    #[test_case("**.sku")]
+    #[test_case("$")]
+    #[test_case("$keys(order)")]
+    #[test_case("items@$i.{'sku': $i.sku, 'id': $$.order.id}")]
+    #[cfg_attr(
+        feature = "higher-order",
+        test_case("$map(items, function($v) { $v.sku & $v.price })")
+    )]
+    #[test_case("($o := order; $o.customer.name & $count(items))")]
+    #[test_case("missing.field")]
+    #[test_case("`*`.id & `**`"; "quoted wildcards")]
+    fn same_result_as_json(expr: &str) {
+        let json = input();
+        let read = RefCell::new(vec![]);
+        let provider = JsonProvider {
+            json: &json,
+            path: String::new(),
+            read: &read,
+        };
+        let arena = Bump::new();
+        let jsonata = JsonAta::new(expr, &arena).unwrap();
+        let provided = jsonata.evaluate_provider(&provider).unwrap();
+        let materialized = jsonata.evaluate(Some(&json.to_string()), None).unwrap();
+        assert_eq!(provided.serialize(false), materialized.serialize(false));
+    }
+}
@@ -0,0 +1,79 @@
+use bumpalo::Bump;
+
+use crate::{ExpressionCache, Result};
+
+// Large enough to hold the handful of expressions a typical program uses, small enough that a
+// program generating expressions doesn't grow without bound.
+const CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    static CACHE: ExpressionCache = ExpressionCache::new(CACHE_CAPACITY);
+}
+
+/// Evaluates `expr` against the JSON text `input` and returns the result as JSON text, or an
+/// empty string if the result is undefined.
+///
+/// Compiled expressions are cached per thread, so calling this repeatedly with the same
+/// expression only parses it once.
+pub fn query(expr: &str, input: &str) -> Result<String> {
+    let compiled = CACHE.with(|cache| cache.get_or_compile(expr))?;
+    let arena = Bump::new();
+    let result = compiled.evaluate(Some(input), &arena)?;
+    Ok(result.serialize(false))
+}
+
+/// Evaluates `expr` against `input`, returning the result as a [`serde_json::Value`]. An undefined
+/// result is returned as `Null`.
+#[cfg(feature = "serde")]
+pub fn query_value(expr: &str, input: &serde_json::Value) -> Result<serde_json::Value> {
+    let compiled = CACHE.with(|cache| cache.get_or_compile(expr))?;
+    let arena = Bump::new();
+    let jsonata = compiled.bind(&arena);
+    let input = crate::Value::from_json(&arena, input);
+    let result = jsonata.evaluate_input(input, None, None)?;
+    result.deserialize()
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn query_text() {
+        assert_eq!(
+            query(
+                "items[price > 1].name",
+                r#"{"items": [{"name": "a", "price": 1}, {"name": "b", "price": 2}]}"#
+            )
+            .unwrap(),
+            r#""b""#
+        );
+        assert_eq!(query("missing", "{}").unwrap(), "");
+        assert_eq!(query("(", "{}").unwrap_err().code(), "S0211");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn query_json_value() {
+        let input = json!({"orders": [{"qty": 2, "price": 1.5}, {"qty": 1, "price": 4}]});
+
+        assert_eq!(
+            query_value("$sum(orders.(qty * price))", &input).unwrap(),
+            json!(7)
+        );
+        assert_eq!(
+            query_value("orders.{\"total\": qty * price}", &input).unwrap(),
+            json!([{"total": 3}, {"total": 4}])
+        );
+        assert_eq!(query_value("missing", &input).unwrap(), json!(null));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn query_value_array_input() {
+        assert_eq!(query_value("$[1]", &json!([1, 2, 3])).unwrap(), json!(2));
+    }
+}
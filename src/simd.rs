@@ -0,0 +1,115 @@
+//! Parsing large JSON inputs with [simd-json](https://docs.rs/simd-json), which is several times
+//! faster than the expression parser which otherwise parses them.
+//!
+//! Inputs are expressions, so they don't have to be strict JSON, and anything simd-json rejects,
+//! such as `[1..10]` or a single-quoted string, is left to the expression parser. So are small
+//! inputs, where there's nothing to gain, and inputs in decimal mode, as simd-json parses numbers
+//! to `f64`s.
+
+use bumpalo::Bump;
+use simd_json::{Node, StaticNode};
+
+use crate::{ArrayFlags, JsonAta, Value};
+
+/// The least length of an input, in bytes, which is parsed with simd-json.
+const MIN_INPUT_LEN: usize = 16 * 1024;
+
+impl<'a> JsonAta<'a> {
+    /// Parses `input` with simd-json if it's large enough and strict JSON.
+    pub(crate) fn parse_large_input(&self, input: &str) -> Option<&'a Value<'a>> {
+        #[cfg(feature = "decimal")]
+        if self.decimal {
+            return None;
+        }
+        if input.len() < MIN_INPUT_LEN {
+            return None;
+        }
+        parse(input, self.arena)
+    }
+}
+
+/// Parses strict JSON into `arena`, or returns `None` if it isn't, or if an object has the same
+/// key twice, which the expression parser reports as an error.
+fn parse<'a>(input: &str, arena: &'a Bump) -> Option<&'a Value<'a>> {
+    // simd-json unescapes strings in place
+    let mut bytes = input.as_bytes().to_vec();
+    let tape = simd_json::to_tape(&mut bytes).ok()?;
+    let mut nodes = tape.0.iter();
+    from_nodes(&mut nodes, arena)
+}
+
+/// Reads the value starting at the next node of the tape.
+fn from_nodes<'a, 'n>(
+    nodes: &mut impl Iterator<Item = &'n Node<'n>>,
+    arena: &'a Bump,
+) -> Option<&'a Value<'a>> {
+    let value = match *nodes.next().expect("The tape has a node for each value") {
+        Node::Static(StaticNode::Null) => Value::null(arena),
+        Node::Static(StaticNode::Bool(b)) => Value::bool(arena, b),
+        Node::Static(StaticNode::I64(n)) => Value::integer(arena, n),
+        Node::Static(StaticNode::U64(n)) => Value::integer(arena, n),
+        Node::Static(StaticNode::F64(n)) => Value::number(arena, n),
+        Node::String(s) => Value::string(arena, s),
+        Node::Array { len, .. } => {
+            let array = Value::array_with_capacity(arena, len, ArrayFlags::empty());
+            for _ in 0..len {
+                array.push(from_nodes(nodes, arena)?);
+            }
+            array
+        }
+        Node::Object { len, .. } => {
+            let object = Value::object_with_capacity(arena, len);
+            for index in 0..len {
+                let Some(Node::String(key)) = nodes.next() else {
+                    unreachable!("Each member of an object starts with its key");
+                };
+                object.insert(key, from_nodes(nodes, arena)?);
+                if object.entries().len() <= index {
+                    return None;
+                }
+            }
+            object
+        }
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(r#"{"b": 1, "a": [true, false, null], "c": {"d": "e\né"}}"#)]
+    #[test_case(r#"[0, -1, 1.5, 1e3, -0.25, 9007199254740993, 18446744073709551615]"#)]
+    #[test_case(r#""text""#)]
+    #[test_case("[]")]
+    fn matches_the_expression_parser(input: &str) {
+        let arena = Bump::new();
+        let simd = parse(input, &arena).unwrap();
+        let parsed = crate::parse_value(input, &arena).unwrap();
+        assert_eq!(simd, parsed);
+        assert_eq!(simd.serialize(false), parsed.serialize(false));
+    }
+
+    #[test_case("[1..3]")]
+    #[test_case("{'a': 1}")]
+    #[test_case("[1, 2")]
+    #[test_case(r#"{"a": 1, "a": 2}"#)]
+    fn not_json(input: &str) {
+        assert!(parse(input, &Bump::new()).is_none());
+    }
+
+    #[test]
+    fn large_input() {
+        let arena = Bump::new();
+        let input = format!("[{}1]", r#"{"price": 2, "name": "item"}, "#.repeat(1000));
+        assert!(input.len() >= MIN_INPUT_LEN);
+
+        let jsonata = JsonAta::new("$sum(price)", &arena).unwrap();
+        assert!(jsonata.parse_large_input(&input).is_some());
+        let result = jsonata.evaluate(Some(&input), None).unwrap();
+        assert_eq!(result.as_f64(), 2000.0);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::evaluator::frame::Frame;
+use crate::parser::ast::AstKind;
+use crate::{JsonAta, Result, Value};
+
+/// The variables bound by a setup expression, such as lookup tables and helper functions, which
+/// can be made visible to other expressions without evaluating the setup again.
+///
+/// A snapshot is never changed by the expressions that use it, as their bindings go in their own
+/// frames. Its values live in the arena of the expression it was taken from, so it can only be
+/// used by expressions in the same arena.
+#[derive(Debug, Clone)]
+pub struct Snapshot<'a> {
+    frame: Frame<'a>,
+}
+
+impl<'a> Snapshot<'a> {
+    /// The value of a variable bound by the expression the snapshot was taken from, or by the
+    /// snapshots it builds on.
+    pub fn get(&self, name: &str) -> Option<&'a Value<'a>> {
+        self.frame.lookup(name)
+    }
+}
+
+impl<'a> JsonAta<'a> {
+    /// Evaluates the expression as a setup expression, returning the variables it binds. The
+    /// variables bound at the top level of a block are kept, so `($a := 1; $b := 2)` binds both
+    /// `$a` and `$b`.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let setup = JsonAta::new(r#"($rates := {"GBP": 1.25}; $usd := function($p) { $p.amount * $lookup($rates, $p.currency) })"#, &arena)?;
+    /// let snapshot = setup.snapshot(None)?;
+    ///
+    /// let expr = JsonAta::new("$usd($)", &arena)?;
+    /// expr.use_snapshot(&snapshot);
+    /// let result = expr.evaluate(Some(r#"{"amount": 10, "currency": "GBP"}"#), None)?;
+    /// assert_eq!(result.as_f64(), 12.5);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn snapshot(&self, input: Option<&str>) -> Result<Snapshot<'a>> {
+        let (_, snapshot) = self.evaluate_and_snapshot(input)?;
+        Ok(snapshot)
+    }
+
+    /// Evaluates the expression like [`JsonAta::evaluate`], also returning the variables bound at
+    /// the top level, like [`JsonAta::snapshot`]. Passing the snapshot on to the next expression
+    /// shares the environment established by this one, and snapshots taken by an expression which
+    /// uses a snapshot build on it, so `$lookupTable` can be bound once and used by a chain of
+    /// expressions.
+    ///
+    /// ```
+    /// # use bumpalo::Bump;
+    /// # use jsonata_rs::JsonAta;
+    /// let arena = Bump::new();
+    /// let first = JsonAta::new(r#"$lookupTable := {"a": 1, "b": 2}"#, &arena)?;
+    /// let (_, snapshot) = first.evaluate_and_snapshot(None)?;
+    ///
+    /// let second = JsonAta::new("($total := $lookupTable.a + $lookupTable.b; $total * 2)", &arena)?;
+    /// second.use_snapshot(&snapshot);
+    /// let (result, snapshot) = second.evaluate_and_snapshot(None)?;
+    /// assert_eq!(result.as_f64(), 6.0);
+    ///
+    /// let third = JsonAta::new("$total / $count($keys($lookupTable))", &arena)?;
+    /// third.use_snapshot(&snapshot);
+    /// assert_eq!(third.evaluate(None, None)?.as_f64(), 1.5);
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    pub fn evaluate_and_snapshot(
+        &self,
+        input: Option<&str>,
+    ) -> Result<(&'a Value<'a>, Snapshot<'a>)> {
+        let input = self.parse_input(input)?;
+        let evaluator = self.evaluator(None, None)?;
+        let input = self.bind_input(input);
+
+        let frame = Frame::new_with_parent(&self.frame);
+        let exprs = match self.ast.kind {
+            AstKind::Block(ref exprs) => exprs.as_slice(),
+            _ => std::slice::from_ref(self.ast.as_ref()),
+        };
+        let mut result = Value::undefined();
+        for expr in exprs {
+            result = evaluator.evaluate(expr, input, &frame)?;
+        }
+
+        Ok((result, Snapshot { frame }))
+    }
+
+    /// Makes the variables in a snapshot visible to every subsequent evaluation, replacing any
+    /// snapshot used before. Variables assigned to this expression take precedence over them.
+    pub fn use_snapshot(&self, snapshot: &Snapshot<'a>) {
+        self.frame.set_parent(&snapshot.frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+
+    use super::*;
+    use crate::Backend;
+
+    const SETUP: &str = r#"(
+        $codes := $.countries{code: name};
+        $name := function($code) { $lookup($codes, $code) };
+        $unused := 1;
+    )"#;
+    const TABLES: &str =
+        r#"{"countries": [{"code": "FR", "name": "France"}, {"code": "JP", "name": "Japan"}]}"#;
+
+    #[test]
+    fn reused_by_other_expressions() {
+        let arena = Bump::new();
+        let snapshot = JsonAta::new(SETUP, &arena)
+            .unwrap()
+            .snapshot(Some(TABLES))
+            .unwrap();
+
+        let first = JsonAta::new("$name(country)", &arena).unwrap();
+        first.use_snapshot(&snapshot);
+        let mut second = JsonAta::new("visits.$name($)", &arena).unwrap();
+        second.set_backend(Backend::Bytecode);
+        second.use_snapshot(&snapshot);
+
+        for _ in 0..3 {
+            let result = first.evaluate(Some(r#"{"country": "JP"}"#), None);
+            assert_eq!(result.unwrap(), Value::string(&arena, "Japan"));
+
+            let result = second.evaluate(Some(r#"{"visits": ["FR", "JP"]}"#), None);
+            assert_eq!(result.unwrap().serialize(false), r#"["France","Japan"]"#);
+        }
+    }
+
+    #[test]
+    fn not_changed_by_use() {
+        let arena = Bump::new();
+        let snapshot = JsonAta::new(SETUP, &arena)
+            .unwrap()
+            .snapshot(Some(TABLES))
+            .unwrap();
+
+        let overriding = JsonAta::new("$codes := {}", &arena).unwrap();
+        overriding.use_snapshot(&snapshot);
+        overriding.evaluate(None, None).unwrap();
+
+        let assigned = JsonAta::new("$unused", &arena).unwrap();
+        assigned.use_snapshot(&snapshot);
+        assigned.assign_var("unused", Value::number(&arena, 2));
+        assert_eq!(assigned.evaluate(None, None).unwrap().as_f64(), 2.0);
+
+        let reading = JsonAta::new("[$name(\"FR\"), $unused, $]", &arena).unwrap();
+        reading.use_snapshot(&snapshot);
+        assert_eq!(
+            reading.evaluate(Some("3"), None).unwrap().serialize(false),
+            r#"["France",1,3]"#
+        );
+    }
+
+    #[test]
+    fn chained() {
+        let arena = Bump::new();
+        let (_, first) = JsonAta::new(SETUP, &arena)
+            .unwrap()
+            .evaluate_and_snapshot(Some(TABLES))
+            .unwrap();
+
+        let second = JsonAta::new("$greeting := 'Bonjour ' & $name('FR')", &arena).unwrap();
+        second.use_snapshot(&first);
+        let (result, second) = second.evaluate_and_snapshot(None).unwrap();
+        assert_eq!(result, Value::string(&arena, "Bonjour France"));
+        assert_eq!(second.get("greeting"), Some(result));
+        assert_eq!(first.get("greeting"), None);
+
+        let third = JsonAta::new("$greeting & ' et ' & $name('JP')", &arena).unwrap();
+        third.use_snapshot(&second);
+        assert_eq!(
+            third.evaluate(None, None).unwrap().serialize(false),
+            r#""Bonjour France et Japan""#
+        );
+    }
+
+    #[test]
+    fn setup_errors() {
+        let arena = Bump::new();
+        let setup = JsonAta::new("$a := $error(\"no tables\")", &arena).unwrap();
+        assert_eq!(setup.snapshot(None).unwrap_err().code(), "D3137");
+    }
+}
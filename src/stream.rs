@@ -0,0 +1,491 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+
+use bumpalo::Bump;
+
+use crate::parser::ast::{Ast, AstKind, UnaryOp};
+use crate::{ArrayFlags, CompiledExpression, Error, OwnedValue, Result, Value};
+
+/// Splits a JSON document which is an array into the source text of each of its members, reading
+/// the source incrementally so that only one member is held in memory at a time.
+///
+/// A document which isn't an array is returned as a single item.
+struct ArraySplitter<R> {
+    reader: BufReader<R>,
+    started: bool,
+    finished: bool,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> ArraySplitter<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            started: false,
+            finished: false,
+            buf: Vec::new(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        let available = self
+            .reader
+            .fill_buf()
+            .map_err(|e| Error::H0101InputRead(e.to_string()))?;
+        Ok(available.first().copied())
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>> {
+        while let Some(b) = self.peek()? {
+            if b.is_ascii_whitespace() {
+                self.reader.consume(1);
+            } else {
+                return Ok(Some(b));
+            }
+        }
+        Ok(None)
+    }
+
+    fn read_to_end(&mut self) -> Result<String> {
+        let mut text = String::new();
+        self.reader
+            .read_to_string(&mut text)
+            .map_err(|e| Error::H0101InputRead(e.to_string()))?;
+        Ok(text)
+    }
+
+    /// Returns the text of the next member, or `None` once the closing bracket has been read.
+    fn next_item(&mut self) -> Result<Option<String>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if !self.started {
+            self.started = true;
+            match self.skip_whitespace()? {
+                Some(b'[') => self.reader.consume(1),
+                Some(_) => {
+                    self.finished = true;
+                    return Ok(Some(self.read_to_end()?));
+                }
+                None => {
+                    self.finished = true;
+                    return Ok(None);
+                }
+            }
+            if self.skip_whitespace()? == Some(b']') {
+                self.reader.consume(1);
+                self.finished = true;
+                return Ok(None);
+            }
+        }
+
+        self.buf.clear();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let b = self
+                .peek()?
+                .ok_or_else(|| Error::H0103MalformedArray("unexpected end of input".to_string()))?;
+            self.reader.consume(1);
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match b {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b']' => {
+                        self.finished = true;
+                        break;
+                    }
+                    b',' if depth == 0 => break,
+                    _ => {}
+                }
+            }
+
+            self.buf.push(b);
+        }
+
+        let item = String::from_utf8(std::mem::take(&mut self.buf))
+            .map_err(|e| Error::H0103MalformedArray(e.to_string()))?;
+        if item.trim().is_empty() {
+            return Err(Error::H0103MalformedArray("empty array member".to_string()));
+        }
+
+        Ok(Some(item))
+    }
+}
+
+/// Whether the result of evaluating an expression against an array is the concatenation of the
+/// results of evaluating it against each member of the array, which is the case for a path
+/// starting with a field name that doesn't sort, group or refer back to the root.
+fn is_per_element(ast: &Ast) -> bool {
+    let AstKind::Path(ref steps) = ast.kind else {
+        return false;
+    };
+
+    let first_step_is_name = matches!(
+        steps.first(),
+        Some(Ast {
+            kind: AstKind::Name(..),
+            ..
+        })
+    );
+    let steps_are_independent = steps.iter().all(|step| {
+        !step.tuple
+            && step.index.is_none()
+            && step.focus.is_none()
+            && !matches!(step.kind, AstKind::Sort(..))
+    });
+
+    first_step_is_name
+        && steps_are_independent
+        && ast.predicates.is_none()
+        && ast.group_by.is_none()
+        && !ast.keep_singleton_array
+        && !ast.keep_array
+        && !references_root(ast)
+}
+
+// Looks for `$$`, which would see a single member rather than the whole array when streaming.
+fn references_root(ast: &Ast) -> bool {
+    let any = |asts: &[Ast]| asts.iter().any(references_root);
+    let any_pair = |pairs: &[(Ast, Ast)]| {
+        pairs
+            .iter()
+            .any(|(k, v)| references_root(k) || references_root(v))
+    };
+
+    let in_node = match ast.kind {
+        AstKind::Var(ref name) => name == "$",
+        AstKind::Unary(UnaryOp::Minus(ref value)) => references_root(value),
+        AstKind::Unary(UnaryOp::ArrayConstructor(ref items)) => any(items),
+        AstKind::Unary(UnaryOp::ObjectConstructor(ref object)) => any_pair(object),
+        AstKind::Binary(_, ref lhs, ref rhs) => references_root(lhs) || references_root(rhs),
+        AstKind::GroupBy(ref lhs, ref object) => references_root(lhs) || any_pair(object),
+        AstKind::OrderBy(ref lhs, ref terms) => {
+            references_root(lhs) || terms.iter().any(|(term, _)| references_root(term))
+        }
+        AstKind::Sort(ref terms) => terms.iter().any(|(term, _)| references_root(term)),
+        AstKind::Block(ref exprs) | AstKind::Path(ref exprs) => any(exprs),
+        AstKind::Function {
+            ref proc, ref args, ..
+        } => references_root(proc) || any(args),
+        AstKind::Lambda { ref body, .. } | AstKind::Filter(ref body) => references_root(body),
+        AstKind::Ternary {
+            ref cond,
+            ref truthy,
+            ref falsy,
+        } => {
+            references_root(cond)
+                || references_root(truthy)
+                || falsy.as_deref().is_some_and(references_root)
+        }
+        AstKind::Transform {
+            ref pattern,
+            ref update,
+            ref delete,
+        } => {
+            references_root(pattern)
+                || references_root(update)
+                || delete.as_deref().is_some_and(references_root)
+        }
+        _ => false,
+    };
+
+    in_node
+        || ast.predicates.as_deref().is_some_and(any)
+        || ast.stages.as_deref().is_some_and(any)
+        || ast
+            .group_by
+            .as_ref()
+            .is_some_and(|(_, object)| any_pair(object))
+}
+
+// Passes each item of a result to `f`, spreading arrays other than those built with an array
+// constructor, which is how the items of per-element results combine into the whole result.
+fn for_each_item<'a, F>(result: &'a Value<'a>, f: &mut F) -> Result<usize>
+where
+    F: for<'v> FnMut(&'v Value<'v>) -> Result<()>,
+{
+    if result.is_undefined() {
+        Ok(0)
+    } else if result.is_array() && !result.has_flags(ArrayFlags::CONS) {
+        for item in result.members() {
+            f(item)?;
+        }
+        Ok(result.len())
+    } else {
+        f(result)?;
+        Ok(1)
+    }
+}
+
+impl CompiledExpression {
+    /// Whether [`CompiledExpression::evaluate_stream`] can evaluate the expression one array
+    /// member at a time.
+    pub fn is_streamable(&self) -> bool {
+        is_per_element(&self.ast)
+    }
+
+    /// Evaluates the expression against a JSON array read from `reader`, passing each item of the
+    /// result to `f` and returning the number of items.
+    ///
+    /// If the expression [is streamable](CompiledExpression::is_streamable), each member of the
+    /// array is read and evaluated on its own, in a fresh arena, so memory use is bounded by the
+    /// largest member rather than the whole document. Otherwise the whole document is read and
+    /// evaluated at once.
+    pub fn evaluate_stream<R, F>(&self, reader: R, mut f: F) -> Result<usize>
+    where
+        R: Read,
+        F: for<'v> FnMut(&'v Value<'v>) -> Result<()>,
+    {
+        if !self.is_streamable() {
+            let mut input = String::new();
+            BufReader::new(reader)
+                .read_to_string(&mut input)
+                .map_err(|e| Error::H0101InputRead(e.to_string()))?;
+            let arena = Bump::new();
+            let result = self.evaluate(Some(&input), &arena)?;
+            return for_each_item(result, &mut f);
+        }
+
+        let mut splitter = ArraySplitter::new(reader);
+        let mut count = 0;
+        while let Some(item) = splitter.next_item()? {
+            let arena = Bump::new();
+            let result = self.evaluate(Some(&item), &arena)?;
+            count += for_each_item(result, &mut f)?;
+        }
+        Ok(count)
+    }
+}
+
+/// The items of the result of evaluating an expression against a JSON array as they're read, see
+/// [`CompiledExpression::evaluate_stream_iter`].
+pub struct StreamItems<'e, R> {
+    expression: &'e CompiledExpression,
+    splitter: ArraySplitter<R>,
+    arena: Bump,
+    /// The items of the result for the last member which haven't been taken yet
+    pending: VecDeque<OwnedValue>,
+    finished: bool,
+}
+
+impl CompiledExpression {
+    /// Evaluates the expression against a JSON array read from `reader` like
+    /// [`CompiledExpression::evaluate_stream`], but as an iterator over the items of the result,
+    /// so that the rest of the array isn't read or evaluated once the caller stops taking them:
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let input = r#"[{"name": "a", "price": 5}, {"name": "b", "price": 15}, {"name": "c", "price": 25}]"#;
+    /// let expression = CompiledExpression::new("name[$ != 'a']")?;
+    ///
+    /// let first = expression.evaluate_stream_iter(input.as_bytes()).next().transpose()?;
+    /// assert_eq!(first.unwrap().as_str(), Some("b"));
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// Only the items of a [streamable](CompiledExpression::is_streamable) expression are produced
+    /// a member at a time. Otherwise the whole input is read and evaluated when the first item is
+    /// taken. The iterator ends after the first error.
+    pub fn evaluate_stream_iter<R: Read>(&self, reader: R) -> StreamItems<'_, R> {
+        StreamItems {
+            expression: self,
+            splitter: ArraySplitter::new(reader),
+            arena: Bump::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> StreamItems<'_, R> {
+    /// Reads and evaluates the next member, or the whole input, adding the items of its result
+    /// to those pending. Returns false once there's nothing left to read.
+    fn evaluate_next(&mut self) -> Result<bool> {
+        let input = if self.expression.is_streamable() {
+            match self.splitter.next_item()? {
+                Some(item) => item,
+                None => return Ok(false),
+            }
+        } else {
+            self.finished = true;
+            self.splitter.read_to_end()?
+        };
+
+        let pending = &mut self.pending;
+        self.expression
+            .evaluate_in(Some(&input), &mut self.arena, |result| {
+                for_each_item(result, &mut |item| {
+                    pending.push_back(OwnedValue::from(item));
+                    Ok(())
+                })
+            })??;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Iterator for StreamItems<'_, R> {
+    type Item = Result<OwnedValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.evaluate_next() {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    const INPUT: &str = r#" [
+        {"name": "a", "price": 5, "tags": ["x", "y"], "nested": [[1, 2], [3]]},
+        {"name": "b, \"quoted\" ]", "price": 15, "tags": "z"},
+        {"name": "c", "price": 25, "tags": []}
+    ] "#;
+
+    fn streamed(expr: &str) -> Vec<String> {
+        let compiled = CompiledExpression::new(expr).unwrap();
+        let mut items = vec![];
+        compiled
+            .evaluate_stream(INPUT.as_bytes(), |item| {
+                items.push(item.serialize(false));
+                Ok(())
+            })
+            .unwrap();
+        items
+    }
+
+    fn materialized(expr: &str) -> Vec<String> {
+        let arena = Bump::new();
+        let result = CompiledExpression::new(expr)
+            .unwrap()
+            .evaluate(Some(INPUT), &arena)
+            .unwrap();
+        let mut items = vec![];
+        for_each_item(result, &mut |item| {
+            items.push(item.serialize(false));
+            Ok(())
+        })
+        .unwrap();
+        items
+    }
+
+    #[test_case("name", true)]
+    #[test_case("tags", true)]
+    #[test_case("nested", true)]
+    #[test_case("$[price > 10].name", false)]
+    #[test_case("name[$ != \"a\"]", true)]
+    #[test_case("{\"n\": name}.n", false)]
+    #[test_case("name.$uppercase()", true)]
+    #[test_case("price.($ * $count($$))", false)]
+    #[test_case("$sum(price)", false)]
+    #[test_case("name^(>$)", false)]
+    fn matches_materialized(expr: &str, streamable: bool) {
+        assert_eq!(
+            CompiledExpression::new(expr).unwrap().is_streamable(),
+            streamable
+        );
+        assert_eq!(streamed(expr), materialized(expr));
+    }
+
+    #[test]
+    fn empty_and_non_array_input() {
+        let compiled = CompiledExpression::new("a").unwrap();
+        assert_eq!(
+            compiled.evaluate_stream(" [ ] ".as_bytes(), |_| Ok(())),
+            Ok(0)
+        );
+        assert_eq!(compiled.evaluate_stream("".as_bytes(), |_| Ok(())), Ok(0));
+
+        let mut items = vec![];
+        compiled
+            .evaluate_stream(r#"{"a": 1}"#.as_bytes(), |item| {
+                items.push(item.as_f64());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(items, vec![1.0]);
+    }
+
+    #[test_case("name")]
+    #[test_case("tags")]
+    #[test_case("name[$ != \"a\"]")]
+    #[test_case("$[price > 10].name"; "not streamable")]
+    #[test_case("$count($)"; "not streamable single")]
+    fn iterates_like_streamed(expr: &str) {
+        let compiled = CompiledExpression::new(expr).unwrap();
+        let items: Vec<String> = compiled
+            .evaluate_stream_iter(INPUT.as_bytes())
+            .map(|item| item.unwrap().serialize(false))
+            .collect();
+        assert_eq!(items, streamed(expr));
+    }
+
+    #[test]
+    fn stops_reading() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("not read"))
+            }
+        }
+
+        let compiled = CompiledExpression::new("a").unwrap();
+        let reader = || r#"[{"a": 1}, {"a": 2},"#.as_bytes().chain(Failing);
+
+        let first: Vec<_> = compiled.evaluate_stream_iter(reader()).take(2).collect();
+        assert_eq!(
+            first,
+            [
+                Ok(OwnedValue::Integer(1i64.into())),
+                Ok(OwnedValue::Integer(2i64.into()))
+            ]
+        );
+
+        let mut all = compiled.evaluate_stream_iter(reader()).skip(2);
+        assert_eq!(all.next().unwrap().unwrap_err().code(), "H0101");
+        assert_eq!(all.next(), None);
+    }
+
+    #[test]
+    fn truncated_input() {
+        let compiled = CompiledExpression::new("a").unwrap();
+        assert_eq!(
+            compiled
+                .evaluate_stream(r#"[{"a": 1}, {"a": "#.as_bytes(), |_| Ok(()))
+                .unwrap_err()
+                .code(),
+            "H0103"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashSet;
+
+use bumpalo::Bump;
+
+use crate::parser::ast::{Ast, AstKind, BinaryOp};
+use crate::parser::visit::{walk_node, Visitor};
+use crate::{CompiledExpression, Error, JsonAta, Result};
+
+impl CompiledExpression {
+    /// Parses an expression in strict mode, in which referring to a variable or calling a function
+    /// that isn't bound fails when the expression is compiled, rather than evaluating to undefined
+    /// or failing when it's evaluated. `variables` are the names, with or without their `$`, of
+    /// the variables the host will assign and the functions it will register.
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let error = CompiledExpression::new_strict("price > $treshold", ["threshold"]).unwrap_err();
+    /// assert_eq!(error.code(), "H0601");
+    ///
+    /// assert!(CompiledExpression::new_strict("price > $threshold", ["threshold"]).is_ok());
+    /// ```
+    ///
+    /// A variable is bound if a block, a lambda or a focus or index binding in the expression
+    /// binds it anywhere it's visible, so a function can call one bound later in the same block.
+    pub fn new_strict<S: AsRef<str>>(
+        expr: &str,
+        variables: impl IntoIterator<Item = S>,
+    ) -> Result<CompiledExpression> {
+        let compiled = CompiledExpression::new(expr)?;
+        let variables: HashSet<String> = variables
+            .into_iter()
+            .map(|name| {
+                let name = name.as_ref();
+                name.strip_prefix('$').unwrap_or(name).to_string()
+            })
+            .collect();
+
+        let arena = Bump::new();
+        compiled
+            .bind(&arena)
+            .check_names(&|name| variables.contains(name))?;
+        Ok(compiled)
+    }
+}
+
+impl JsonAta<'_> {
+    /// Checks the expression as [`CompiledExpression::new_strict`] does, with the variables
+    /// assigned to it and the functions registered with it so far, failing with `H0601` for the
+    /// first variable that isn't bound or `H0602` for the first function.
+    pub fn check_strict(&self) -> Result<()> {
+        self.check_names(&|_| false)
+    }
+
+    fn check_names(&self, is_host: &dyn Fn(&str) -> bool) -> Result<()> {
+        // Binds the built-in functions
+        let _ = self.evaluator(None, None);
+        let is_bound = |name: &str| self.frame.lookup(name).is_some() || is_host(name);
+
+        let mut checker = Checker {
+            scopes: vec![block_bindings(std::slice::from_ref(&*self.ast))],
+            is_bound: &is_bound,
+            error: None,
+        };
+        checker.visit_node(&self.ast);
+        checker.error.map_or(Ok(()), Err)
+    }
+}
+
+struct Checker<'c> {
+    /// The variables bound by the expression in each enclosing block, lambda or path
+    scopes: Vec<Vec<String>>,
+    is_bound: &'c dyn Fn(&str) -> bool,
+    error: Option<Error>,
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_node(&mut self, ast: &Ast) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let scope = match ast.kind {
+            AstKind::Block(ref exprs) => Some(block_bindings(exprs)),
+            AstKind::Lambda {
+                ref args, ref body, ..
+            } => {
+                let mut scope = block_bindings(std::slice::from_ref(&**body));
+                scope.extend(args.iter().filter_map(|arg| match arg.kind {
+                    AstKind::Var(ref name) => Some(name.clone()),
+                    _ => None,
+                }));
+                Some(scope)
+            }
+            // Focus and index variables are bound for the whole path
+            AstKind::Path(ref steps) => Some(
+                steps
+                    .iter()
+                    .flat_map(|step| [&step.focus, &step.index])
+                    .flatten()
+                    .cloned()
+                    .collect(),
+            ),
+            AstKind::Var(ref name) => {
+                if !self.is_known(name) {
+                    self.error = Some(Error::H0601UnboundVariable(ast.char_index, name.clone()));
+                }
+                return;
+            }
+            AstKind::Function {
+                ref proc, ref args, ..
+            } => {
+                self.check_function(proc);
+                for arg in args {
+                    self.visit_node(arg);
+                }
+                for_each_stage(self, ast);
+                return;
+            }
+            AstKind::Binary(BinaryOp::Apply, ref lhs, ref rhs) => {
+                self.visit_node(lhs);
+                self.check_function(rhs);
+                for_each_stage(self, ast);
+                return;
+            }
+            _ => None,
+        };
+
+        match scope {
+            Some(scope) => {
+                self.scopes.push(scope);
+                walk_node(self, ast);
+                self.scopes.pop();
+            }
+            None => walk_node(self, ast),
+        }
+    }
+}
+
+impl Checker<'_> {
+    /// Checks the function that's called or applied, which is an unknown function rather than an
+    /// unbound variable if it's named.
+    fn check_function(&mut self, proc: &Ast) {
+        match proc.kind {
+            AstKind::Var(ref name) if !self.is_known(name) => {
+                if self.error.is_none() {
+                    self.error = Some(Error::H0602UnknownFunction(proc.char_index, name.clone()));
+                }
+            }
+            AstKind::Var(..) => {}
+            _ => self.visit_node(proc),
+        }
+    }
+
+    fn is_known(&self, name: &str) -> bool {
+        // `$` and `$$` are the context and the input
+        name.is_empty()
+            || name == "$"
+            || self.scopes.iter().flatten().any(|bound| bound == name)
+            || (self.is_bound)(name)
+    }
+}
+
+/// Visits the predicates, stages and group-by of a node whose operands were visited separately.
+fn for_each_stage(checker: &mut Checker, ast: &Ast) {
+    crate::parser::visit::for_each_stage(ast, &mut |stage| checker.visit_node(stage));
+}
+
+/// The variables `exprs` bind in the frame they're evaluated in, which are those bound with `:=`
+/// outside of nested blocks and lambdas, as those have frames of their own.
+pub(crate) fn block_bindings(exprs: &[Ast]) -> Vec<String> {
+    struct Bindings(Vec<String>);
+
+    impl Visitor for Bindings {
+        fn visit_node(&mut self, ast: &Ast) {
+            match ast.kind {
+                AstKind::Block(..) | AstKind::Lambda { .. } => return,
+                AstKind::Binary(BinaryOp::Bind, ref lhs, _) => {
+                    if let AstKind::Var(ref name) = lhs.kind {
+                        self.0.push(name.clone());
+                    }
+                }
+                _ => {}
+            }
+            walk_node(self, ast);
+        }
+    }
+
+    let mut bindings = Bindings(vec![]);
+    for expr in exprs {
+        bindings.visit_node(expr);
+    }
+    bindings.0
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::Value;
+
+    #[test_case("price > $threshold", None)]
+    #[test_case("$sum(items.price) * $rate", None)]
+    #[test_case("($x := 1; $x + 1)", None)]
+    #[test_case("$x := 1", None; "top level binding")]
+    #[test_case("$f := function($n) { $n < 2 ? $n : $f($n - 1) }", None; "recursion")]
+    #[test_case(
+        "($f := function() { $g() }; $g := function() { 1 }; $f())",
+        None;
+        "bound later in the block"
+    )]
+    #[test_case("items@$i.parts#$n[$n > 0].{'item': $i.name}", None)]
+    #[test_case("items[price > $$.limit].$", None)]
+    #[test_case("$ ~> $sum", None)]
+    #[cfg_attr(
+        feature = "higher-order",
+        test_case("$map(items, function($v, $i) { $v * $i })", None)
+    )]
+    #[test_case("price > $treshold", Some(Error::H0601UnboundVariable(8, "treshold".to_string())))]
+    #[test_case("$sume(items)", Some(Error::H0602UnknownFunction(0, "sume".to_string())))]
+    #[test_case("items ~> $sume", Some(Error::H0602UnknownFunction(9, "sume".to_string())))]
+    #[test_case("items ~> $sume(1)", Some(Error::H0602UnknownFunction(9, "sume".to_string())))]
+    #[test_case("(($x := 1); $x)", Some(Error::H0601UnboundVariable(12, "x".to_string())))]
+    #[test_case(
+        "function($a) { $b }",
+        Some(Error::H0601UnboundVariable(15, "b".to_string()));
+        "lambda"
+    )]
+    #[test_case("(a@$x.b; $x)", Some(Error::H0601UnboundVariable(9, "x".to_string())))]
+    #[test_case("items[$limit]", Some(Error::H0601UnboundVariable(6, "limit".to_string())))]
+    #[test_case("$rate", None; "host variable")]
+    fn strict(expr: &str, expected: Option<Error>) {
+        let result = CompiledExpression::new_strict(expr, ["threshold", "$rate"]);
+        assert_eq!(result.err(), expected);
+    }
+
+    #[test]
+    fn assigned_and_registered() {
+        let arena = Bump::new();
+        let jsonata = JsonAta::new("$double($x)", &arena).unwrap();
+        assert_eq!(jsonata.check_strict().unwrap_err().code(), "H0602");
+        jsonata.register_function("double", 1, |ctx, args| {
+            Ok(Value::number(ctx.arena, args[0].as_f64() * 2.0))
+        });
+        assert_eq!(jsonata.check_strict().unwrap_err().code(), "H0601");
+        jsonata.assign_var("x", Value::number(&arena, 2));
+        assert_eq!(jsonata.check_strict(), Ok(()));
+    }
+}
@@ -0,0 +1,364 @@
+//! Runs the test suite of the reference implementation against this engine, to track how much of
+//! the language it supports. The suite is the `test/test-suite` directory of
+//! [jsonata-js](https://github.com/jsonata-js/jsonata), which holds a directory of cases for each
+//! group in `groups`, and the inputs they share in `datasets`:
+//!
+//! ```no_run
+//! let report = jsonata_rs::test_suite::run("jsonata/test/test-suite")?;
+//! print!("{}", report);
+//! println!("{} of {} cases passed", report.passed(), report.cases());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Each case is evaluated with a fresh engine, and one that panics is reported as a failure, so a
+//! run always covers the whole suite.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use bumpalo::Bump;
+
+use crate::{parse_value, ArrayFlags, JsonAta, Value};
+
+/// The results of each group of the suite, see [`run`].
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub groups: Vec<GroupReport>,
+}
+
+/// The results of the cases in a group.
+#[derive(Debug, Clone, Default)]
+pub struct GroupReport {
+    pub name: String,
+    pub passed: usize,
+    pub failures: Vec<Failure>,
+}
+
+/// A case which didn't give the expected result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// The name of the case's file, such as `case004`, followed by its index if the file holds
+    /// more than one case
+    pub case: String,
+    pub expr: String,
+    /// What went wrong, such as the result that was expected and the one that was given
+    pub reason: String,
+}
+
+/// Runs every group in the suite at `dir`, in the order of their names.
+pub fn run(dir: impl AsRef<Path>) -> io::Result<Report> {
+    let dir = dir.as_ref();
+    let datasets = dir.join("datasets");
+
+    let mut groups = vec![];
+    for entry in fs::read_dir(dir.join("groups"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            groups.push(path);
+        }
+    }
+    groups.sort();
+
+    Ok(Report {
+        groups: groups
+            .iter()
+            .map(|group| run_group(group, &datasets))
+            .collect::<io::Result<_>>()?,
+    })
+}
+
+/// Runs the cases in the directory `group`, reading the datasets they refer to from `datasets`.
+pub fn run_group(group: impl AsRef<Path>, datasets: impl AsRef<Path>) -> io::Result<GroupReport> {
+    let group = group.as_ref();
+
+    let mut files = vec![];
+    for entry in fs::read_dir(group)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let mut report = GroupReport {
+        name: file_name(group),
+        ..Default::default()
+    };
+    for file in files {
+        let arena = Bump::new();
+        let source = fs::read_to_string(&file)?;
+        // The engine reads the cases, so a file it can't parse, say because it has a string with
+        // an escape the tokenizer doesn't accept, fails as a whole
+        let cases = match parse_value(&source, &arena) {
+            Ok(cases) => cases,
+            Err(error) => {
+                report.failures.push(Failure {
+                    case: file_name(&file),
+                    expr: String::new(),
+                    reason: format!("could not read the case: {}", error),
+                });
+                continue;
+            }
+        };
+        let cases = Value::wrap_in_array_if_needed(&arena, cases, ArrayFlags::empty());
+
+        for (index, case) in cases.members().enumerate() {
+            let name = if cases.len() == 1 {
+                file_name(&file)
+            } else {
+                format!("{}[{}]", file_name(&file), index)
+            };
+            let expr = expr(case, group)?;
+            let input = input(case, datasets.as_ref())?;
+
+            let outcome =
+                panic::catch_unwind(AssertUnwindSafe(|| run_case(case, &expr, &input, &arena)))
+                    .unwrap_or_else(|panic| {
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .map(|message| message.to_string())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+                        Err(format!("panicked: {}", message))
+                    });
+            match outcome {
+                Ok(()) => report.passed += 1,
+                Err(reason) => report.failures.push(Failure {
+                    case: name,
+                    expr,
+                    reason,
+                }),
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn expr(case: &Value, group: &Path) -> io::Result<String> {
+    let expr = &case["expr"];
+    let expr_file = &case["expr-file"];
+    if expr.is_string() {
+        Ok(expr.as_str().to_string())
+    } else if expr_file.is_string() {
+        fs::read_to_string(group.join(&*expr_file.as_str()))
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: a case has no expression", group.display()),
+        ))
+    }
+}
+
+/// The JSON input of a case, which is empty if its input is undefined.
+fn input<'a>(case: &'a Value<'a>, datasets: &Path) -> io::Result<String> {
+    let data = &case["data"];
+    let dataset = &case["dataset"];
+    if dataset.is_string() {
+        fs::read_to_string(datasets.join(format!("{}.json", dataset.as_str())))
+    } else if data.is_undefined() {
+        Ok(String::new())
+    } else {
+        Ok(data.serialize(false))
+    }
+}
+
+fn run_case<'a>(
+    case: &'a Value<'a>,
+    expr: &str,
+    input: &str,
+    arena: &'a Bump,
+) -> Result<(), String> {
+    let limit = |name: &str| {
+        let limit = &case[name];
+        if limit.is_integer() {
+            Some(limit.as_usize())
+        } else {
+            None
+        }
+    };
+
+    let result = JsonAta::new(expr, arena).and_then(|jsonata| {
+        if case["bindings"].is_object() {
+            for (name, value) in case["bindings"].entries() {
+                jsonata.assign_var(name, value);
+            }
+        }
+        let input = if input.is_empty() { None } else { Some(input) };
+        jsonata.evaluate_timeboxed(input, limit("depth"), limit("timelimit"))
+    });
+
+    let code = if case["error"].is_undefined() {
+        &case["code"]
+    } else {
+        &case["error"]["code"]
+    };
+
+    match result {
+        Ok(result) if code.is_string() => Err(format!(
+            "expected error {}, got {}",
+            code.as_str(),
+            summarize(result)
+        )),
+        Ok(result) => {
+            let expected = &case["result"];
+            let passed = if case["undefinedResult"] == true {
+                result.is_undefined()
+            } else if case["unordered"] == true {
+                // The order of the members is implementation dependent
+                result.is_array()
+                    && expected.is_array()
+                    && result.len() == expected.len()
+                    && expected
+                        .members()
+                        .all(|expected| result.members().any(|member| member == expected))
+            } else {
+                result == expected
+            };
+            if passed {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected {}, got {}",
+                    summarize(expected),
+                    summarize(result)
+                ))
+            }
+        }
+        Err(error) if *code == error.code() => Ok(()),
+        Err(error) if code.is_string() => {
+            Err(format!("expected error {}, got {}", code.as_str(), error))
+        }
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn summarize<'a>(value: &'a Value<'a>) -> String {
+    if value.is_undefined() {
+        "undefined".to_string()
+    } else {
+        value.serialize(false)
+    }
+}
+
+impl Report {
+    /// The number of cases that passed, in every group.
+    pub fn passed(&self) -> usize {
+        self.groups.iter().map(|group| group.passed).sum()
+    }
+
+    /// The number of cases, in every group.
+    pub fn cases(&self) -> usize {
+        self.groups.iter().map(GroupReport::cases).sum()
+    }
+
+    /// The group named `name`, such as `string-concat`.
+    pub fn group(&self, name: &str) -> Option<&GroupReport> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+impl GroupReport {
+    pub fn cases(&self) -> usize {
+        self.passed + self.failures.len()
+    }
+
+    /// Whether every case in the group passed.
+    pub fn is_passing(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// A line for each group, with its name and how many of its cases passed.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .groups
+            .iter()
+            .map(|group| group.name.len())
+            .max()
+            .unwrap_or(0);
+        for group in self.groups.iter() {
+            writeln!(
+                f,
+                "{:width$}  {}/{}{}",
+                group.name,
+                group.passed,
+                group.cases(),
+                if group.is_passing() { "" } else { "  FAILED" },
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testsuite");
+
+    #[test]
+    fn passing_group() {
+        let report = run_group(
+            Path::new(SUITE).join("groups/string-concat"),
+            Path::new(SUITE).join("datasets"),
+        )
+        .unwrap();
+        assert_eq!(report.name, "string-concat");
+        assert!(report.passed > 0);
+        assert!(report.is_passing(), "{:?}", report.failures);
+    }
+
+    #[test]
+    fn failing_group() {
+        let report = run_group(
+            Path::new(SUITE).join("skip/function-sift"),
+            Path::new(SUITE).join("datasets"),
+        )
+        .unwrap();
+        assert!(!report.is_passing());
+        let failure = &report.failures[0];
+        assert_eq!(failure.case, "case000");
+        assert!(!failure.expr.is_empty());
+        assert!(!failure.reason.is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let report = Report {
+            groups: vec![
+                GroupReport {
+                    name: "fields".to_string(),
+                    passed: 3,
+                    failures: vec![],
+                },
+                GroupReport {
+                    name: "regex".to_string(),
+                    passed: 1,
+                    failures: vec![Failure {
+                        case: "case001".to_string(),
+                        expr: "$match('a', /a/)".to_string(),
+                        reason: "S0302".to_string(),
+                    }],
+                },
+            ],
+        };
+        assert_eq!(report.to_string(), "fields  3/3\nregex   1/2  FAILED\n");
+        assert_eq!(report.passed(), 4);
+        assert_eq!(report.cases(), 5);
+    }
+}
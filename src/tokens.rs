@@ -0,0 +1,197 @@
+use crate::parser::tokenizer::{TokenKind, Tokenizer};
+use crate::Result;
+
+/// A token of an expression, as read by [`tokenize`]. Whitespace isn't a token, so it's whatever
+/// is between the spans of the tokens.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Token {
+    /// A `/* ... */` comment
+    Comment,
+
+    /// An operator or punctuation, such as `.`, `[`, `:=` or `~>`. Its text is the text of its
+    /// span.
+    Operator,
+
+    /// `and`, `or` or `in`
+    Keyword,
+
+    Null,
+    Bool(bool),
+
+    /// A string literal, with its escape sequences decoded
+    String(String),
+
+    Number(f64),
+
+    /// A field name, such as `price`, or `order date` written as `` `order date` ``
+    Name(String),
+
+    /// A variable or function, such as `$total` or `$sum`, without the `$`
+    Variable(String),
+}
+
+/// Where a token is in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// The byte offsets of the start and end of the token, so `&source[span.start..span.end]` is
+    /// its text
+    pub start: usize,
+    pub end: usize,
+
+    /// The index in the chars of the source of the start of the token, as used by [`Error`],
+    /// [`Diagnostic`] and [`Warning`]
+    ///
+    /// [`Error`]: crate::Error
+    /// [`Diagnostic`]: crate::Diagnostic
+    /// [`Warning`]: crate::Warning
+    pub char_index: usize,
+}
+
+/// The tokens of an expression, see [`tokenize`].
+#[derive(Debug)]
+pub struct Tokens<'a> {
+    tokenizer: Tokenizer<'a>,
+    done: bool,
+}
+
+/// Splits an expression into tokens with the same rules as the parser, so that editors can
+/// highlight it. Tokens are read until the end of the source or the first error; tokens that
+/// follow one which doesn't lex, like an unterminated string, can't be told apart from its text.
+///
+/// ```
+/// # use jsonata_rs::{tokenize, Token};
+/// let source = "$sum(items.price) /* total */";
+/// let tokens: Vec<_> = tokenize(source).collect::<Result<_, _>>()?;
+///
+/// let (token, span) = &tokens[0];
+/// assert_eq!(token, &Token::Variable("sum".to_string()));
+/// assert_eq!(&source[span.start..span.end], "$sum");
+/// assert_eq!(tokens.last().unwrap().0, Token::Comment);
+/// # Ok::<(), jsonata_rs::Error>(())
+/// ```
+pub fn tokenize(source: &str) -> Tokens<'_> {
+    Tokens {
+        tokenizer: Tokenizer::new(source),
+        done: false,
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = Result<(Token, Span)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let lexeme = match self.tokenizer.next_lexeme() {
+                Ok(lexeme) => lexeme,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+            let span = Span {
+                start: lexeme.byte_index,
+                end: lexeme.byte_index + lexeme.len,
+                char_index: lexeme.char_index,
+            };
+
+            let token = match lexeme.kind {
+                TokenKind::End => {
+                    self.done = true;
+                    return None;
+                }
+                TokenKind::Whitespace => continue,
+                TokenKind::Comment => Token::Comment,
+                TokenKind::Or | TokenKind::In | TokenKind::And => Token::Keyword,
+                TokenKind::Null => Token::Null,
+                TokenKind::Bool(b) => Token::Bool(b),
+                TokenKind::Str(s) => Token::String(s),
+                TokenKind::Number(n) => Token::Number(n),
+                TokenKind::Name(name) => Token::Name(name),
+                TokenKind::Var(name) => Token::Variable(name),
+                _ => Token::Operator,
+            };
+            return Some(Ok((token, span)));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(Token, &str)> {
+        tokenize(source)
+            .map(|token| {
+                let (token, span) = token.unwrap();
+                (token, &source[span.start..span.end])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_kind() {
+        assert_eq!(
+            tokens("a.`b c`[x > 1.5 and $y] /* c */ ~> $f('s', null, true)"),
+            vec![
+                (Token::Name("a".to_string()), "a"),
+                (Token::Operator, "."),
+                (Token::Name("b c".to_string()), "`b c`"),
+                (Token::Operator, "["),
+                (Token::Name("x".to_string()), "x"),
+                (Token::Operator, ">"),
+                (Token::Number(1.5), "1.5"),
+                (Token::Keyword, "and"),
+                (Token::Variable("y".to_string()), "$y"),
+                (Token::Operator, "]"),
+                (Token::Comment, "/* c */"),
+                (Token::Operator, "~>"),
+                (Token::Variable("f".to_string()), "$f"),
+                (Token::Operator, "("),
+                (Token::String("s".to_string()), "'s'"),
+                (Token::Operator, ","),
+                (Token::Null, "null"),
+                (Token::Operator, ","),
+                (Token::Bool(true), "true"),
+                (Token::Operator, ")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<Span> = tokenize("'é' &\n x")
+            .map(|token| token.unwrap().1)
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span {
+                    start: 0,
+                    end: 4,
+                    char_index: 0
+                },
+                Span {
+                    start: 5,
+                    end: 6,
+                    char_index: 4
+                },
+                Span {
+                    start: 8,
+                    end: 9,
+                    char_index: 7
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_the_first_error() {
+        let mut tokens = tokenize("a & 'b");
+        assert!(tokens.next().unwrap().is_ok());
+        assert!(tokens.next().unwrap().is_ok());
+        assert_eq!(tokens.next().unwrap().unwrap_err().code(), "S0101");
+        assert!(tokens.next().is_none());
+    }
+}
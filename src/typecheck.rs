@@ -0,0 +1,569 @@
+use std::collections::HashMap;
+
+use crate::evaluator::signature::Signature;
+use crate::parser::ast::{Ast, AstKind, BinaryOp, UnaryOp};
+use crate::parser::visit::{for_each_operand, for_each_stage};
+use crate::strict::block_bindings;
+use crate::{CompiledExpression, Error};
+
+/// The signatures of the built-in functions which are checked, with the types they return. Like
+/// in reference JSONata, but only as strict as the functions are here, as an error is only reported
+/// if evaluating the call would fail with it.
+const SIGNATURES: &[(&str, &str, &str)] = &[
+    ("abs", "<n-:n>", "n"),
+    ("base64decode", "<s-:s>", "s"),
+    ("base64encode", "<s-:s>", "s"),
+    ("boolean", "<x-:b>", "b"),
+    ("ceil", "<n-:n>", "n"),
+    ("contains", "<s-(sf):b>", "b"),
+    ("count", "<a:n>", "n"),
+    ("exists", "<x:b>", "b"),
+    #[cfg(feature = "higher-order")]
+    ("filter", "<af>", "a"),
+    ("floor", "<n-:n>", "n"),
+    ("keys", "<x-:a<s>>", "a"),
+    ("length", "<s-:n>", "n"),
+    #[cfg(feature = "higher-order")]
+    ("map", "<af>", "a"),
+    ("max", "<a<n>:n>", "n"),
+    ("min", "<a<n>:n>", "n"),
+    ("not", "<x-:b>", "b"),
+    ("number", "<(nsbl)-:(nl)>", "nl"),
+    ("pad", "<s-ns?:s>", "s"),
+    ("power", "<n-n:n>", "n"),
+    ("replace", "<s-(sf)(sf)n?:s>", "s"),
+    ("reverse", "<a:a>", "a"),
+    ("round", "<n-n?:n>", "n"),
+    ("split", "<s-(sf)n?:a<s>>", "a"),
+    ("spread", "<x-:a<o>>", "a"),
+    ("sqrt", "<n-:n>", "n"),
+    ("string", "<x-b?:s>", "s"),
+    ("substring", "<s-nn?:s>", "s"),
+    ("substringAfter", "<s-s:s>", "s"),
+    ("substringBefore", "<s-s:s>", "s"),
+    ("sum", "<a<n>:n>", "n"),
+];
+
+/// The most combinations of the possible types of the arguments of a call which are checked.
+const MAX_COMBINATIONS: usize = 256;
+
+impl CompiledExpression {
+    /// Infers the types of values flowing through the expression from its literals, operators
+    /// and the signatures of functions, and returns the type errors evaluating it is sure to fail
+    /// with, such as `"a" + 1` or `$sum("a")`, whenever the part of the expression with the error
+    /// is evaluated:
+    ///
+    /// ```
+    /// # use jsonata_rs::CompiledExpression;
+    /// let expression = CompiledExpression::new("items.('Total: ' + price)")?;
+    /// let errors = expression.type_check();
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].code(), "T2001");
+    /// # Ok::<(), jsonata_rs::Error>(())
+    /// ```
+    ///
+    /// The checking is conservative, so only errors which happen whatever the input is are
+    /// reported, and the types of the input, of variables bound more than once and of most
+    /// function results aren't known. Variables the host assigns are assumed not to replace the
+    /// built-in functions.
+    pub fn type_check(&self) -> Vec<Error> {
+        let mut checker = TypeChecker {
+            scopes: vec![Scope::new(block_bindings(std::slice::from_ref(&*self.ast)))],
+            errors: vec![],
+        };
+        checker.infer(&self.ast);
+        checker.errors
+    }
+}
+
+/// A set of the types a value can have, by the symbols used for them in signatures, with `m` for
+/// undefined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Types(u8);
+
+impl Types {
+    const SYMBOLS: &'static str = "asnblfom";
+    const ANY: Types = Types(u8::MAX);
+    const DEFINED: Types = Types(u8::MAX >> 1);
+
+    fn of(symbols: &str) -> Types {
+        Types(symbols.chars().fold(0, |types, symbol| {
+            types | Types::SYMBOLS.find(symbol).map_or(0, |bit| 1 << bit)
+        }))
+    }
+
+    fn contains(self, symbol: char) -> bool {
+        Types::of(&symbol.to_string()).0 & self.0 != 0
+    }
+
+    fn union(self, other: Types) -> Types {
+        Types(self.0 | other.0)
+    }
+
+    fn symbols(self) -> Vec<char> {
+        Types::SYMBOLS
+            .chars()
+            .filter(|&symbol| self.contains(symbol))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    types: Types,
+
+    /// The signature and name of the lambda the variable is bound to, if it has a signature
+    lambda: Option<(Signature, String)>,
+}
+
+struct Scope {
+    /// How many times each variable is bound anywhere in the block, lambda or path
+    bindings: HashMap<String, usize>,
+
+    /// The variables bound once which have been bound so far
+    bound: HashMap<String, Binding>,
+}
+
+impl Scope {
+    fn new(names: Vec<String>) -> Scope {
+        let mut bindings = HashMap::new();
+        for name in names {
+            *bindings.entry(name).or_insert(0) += 1;
+        }
+        Scope {
+            bindings,
+            bound: HashMap::new(),
+        }
+    }
+}
+
+/// What's known about a variable.
+enum Lookup<'c> {
+    /// It's bound once and has been bound
+    Bound(&'c Binding),
+
+    /// It's bound more than once, or hasn't been bound yet
+    Unknown,
+
+    /// The expression doesn't bind it, so it's a built-in function or bound by the host
+    Free,
+}
+
+struct TypeChecker {
+    scopes: Vec<Scope>,
+    errors: Vec<Error>,
+}
+
+impl TypeChecker {
+    /// Infers the types `ast` can evaluate to, recording the errors in it and its children.
+    fn infer(&mut self, ast: &Ast) -> Types {
+        let types = self.infer_kind(ast);
+        for_each_stage(ast, &mut |stage| {
+            self.infer(stage);
+        });
+
+        if ast.keep_array
+            || ast.cons_array
+            || ast.predicates.is_some()
+            || ast.stages.is_some()
+            || ast.group_by.is_some()
+        {
+            Types::ANY
+        } else {
+            types
+        }
+    }
+
+    fn infer_kind(&mut self, ast: &Ast) -> Types {
+        match ast.kind {
+            AstKind::Null => Types::of("l"),
+            AstKind::Bool(..) => Types::of("b"),
+            AstKind::String(..) => Types::of("s"),
+            AstKind::Number(..) | AstKind::Integer(..) => Types::of("n"),
+            #[cfg(feature = "decimal")]
+            AstKind::Decimal(..) => Types::of("n"),
+            AstKind::Unary(UnaryOp::ArrayConstructor(ref items)) => {
+                for item in items {
+                    self.infer(item);
+                }
+                Types::of("a")
+            }
+            AstKind::Unary(UnaryOp::ObjectConstructor(..)) => {
+                self.infer_operands(ast);
+                Types::of("o")
+            }
+            AstKind::Unary(UnaryOp::Minus(ref value)) => {
+                self.infer(value);
+                Types::of("nm")
+            }
+            AstKind::Var(ref name) => match self.lookup(name) {
+                Lookup::Bound(binding) => binding.types,
+                Lookup::Free if builtin(name).is_some() => Types::of("f"),
+                _ => Types::ANY,
+            },
+            AstKind::Block(ref exprs) => {
+                self.scopes.push(Scope::new(block_bindings(exprs)));
+                let types = exprs
+                    .iter()
+                    .fold(Types::of("m"), |_, expr| self.infer(expr));
+                self.scopes.pop();
+                types
+            }
+            AstKind::Lambda {
+                ref args, ref body, ..
+            } => {
+                let mut scope = Scope::new(block_bindings(std::slice::from_ref(&**body)));
+                // The types of the arguments aren't known, as any of them can be undefined
+                for arg in args {
+                    if let AstKind::Var(ref name) = arg.kind {
+                        *scope.bindings.entry(name.clone()).or_insert(0) += 1;
+                    }
+                }
+                self.scopes.push(scope);
+                self.infer(body);
+                self.scopes.pop();
+                Types::of("f")
+            }
+            AstKind::Ternary {
+                ref cond,
+                ref truthy,
+                ref falsy,
+            } => {
+                self.infer(cond);
+                let truthy = self.infer(truthy);
+                match falsy {
+                    Some(falsy) => truthy.union(self.infer(falsy)),
+                    None => truthy.union(Types::of("m")),
+                }
+            }
+            AstKind::Path(ref steps) => {
+                // Focus and index variables are bound for the whole path
+                let names = steps
+                    .iter()
+                    .flat_map(|step| [&step.focus, &step.index])
+                    .flatten()
+                    .cloned()
+                    .collect();
+                self.scopes.push(Scope::new(names));
+                for step in steps {
+                    self.infer(step);
+                }
+                self.scopes.pop();
+                Types::ANY
+            }
+            AstKind::Transform { .. } => {
+                self.infer_operands(ast);
+                Types::of("f")
+            }
+            AstKind::Binary(ref op, ref lhs, ref rhs) => self.infer_binary(ast, op, lhs, rhs),
+            AstKind::Function {
+                ref proc,
+                ref args,
+                is_partial,
+                ..
+            } => {
+                let args: Vec<Types> = args.iter().map(|arg| self.infer(arg)).collect();
+                if is_partial {
+                    self.infer(proc);
+                    return Types::of("f");
+                }
+                self.infer_call(proc, &args)
+            }
+            _ => {
+                self.infer_operands(ast);
+                Types::ANY
+            }
+        }
+    }
+
+    fn infer_operands(&mut self, ast: &Ast) {
+        for_each_operand(ast, &mut |operand| {
+            self.infer(operand);
+        });
+    }
+
+    fn infer_binary(&mut self, ast: &Ast, op: &BinaryOp, lhs: &Ast, rhs: &Ast) -> Types {
+        if *op == BinaryOp::Bind {
+            let types = self.infer(rhs);
+            if let AstKind::Var(ref name) = lhs.kind {
+                let lambda = lambda(rhs);
+                self.bind(name, Binding { types, lambda });
+            }
+            return types;
+        }
+
+        let (l, r) = (self.infer(lhs), self.infer(rhs));
+        let is_number = |types: Types| types == Types::of("n");
+        let can_be = |types: Types, symbols: &str| symbols.chars().any(|s| types.contains(s));
+        let char_index = ast.char_index;
+
+        match op {
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulus => {
+                if !can_be(l, "nm") {
+                    self.errors
+                        .push(Error::T2001LeftSideNotNumber(char_index, op.to_string()));
+                } else if is_number(l) && !can_be(r, "nm") {
+                    self.errors
+                        .push(Error::T2002RightSideNotNumber(char_index, op.to_string()));
+                }
+                Types::of("nm")
+            }
+            BinaryOp::LessThan
+            | BinaryOp::LessThanEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanEqual => {
+                if !can_be(l, "m") && !can_be(r, "m") && (!can_be(l, "ns") || !can_be(r, "ns")) {
+                    self.errors
+                        .push(Error::T2010BinaryOpTypes(char_index, op.to_string()));
+                }
+                Types::of("bm")
+            }
+            BinaryOp::Range => {
+                if !can_be(l, "nm") {
+                    self.errors.push(Error::T2003LeftSideNotInteger(char_index));
+                }
+                Types::of("am")
+            }
+            BinaryOp::Equal | BinaryOp::NotEqual | BinaryOp::And | BinaryOp::Or | BinaryOp::In => {
+                Types::of("b")
+            }
+            BinaryOp::Concat => Types::of("s"),
+            _ => Types::ANY,
+        }
+    }
+
+    /// Checks the arguments of a call against the signature of the function, if it's known, and
+    /// infers what it returns.
+    fn infer_call(&mut self, proc: &Ast, args: &[Types]) -> Types {
+        let (function, returns, builtin) = match proc.kind {
+            AstKind::Var(ref name) => match self.lookup(name) {
+                Lookup::Bound(binding) => (binding.lambda.clone(), Types::ANY, false),
+                Lookup::Free => match builtin(name) {
+                    Some((signature, returns)) => (
+                        Some((signature, name.clone())),
+                        returns.union(Types::of("m")),
+                        true,
+                    ),
+                    None => (None, Types::ANY, false),
+                },
+                Lookup::Unknown => (None, Types::ANY, false),
+            },
+            _ => {
+                self.infer(proc);
+                (lambda(proc), Types::ANY, false)
+            }
+        };
+
+        if let Some((signature, name)) = function {
+            if let Some(error) = check_call(&signature, args, proc.char_index, &name, builtin) {
+                self.errors.push(error);
+            }
+        }
+        returns
+    }
+
+    fn lookup(&self, name: &str) -> Lookup<'_> {
+        for scope in self.scopes.iter().rev() {
+            match scope.bindings.get(name) {
+                Some(1) => return scope.bound.get(name).map_or(Lookup::Unknown, Lookup::Bound),
+                Some(_) => return Lookup::Unknown,
+                None => {}
+            }
+        }
+        Lookup::Free
+    }
+
+    /// Records what a variable is bound to, in the innermost scope which binds it.
+    fn bind(&mut self, name: &str, binding: Binding) {
+        if let Some(scope) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.bindings.contains_key(name))
+        {
+            scope.bound.insert(name.to_string(), binding);
+        }
+    }
+}
+
+/// The signature and name of `ast` if it's a lambda with a signature.
+fn lambda(ast: &Ast) -> Option<(Signature, String)> {
+    match ast.kind {
+        AstKind::Lambda {
+            ref name,
+            signature: Some(ref signature),
+            ..
+        } => Some((signature.clone(), name.clone())),
+        _ => None,
+    }
+}
+
+/// The signature of a built-in function and the types it returns, other than undefined.
+fn builtin(name: &str) -> Option<(Signature, Types)> {
+    let &(_, signature, returns) = SIGNATURES.iter().find(|&&(n, ..)| n == name)?;
+    let signature = Signature::parse(signature, 0).ok()?;
+    let returns = if returns.is_empty() {
+        Types::ANY
+    } else {
+        Types::of(returns)
+    };
+    Some((signature, returns))
+}
+
+/// The error a call fails with whatever the types of its arguments turn out to be, if it's always
+/// the same one. The built-in functions mostly return undefined when an argument is undefined
+/// rather than checking the others, so a call to one which might be passed undefined isn't
+/// checked.
+fn check_call(
+    signature: &Signature,
+    args: &[Types],
+    char_index: usize,
+    name: &str,
+    builtin: bool,
+) -> Option<Error> {
+    let mut args = args.to_vec();
+    if builtin {
+        // Some of them ignore extra arguments rather than failing
+        if args.len() > signature.param_count() || args.iter().any(|types| types.contains('m')) {
+            return None;
+        }
+        // Missing arguments are undefined or the context
+        args.resize(signature.param_count(), Types::DEFINED);
+    }
+
+    let choices: Vec<Vec<char>> = args.iter().map(|types| types.symbols()).collect();
+    let combinations = choices
+        .iter()
+        .try_fold(1usize, |total, choice| total.checked_mul(choice.len()))?;
+    if combinations == 0 || combinations > MAX_COMBINATIONS {
+        return None;
+    }
+
+    let mut error = None;
+    let mut symbols = vec![' '; args.len()];
+    for mut combination in 0..combinations {
+        for (symbol, choice) in symbols.iter_mut().zip(&choices) {
+            *symbol = choice[combination % choice.len()];
+            combination /= choice.len();
+        }
+        match signature.check_symbols(&symbols, char_index, name) {
+            Ok(()) => return None,
+            Err(found) if error.as_ref().is_some_and(|error| *error != found) => return None,
+            Err(found) => error = Some(found),
+        }
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use bumpalo::Bump;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn codes(expr: &str) -> Vec<String> {
+        CompiledExpression::new(expr)
+            .unwrap()
+            .type_check()
+            .iter()
+            .map(|error| error.code().to_string())
+            .collect()
+    }
+
+    #[test_case("'a' + 1", &["T2001"])]
+    #[test_case("1 + 'a'", &["T2002"])]
+    #[test_case("1 + true", &["T2002"])]
+    #[test_case("price + 'a'", &[]; "the left side could be undefined")]
+    #[test_case("'a' & 1", &[])]
+    #[test_case("-(1) * {}", &[])]
+    #[test_case("[1] - 1", &["T2001"])]
+    #[test_case("'a' < true", &["T2010"])]
+    #[test_case("'a' < 1", &[]; "mismatched types aren't checked")]
+    #[test_case("['a'..5]", &["T2003"])]
+    #[test_case("$sum('a')", &["T0412"])]
+    #[test_case("$sum(['a'])", &[]; "the items of arrays aren't known")]
+    #[test_case("$sum(1, 2)", &[]; "extra arguments")]
+    #[test_case("$round(1, 'a')", &["T0410"])]
+    #[test_case("$sum(prices)", &[])]
+    #[test_case("$length({})", &["T0410"])]
+    #[test_case("$length($string(x))", &[])]
+    #[test_case("$substring('abc', 'b')", &["T0410"])]
+    #[test_case("$substring('abc', $string(1))", &[]; "the string could be undefined")]
+    #[test_case("$substring(name, 'b')", &[])]
+    #[test_case("$substring(?, 'b')", &[]; "partial application")]
+    #[test_case("($x := 'a'; $x + 1)", &["T2001"])]
+    #[test_case("($x := 'a'; $x := 1; $x + 1)", &[]; "bound twice")]
+    #[test_case("($y := $x + 1; $x := 'a')", &[]; "not bound yet")]
+    #[test_case("($sum := function($a) { $a }; $sum('a'))", &[]; "shadowed")]
+    #[test_case("($f := function($n)<n:n> { $n }; $f('a'))", &["T0410"])]
+    #[test_case("function($s)<s:s> { $s * 2 }", &[]; "parameters can be undefined")]
+    #[test_case("items.('a' - 1)", &["T2001"]; "nested")]
+    #[test_case("true ? 'a' : 1", &[])]
+    #[test_case("(true ? 'a' : b) + 1", &[]; "either branch")]
+    #[test_case("(true ? 'a' : {}) + 1", &["T2001"]; "both branches")]
+    fn type_errors(expr: &str, expected: &[&str]) {
+        assert_eq!(codes(expr), expected);
+    }
+
+    #[test_case("[1] - 1")]
+    #[test_case("'a' < true")]
+    #[test_case("['a'..5]")]
+    #[test_case("1 + true")]
+    #[test_case("(true ? 'a' : {}) + 1")]
+    #[test_case("($f := function($n)<n:n> { $n }; $f('a'))")]
+    #[test_case("function($s)<s?:s> { $s }(1)")]
+    fn same_error_as_evaluation(expr: &str) {
+        let expression = CompiledExpression::new(expr).unwrap();
+        let arena = Bump::new();
+        assert_eq!(
+            expression.type_check(),
+            [expression.evaluate(None, &arena).unwrap_err()]
+        );
+    }
+
+    const SAMPLES: &[&str] = &[
+        "'a'", "1", "true", "null", "[1]", "['a']", "{}", "$abs", "()",
+    ];
+
+    /// Evaluating each call to a built-in function with literal arguments that an error is
+    /// reported for fails with an error with the same code, whatever the context is.
+    #[test]
+    fn agrees_with_evaluation() {
+        for &(name, ..) in SIGNATURES {
+            let mut calls = vec![format!("${}()", name)];
+            for a in SAMPLES {
+                calls.push(format!("${}({})", name, a));
+                for b in SAMPLES {
+                    calls.push(format!("${}({}, {})", name, a, b));
+                    for c in &SAMPLES[..4] {
+                        calls.push(format!("${}({}, {}, {})", name, a, b, c));
+                    }
+                }
+            }
+
+            for call in calls {
+                let expression = CompiledExpression::new(&call).unwrap();
+                let Some(error) = expression.type_check().into_iter().next() else {
+                    continue;
+                };
+                for input in [None, Some(r#""s""#), Some("2")] {
+                    let arena = Bump::new();
+                    let result = expression.evaluate(input, &arena);
+                    let code = result.err().map(|error| error.code().to_string());
+                    assert_eq!(
+                        code.as_deref(),
+                        Some(error.code()),
+                        "{} with {:?}",
+                        call,
+                        input
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::parser::ast::{Ast, AstKind, BinaryOp};
+use crate::parser::visit::for_each_child;
+use crate::{CompiledExpression, JsonAta};
+
+/// A kind of expression which is valid, but where operator precedence often means it isn't
+/// grouped the way it reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// `a and b or c`, which is `(a and b) or c` as `and` binds more tightly than `or`.
+    MixedAndOr,
+
+    /// `a = "x" or "y"`, which is `(a = "x") or "y"` rather than comparing `a` with either value.
+    LiteralInCondition,
+
+    /// `"Big: " & a > 5`, which is `("Big: " & a) > 5` as `&` binds more tightly than comparisons,
+    /// `and` and `or`.
+    Concatenation,
+}
+
+/// Which kinds of precedence warning to report, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrecedenceWarnings {
+    pub mixed_and_or: bool,
+    pub literal_in_condition: bool,
+    pub concatenation: bool,
+}
+
+impl Default for PrecedenceWarnings {
+    fn default() -> Self {
+        Self {
+            mixed_and_or: true,
+            literal_in_condition: true,
+            concatenation: true,
+        }
+    }
+}
+
+impl PrecedenceWarnings {
+    fn reports(&self, kind: WarningKind) -> bool {
+        match kind {
+            WarningKind::MixedAndOr => self.mixed_and_or,
+            WarningKind::LiteralInCondition => self.literal_in_condition,
+            WarningKind::Concatenation => self.concatenation,
+        }
+    }
+}
+
+/// A suggestion to add parentheses to part of an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+
+    /// The index in the source of the operator the warning is about
+    pub char_index: usize,
+
+    pub message: String,
+}
+
+impl CompiledExpression {
+    /// Looks for operators whose precedence may group the expression differently than it reads,
+    /// returning a warning for each one of the kinds in `enabled`. Parenthesizing the operands
+    /// silences the warning.
+    pub fn precedence_warnings(&self, enabled: &PrecedenceWarnings) -> Vec<Warning> {
+        precedence_warnings(&self.ast, enabled)
+    }
+}
+
+impl JsonAta<'_> {
+    /// See [`CompiledExpression::precedence_warnings`].
+    pub fn precedence_warnings(&self, enabled: &PrecedenceWarnings) -> Vec<Warning> {
+        precedence_warnings(&self.ast, enabled)
+    }
+}
+
+fn precedence_warnings(ast: &Ast, enabled: &PrecedenceWarnings) -> Vec<Warning> {
+    let mut warnings = vec![];
+    check(ast, &mut |kind, char_index, message| {
+        if enabled.reports(kind) {
+            warnings.push(Warning {
+                kind,
+                char_index,
+                message,
+            });
+        }
+    });
+    warnings
+}
+
+fn check(ast: &Ast, warn: &mut impl FnMut(WarningKind, usize, String)) {
+    if let AstKind::Binary(ref op, ref lhs, ref rhs) = ast.kind {
+        let operands = [lhs.as_ref(), rhs.as_ref()];
+
+        if is_logical(op) {
+            for operand in operands {
+                match operand.kind {
+                    AstKind::Binary(ref inner, ..) if is_logical(inner) && inner != op => warn(
+                        WarningKind::MixedAndOr,
+                        ast.char_index,
+                        "`and` binds more tightly than `or`; add parentheses to make the grouping \
+                         clear"
+                            .to_string(),
+                    ),
+                    _ => {}
+                }
+            }
+
+            let has_comparison = operands.iter().any(|operand| is_comparison(operand));
+            for operand in operands {
+                if has_comparison && is_literal(operand) {
+                    warn(
+                        WarningKind::LiteralInCondition,
+                        operand.char_index,
+                        format!(
+                            "`{}` combines this value with the result of the comparison, rather \
+                             than comparing with it; compare with each value or use `in`",
+                            op
+                        ),
+                    );
+                }
+            }
+        }
+
+        if is_logical(op) || is_comparison_op(op) {
+            for operand in operands {
+                if let AstKind::Binary(BinaryOp::Concat, ..) = operand.kind {
+                    warn(
+                        WarningKind::Concatenation,
+                        operand.char_index,
+                        format!(
+                            "`&` binds more tightly than `{}`, so the result of the concatenation \
+                             is used; add parentheses to make the grouping clear",
+                            op
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    for_each_child(ast, &mut |child| check(child, warn));
+}
+
+fn is_logical(op: &BinaryOp) -> bool {
+    matches!(op, BinaryOp::And | BinaryOp::Or)
+}
+
+pub(crate) fn is_comparison_op(op: &BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::LessThan
+            | BinaryOp::GreaterThan
+            | BinaryOp::LessThanEqual
+            | BinaryOp::GreaterThanEqual
+            | BinaryOp::In
+    )
+}
+
+fn is_literal(ast: &Ast) -> bool {
+    match ast.kind {
+        AstKind::String(..) | AstKind::Number(..) | AstKind::Integer(..) => true,
+        #[cfg(feature = "decimal")]
+        AstKind::Decimal(..) => true,
+        _ => false,
+    }
+}
+
+fn is_comparison(ast: &Ast) -> bool {
+    matches!(ast.kind, AstKind::Binary(ref op, ..) if is_comparison_op(op))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    fn kinds(expr: &str, enabled: &PrecedenceWarnings) -> Vec<WarningKind> {
+        CompiledExpression::new(expr)
+            .unwrap()
+            .precedence_warnings(enabled)
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect()
+    }
+
+    #[test_case("a and b or c", &[WarningKind::MixedAndOr])]
+    #[test_case("a or b and c", &[WarningKind::MixedAndOr])]
+    #[test_case("(a and b) or c", &[])]
+    #[test_case("a and b and c", &[])]
+    #[test_case("a = \"x\" or \"y\"", &[WarningKind::LiteralInCondition])]
+    #[test_case("1 and a > 2", &[WarningKind::LiteralInCondition])]
+    #[test_case("a = \"x\" or a = \"y\"", &[])]
+    #[test_case("a or \"y\"", &[])]
+    #[test_case("\"Big: \" & a > 5", &[WarningKind::Concatenation])]
+    #[test_case("a & b or c", &[WarningKind::Concatenation])]
+    #[test_case("\"Big: \" & (a > 5)", &[])]
+    #[test_case("a & b & c", &[])]
+    #[test_case("items[a and b or c].name", &[WarningKind::MixedAndOr])]
+    #[test_case("$f := function($x) { $x = 1 or 2 }", &[WarningKind::LiteralInCondition])]
+    fn warnings(expr: &str, expected: &[WarningKind]) {
+        assert_eq!(kinds(expr, &PrecedenceWarnings::default()), expected);
+    }
+
+    #[test]
+    fn disabled() {
+        let expr = "a & b = c and d or 1 = e";
+        assert_eq!(
+            kinds(expr, &PrecedenceWarnings::default()),
+            vec![WarningKind::MixedAndOr, WarningKind::Concatenation]
+        );
+
+        let enabled = PrecedenceWarnings {
+            mixed_and_or: false,
+            ..Default::default()
+        };
+        assert_eq!(kinds(expr, &enabled), vec![WarningKind::Concatenation]);
+    }
+
+    #[test]
+    fn position() {
+        let warnings = CompiledExpression::new("a = 1 or 2")
+            .unwrap()
+            .precedence_warnings(&PrecedenceWarnings::default());
+        assert_eq!(warnings[0].char_index, 9);
+    }
+}
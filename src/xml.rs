@@ -0,0 +1,143 @@
+//! Reads XML documents as input, so that expressions written to map XML can be evaluated. A
+//! document is converted into the usual JSON representation of XML:
+//!
+//! - The document is an object with a member named after its root element.
+//! - An element with neither attributes nor child elements is its text, or `null` if it's empty.
+//! - Any other element is an object. Its attributes are members named after them with an `@` in
+//!   front, its child elements are members named after them, and its text is a `#text` member.
+//! - Child elements which share a name are an array, in the order they appear in.
+//!
+//! Names keep their namespace prefix, as in `soap:Envelope`. Text is trimmed of surrounding
+//! whitespace, and as XML doesn't say which text is a number, every value is a string, so
+//! `$number()` is needed to do arithmetic on them. Comments and processing instructions are
+//! ignored, and documents with a DTD are rejected.
+//!
+//! ```
+//! # use bumpalo::Bump;
+//! # use jsonata_rs::JsonAta;
+//! let arena = Bump::new();
+//! let input = jsonata_rs::xml::parse(
+//!     r#"<order id="7"><item price="1.50">Widget</item><item price="2">Gadget</item></order>"#,
+//!     &arena,
+//! )?;
+//!
+//! let jsonata = JsonAta::new(r#"$sum(order.item.$number(`@price`))"#, &arena)?;
+//! assert_eq!(jsonata.evaluate_value(input)?.as_f64(), 3.5);
+//! # Ok::<(), jsonata_rs::Error>(())
+//! ```
+
+use bumpalo::Bump;
+use indexmap::IndexMap;
+use roxmltree::{Document, ExpandedName, Node};
+
+use crate::{ArrayFlags, Error, Result, Value};
+
+/// Parses an XML document into `arena`, as described in the [module documentation](self).
+pub fn parse<'a>(source: &str, arena: &'a Bump) -> Result<&'a Value<'a>> {
+    let document = Document::parse(source).map_err(|e| Error::H0104MalformedXml(e.to_string()))?;
+    let root = document.root_element();
+
+    let value = Value::object(arena);
+    value.insert(&name(root, root.tag_name()), element(root, arena));
+    Ok(value)
+}
+
+fn element<'a>(node: Node, arena: &'a Bump) -> &'a Value<'a> {
+    let text: String = node
+        .children()
+        .filter(Node::is_text)
+        .filter_map(|child| child.text())
+        .collect();
+    let text = text.trim();
+
+    let mut children: IndexMap<String, Vec<&Value>> = IndexMap::new();
+    for child in node.children().filter(Node::is_element) {
+        children
+            .entry(name(child, child.tag_name()))
+            .or_default()
+            .push(element(child, arena));
+    }
+
+    if children.is_empty() && node.attributes().len() == 0 {
+        return if text.is_empty() {
+            Value::null(arena)
+        } else {
+            Value::string(arena, text)
+        };
+    }
+
+    let object = Value::object(arena);
+    for attribute in node.attributes() {
+        let name = match attribute.namespace() {
+            Some(namespace) => name(node, ExpandedName::from((namespace, attribute.name()))),
+            None => attribute.name().to_string(),
+        };
+        object.insert(
+            &format!("@{}", name),
+            Value::string(arena, attribute.value()),
+        );
+    }
+    for (name, mut elements) in children {
+        let value = if elements.len() == 1 {
+            elements.remove(0)
+        } else {
+            let array = Value::array_with_capacity(arena, elements.len(), ArrayFlags::empty());
+            for element in elements {
+                array.push(element);
+            }
+            array
+        };
+        object.insert(&name, value);
+    }
+    if !text.is_empty() {
+        object.insert("#text", Value::string(arena, text));
+    }
+    object
+}
+
+/// The name of an element or attribute as it's written, with the prefix of its namespace if it
+/// has one.
+fn name(node: Node, name: ExpandedName) -> String {
+    match name
+        .namespace()
+        .and_then(|namespace| node.lookup_prefix(namespace))
+    {
+        Some(prefix) => format!("{}:{}", prefix, name.name()),
+        None => name.name().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("<a/>", r#"{"a":null}"#)]
+    #[test_case("<a>  text </a>", r#"{"a":"text"}"#)]
+    #[test_case("<a><![CDATA[1 < 2]]></a>", r#"{"a":"1 < 2"}"#)]
+    #[test_case(r#"<a id="1"/>"#, r#"{"a":{"@id":"1"}}"#)]
+    #[test_case(r#"<a id="1">text</a>"#, r##"{"a":{"@id":"1","#text":"text"}}"##)]
+    #[test_case(
+        "<a>\n  <b>1</b>\n  <c/>\n  <b>2</b>\n</a>",
+        r#"{"a":{"b":["1","2"],"c":null}}"#
+    )]
+    #[test_case("<a>one<b/>two</a>", r##"{"a":{"b":null,"#text":"onetwo"}}"##)]
+    #[test_case("<!-- note --><a><?pi x?>1<!-- note --></a>", r#"{"a":"1"}"#)]
+    #[test_case(
+        r#"<s:Envelope xmlns:s="urn:s" xmlns="urn:d"><Body s:id="2" xml:lang="en"/></s:Envelope>"#,
+        r#"{"s:Envelope":{"Body":{"@s:id":"2","@xml:lang":"en"}}}"#
+    )]
+    fn documents(xml: &str, expected: &str) {
+        let arena = Bump::new();
+        assert_eq!(parse(xml, &arena).unwrap().serialize(false), expected);
+    }
+
+    #[test_case("<a><b></a>")]
+    #[test_case("")]
+    #[test_case(r#"<!DOCTYPE a [<!ENTITY e SYSTEM "file:///etc/passwd">]><a>&e;</a>"#)]
+    fn malformed(xml: &str) {
+        let arena = Bump::new();
+        assert_eq!(parse(xml, &arena).unwrap_err().code(), "H0104");
+    }
+}
@@ -1,8 +1,9 @@
-#![cfg(test)]
+// The suite covers the whole language, including the functions `higher-order` adds
+#![cfg(all(test, feature = "higher-order"))]
 extern crate test_generator;
 
 use bumpalo::Bump;
-use jsonata_rs::{ArrayFlags, JsonAta, Value};
+use jsonata_rs::{ArrayFlags, Backend, JsonAta, Value};
 use std::fs;
 use std::path;
 
@@ -82,6 +83,20 @@ fn test_case(resource: &str) {
 
                 let result = jsonata.evaluate_timeboxed(data, depth, timelimit);
 
+                // Expressions that can be compiled to bytecode must give the same result on both
+                // backends
+                let mut bytecode = JsonAta::new(&expr, &arena).unwrap();
+                bytecode.set_backend(Backend::Bytecode);
+                if bytecode.backend() == Backend::Bytecode && depth.is_none() && timelimit.is_none()
+                {
+                    if case["bindings"].is_object() {
+                        for (key, value) in case["bindings"].entries() {
+                            bytecode.assign_var(key, value);
+                        }
+                    }
+                    assert_eq!(bytecode.evaluate(data, None), result);
+                }
+
                 match result {
                     Ok(result) => {
                         let expected_result = &case["result"];